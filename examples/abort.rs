@@ -0,0 +1,33 @@
+extern crate skim;
+use skim::prelude::*;
+use std::io::Cursor;
+use std::thread;
+use std::time::Duration;
+
+/// Abort skim if nothing is accepted in 5 seconds
+pub fn main() {
+    let abort_handle = AbortHandle::new();
+    let options = SkimOptionsBuilder::default()
+        .abort_handle(Some(abort_handle.clone()))
+        .build()
+        .unwrap();
+
+    thread::spawn(move || {
+        thread::sleep(Duration::from_secs(5));
+        abort_handle.abort();
+    });
+
+    let input = "aaaaa\nbbbb\nccc".to_string();
+    let item_reader = SkimItemReader::default();
+    let items = item_reader.of_bufread(Cursor::new(input));
+
+    match Skim::run_with(&options, Some(items)) {
+        Some(out) if out.is_abort => println!("aborted"),
+        Some(out) => {
+            for item in out.selected_items.iter() {
+                println!("{}", item.output());
+            }
+        }
+        None => {}
+    }
+}
@@ -0,0 +1,78 @@
+extern crate skim;
+use skim::prelude::*;
+use std::fmt::{Display, Error, Formatter};
+
+// This example shows a custom match engine that fuzzy matches only the file name of the paths,
+// ranked by skim's `RankBuilder` so that the `--tiebreak` criteria still apply.
+
+struct FileNameEngine {
+    query: String,
+    matcher: SkimMatcherV2,
+    rank_builder: Arc<RankBuilder>,
+}
+
+impl MatchEngine for FileNameEngine {
+    fn match_item(&self, item: Arc<dyn SkimItem>) -> Option<MatchResult> {
+        let text = item.text();
+        let file_name_start = text.rfind('/').map(|slash| slash + 1).unwrap_or(0);
+        let (score, indices) = self.matcher.fuzzy_indices(&text[file_name_start..], &self.query)?;
+
+        let offset = byte_to_char_index(&text, file_name_start);
+        let indices: Vec<usize> = indices.into_iter().map(|idx| idx + offset).collect();
+        let begin = *indices.first().unwrap_or(&0);
+        let end = *indices.last().unwrap_or(&0);
+        Some(MatchResult {
+            rank: self.rank_builder.build_rank(score as i32, begin, end, text.len()),
+            matched_range: MatchRange::Chars(indices),
+        })
+    }
+}
+
+impl Display for FileNameEngine {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "(FileName: {})", self.query)
+    }
+}
+
+struct FileNameEngineFactory {
+    rank_builder: Arc<RankBuilder>,
+}
+
+impl MatchEngineFactory for FileNameEngineFactory {
+    fn create_engine_with_case(&self, query: &str, case: CaseMatching) -> Box<dyn MatchEngine> {
+        let matcher = SkimMatcherV2::default();
+        let matcher = if case.is_sensitive(query) {
+            matcher.respect_case()
+        } else {
+            matcher.ignore_case()
+        };
+        Box::new(FileNameEngine {
+            query: query.to_string(),
+            matcher,
+            rank_builder: self.rank_builder.clone(),
+        })
+    }
+}
+
+pub fn main() {
+    let rank_builder = Arc::new(RankBuilder::new(vec![RankCriteria::Score, RankCriteria::Length]));
+    let options = SkimOptionsBuilder::default()
+        .height(Some("50%"))
+        .engine_factory(Some(Arc::new(FileNameEngineFactory { rank_builder })))
+        .build()
+        .unwrap();
+
+    let (tx, rx): (SkimItemSender, SkimItemReceiver) = unbounded();
+    for path in &["src/main.rs", "src/model.rs", "main/lib.rs", "model/main/util.rs"] {
+        tx.send(Arc::new(path.to_string())).unwrap();
+    }
+    drop(tx);
+
+    let selected_items = Skim::run_with(&options, Some(rx))
+        .map(|out| out.selected_items)
+        .unwrap_or_default();
+
+    for item in selected_items.iter() {
+        println!("{}", item.output());
+    }
+}
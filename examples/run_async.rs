@@ -0,0 +1,32 @@
+extern crate skim;
+use skim::prelude::*;
+use std::io::Cursor;
+
+/// Follow the query and the selection while skim runs, the events are printed after skim quits
+/// since skim owns the screen.
+pub fn main() {
+    let options = SkimOptionsBuilder::default().multi(true).build().unwrap();
+
+    let input = "aaaaa\nbbbb\nccc".to_string();
+    let item_reader = SkimItemReader::default();
+    let items = item_reader.of_bufread(Cursor::new(input));
+
+    let mut history = vec![];
+    for event in Skim::run_async(options, Some(items)) {
+        match event {
+            SkimEvent::Query(query) => history.push(format!("query: {}", query)),
+            SkimEvent::Focus(index, text) => history.push(format!("focus: {} {}", index, text)),
+            SkimEvent::Selection(texts) => history.push(format!("selection: {:?}", texts)),
+            SkimEvent::Finished(Some(out)) if !out.is_abort => {
+                for item in out.selected_items.iter() {
+                    history.push(format!("accepted: {}", item.output()));
+                }
+            }
+            SkimEvent::Finished(_) => history.push("aborted".to_string()),
+        }
+    }
+
+    for line in history {
+        println!("{}", line);
+    }
+}
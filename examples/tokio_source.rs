@@ -0,0 +1,31 @@
+extern crate skim;
+use skim::prelude::*;
+use std::thread;
+use tokio::sync::mpsc;
+
+/// Feed skim from async code, run with `cargo run --example tokio_source --features tokio`
+pub fn main() {
+    let (tx, rx) = mpsc::channel(16);
+
+    let producer = thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async move {
+            for i in 0..100_000 {
+                let item: Arc<dyn SkimItem> = Arc::new(format!("item {}", i));
+                if tx.send(item).await.is_err() {
+                    break; // skim is gone
+                }
+            }
+        });
+    });
+
+    let options = SkimOptionsBuilder::default().multi(true).build().unwrap();
+    let selected_items = Skim::run_with(&options, Some(from_tokio_receiver(rx)))
+        .map(|out| out.selected_items)
+        .unwrap_or_default();
+
+    for item in selected_items.iter() {
+        println!("{}", item.output());
+    }
+    let _ = producer.join();
+}
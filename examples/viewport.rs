@@ -0,0 +1,36 @@
+extern crate skim;
+use skim::prelude::*;
+
+/// This example records the items visible on the screen while the user scrolls, which a custom
+/// side panel could follow.
+
+#[derive(Default)]
+struct VisibleItems {
+    last: Mutex<Vec<String>>,
+}
+
+impl ViewportListener for VisibleItems {
+    fn on_viewport_change(&self, viewport: &Viewport) {
+        let texts = viewport.items.iter().map(|item| item.text().into_owned()).collect();
+        *self.last.lock().unwrap() = texts;
+    }
+}
+
+pub fn main() {
+    let visible_items = Arc::new(VisibleItems::default());
+    let options = SkimOptionsBuilder::default()
+        .height(Some("50%"))
+        .viewport_listener(Some(visible_items.clone()))
+        .build()
+        .unwrap();
+
+    let (tx, rx): (SkimItemSender, SkimItemReceiver) = unbounded();
+    for i in 0..1000 {
+        tx.send(Arc::new(format!("item {}", i))).unwrap();
+    }
+    drop(tx);
+
+    Skim::run_with(&options, Some(rx));
+
+    println!("visible on exit: {:?}", visible_items.last.lock().unwrap());
+}
@@ -0,0 +1,78 @@
+//! Abort a running skim from another thread, e.g. when the embedding application shuts down.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tuikit::key::Key;
+
+use crate::event::{Event, EventSender};
+
+/// A handle to abort `Skim::run_with`, which returns as if the user aborted(`is_abort`).
+///
+/// The handle is passed by `SkimOptions::abort_handle` and could be cloned to other threads. An
+/// abort before skim starts takes effect once it starts, aborting a finished skim does nothing.
+#[derive(Clone, Default)]
+pub struct AbortHandle {
+    inner: Arc<AbortInner>,
+}
+
+#[derive(Default)]
+struct AbortInner {
+    aborted: AtomicBool,
+    tx: Mutex<Option<EventSender>>,
+}
+
+impl AbortHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn abort(&self) {
+        self.inner.aborted.store(true, Ordering::SeqCst);
+        if let Some(tx) = self.inner.tx.lock().unwrap().as_ref() {
+            let _ = tx.send((Key::Null, Event::EvActAbort(None)));
+        }
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.inner.aborted.load(Ordering::SeqCst)
+    }
+
+    /// send the abort to `tx` of the running skim, at once if it was aborted already
+    pub(crate) fn attach(&self, tx: EventSender) {
+        let mut guard = self.inner.tx.lock().unwrap();
+        if self.is_aborted() {
+            let _ = tx.send((Key::Null, Event::EvActAbort(None)));
+        }
+        *guard = Some(tx);
+    }
+
+    pub(crate) fn detach(&self) {
+        self.inner.tx.lock().unwrap().take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_abort_handle() {
+        let handle = AbortHandle::new();
+        let (tx, rx) = channel();
+        handle.attach(tx);
+        assert!(rx.try_recv().is_err());
+
+        handle.clone().abort();
+        assert_eq!(rx.try_recv().map(|(_, ev)| ev), Ok(Event::EvActAbort(None)));
+
+        handle.detach();
+        handle.abort();
+        assert!(rx.try_recv().is_err());
+
+        // aborted before skim starts
+        let (tx, rx) = channel();
+        handle.attach(tx);
+        assert_eq!(rx.try_recv().map(|(_, ev)| ev), Ok(Event::EvActAbort(None)));
+    }
+}
@@ -2,8 +2,11 @@
 use std::default::Default;
 
 use beef::lean::Cow;
-use std::cmp::max;
+use regex::Regex;
+use std::cmp::{max, min};
+use std::ops::Range;
 use tuikit::prelude::*;
+use unicode_width::UnicodeWidthChar;
 use vte::{Params, Perform};
 
 /// An ANSI Parser, will parse one line at a time.
@@ -63,92 +66,7 @@ impl Perform for ANSIParser {
             return;
         }
 
-        // \[[m => means reset
-        let mut attr = if params.is_empty() {
-            Attr::default()
-        } else {
-            self.last_attr
-        };
-
-        let mut iter = params.iter();
-        while let Some(code) = iter.next() {
-            match code[0] {
-                0 => attr = Attr::default(),
-                1 => attr.effect |= Effect::BOLD,
-                2 => attr.effect |= !Effect::BOLD,
-                4 => attr.effect |= Effect::UNDERLINE,
-                5 => attr.effect |= Effect::BLINK,
-                7 => attr.effect |= Effect::REVERSE,
-                num @ 30..=37 => attr.fg = Color::AnsiValue((num - 30) as u8),
-                38 => match iter.next() {
-                    Some(&[2]) => {
-                        // ESC[ 38;2;<r>;<g>;<b> m Select RGB foreground color
-                        let (r, g, b) = match (iter.next(), iter.next(), iter.next()) {
-                            (Some(r), Some(g), Some(b)) => (r[0] as u8, g[0] as u8, b[0] as u8),
-                            _ => {
-                                trace!("ignore CSI {:?} m", params);
-                                continue;
-                            }
-                        };
-
-                        attr.fg = Color::Rgb(r, g, b);
-                    }
-                    Some(&[5]) => {
-                        // ESC[ 38;5;<n> m Select foreground color
-                        let color = match iter.next() {
-                            Some(color) => color[0] as u8,
-                            None => {
-                                trace!("ignore CSI {:?} m", params);
-                                continue;
-                            }
-                        };
-
-                        attr.fg = Color::AnsiValue(color);
-                    }
-                    _ => {
-                        trace!("error on parsing CSI {:?} m", params);
-                    }
-                },
-                39 => attr.fg = Color::Default,
-                num @ 40..=47 => attr.bg = Color::AnsiValue((num - 40) as u8),
-                48 => match iter.next() {
-                    Some(&[2]) => {
-                        // ESC[ 48;2;<r>;<g>;<b> m Select RGB background color
-                        let (r, g, b) = match (iter.next(), iter.next(), iter.next()) {
-                            (Some(r), Some(g), Some(b)) => (r[0] as u8, g[0] as u8, b[0] as u8),
-                            _ => {
-                                trace!("ignore CSI {:?} m", params);
-                                continue;
-                            }
-                        };
-
-                        attr.bg = Color::Rgb(r, g, b);
-                    }
-                    Some(&[5]) => {
-                        // ESC[ 48;5;<n> m Select background color
-                        let color = match iter.next() {
-                            Some(color) => color[0] as u8,
-                            None => {
-                                trace!("ignore CSI {:?} m", params);
-                                continue;
-                            }
-                        };
-
-                        attr.bg = Color::AnsiValue(color);
-                    }
-                    _ => {
-                        trace!("ignore CSI {:?} m", params);
-                    }
-                },
-                49 => attr.bg = Color::Default,
-                num @ 90..=97 => attr.fg = Color::AnsiValue((num - 82) as u8),
-                num @ 100..=107 => attr.bg = Color::AnsiValue((num - 92) as u8),
-                _ => {
-                    trace!("ignore CSI {:?} m", params);
-                }
-            }
-        }
-
+        let attr = parse_sgr(params, self.last_attr);
         self.attr_change(attr);
     }
 
@@ -204,6 +122,293 @@ impl ANSIParser {
     }
 }
 
+/// A virtual screen that interprets the cursor positioning and erasing sequences, e.g. for the
+/// previews of TUI-like programs. Rows are counted from the start of the output.
+#[derive(Debug, Default)]
+pub struct ANSIScreen {
+    // `None` is an empty cell or the second half of a wide char
+    rows: Vec<Vec<Option<(char, Attr)>>>,
+    row: usize,
+    col: usize,
+    saved_cursor: (usize, usize),
+    attr: Attr,
+}
+
+// the output that lands this far beyond the rows(or the columns of a row) so far is dropped, to
+// avoid huge allocations by the cursor movements
+const MAX_SCREEN_SIZE: usize = 10000;
+
+impl ANSIScreen {
+    /// Whether the text contains sequences that need an `ANSIScreen` to render
+    pub fn is_needed(text: &str) -> bool {
+        lazy_static! {
+            static ref RE_CURSOR: Regex = Regex::new(r"\x1B\[[0-9;]*[ABCDEFGHJKdf]|\r[^\n]").unwrap();
+        }
+        RE_CURSOR.is_match(text)
+    }
+
+    pub fn parse_lines(mut self, text: &str) -> Vec<AnsiString<'static>> {
+        let mut statemachine = vte::Parser::new();
+        for byte in text.as_bytes() {
+            statemachine.advance(&mut self, *byte);
+        }
+
+        // drop the empty line left by the trailing newline
+        if self.rows.last().map(|row| row.is_empty()).unwrap_or(false) {
+            self.rows.pop();
+        }
+
+        self.rows.into_iter().map(Self::row_to_string).collect()
+    }
+
+    fn row_to_string(row: Vec<Option<(char, Attr)>>) -> AnsiString<'static> {
+        let mut stripped = String::new();
+        let mut fragments: Vec<(Attr, (u32, u32))> = Vec::new();
+        let cells = row.iter().enumerate().filter(|(col, cell)| {
+            // skip the second half of the wide chars
+            cell.is_some() || *col == 0 || row[col - 1].map(|(ch, _)| ch.width().unwrap_or(1) < 2).unwrap_or(true)
+        });
+        for (char_idx, (_, cell)) in cells.enumerate() {
+            let (ch, attr) = cell.unwrap_or((' ', Attr::default()));
+            stripped.push(ch);
+            let char_idx = char_idx as u32;
+            match fragments.last_mut() {
+                Some((last_attr, (_, end))) if *last_attr == attr && *end == char_idx => *end += 1,
+                _ => fragments.push((attr, (char_idx, char_idx + 1))),
+            }
+        }
+        AnsiString::new_string(stripped, fragments)
+    }
+
+    /// the row under the cursor, `None` if it is too far beyond the rows so far
+    fn current_row(&mut self) -> Option<&mut Vec<Option<(char, Attr)>>> {
+        if self.row >= self.rows.len() + MAX_SCREEN_SIZE {
+            return None;
+        }
+        while self.rows.len() <= self.row {
+            self.rows.push(Vec::new());
+        }
+        Some(&mut self.rows[self.row])
+    }
+
+    fn put(&mut self, ch: char) {
+        let width = ch.width().unwrap_or(0);
+        if width == 0 {
+            return;
+        }
+
+        let (col, attr) = (self.col, self.attr);
+        self.col = col + width;
+        let row = match self.current_row() {
+            Some(row) if col < row.len() + MAX_SCREEN_SIZE => row,
+            _ => return,
+        };
+        if row.len() < col + width {
+            row.resize(col + width, None);
+        }
+        row[col] = Some((ch, attr));
+        if width > 1 {
+            row[col + 1] = None;
+        }
+    }
+
+    fn erase_line(&mut self, from: usize, to: usize) {
+        let row = match self.current_row() {
+            Some(row) => row,
+            None => return,
+        };
+        let to = min(to, row.len());
+        if from >= to {
+            return;
+        }
+        if to == row.len() {
+            row.truncate(from);
+        } else {
+            row[from..to]
+                .iter_mut()
+                .for_each(|cell| *cell = Some((' ', Attr::default())));
+        }
+    }
+}
+
+impl Perform for ANSIScreen {
+    fn print(&mut self, ch: char) {
+        self.put(ch);
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            0x08 => self.col = self.col.saturating_sub(1),
+            0x09 => {
+                for _ in 0..(8 - self.col % 8) {
+                    self.put(' ');
+                }
+            }
+            0x0A => {
+                self.row += 1;
+                self.col = 0;
+                self.current_row();
+            }
+            0x0D => self.col = 0,
+            _ => trace!("ANSIScreen:execute ignored {:?}", byte),
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, _intermediates: &[u8], _ignore: bool, action: char) {
+        if action == 'm' {
+            self.attr = parse_sgr(params, self.attr);
+            return;
+        }
+
+        let mut iter = params.iter();
+        let first = iter.next().map(|param| param[0] as usize).unwrap_or(0);
+        let second = iter.next().map(|param| param[0] as usize).unwrap_or(0);
+        let count = max(first, 1);
+
+        match action {
+            'A' => self.row = self.row.saturating_sub(count),
+            'B' => self.row += count,
+            'C' => self.col += count,
+            'D' => self.col = self.col.saturating_sub(count),
+            'E' => {
+                self.row += count;
+                self.col = 0;
+            }
+            'F' => {
+                self.row = self.row.saturating_sub(count);
+                self.col = 0;
+            }
+            'G' => self.col = count - 1,
+            'd' => self.row = count - 1,
+            'H' | 'f' => {
+                self.row = count - 1;
+                self.col = max(second, 1) - 1;
+            }
+            'J' => {
+                let col = self.col;
+                match first {
+                    0 => {
+                        self.erase_line(col, usize::MAX);
+                        self.rows.truncate(self.row + 1);
+                    }
+                    1 => {
+                        self.rows.iter_mut().take(self.row).for_each(|row| row.clear());
+                        self.erase_line(0, col + 1);
+                    }
+                    _ => self.rows.clear(),
+                }
+            }
+            'K' => {
+                let col = self.col;
+                match first {
+                    0 => self.erase_line(col, usize::MAX),
+                    1 => self.erase_line(0, col + 1),
+                    _ => self.erase_line(0, usize::MAX),
+                }
+            }
+            _ => trace!("ANSIScreen: ignore: params: {:?}, action : {:?}", params, action),
+        }
+    }
+
+    fn esc_dispatch(&mut self, _intermediates: &[u8], _ignore: bool, byte: u8) {
+        match byte {
+            b'7' => self.saved_cursor = (self.row, self.col),
+            b'8' => {
+                self.row = self.saved_cursor.0;
+                self.col = self.saved_cursor.1;
+            }
+            _ => trace!("ANSIScreen:esc ignored {:?}", byte),
+        }
+    }
+}
+
+/// parse the SGR(Select Graphic Rendition) parameters, e.g. `ESC[1;31m`, based on `last_attr`
+fn parse_sgr(params: &Params, last_attr: Attr) -> Attr {
+    // \[[m => means reset
+    let mut attr = if params.is_empty() { Attr::default() } else { last_attr };
+
+    let mut iter = params.iter();
+    while let Some(code) = iter.next() {
+        match code[0] {
+            0 => attr = Attr::default(),
+            1 => attr.effect |= Effect::BOLD,
+            2 => attr.effect |= !Effect::BOLD,
+            4 => attr.effect |= Effect::UNDERLINE,
+            5 => attr.effect |= Effect::BLINK,
+            7 => attr.effect |= Effect::REVERSE,
+            num @ 30..=37 => attr.fg = Color::AnsiValue((num - 30) as u8),
+            38 => match iter.next() {
+                Some(&[2]) => {
+                    // ESC[ 38;2;<r>;<g>;<b> m Select RGB foreground color
+                    let (r, g, b) = match (iter.next(), iter.next(), iter.next()) {
+                        (Some(r), Some(g), Some(b)) => (r[0] as u8, g[0] as u8, b[0] as u8),
+                        _ => {
+                            trace!("ignore CSI {:?} m", params);
+                            continue;
+                        }
+                    };
+
+                    attr.fg = Color::Rgb(r, g, b);
+                }
+                Some(&[5]) => {
+                    // ESC[ 38;5;<n> m Select foreground color
+                    let color = match iter.next() {
+                        Some(color) => color[0] as u8,
+                        None => {
+                            trace!("ignore CSI {:?} m", params);
+                            continue;
+                        }
+                    };
+
+                    attr.fg = Color::AnsiValue(color);
+                }
+                _ => {
+                    trace!("error on parsing CSI {:?} m", params);
+                }
+            },
+            39 => attr.fg = Color::Default,
+            num @ 40..=47 => attr.bg = Color::AnsiValue((num - 40) as u8),
+            48 => match iter.next() {
+                Some(&[2]) => {
+                    // ESC[ 48;2;<r>;<g>;<b> m Select RGB background color
+                    let (r, g, b) = match (iter.next(), iter.next(), iter.next()) {
+                        (Some(r), Some(g), Some(b)) => (r[0] as u8, g[0] as u8, b[0] as u8),
+                        _ => {
+                            trace!("ignore CSI {:?} m", params);
+                            continue;
+                        }
+                    };
+
+                    attr.bg = Color::Rgb(r, g, b);
+                }
+                Some(&[5]) => {
+                    // ESC[ 48;5;<n> m Select background color
+                    let color = match iter.next() {
+                        Some(color) => color[0] as u8,
+                        None => {
+                            trace!("ignore CSI {:?} m", params);
+                            continue;
+                        }
+                    };
+
+                    attr.bg = Color::AnsiValue(color);
+                }
+                _ => {
+                    trace!("ignore CSI {:?} m", params);
+                }
+            },
+            49 => attr.bg = Color::Default,
+            num @ 90..=97 => attr.fg = Color::AnsiValue((num - 82) as u8),
+            num @ 100..=107 => attr.bg = Color::AnsiValue((num - 92) as u8),
+            _ => {
+                trace!("ignore CSI {:?} m", params);
+            }
+        }
+    }
+
+    attr
+}
+
 /// A String that contains ANSI state (e.g. colors)
 ///
 /// It is internally represented as Vec<(attr, string)>
@@ -254,10 +459,78 @@ impl<'a> AnsiString<'a> {
         }
     }
 
+    /// the spans are the `(attr, range)` of `text` by byte offsets(e.g. from `str::find` or regex
+    /// matches), an offset inside a character extends the span to the whole character
+    pub fn from_spans_bytes(text: &'a str, spans: &[(Attr, Range<usize>)]) -> Self {
+        let char_starts: Vec<usize> = text.char_indices().map(|(idx, _)| idx).collect();
+        let mut fragments: Vec<(Attr, (u32, u32))> = spans
+            .iter()
+            .filter(|(_, range)| range.start < range.end)
+            .map(|(attr, range)| {
+                let start = char_starts.partition_point(|&idx| idx <= range.start).saturating_sub(1);
+                let start = if range.start >= text.len() {
+                    char_starts.len()
+                } else {
+                    start
+                };
+                let end = char_starts.partition_point(|&idx| idx < range.end);
+                (*attr, (start as u32, end as u32))
+            })
+            .filter(|(_, (start, end))| start < end)
+            .collect();
+        fragments.sort_by_key(|&(_, range)| range);
+        AnsiString::new_str(text, fragments)
+    }
+
+    /// concatenate the strings, keeping the attributes of each
+    pub fn concat<'b>(strings: impl IntoIterator<Item = AnsiString<'b>>) -> AnsiString<'static> {
+        let mut ret = AnsiString::new_empty();
+        for string in strings {
+            ret.append(&string);
+        }
+        ret
+    }
+
     pub fn parse(raw: &'a str) -> AnsiString<'static> {
         ANSIParser::default().parse_ansi(raw)
     }
 
+    /// append `string` in the default attribute
+    pub fn push_str(&mut self, string: &str) {
+        self.push_str_with_attr(string, Attr::default());
+    }
+
+    pub fn push_str_with_attr(&mut self, string: &str, attr: Attr) {
+        if string.is_empty() {
+            return;
+        }
+        let start = self.stripped.chars().count() as u32;
+        let end = start + string.chars().count() as u32;
+        self.push_stripped(string);
+        if attr != Attr::default() {
+            self.fragments.get_or_insert_with(Vec::new).push((attr, (start, end)));
+        }
+    }
+
+    /// append `other` with its attributes
+    pub fn append(&mut self, other: &AnsiString) {
+        let offset = self.stripped.chars().count() as u32;
+        self.push_stripped(other.stripped());
+        if let Some(other_fragments) = other.fragments.as_ref() {
+            self.fragments.get_or_insert_with(Vec::new).extend(
+                other_fragments
+                    .iter()
+                    .map(|&(attr, (start, end))| (attr, (start + offset, end + offset))),
+            );
+        }
+    }
+
+    fn push_stripped(&mut self, string: &str) {
+        let mut stripped = std::mem::replace(&mut self.stripped, Cow::borrowed("")).into_owned();
+        stripped.push_str(string);
+        self.stripped = Cow::owned(stripped);
+    }
+
     #[inline]
     pub fn is_empty(&self) -> bool {
         self.stripped.is_empty()
@@ -288,6 +561,31 @@ impl<'a> AnsiString<'a> {
         &self.stripped
     }
 
+    /// put the background colors of `original`, the same text without the highlight, back under
+    /// the chars whose attributes are overridden, so that the text stays readable on them.
+    pub fn keep_backgrounds_of(&mut self, original: &AnsiString) {
+        if original.fragments.is_none() || original.stripped() != self.stripped() {
+            return;
+        }
+
+        let mut backgrounds: Vec<(Attr, (u32, u32))> = vec![];
+        for (idx, ((_, attr), (_, original_attr))) in self.iter().zip(original.iter()).enumerate() {
+            if attr == original_attr || original_attr.bg == Color::Default {
+                continue;
+            }
+            let idx = idx as u32;
+            let attr = Attr {
+                bg: original_attr.bg,
+                ..attr
+            };
+            match backgrounds.last_mut() {
+                Some((last_attr, (_, end))) if *last_attr == attr && *end == idx => *end += 1,
+                _ => backgrounds.push((attr, (idx, idx + 1))),
+            }
+        }
+        self.override_attrs(backgrounds);
+    }
+
     pub fn override_attrs(&mut self, attrs: Vec<(Attr, (u32, u32))>) {
         if attrs.is_empty() {
             // pass
@@ -378,6 +676,8 @@ impl<'a> Iterator for AnsiStringIterator<'a> {
     }
 }
 
+/// split the new fragments at the old ones that have background colors, the parts over them take
+/// their background colors
 fn merge_fragments(old: &[(Attr, (u32, u32))], new: &[(Attr, (u32, u32))]) -> Vec<(Attr, (u32, u32))> {
     let mut ret = vec![];
     let mut i = 0;
@@ -606,4 +906,160 @@ mod tests {
         assert_eq!(Some(('a', highlight)), it.next());
         assert_eq!(None, it.next());
     }
+
+    fn screen_lines(text: &str) -> Vec<String> {
+        ANSIScreen::default()
+            .parse_lines(text)
+            .iter()
+            .map(|line| line.stripped().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_screen_cursor_movement() {
+        assert!(!ANSIScreen::is_needed("\x1B[31mred\x1B[0m\n"));
+        assert!(ANSIScreen::is_needed("a\x1B[2;3Hb"));
+
+        assert_eq!(screen_lines("abc\n\x1B[1;2HX\x1B[2;4HY\n"), vec!["aXc", "   Y"]);
+        assert_eq!(screen_lines("left\x1B[10Gright"), vec!["left     right"]);
+        assert_eq!(screen_lines("12345\r\x1B[2Cab"), vec!["12ab5"]);
+        assert_eq!(screen_lines("one\ntwo\x1B[Aw\x1B[Bx"), vec!["onew", "two x"]);
+        assert_eq!(screen_lines("\x1B7abc\x1B8X"), vec!["Xbc"]);
+    }
+
+    #[test]
+    fn test_screen_erase() {
+        assert_eq!(screen_lines("abcdef\x1B[3D\x1B[K"), vec!["abc"]);
+        assert_eq!(screen_lines("abcdef\x1B[3D\x1B[1K"), vec!["    ef"]);
+        assert_eq!(screen_lines("abc\ndef\x1B[2J\x1B[Hxyz"), vec!["xyz"]);
+        assert_eq!(screen_lines("abc\ndef\nghi\x1B[2;2H\x1B[J"), vec!["abc", "d"]);
+    }
+
+    #[test]
+    fn test_screen_attrs_and_wide_chars() {
+        let lines = ANSIScreen::default().parse_lines("中\x1B[31m文\x1B[0m\x1B[1;3HX");
+        assert_eq!(lines.len(), 1);
+        let mut it = lines[0].iter();
+        let red = Attr {
+            fg: Color::AnsiValue(1),
+            ..Attr::default()
+        };
+        assert_eq!(Some(('中', Attr::default())), it.next());
+        assert_eq!(Some(('X', Attr::default())), it.next());
+        // the rest half of the overwritten wide char
+        assert_eq!(Some((' ', Attr::default())), it.next());
+        assert_eq!(None, it.next());
+
+        let lines = ANSIScreen::default().parse_lines("中\x1B[31m文\x1B[0m!");
+        let chars: Vec<_> = lines[0].iter().collect();
+        assert_eq!(
+            chars,
+            vec![('中', Attr::default()), ('文', red), ('!', Attr::default())]
+        );
+
+        // the output after huge cursor movements is dropped
+        assert_eq!(screen_lines("a\x1B[60000;1Hb\x1B[1;60000Hc\x1B[2;1Hd"), vec!["a", "d"]);
+
+        // while the rows and columns could grow past the limit one by one
+        let text = format!("\x1B[K{}", "a\n".repeat(MAX_SCREEN_SIZE + 5));
+        let lines = screen_lines(&text);
+        assert_eq!(lines.len(), MAX_SCREEN_SIZE + 5);
+        assert!(lines.iter().all(|line| line == "a"));
+        let text = format!("\x1B[K{}b", "a".repeat(MAX_SCREEN_SIZE + 5));
+        assert_eq!(screen_lines(&text)[0].len(), MAX_SCREEN_SIZE + 6);
+    }
+
+    #[test]
+    fn test_build_ansi_string() {
+        let red = Attr {
+            fg: Color::AnsiValue(1),
+            ..Attr::default()
+        };
+        let bold = Attr {
+            effect: Effect::BOLD,
+            ..Attr::default()
+        };
+
+        let mut string = AnsiString::from("中");
+        string.push_str_with_attr("文", red);
+        string.push_str("!");
+        assert_eq!(string.stripped(), "中文!");
+        let chars: Vec<_> = string.iter().collect();
+        assert_eq!(
+            chars,
+            vec![('中', Attr::default()), ('文', red), ('!', Attr::default())]
+        );
+
+        let string = AnsiString::concat(vec![string, AnsiString::parse("\x1B[1mb\x1B[0mc")]);
+        assert_eq!(string.stripped(), "中文!bc");
+        let attrs: Vec<_> = string.iter().map(|(_, attr)| attr).collect();
+        assert_eq!(
+            attrs,
+            vec![Attr::default(), red, Attr::default(), bold, Attr::default()]
+        );
+    }
+
+    #[test]
+    fn test_from_spans_bytes() {
+        let red = Attr {
+            fg: Color::AnsiValue(1),
+            ..Attr::default()
+        };
+        let text = "a中b文c";
+        let highlighted = |spans: &[(Attr, Range<usize>)]| -> String {
+            let string = AnsiString::from_spans_bytes(text, spans);
+            string
+                .iter()
+                .filter(|(_, attr)| *attr == red)
+                .map(|(ch, _)| ch)
+                .collect()
+        };
+
+        let start = text.find('b').unwrap();
+        assert_eq!(highlighted(&[(red, start..start + 1)]), "b");
+        assert_eq!(highlighted(&[(red, 1..4)]), "中");
+        // offsets inside a character cover the whole character, the spans could be unordered
+        assert_eq!(highlighted(&[(red, 6..7), (red, 2..3)]), "中文");
+        assert_eq!(highlighted(&[(red, 8..100)]), "c");
+        assert_eq!(highlighted(&[(red, 9..100), (red, 3..3)]), "");
+    }
+
+    #[test]
+    fn test_keep_backgrounds_of() {
+        let highlight = Attr {
+            fg: Color::AnsiValue(2),
+            bg: Color::AnsiValue(0),
+            ..Attr::default()
+        };
+        let on_blue = Attr {
+            fg: Color::AnsiValue(2),
+            bg: Color::AnsiValue(4),
+            ..Attr::default()
+        };
+
+        let mut ansi_parser: ANSIParser = Default::default();
+        let text = ansi_parser.parse_ansi("ab\x1b[44mcd\x1b[0me\x1b[31mf\x1b[0m");
+        let attrs = |string: &AnsiString| string.iter().map(|(_, attr)| attr).collect::<Vec<_>>();
+
+        let mut replaced = text.clone();
+        replaced.override_attrs(vec![(highlight, (1, 6))]);
+        let mut kept = replaced.clone();
+        kept.keep_backgrounds_of(&text);
+
+        let plain = Attr::default();
+        assert_eq!(
+            attrs(&kept),
+            vec![plain, highlight, on_blue, on_blue, highlight, highlight]
+        );
+        assert_eq!(
+            attrs(&replaced),
+            vec![plain, highlight, highlight, highlight, highlight, highlight]
+        );
+
+        // without fragments
+        let mut plain_text = AnsiString::from("abc");
+        plain_text.override_attrs(vec![(highlight, (0, 1))]);
+        plain_text.keep_backgrounds_of(&AnsiString::from("abc"));
+        assert_eq!(attrs(&plain_text), vec![highlight, plain, plain]);
+    }
 }
@@ -9,10 +9,15 @@ extern crate time;
 
 use derive_builder::Builder;
 use std::env;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::os::unix::io::RawFd;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use clap::{crate_version, App, Arg, ArgMatches};
+use nix::fcntl::{fcntl, FcntlArg};
+use nix::sys::stat::{fstat, SFlag};
 use skim::prelude::*;
 
 const USAGE: &str = "
@@ -21,44 +26,70 @@ Usage: sk [options]
   Options
     -h, --help           print this help menu
     --version            print out the current version of skim
+    --capabilities       print the version, features, algorithms, actions
+                         and keys of skim as JSON
+    --profile NAME       load the options in ~/.config/skim/NAME.conf
+                         (see also the config file ~/.config/skim/config.toml)
+    --no-config          do not load the config file
 
   Search
-    --tac                reverse the order of search result
+    --tac                reverse the order of the input
     --no-sort            Do not sort the result
-    -t, --tiebreak [score,begin,end,-score,length...]
+    --follow             keep the cursor on the newest item with --no-sort
+    -t, --tiebreak [score,begin,end,-score,length,index...]
 
                          comma seperated criteria
     -n, --nth 1,2..5     specify the fields to be matched
+    --nth-concat         match the fields of --nth as one text
     --with-nth 1,2..5    specify the fields to be transformed
+    --align              pad the fields into aligned columns
     -d, --delimiter \\t  specify the delimiter(in REGEX) for fields
     -e, --exact          start skim in exact mode
     --regex              use regex instead of fuzzy match
+    --literal            treat the query as literal text, no special syntax
+    --path-mode          match items as paths, prefer matches in basename
+    --match-display      match the displayed text instead of the raw text
     --algo=TYPE          Fuzzy matching algorithm:
                          [skim_v1|skim_v2|clangd] (default: skim_v2)
     --case [respect,ignore,smart] (default: smart)
                          case sensitive or not
+    --compact-match      save memory by not keeping the matched positions
 
   Interface
     -b, --bind KEYBINDS  comma seperated keybindings, in KEY:ACTION
                          such as 'ctrl-j:accept,ctrl-k:kill-line'
     -m, --multi          Enable Multiple Selection
     --no-multi           Disable Multiple Selection
+    --multi-max=N        Enable Multiple Selection of at most N items
     --no-mouse           Disable mouse events
     -c, --cmd ag         command to invoke dynamically
+    --cmd-cwd=PATH       working directory of the invoked commands
+    --exclude=PATTERNS   Skip the matching names in the default find command
+                         such as 'node_modules,*.o'
+    --cmd-env=KEY=VALUE  extra environment variable of the invoked commands
     -i, --interactive    Start skim in interactive(command) mode
+    --split-prompt       Show the command query and the query in two rows
+    --reuse-items-on-superset
+                         Filter the items of the command instead of running
+                         it again when the command query is extended
+    --filepath-word      Make the word motions respect path separators
     --color [BASE][,COLOR:ANSI]
-                         change color theme
+                         change color theme, @FILE reads the colors in FILE
+    --reload-theme-on-sigusr1
+                         Re-read the color theme on SIGUSR1
     --no-hscroll         Disable horizontal scroll
     --keep-right         Keep the right end of the line visible on overflow
     --skip-to-pattern    Line starts with the start of matched pattern
     --no-clear-if-empty  Do not clear previous items if command returns empty result
     --no-clear-start     Do not clear on start
     --show-cmd-error     Send command error message if command fails
+    --drill-cmd=CMD      Accepting an item replaces the items with the output
+                         of CMD ({}), the parent action goes back
 
   Layout
     --layout=LAYOUT      Choose layout: [default|reverse|reverse-list]
     --height=HEIGHT      Height of skim's window (--height 40%)
-    --no-height          Disable height feature
+    --no-height          Use the full screen, overriding --height
     --min-height=HEIGHT  Minimum height when --height is given by percent
                          (default: 10)
     --margin=MARGIN      Screen Margin (TRBL / TB,RL / T,RL,B / T,R,B,L)
@@ -68,10 +99,24 @@ Usage: sk [options]
 
   Display
     --ansi               parse ANSI color codes for input strings
+    --ansi-output        output the selected lines with their ANSI codes
+    --strip-ansi-output  output the selected lines without their ANSI codes,
+                         whether or not --ansi is given
     --tabstop=SPACES     Number of spaces for a tab character (default: 8)
+    --max-fps=N          Maximum redraws per second, 0 for no limit
+                         (default: 30)
     --inline-info        Display info next to query
+    --no-info            Hide the info line
+    --no-unicode         Draw the spinner and borders with ASCII characters
     --header=STR         Display STR next to info
     --header-lines=N     The first N lines of the input are treated as header
+    --line-numbers=TYPE  Show line numbers of the items in a gutter:
+                         [absolute|relative|both]
+    --icons=ICONS        Show icons before the items by extension:
+                         EXT:GLYPH[:COLOR],... ('*' for the others)
+    --scrollbar[=CHARS]  Show a scrollbar on the right of the items, the
+                         characters of the thumb and the track (default: │)
+    --highlight-all      Highlight every occurrence of the query terms
 
   History
     --history=FILE       History file
@@ -83,22 +128,56 @@ Usage: sk [options]
     --preview=COMMAND    command to preview current highlighted line ({})
                          We can specify the fields. e.g. ({1}, {..3}, {0..})
     --preview-window=OPT Preview window layout (default: right:50%)
-                         [up|down|left|right][:SIZE[%]][:hidden][:+SCROLL[-OFFSET]]
+                         [up|down|left|right][:SIZE[%]][:hidden][:auto-hide<COLS]
+                         [:+SCROLL[-OFFSET]]
+    --preview-keep-scroll
+                         Restore the scroll position of revisited items
+    --preview-timeout=MS Kill the preview command after MS milliseconds
+    --preview-debug      Show the preview command with the fields injected
+    --preview-rules=RULES
+                         Preview commands by the file kinds of the items
+                         e.g. 'dir:ls -la {},bin:xxd {},file:cat {}'
+    --preview-builtin=file[:FIELDS]
+                         Read and highlight the text files named by {}
+                         or FIELDS, instead of running the commands
+    --preview-shell-init=FILE
+                         Run FILE before the preview command, e.g. to
+                         define the shell functions it calls
 
   Scripting
     -q, --query \"\"       specify the initial query
     --cmd-query \"\"       specify the initial query for interactive mode
     --expect KEYS        comma seperated keys that can be used to complete skim
+    --expect-hint        Show the --expect keys above the items
     --read0              Read input delimited by ASCII NUL(\\0) characters
+    --trim-trailing      Remove trailing whitespaces of the input lines
+    --skip-blank         Skip blank input lines
+    --skip-pattern=REGEX Skip input lines matching REGEX (e.g. '^#')
     --print0             Print output delimited by ASCII NUL(\\0) characters
+    --output-delimiter=STR
+                         Join the output items by STR, e.g. ' '
+    --no-buffer          Write every output line to stdout at once
     --no-clear-start     Do not clear screen on start
     --no-clear           Do not clear screen on exit
     --print-query        Print query as the first line
     --print-cmd          Print command query as the first line (after --print-query)
     --print-score        Print matching score in filter output (with --filter)
+    --print-positions    Print the matched text of the items and the matched byte
+                         ranges in filter output (with --filter),
+                         e.g. 'src/main.rs\\t0:1,4:6'
+    --print-summary      Print a summary line to stderr on exit:
+                         selected=N matched=N elapsed_ms=N
+    --summary-fd=FD      Print the summary to file descriptor FD instead
+    --notify-fd=FD       Write the focus, selection and query changes to
+                         file descriptor FD as JSON lines
     -1, --select-1       Automatically select the only match
     -0, --exit-0         Exit immediately when there's no match
+    --accept-nth=N       Automatically accept the Nth match once all items
+                         are matched
     --sync               Synchronous search for multi-staged filtering
+    --bench-matcher FILE QUERY
+                         Compare the timings of the fuzzy algorithms
+                         matching QUERY against the lines of FILE
     --pre-select-n=NUM   Pre-select the first n items in multi-selection mode
     --pre-select-pat=REGEX
                          Pre-select the matched items in multi-selection mode
@@ -106,22 +185,25 @@ Usage: sk [options]
                          Pre-select the items separated by newline character
     --pre-select-file=FILENAME
                          Pre-select the items read from file
+    --pre-select-last-accepted=FILENAME
+                         Pre-select the items accepted last time, the
+                         accepted items are saved to the file
 
   Environment variables
     SKIM_DEFAULT_COMMAND Default command to use when input is tty
+    SKIM_LEVEL           Set by skim for its commands: how deep skim is nested
     SKIM_DEFAULT_OPTIONS Default options (e.g. '--ansi --regex')
                          You should not include other environment variables
                          (e.g. '-c \"$HOME/bin/ag\"')
+    SKIM_CONFIG          The config file instead of ~/.config/skim/config.toml
 
   Removed
     -I replstr           replace `replstr` with the selected item
 
   Reserved (not used for now)
     --extended
-    --literal
     --cycle
     --hscroll-off=COL
-    --filepath-word
     --jump-labels=CHARS
     --border
     --no-bold
@@ -151,120 +233,144 @@ fn main() {
 
 #[rustfmt::skip]
 fn real_main() -> Result<i32, std::io::Error> {
-    let mut stdout = std::io::stdout();
+    let start_time = Instant::now();
 
     let mut args = Vec::new();
 
     args.push(env::args().next().expect("there should be at least one arg: the application name"));
-    args.extend(env::var("SKIM_DEFAULT_OPTIONS")
+
+    let default_args: Vec<String> = env::var("SKIM_DEFAULT_OPTIONS")
         .ok()
         .and_then(|val| shlex::split(&val))
-        .unwrap_or_default());
-    for arg in env::args().skip(1) {
-        args.push(arg);
+        .unwrap_or_default();
+    let cli_args: Vec<String> = env::args().skip(1).collect();
+
+    // precedence: config file < SKIM_DEFAULT_OPTIONS < --profile < command line
+    let no_config = scan_options(default_args.iter().chain(cli_args.iter()))
+        .iter()
+        .any(|(name, _)| *name == "no-config");
+    let mut config = SkimConfig::default();
+    if !no_config {
+        match read_config() {
+            Ok((toml_config, config_args)) => {
+                config = toml_config;
+                args.extend(config_args);
+            }
+            Err(err) => {
+                eprintln!("sk: failed to load the config file: {}", err);
+                return Ok(2);
+            }
+        }
+    }
+    args.extend(default_args);
+
+    // the options of `--profile` go between the default options and the ones on the command line
+    if let Some(name) = profile_name(args.iter().skip(1).chain(cli_args.iter())) {
+        match read_profile(&name) {
+            Ok(profile_args) => args.extend(profile_args),
+            Err(err) => {
+                eprintln!("sk: failed to load the profile {}: {}", name, err);
+                return Ok(2);
+            }
+        }
+    }
+    args.extend(cli_args);
+
+    // the options given otherwise take precedence over those of the TOML config file
+    for (name, _) in scan_options(args.iter().skip(1)) {
+        config.forget(name);
     }
 
+    // let the commands run by skim know how deep skim is nested
+    let level: usize = env::var("SKIM_LEVEL").ok().and_then(|level| level.parse().ok()).unwrap_or(0);
+    env::set_var("SKIM_LEVEL", (level + 1).to_string());
+
 
     //------------------------------------------------------------------------------
     // parse options
-    let opts = App::new("sk")
-        .author("Jinzhou Zhang<lotabout@gmail.com>")
-        .version(crate_version!())
-        .arg(Arg::with_name("help").long("help").short('h'))
-        .arg(Arg::with_name("bind").long("bind").short('b').multiple(true).takes_value(true))
-        .arg(Arg::with_name("multi").long("multi").short('m').multiple(true))
-        .arg(Arg::with_name("no-multi").long("no-multi").multiple(true))
-        .arg(Arg::with_name("prompt").long("prompt").short('p').multiple(true).takes_value(true).default_value("> "))
-        .arg(Arg::with_name("cmd-prompt").long("cmd-prompt").multiple(true).takes_value(true).default_value("c> "))
-        .arg(Arg::with_name("expect").long("expect").multiple(true).takes_value(true))
-        .arg(Arg::with_name("tac").long("tac").multiple(true))
-        .arg(Arg::with_name("tiebreak").long("tiebreak").short('t').multiple(true).takes_value(true))
-        .arg(Arg::with_name("ansi").long("ansi").multiple(true))
-        .arg(Arg::with_name("exact").long("exact").short('e').multiple(true))
-        .arg(Arg::with_name("cmd").long("cmd").short('c').multiple(true).takes_value(true))
-        .arg(Arg::with_name("interactive").long("interactive").short('i').multiple(true))
-        .arg(Arg::with_name("query").long("query").short('q').multiple(true).takes_value(true))
-        .arg(Arg::with_name("cmd-query").long("cmd-query").multiple(true).takes_value(true))
-        .arg(Arg::with_name("regex").long("regex").multiple(true))
-        .arg(Arg::with_name("delimiter").long("delimiter").short('d').multiple(true).takes_value(true))
-        .arg(Arg::with_name("nth").long("nth").short('n').multiple(true).takes_value(true))
-        .arg(Arg::with_name("with-nth").long("with-nth").multiple(true).takes_value(true))
-        .arg(Arg::with_name("replstr").short('I').multiple(true).takes_value(true))
-        .arg(Arg::with_name("color").long("color").multiple(true).takes_value(true))
-        .arg(Arg::with_name("margin").long("margin").multiple(true).takes_value(true).default_value("0,0,0,0"))
-        .arg(Arg::with_name("min-height").long("min-height").multiple(true).takes_value(true).default_value("10"))
-        .arg(Arg::with_name("height").long("height").multiple(true).takes_value(true).default_value("100%"))
-        .arg(Arg::with_name("no-height").long("no-height").multiple(true))
-        .arg(Arg::with_name("no-clear").long("no-clear").multiple(true))
-        .arg(Arg::with_name("no-clear-start").long("no-clear-start").multiple(true))
-        .arg(Arg::with_name("no-mouse").long("no-mouse").multiple(true))
-        .arg(Arg::with_name("preview").long("preview").multiple(true).takes_value(true))
-        .arg(Arg::with_name("preview-window").long("preview-window").multiple(true).takes_value(true).default_value("right:50%"))
-        .arg(Arg::with_name("reverse").long("reverse").multiple(true))
+    let opts = app().get_matches_from(args);
 
-        .arg(Arg::with_name("algorithm").long("algo").multiple(true).takes_value(true).default_value("skim_v2"))
-        .arg(Arg::with_name("case").long("case").multiple(true).takes_value(true).default_value("smart"))
-        .arg(Arg::with_name("literal").long("literal").multiple(true))
-        .arg(Arg::with_name("cycle").long("cycle").multiple(true))
-        .arg(Arg::with_name("no-hscroll").long("no-hscroll").multiple(true))
-        .arg(Arg::with_name("hscroll-off").long("hscroll-off").multiple(true).takes_value(true).default_value("10"))
-        .arg(Arg::with_name("filepath-word").long("filepath-word").multiple(true))
-        .arg(Arg::with_name("jump-labels").long("jump-labels").multiple(true).takes_value(true).default_value("abcdefghijklmnopqrstuvwxyz"))
-        .arg(Arg::with_name("border").long("border").multiple(true))
-        .arg(Arg::with_name("inline-info").long("inline-info").multiple(true))
-        .arg(Arg::with_name("header").long("header").multiple(true).takes_value(true).default_value(""))
-        .arg(Arg::with_name("header-lines").long("header-lines").multiple(true).takes_value(true).default_value("0"))
-        .arg(Arg::with_name("tabstop").long("tabstop").multiple(true).takes_value(true).default_value("8"))
-        .arg(Arg::with_name("no-bold").long("no-bold").multiple(true))
-        .arg(Arg::with_name("history").long("history").multiple(true).takes_value(true))
-        .arg(Arg::with_name("cmd-history").long("cmd-history").multiple(true).takes_value(true))
-        .arg(Arg::with_name("history-size").long("history-size").multiple(true).takes_value(true).default_value("1000"))
-        .arg(Arg::with_name("cmd-history-size").long("cmd-history-size").multiple(true).takes_value(true).default_value("1000"))
-        .arg(Arg::with_name("print-query").long("print-query").multiple(true))
-        .arg(Arg::with_name("print-cmd").long("print-cmd").multiple(true))
-        .arg(Arg::with_name("print-score").long("print-score").multiple(true))
-        .arg(Arg::with_name("read0").long("read0").multiple(true))
-        .arg(Arg::with_name("print0").long("print0").multiple(true))
-        .arg(Arg::with_name("sync").long("sync").multiple(true))
-        .arg(Arg::with_name("extended").long("extended").short('x').multiple(true))
-        .arg(Arg::with_name("no-sort").long("no-sort").multiple(true))
-        .arg(Arg::with_name("select-1").long("select-1").short('1').multiple(true))
-        .arg(Arg::with_name("exit-0").long("exit-0").short('0').multiple(true))
-        .arg(Arg::with_name("filter").long("filter").short('f').takes_value(true).multiple(true))
-        .arg(Arg::with_name("layout").long("layout").multiple(true).takes_value(true).default_value("default"))
-        .arg(Arg::with_name("keep-right").long("keep-right").multiple(true))
-        .arg(Arg::with_name("skip-to-pattern").long("skip-to-pattern").multiple(true).takes_value(true).default_value(""))
-        .arg(Arg::with_name("pre-select-n").long("pre-select-n").multiple(true).takes_value(true).default_value("0"))
-        .arg(Arg::with_name("pre-select-pat").long("pre-select-pat").multiple(true).takes_value(true).default_value(""))
-        .arg(Arg::with_name("pre-select-items").long("pre-select-items").multiple(true).takes_value(true))
-        .arg(Arg::with_name("pre-select-file").long("pre-select-file").multiple(true).takes_value(true).default_value(""))
-        .arg(Arg::with_name("no-clear-if-empty").long("no-clear-if-empty").multiple(true))
-        .arg(Arg::with_name("show-cmd-error").long("show-cmd-error").multiple(true))
-        .get_matches_from(args);
+    let mut stdout = Output::new(opts.is_present("no-buffer"));
 
     if opts.is_present("help") {
         write!(stdout, "{}", USAGE)?;
         return Ok(0);
     }
 
+    if opts.is_present("capabilities") {
+        writeln!(stdout, "{}", capabilities())?;
+        return Ok(0);
+    }
+
     //------------------------------------------------------------------------------
-    let mut options = parse_options(&opts);
+    let bind_errors: Vec<String> = config
+        .bind
+        .iter()
+        .map(String::as_str)
+        .chain(opts.values_of("bind").into_iter().flatten())
+        .flat_map(check_keymap)
+        .collect();
+    if !bind_errors.is_empty() {
+        for error in bind_errors {
+            eprintln!("sk: invalid --bind: {}", error);
+        }
+        return Ok(2);
+    }
+
+    let multi_max = opts.values_of("multi-max").and_then(|vals| vals.last());
+    if let Some(max) = multi_max.filter(|max| !matches!(max.parse::<usize>(), Ok(max) if max > 0)) {
+        eprintln!("sk: invalid --multi-max: {}, expects a positive number", max);
+        return Ok(2);
+    }
+
+    let accept_nth = opts.values_of("accept-nth").and_then(|vals| vals.last());
+    if let Some(nth) = accept_nth.filter(|nth| !matches!(nth.parse::<usize>(), Ok(nth) if nth > 0)) {
+        eprintln!("sk: invalid --accept-nth: {}, expects a positive number", nth);
+        return Ok(2);
+    }
+
+    let summary_fd = opts.values_of("summary-fd").and_then(|vals| vals.last());
+    if let Some(fd) = summary_fd.filter(|fd| parse_open_fd(fd).is_none()) {
+        eprintln!("sk: invalid --summary-fd: {}, expects an open file descriptor", fd);
+        return Ok(2);
+    }
+
+    //------------------------------------------------------------------------------
+    let mut options = match parse_options(&opts) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("sk: {}", err);
+            return Ok(2);
+        }
+    };
 
     let preview_window_joined = opts.values_of("preview-window").map(|x| x.collect::<Vec<_>>().join(":"));
     options.preview_window = preview_window_joined.as_deref();
+    let mut options = options.merge_config(&config);
 
     //------------------------------------------------------------------------------
     // initialize collector
     let item_reader_option = SkimItemReaderOption::default()
         .ansi(opts.is_present("ansi"))
-        .delimiter(opts.values_of("delimiter").and_then(|vals| vals.last()).unwrap_or(""))
+        .ansi_output(opts.is_present("ansi-output"))
+        .strip_ansi_output(opts.is_present("strip-ansi-output"))
+        .delimiter(options.delimiter.unwrap_or(
+            // so that `--nth=-1` matches the basename
+            if opts.is_present("path-mode") { "/" } else { "" },
+        ))
         .with_nth(opts.values_of("with-nth").and_then(|vals| vals.last()).unwrap_or(""))
         .nth(opts.values_of("nth").and_then(|vals| vals.last()).unwrap_or(""))
+        .align(opts.is_present("align"))
         .read0(opts.is_present("read0"))
+        .trim_trailing(opts.is_present("trim-trailing"))
+        .skip_blank(opts.is_present("skip-blank"))
+        .skip_pattern(opts.values_of("skip-pattern").and_then(|vals| vals.last()).unwrap_or(""))
         .show_error(opts.is_present("show-cmd-error"))
+        .cmd_cwd(opts.values_of("cmd-cwd").and_then(|vals| vals.last()).unwrap_or(""))
+        .cmd_env(&opts.values_of("cmd-env").map(|x| x.collect::<Vec<_>>()).unwrap_or_default())
         .build();
 
-    let cmd_collector = Rc::new(RefCell::new(SkimItemReader::new(item_reader_option)));
+    let cmd_collector = Arc::new(Mutex::new(SkimItemReader::new(item_reader_option)));
     options.cmd_collector = cmd_collector.clone();
 
     //------------------------------------------------------------------------------
@@ -287,19 +393,20 @@ fn real_main() -> Result<i32, std::io::Error> {
     let pre_select_pat = opts.values_of("pre-select-pat").and_then(|vals| vals.last());
     let pre_select_items: Option<Vec<String>> = opts.values_of("pre-select-items").map(|vals| vals.flat_map(|m|m.split('\n')).map(|s|s.to_string()).collect());
     let pre_select_file = opts.values_of("pre-select-file").and_then(|vals| vals.last());
+    let last_accepted_file = opts.values_of("pre-select-last-accepted").and_then(|vals| vals.last());
 
     if pre_select_n.is_some() || pre_select_pat.is_some() || pre_select_items.is_some() || pre_select_file.is_some() {
         let first_n = pre_select_n.unwrap_or(0);
         let pattern = pre_select_pat.unwrap_or("");
         let preset_items = pre_select_items.unwrap_or_default();
-        let preset_file = pre_select_file.and_then(|filename| read_file_lines(filename).ok()).unwrap_or_default();
 
         let selector = DefaultSkimSelector::default()
             .first_n(first_n)
             .regex(pattern)
             .preset(preset_items)
-            .preset(preset_file);
-        options.selector = Some(Rc::new(selector));
+            .preset_file(pre_select_file.unwrap_or(""))
+            .preset_file(last_accepted_file.unwrap_or(""));
+        options.selector = Some(Arc::new(selector));
     }
 
     let options = options;
@@ -309,14 +416,50 @@ fn real_main() -> Result<i32, std::io::Error> {
         .filter(opts.values_of("filter").and_then(|vals| vals.last()))
         .print_query(opts.is_present("print-query"))
         .print_cmd(opts.is_present("print-cmd"))
+        .print_positions(opts.is_present("print-positions"))
+        .summary_fd(if opts.is_present("print-summary") || opts.is_present("summary-fd") {
+            summary_fd.and_then(parse_open_fd).or(Some(2))
+        } else {
+            None
+        })
+        .start_time(start_time)
         .output_ending(if opts.is_present("print0") { "\0" } else { "\n" })
+        .output_delimiter(opts.values_of("output-delimiter").and_then(|vals| vals.last()))
         .build()
         .expect("");
 
+    //------------------------------------------------------------------------------
+    // bench mode
+    if let Some(mut args) = opts.values_of("bench-matcher") {
+        let filename = args.next().unwrap_or("");
+        let query = args.next().unwrap_or("");
+        let reader = cmd_collector.lock().unwrap();
+        return bench_matcher(&mut stdout, options, &reader, filename, query);
+    }
+
+    //------------------------------------------------------------------------------
+    // the finder needs a terminal of its own
+    if !opts.is_present("filter") {
+        if env::var_os("SKIM_TERMINAL_IN_USE").is_some() {
+            eprintln!("sk: the terminal is in use by the skim running this command, try --filter");
+            return Ok(2);
+        }
+
+        if let Err(err) = OpenOptions::new().read(true).write(true).open("/dev/tty") {
+            eprintln!("sk: failed to open the terminal(/dev/tty): {}", err);
+            return Ok(2);
+        }
+    }
+
     //------------------------------------------------------------------------------
     // read from pipe or command
-    let rx_item = if atty::isnt(atty::Stream::Stdin) {
-            let rx_item = cmd_collector.borrow().of_bufread(BufReader::new(std::io::stdin()));
+    let read_stdin = atty::isnt(atty::Stream::Stdin) && stdin_is_readable();
+    if atty::isnt(atty::Stream::Stdin) && !read_stdin && options.cmd.is_none() {
+        eprintln!("sk: stdin is neither a terminal nor a pipe, run SKIM_DEFAULT_COMMAND instead");
+    }
+
+    let rx_item = if read_stdin {
+            let rx_item = cmd_collector.lock().unwrap().of_bufread(BufReader::new(std::io::stdin()));
             Some(rx_item)
         } else {
          None
@@ -325,7 +468,7 @@ fn real_main() -> Result<i32, std::io::Error> {
     //------------------------------------------------------------------------------
     // filter mode
     if opts.is_present("filter") {
-        return filter(&bin_options, &options, rx_item);
+        return filter(&mut stdout, &bin_options, &options, rx_item);
     }
 
     //------------------------------------------------------------------------------
@@ -337,8 +480,12 @@ fn real_main() -> Result<i32, std::io::Error> {
     //------------------------------------------------------------------------------
     // output
     let output = output.unwrap();
+    print_summary(&bin_options, output.selected_items.len(), output.num_matched);
     if output.is_abort {
-        return Ok(130);
+        return Ok(match output.final_event {
+            Event::EvActAbort(Some(exit_code)) => exit_code,
+            _ => 130,
+        });
     }
 
     // output query
@@ -362,8 +509,21 @@ fn real_main() -> Result<i32, std::io::Error> {
         }
     }
 
-    for item in output.selected_items.iter() {
-        write!(stdout, "{}{}", item.output(), bin_options.output_ending)?;
+    for (index, item) in output.selected_items.iter().enumerate() {
+        write_item(&mut stdout, &bin_options, index, &item.output_fields().join("\t"))?;
+    }
+    end_items(&mut stdout, &bin_options, output.selected_items.len())?;
+
+    // nothing matched, the query is the result of accept-or-print-query
+    if output.is_query_accepted {
+        write!(stdout, "{}{}", output.query, bin_options.output_ending)?;
+    }
+    stdout.flush()?;
+
+    // the texts are saved, which are what the pre-selection compares
+    if let Some(file) = last_accepted_file {
+        let lines: Vec<String> = output.selected_items.iter().map(|item| format!("{}\n", item.text())).collect();
+        std::fs::write(file, lines.concat())?;
     }
 
     //------------------------------------------------------------------------------
@@ -382,21 +542,157 @@ fn real_main() -> Result<i32, std::io::Error> {
         write_history_to_file(&cmd_history, &output.cmd, limit, file)?;
     }
 
-    Ok(if output.selected_items.is_empty() { 1 } else { 0 })
+    Ok(if output.selected_items.is_empty() && !output.is_query_accepted { 1 } else { 0 })
 }
 
-fn parse_options(options: &ArgMatches) -> SkimOptions<'_> {
+/// the options that sk takes
+#[rustfmt::skip]
+fn app() -> App<'static> {
+    App::new("sk")
+        .author("Jinzhou Zhang<lotabout@gmail.com>")
+        .version(crate_version!())
+        .arg(Arg::with_name("help").long("help").short('h'))
+        .arg(Arg::with_name("capabilities").long("capabilities"))
+        .arg(Arg::with_name("bind").long("bind").short('b').multiple(true).takes_value(true))
+        .arg(Arg::with_name("multi").long("multi").short('m').multiple(true))
+        .arg(Arg::with_name("no-multi").long("no-multi").multiple(true))
+        .arg(Arg::with_name("multi-max").long("multi-max").multiple(true).takes_value(true))
+        .arg(Arg::with_name("prompt").long("prompt").short('p').multiple(true).takes_value(true).default_value("> "))
+        .arg(Arg::with_name("cmd-prompt").long("cmd-prompt").multiple(true).takes_value(true).default_value("c> "))
+        .arg(Arg::with_name("expect").long("expect").multiple(true).takes_value(true))
+        .arg(Arg::with_name("expect-hint").long("expect-hint").multiple(true))
+        .arg(Arg::with_name("tac").long("tac").multiple(true))
+        .arg(Arg::with_name("tiebreak").long("tiebreak").short('t').multiple(true).takes_value(true))
+        .arg(Arg::with_name("ansi").long("ansi").multiple(true))
+        .arg(Arg::with_name("ansi-output").long("ansi-output").multiple(true))
+        .arg(Arg::with_name("strip-ansi-output").long("strip-ansi-output").multiple(true))
+        .arg(Arg::with_name("exact").long("exact").short('e').multiple(true))
+        .arg(Arg::with_name("cmd").long("cmd").short('c').multiple(true).takes_value(true))
+        .arg(Arg::with_name("exclude").long("exclude").multiple(true).takes_value(true))
+        .arg(Arg::with_name("cmd-cwd").long("cmd-cwd").multiple(true).takes_value(true))
+        .arg(Arg::with_name("cmd-env").long("cmd-env").multiple(true).takes_value(true))
+        .arg(Arg::with_name("interactive").long("interactive").short('i').multiple(true))
+        .arg(Arg::with_name("reuse-items-on-superset").long("reuse-items-on-superset").multiple(true))
+        .arg(Arg::with_name("split-prompt").long("split-prompt").multiple(true))
+        .arg(Arg::with_name("query").long("query").short('q').multiple(true).takes_value(true))
+        .arg(Arg::with_name("cmd-query").long("cmd-query").multiple(true).takes_value(true))
+        .arg(Arg::with_name("regex").long("regex").multiple(true))
+        .arg(Arg::with_name("delimiter").long("delimiter").short('d').multiple(true).takes_value(true))
+        .arg(Arg::with_name("nth").long("nth").short('n').multiple(true).takes_value(true))
+        .arg(Arg::with_name("with-nth").long("with-nth").multiple(true).takes_value(true))
+        .arg(Arg::with_name("profile").long("profile").multiple(true).takes_value(true))
+        .arg(Arg::with_name("no-config").long("no-config").multiple(true))
+        .arg(Arg::with_name("align").long("align").multiple(true))
+        .arg(Arg::with_name("replstr").short('I').multiple(true).takes_value(true))
+        .arg(Arg::with_name("color").long("color").multiple(true).takes_value(true))
+        .arg(Arg::with_name("reload-theme-on-sigusr1").long("reload-theme-on-sigusr1").multiple(true))
+        .arg(Arg::with_name("margin").long("margin").multiple(true).takes_value(true).default_value("0,0,0,0"))
+        .arg(Arg::with_name("min-height").long("min-height").multiple(true).takes_value(true).default_value("10"))
+        .arg(Arg::with_name("height").long("height").multiple(true).takes_value(true).default_value("100%"))
+        .arg(Arg::with_name("no-height").long("no-height").multiple(true))
+        .arg(Arg::with_name("no-clear").long("no-clear").multiple(true))
+        .arg(Arg::with_name("no-clear-start").long("no-clear-start").multiple(true))
+        .arg(Arg::with_name("no-mouse").long("no-mouse").multiple(true))
+        .arg(Arg::with_name("preview").long("preview").multiple(true).takes_value(true))
+        .arg(Arg::with_name("preview-window").long("preview-window").multiple(true).takes_value(true).default_value("right:50%"))
+        .arg(Arg::with_name("preview-keep-scroll").long("preview-keep-scroll").multiple(true))
+        .arg(Arg::with_name("preview-timeout").long("preview-timeout").takes_value(true).multiple(true))
+        .arg(Arg::with_name("preview-debug").long("preview-debug").multiple(true))
+        .arg(Arg::with_name("preview-rules").long("preview-rules").takes_value(true).multiple(true))
+        .arg(Arg::with_name("preview-builtin").long("preview-builtin").takes_value(true).multiple(true))
+        .arg(Arg::with_name("preview-shell-init").long("preview-shell-init").takes_value(true).multiple(true))
+        .arg(Arg::with_name("reverse").long("reverse").multiple(true))
+
+        .arg(Arg::with_name("algorithm").long("algo").multiple(true).takes_value(true).default_value("skim_v2"))
+        .arg(Arg::with_name("case").long("case").multiple(true).takes_value(true).default_value("smart"))
+        .arg(Arg::with_name("literal").long("literal").multiple(true))
+        .arg(Arg::with_name("path-mode").long("path-mode").multiple(true))
+        .arg(Arg::with_name("nth-concat").long("nth-concat").multiple(true))
+        .arg(Arg::with_name("match-display").long("match-display").multiple(true))
+        .arg(Arg::with_name("cycle").long("cycle").multiple(true))
+        .arg(Arg::with_name("no-hscroll").long("no-hscroll").multiple(true))
+        .arg(Arg::with_name("hscroll-off").long("hscroll-off").multiple(true).takes_value(true).default_value("10"))
+        .arg(Arg::with_name("filepath-word").long("filepath-word").multiple(true))
+        .arg(Arg::with_name("jump-labels").long("jump-labels").multiple(true).takes_value(true).default_value("abcdefghijklmnopqrstuvwxyz"))
+        .arg(Arg::with_name("border").long("border").multiple(true))
+        .arg(Arg::with_name("inline-info").long("inline-info").multiple(true))
+        .arg(Arg::with_name("no-info").long("no-info").multiple(true))
+        .arg(Arg::with_name("header").long("header").multiple(true).takes_value(true).default_value(""))
+        .arg(Arg::with_name("header-lines").long("header-lines").multiple(true).takes_value(true).default_value("0"))
+        .arg(Arg::with_name("tabstop").long("tabstop").multiple(true).takes_value(true).default_value("8"))
+        .arg(Arg::with_name("max-fps").long("max-fps").multiple(true).takes_value(true).default_value("30"))
+        .arg(Arg::with_name("no-bold").long("no-bold").multiple(true))
+        .arg(Arg::with_name("history").long("history").multiple(true).takes_value(true))
+        .arg(Arg::with_name("cmd-history").long("cmd-history").multiple(true).takes_value(true))
+        .arg(Arg::with_name("history-size").long("history-size").multiple(true).takes_value(true).default_value("1000"))
+        .arg(Arg::with_name("cmd-history-size").long("cmd-history-size").multiple(true).takes_value(true).default_value("1000"))
+        .arg(Arg::with_name("print-query").long("print-query").multiple(true))
+        .arg(Arg::with_name("print-cmd").long("print-cmd").multiple(true))
+        .arg(Arg::with_name("print-score").long("print-score").multiple(true))
+        .arg(Arg::with_name("print-positions").long("print-positions").multiple(true))
+        .arg(Arg::with_name("print-summary").long("print-summary").multiple(true))
+        .arg(Arg::with_name("summary-fd").long("summary-fd").multiple(true).takes_value(true))
+        .arg(Arg::with_name("notify-fd").long("notify-fd").multiple(true).takes_value(true))
+        .arg(Arg::with_name("read0").long("read0").multiple(true))
+        .arg(Arg::with_name("trim-trailing").long("trim-trailing").multiple(true))
+        .arg(Arg::with_name("skip-blank").long("skip-blank").multiple(true))
+        .arg(Arg::with_name("skip-pattern").long("skip-pattern").multiple(true).takes_value(true))
+        .arg(Arg::with_name("print0").long("print0").multiple(true))
+        .arg(Arg::with_name("output-delimiter").long("output-delimiter").multiple(true).takes_value(true))
+        .arg(Arg::with_name("no-buffer").long("no-buffer").multiple(true))
+        .arg(Arg::with_name("sync").long("sync").multiple(true))
+        .arg(Arg::with_name("extended").long("extended").short('x').multiple(true))
+        .arg(Arg::with_name("no-sort").long("no-sort").multiple(true))
+        .arg(Arg::with_name("follow").long("follow").multiple(true))
+        .arg(Arg::with_name("select-1").long("select-1").short('1').multiple(true))
+        .arg(Arg::with_name("exit-0").long("exit-0").short('0').multiple(true))
+        .arg(Arg::with_name("accept-nth").long("accept-nth").takes_value(true).multiple(true))
+        .arg(Arg::with_name("filter").long("filter").short('f').takes_value(true).multiple(true))
+        .arg(Arg::with_name("layout").long("layout").multiple(true).takes_value(true).default_value("default"))
+        .arg(Arg::with_name("keep-right").long("keep-right").multiple(true))
+        .arg(Arg::with_name("skip-to-pattern").long("skip-to-pattern").multiple(true).takes_value(true).default_value(""))
+        .arg(Arg::with_name("pre-select-n").long("pre-select-n").multiple(true).takes_value(true).default_value("0"))
+        .arg(Arg::with_name("pre-select-pat").long("pre-select-pat").multiple(true).takes_value(true).default_value(""))
+        .arg(Arg::with_name("pre-select-items").long("pre-select-items").multiple(true).takes_value(true))
+        .arg(Arg::with_name("pre-select-file").long("pre-select-file").multiple(true).takes_value(true).default_value(""))
+        .arg(Arg::with_name("pre-select-last-accepted").long("pre-select-last-accepted").multiple(true).takes_value(true))
+        .arg(Arg::with_name("no-clear-if-empty").long("no-clear-if-empty").multiple(true))
+        .arg(Arg::with_name("show-cmd-error").long("show-cmd-error").multiple(true))
+        .arg(Arg::with_name("drill-cmd").long("drill-cmd").multiple(true).takes_value(true))
+        .arg(Arg::with_name("line-numbers").long("line-numbers").multiple(true).takes_value(true))
+        .arg(Arg::with_name("icons").long("icons").multiple(true).takes_value(true))
+        .arg(Arg::with_name("scrollbar").long("scrollbar").multiple(true).takes_value(true).min_values(0).require_equals(true))
+        .arg(Arg::with_name("highlight-all").long("highlight-all").multiple(true))
+        .arg(Arg::with_name("compact-match").long("compact-match").multiple(true))
+        .arg(Arg::with_name("no-unicode").long("no-unicode").multiple(true))
+        .arg(Arg::with_name("bench-matcher").long("bench-matcher").number_of_values(2).value_names(&["FILE", "QUERY"]))
+}
+
+fn parse_options(options: &ArgMatches) -> Result<SkimOptions<'_>, SkimOptionsBuilderError> {
     SkimOptionsBuilder::default()
         .color(options.values_of("color").and_then(|vals| vals.last()))
+        .reload_theme_on_sigusr1(options.is_present("reload-theme-on-sigusr1"))
         .min_height(options.values_of("min-height").and_then(|vals| vals.last()))
-        .no_height(options.is_present("no-height"))
+        .no_height(no_height(options))
         .height(options.values_of("height").and_then(|vals| vals.last()))
         .margin(options.values_of("margin").and_then(|vals| vals.last()))
         .preview(options.values_of("preview").and_then(|vals| vals.last()))
         .cmd(options.values_of("cmd").and_then(|vals| vals.last()))
+        .exclude(options.values_of("exclude").and_then(|vals| vals.last()))
+        .cmd_cwd(options.values_of("cmd-cwd").and_then(|vals| vals.last()))
+        .drill_cmd(options.values_of("drill-cmd").and_then(|vals| vals.last()))
+        .cmd_env(
+            options
+                .values_of("cmd-env")
+                .map(|x| x.collect::<Vec<_>>())
+                .unwrap_or_default(),
+        )
         .query(options.values_of("query").and_then(|vals| vals.last()))
         .cmd_query(options.values_of("cmd-query").and_then(|vals| vals.last()))
         .interactive(options.is_present("interactive"))
+        .reuse_items_on_superset(options.is_present("reuse-items-on-superset"))
+        .split_prompt(options.is_present("split-prompt"))
+        .filepath_word(options.is_present("filepath-word"))
         .prompt(options.values_of("prompt").and_then(|vals| vals.last()))
         .cmd_prompt(options.values_of("cmd-prompt").and_then(|vals| vals.last()))
         .bind(
@@ -406,11 +702,18 @@ fn parse_options(options: &ArgMatches) -> SkimOptions<'_> {
                 .unwrap_or_default(),
         )
         .expect(options.values_of("expect").map(|x| x.collect::<Vec<_>>().join(",")))
+        .expect_hint(options.is_present("expect-hint"))
         .multi(if options.is_present("no-multi") {
             false
         } else {
-            options.is_present("multi")
+            options.is_present("multi") || options.is_present("multi-max")
         })
+        .multi_max(
+            options
+                .values_of("multi-max")
+                .and_then(|vals| vals.last())
+                .and_then(|s| s.parse().ok()),
+        )
         .layout(options.values_of("layout").and_then(|vals| vals.last()).unwrap_or(""))
         .reverse(options.is_present("reverse"))
         .no_hscroll(options.is_present("no-hscroll"))
@@ -418,13 +721,43 @@ fn parse_options(options: &ArgMatches) -> SkimOptions<'_> {
         .no_clear(options.is_present("no-clear"))
         .no_clear_start(options.is_present("no-clear-start"))
         .tabstop(options.values_of("tabstop").and_then(|vals| vals.last()))
+        .max_fps(
+            options
+                .values_of("max-fps")
+                .and_then(|vals| vals.last())
+                .and_then(|s| s.parse::<usize>().ok())
+                .unwrap_or(30),
+        )
+        .notify_fd(
+            options
+                .values_of("notify-fd")
+                .and_then(|vals| vals.last())
+                .and_then(|fd| fd.parse().ok()),
+        )
         .tiebreak(options.values_of("tiebreak").map(|x| x.collect::<Vec<_>>().join(",")))
         .tac(options.is_present("tac"))
         .nosort(options.is_present("no-sort"))
+        .follow(options.is_present("follow"))
         .exact(options.is_present("exact"))
+        .literal(options.is_present("literal"))
+        .path_mode(options.is_present("path-mode"))
+        .nth_concat(options.is_present("nth-concat"))
+        .match_display(options.is_present("match-display"))
         .regex(options.is_present("regex"))
         .delimiter(options.values_of("delimiter").and_then(|vals| vals.last()))
         .inline_info(options.is_present("inline-info"))
+        .no_info(options.is_present("no-info"))
+        .preview_keep_scroll(options.is_present("preview-keep-scroll"))
+        .preview_shell_init(options.values_of("preview-shell-init").and_then(|vals| vals.last()))
+        .preview_timeout(
+            options
+                .values_of("preview-timeout")
+                .and_then(|vals| vals.last())
+                .and_then(|s| s.parse().ok()),
+        )
+        .preview_debug(options.is_present("preview-debug"))
+        .preview_rules(options.values_of("preview-rules").and_then(|vals| vals.last()))
+        .preview_builtin(options.values_of("preview-builtin").and_then(|vals| vals.last()))
         .header(options.values_of("header").and_then(|vals| vals.last()))
         .header_lines(
             options
@@ -451,10 +784,28 @@ fn parse_options(options: &ArgMatches) -> SkimOptions<'_> {
         )
         .select1(options.is_present("select-1"))
         .exit0(options.is_present("exit-0"))
+        .accept_nth(
+            options
+                .values_of("accept-nth")
+                .and_then(|vals| vals.last())
+                .and_then(|s| s.parse().ok()),
+        )
         .sync(options.is_present("sync"))
         .no_clear_if_empty(options.is_present("no-clear-if-empty"))
+        .line_numbers(options.values_of("line-numbers").and_then(|vals| vals.last()))
+        .icons(options.values_of("icons").and_then(|vals| vals.last()))
+        .scrollbar(options.values_of("scrollbar").map(|vals| vals.last().unwrap_or("")))
+        .highlight_all(options.is_present("highlight-all"))
+        .compact_match(options.is_present("compact-match"))
+        .no_unicode(options.is_present("no-unicode"))
         .build()
-        .unwrap()
+}
+
+/// the file descriptor if it is open
+fn parse_open_fd(fd: &str) -> Option<RawFd> {
+    fd.parse()
+        .ok()
+        .filter(|&fd| fd >= 0 && fcntl(fd, FcntlArg::F_GETFD).is_ok())
 }
 
 fn read_file_lines(filename: &str) -> Result<Vec<String>, std::io::Error> {
@@ -494,21 +845,296 @@ fn write_history_to_file(
 pub struct BinOptions<'a> {
     filter: Option<&'a str>,
     output_ending: &'a str,
+    // joins the items instead of `output_ending`
+    output_delimiter: Option<&'a str>,
     print_query: bool,
     print_cmd: bool,
+    print_positions: bool,
+    summary_fd: Option<RawFd>,
+    start_time: Instant,
+}
+
+/// the capabilities of skim as JSON, for the wrapper scripts to detect what this version supports
+fn capabilities() -> String {
+    fn json_string(text: &str) -> String {
+        let mut ret = String::with_capacity(text.len() + 2);
+        ret.push('"');
+        for ch in text.chars() {
+            match ch {
+                '"' => ret.push_str("\\\""),
+                '\\' => ret.push_str("\\\\"),
+                '\n' => ret.push_str("\\n"),
+                '\r' => ret.push_str("\\r"),
+                '\t' => ret.push_str("\\t"),
+                ch if (ch as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", ch as u32)),
+                ch => ret.push(ch),
+            }
+        }
+        ret.push('"');
+        ret
+    }
+
+    fn json_array<T: AsRef<str>>(values: impl IntoIterator<Item = T>) -> String {
+        let values: Vec<String> = values.into_iter().map(|value| json_string(value.as_ref())).collect();
+        format!("[{}]", values.join(","))
+    }
+
+    let features = [
+        ("cli", cfg!(feature = "cli")),
+        ("config", cfg!(feature = "config")),
+        ("highlight", cfg!(feature = "highlight")),
+        ("simd", cfg!(feature = "simd")),
+        ("tokio", cfg!(feature = "tokio")),
+    ];
+    let features = features.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| name);
+
+    format!(
+        r#"{{"version":"{}","features":{},"algorithms":{},"actions":{},"keys":{}}}"#,
+        crate_version!(),
+        json_array(features),
+        json_array(FuzzyAlgorithm::names()),
+        json_array(action_names()),
+        json_array(key_names())
+    )
+}
+
+/// The names of the options in the arguments with their values, told apart as clap does: an
+/// option taking values takes the arguments after it up to the next option, e.g. the `-q` of
+/// `-q --profile` has no value and `--profile` is an option.
+fn scan_options<'a>(args: impl Iterator<Item = &'a String>) -> Vec<(&'static str, Option<String>)> {
+    let app = app();
+    let mut options = Vec::new();
+    // the option that takes the next arguments as its values
+    let mut taking_values = None;
+    for arg in args {
+        if arg == "--" {
+            break;
+        }
+
+        let (option, value) = if let Some(long) = arg.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            (
+                app.get_arguments().find(|option| option.get_long() == Some(name)),
+                value,
+            )
+        } else if let Some(shorts) = arg.strip_prefix('-').filter(|shorts| !shorts.is_empty()) {
+            // the flags could be clustered, the first option taking a value takes the rest
+            let mut found = None;
+            for (index, ch) in shorts.char_indices() {
+                match app.get_arguments().find(|option| option.get_short() == Some(ch)) {
+                    Some(option) if option.is_takes_value_set() => {
+                        let rest = &shorts[index + ch.len_utf8()..];
+                        let rest = rest.strip_prefix('=').unwrap_or(rest);
+                        found = Some((Some(option), Some(rest.to_string()).filter(|rest| !rest.is_empty())));
+                        break;
+                    }
+                    Some(option) => options.push((option.get_id(), None)),
+                    None => {}
+                }
+            }
+            found.unwrap_or((None, None))
+        } else {
+            if let Some(name) = taking_values {
+                options.push((name, Some(arg.to_string())));
+            }
+            continue;
+        };
+
+        taking_values = None;
+        if let Some(option) = option {
+            if option.is_takes_value_set() && value.is_none() && !option.is_require_equals_set() {
+                taking_values = Some(option.get_id());
+            } else {
+                options.push((option.get_id(), value));
+            }
+        }
+    }
+    options
+}
+
+/// the value of the last `--profile NAME` or `--profile=NAME` in the arguments
+fn profile_name<'a>(args: impl Iterator<Item = &'a String>) -> Option<String> {
+    scan_options(args)
+        .into_iter()
+        .filter(|(name, _)| *name == "profile")
+        .filter_map(|(_, value)| value)
+        .last()
+}
+
+/// `$XDG_CONFIG_HOME/skim` or `~/.config/skim`
+fn config_dir() -> Option<PathBuf> {
+    match (env::var_os("XDG_CONFIG_HOME"), env::var_os("HOME")) {
+        (Some(config_home), _) if !config_home.is_empty() => Some(PathBuf::from(config_home).join("skim")),
+        (_, Some(home)) => Some(PathBuf::from(home).join(".config").join("skim")),
+        _ => None,
+    }
+}
+
+/// read the config file `$SKIM_CONFIG`, or `~/.config/skim/config.toml` or `~/.config/skim/config`
+/// if it exists. A file named `*.toml` is read as `SkimConfig`, the others have the options as on
+/// the command line.
+fn read_config() -> Result<(SkimConfig, Vec<String>), String> {
+    let path = match env::var_os("SKIM_CONFIG") {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => match config_dir() {
+            Some(dir) if dir.join("config.toml").exists() => dir.join("config.toml"),
+            Some(dir) if dir.join("config").exists() => dir.join("config"),
+            _ => return Ok((SkimConfig::default(), Vec::new())),
+        },
+    };
+
+    if path.extension().map_or(false, |ext| ext == "toml") {
+        let content = std::fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let config = SkimConfig::from_toml(&content).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Ok((config, Vec::new()))
+    } else {
+        Ok((SkimConfig::default(), read_options_file(&path)?))
+    }
+}
+
+/// read the options in `~/.config/skim/NAME.conf`
+fn read_profile(name: &str) -> Result<Vec<String>, String> {
+    if name.is_empty() || name.contains('/') {
+        return Err("invalid profile name".to_string());
+    }
+
+    let config_dir = config_dir().ok_or_else(|| "neither XDG_CONFIG_HOME nor HOME is set".to_string())?;
+    read_options_file(&config_dir.join(format!("{}.conf", name)))
+}
+
+/// read the options in the file, which has one or more options per line, quoted like in the
+/// shell, e.g. `--bind 'ctrl-y:execute(echo {})'`. Blank lines and lines starting with `#` are
+/// ignored.
+fn read_options_file(path: &Path) -> Result<Vec<String>, String> {
+    let content = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+    let mut args = Vec::new();
+    for (line_num, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line_args =
+            shlex::split(line).ok_or_else(|| format!("{}:{}: unbalanced quotes", path.display(), line_num + 1))?;
+        args.extend(line_args);
+    }
+    Ok(args)
+}
+
+/// write the `index`th item of the output, the items are joined by `--output-delimiter` if given
+fn write_item(stdout: &mut Output, bin_option: &BinOptions, index: usize, item: &str) -> std::io::Result<()> {
+    match bin_option.output_delimiter {
+        Some(delimiter) if index > 0 => write!(stdout, "{}{}", delimiter, item),
+        Some(_) => write!(stdout, "{}", item),
+        None => write!(stdout, "{}{}", item, bin_option.output_ending),
+    }
+}
+
+/// end the line of the items joined by `--output-delimiter`
+fn end_items(stdout: &mut Output, bin_option: &BinOptions, num_items: usize) -> std::io::Result<()> {
+    if bin_option.output_delimiter.is_some() && num_items > 0 {
+        write!(stdout, "{}", bin_option.output_ending)?;
+    }
+    Ok(())
+}
+
+/// whether `--no-height` is given after the last `--height`, so that the command line overrides the
+/// height of SKIM_DEFAULT_OPTIONS and the other way round
+fn no_height(options: &ArgMatches) -> bool {
+    let last_index = |name| options.indices_of(name).and_then(Iterator::max);
+    match (last_index("no-height"), last_index("height")) {
+        (Some(no_height), Some(height)) if options.occurrences_of("height") > 0 => no_height > height,
+        (no_height, _) => no_height.is_some(),
+    }
+}
+
+/// write the summary record, e.g. `selected=1 matched=42 elapsed_ms=1024`, to `--summary-fd`
+fn print_summary(bin_option: &BinOptions, num_selected: usize, num_matched: usize) {
+    if let Some(fd) = bin_option.summary_fd {
+        let summary = format!(
+            "selected={} matched={} elapsed_ms={}\n",
+            num_selected,
+            num_matched,
+            bin_option.start_time.elapsed().as_millis()
+        );
+        // the fd is owned by the caller, write to it without closing
+        let _ = nix::unistd::write(fd, summary.as_bytes());
+    }
+}
+
+/// run every fuzzy algorithm against the lines of the file and report the timings
+fn bench_matcher(
+    stdout: &mut Output,
+    mut options: SkimOptions,
+    reader: &SkimItemReader,
+    filename: &str,
+    query: &str,
+) -> Result<i32, std::io::Error> {
+    const ROUNDS: u32 = 5;
+    let file = File::open(filename)?;
+    let items: Vec<Arc<dyn SkimItem>> = reader.of_bufread(BufReader::new(file)).iter().collect();
+
+    writeln!(stdout, "items: {}, query: {:?}, rounds: {}", items.len(), query, ROUNDS)?;
+    writeln!(
+        stdout,
+        "{:<10}{:>10}{:>12}{:>12}",
+        "algo", "matched", "min(ms)", "avg(ms)"
+    )?;
+
+    for algorithm in FuzzyAlgorithm::ALL {
+        // the engine of the interactive mode, e.g. with `--exact` or `--tiebreak`
+        options.algorithm = algorithm;
+        let engine = Skim::engine_factory(&options).create_engine_with_case(query, options.case);
+
+        let mut matched = 0;
+        let mut min_elapsed = std::time::Duration::MAX;
+        let mut total_elapsed = std::time::Duration::ZERO;
+        for _ in 0..ROUNDS {
+            let start = Instant::now();
+            matched = items
+                .iter()
+                .filter(|item| engine.match_item((*item).clone()).is_some())
+                .count();
+            let elapsed = start.elapsed();
+            min_elapsed = min_elapsed.min(elapsed);
+            total_elapsed += elapsed;
+        }
+
+        writeln!(
+            stdout,
+            "{:<10}{:>10}{:>12.2}{:>12.2}",
+            algorithm.name(),
+            matched,
+            min_elapsed.as_secs_f64() * 1000.0,
+            total_elapsed.as_secs_f64() * 1000.0 / ROUNDS as f64
+        )?;
+    }
+
+    Ok(0)
+}
+
+/// whether stdin is a pipe, file or socket to read items from, rather than e.g. `/dev/null` or a
+/// closed descriptor
+fn stdin_is_readable() -> bool {
+    match fstat(0) {
+        Ok(stat) => {
+            let file_type = SFlag::from_bits_truncate(stat.st_mode) & SFlag::S_IFMT;
+            file_type == SFlag::S_IFIFO || file_type == SFlag::S_IFREG || file_type == SFlag::S_IFSOCK
+        }
+        Err(_) => false,
+    }
 }
 
 pub fn filter(
+    stdout: &mut Output,
     bin_option: &BinOptions,
     options: &SkimOptions,
     source: Option<SkimItemReceiver>,
 ) -> Result<i32, std::io::Error> {
-    let mut stdout = std::io::stdout();
-
-    let default_command = match env::var("SKIM_DEFAULT_COMMAND").as_ref().map(String::as_ref) {
-        Ok("") | Err(_) => "find .".to_owned(),
-        Ok(val) => val.to_owned(),
-    };
+    let default_command = options.default_command();
     let query = bin_option.filter.unwrap_or("");
     let cmd = options.cmd.unwrap_or(&default_command);
 
@@ -529,8 +1155,15 @@ pub fn filter(
         let fuzzy_engine_factory = ExactOrFuzzyEngineFactory::builder()
             .fuzzy_algorithm(options.algorithm)
             .exact_mode(options.exact)
+            .literal(options.literal)
+            .path_mode(options.path_mode)
+            .concat_fields(options.nth_concat)
             .build();
-        Box::new(AndOrEngineFactory::new(fuzzy_engine_factory))
+        if options.literal {
+            Box::new(fuzzy_engine_factory)
+        } else {
+            Box::new(AndOrEngineFactory::new(fuzzy_engine_factory))
+        }
     };
 
     let engine = engine_factory.create_engine_with_case(query, options.case);
@@ -541,18 +1174,153 @@ pub fn filter(
 
     let stream_of_item = source.unwrap_or_else(|| {
         let cmd_collector = options.cmd_collector.clone();
-        let (ret, _control) = cmd_collector.borrow_mut().invoke(cmd, components_to_stop);
+        let (ret, _control) = cmd_collector.lock().unwrap().invoke(cmd, components_to_stop);
         ret
     });
 
     let mut num_matched = 0;
-    stream_of_item
+    let matched_items = stream_of_item
         .into_iter()
-        .filter_map(|item| engine.match_item(item.clone()).map(|result| (item, result)))
-        .try_for_each(|(item, _match_result)| {
-            num_matched += 1;
-            write!(stdout, "{}{}", item.output(), bin_option.output_ending)
-        })?;
+        .filter_map(|item| engine.match_item(item.clone()).map(|result| (item, result)));
+    for (item, match_result) in matched_items {
+        // no need to match the rest once the reader of the output is gone
+        if stdout.is_closed() {
+            break;
+        }
+        if bin_option.print_positions {
+            // the positions are of the matched text, which is printed instead of the output, as
+            // they differ with e.g. `--with-nth`
+            let text = item.text();
+            let positions: Vec<String> = match_result
+                .range_byte_ranges(&text)
+                .into_iter()
+                .map(|(start, end)| format!("{}:{}", start, end))
+                .collect();
+            let line = format!("{}\t{}", text, positions.join(","));
+            write_item(stdout, bin_option, num_matched, &line)?;
+        } else {
+            write_item(stdout, bin_option, num_matched, &item.output_fields().join("\t"))?;
+        }
+        num_matched += 1;
+    }
+    end_items(stdout, bin_option, num_matched)?;
+
+    stdout.flush()?;
+
+    print_summary(bin_option, num_matched, num_matched);
 
     Ok(if num_matched == 0 { 1 } else { 0 })
 }
+
+/// The standard output of sk. The output is buffered unless `--no-buffer` is given. Once the
+/// reader of the pipe is gone(EPIPE), the rest of the output is dropped silently instead of
+/// failing every write.
+pub struct Output {
+    stdout: BufWriter<std::io::Stdout>,
+    unbuffered: bool,
+    closed: bool,
+}
+
+impl Output {
+    fn new(unbuffered: bool) -> Self {
+        Self {
+            stdout: BufWriter::new(std::io::stdout()),
+            unbuffered,
+            closed: false,
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    fn ignore_broken_pipe<T>(&mut self, result: std::io::Result<T>, closed_value: T) -> std::io::Result<T> {
+        match result {
+            Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => {
+                self.closed = true;
+                Ok(closed_value)
+            }
+            result => result,
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.closed {
+            return Ok(buf.len());
+        }
+        let written = self.stdout.write(buf);
+        let written = self.ignore_broken_pipe(written, buf.len())?;
+        if self.unbuffered {
+            self.flush()?;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        let flushed = self.stdout.flush();
+        self.ignore_broken_pipe(flushed, ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_name() {
+        let profile = |args: &[&str]| profile_name(args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().iter());
+        assert_eq!(profile(&["--profile", "a", "--profile=b"]), Some("b".to_string()));
+        assert_eq!(profile(&["-m", "--profile", "a"]), Some("a".to_string()));
+        assert_eq!(profile(&["--query", "--profile"]), None);
+        assert_eq!(
+            profile(&["--query=x", "--bind", "ctrl-a:accept", "--profile", "a"]),
+            Some("a".to_string())
+        );
+        assert_eq!(profile(&["-q", "--profile=a"]), Some("a".to_string()));
+        assert_eq!(profile(&["-mq", "--profile", "a"]), Some("a".to_string()));
+        assert_eq!(profile(&["-q--profile", "a"]), None);
+        assert_eq!(profile(&["--profile", "a", "b"]), Some("b".to_string()));
+        assert_eq!(profile(&["--", "--profile", "a"]), None);
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let capabilities = capabilities();
+        assert!(capabilities.contains(r#""algorithms":["skim_v1","skim_v2","clangd"]"#));
+        assert!(capabilities.contains(r#""ctrl-a""#));
+
+        let features = capabilities
+            .split(r#""features":"#)
+            .nth(1)
+            .unwrap()
+            .split(']')
+            .next()
+            .unwrap();
+        for (feature, enabled) in [
+            ("cli", cfg!(feature = "cli")),
+            ("config", cfg!(feature = "config")),
+            ("highlight", cfg!(feature = "highlight")),
+            ("simd", cfg!(feature = "simd")),
+            ("tokio", cfg!(feature = "tokio")),
+        ] {
+            assert_eq!(enabled, features.contains(&format!(r#""{}""#, feature)), "{}", feature);
+        }
+    }
+
+    #[test]
+    fn test_no_config() {
+        let no_config = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            scan_options(args.iter()).iter().any(|(name, _)| *name == "no-config")
+        };
+        assert!(no_config(&["-m", "--no-config"]));
+        assert!(no_config(&["--query", "a", "--no-config"]));
+        assert!(!no_config(&["--bind", "ctrl-a:accept", "--query=--no-config"]));
+        assert!(!no_config(&["-q--no-config"]));
+    }
+}
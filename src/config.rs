@@ -0,0 +1,131 @@
+//! The options of a TOML config file, e.g. `~/.config/skim/config.toml`, applied to
+//! `SkimOptions` by `SkimOptions::merge_config`. The keys are the long names of the options:
+//!
+//! ```toml
+//! multi = true
+//! layout = "reverse"
+//! preview = "cat {}"
+//! bind = ["ctrl-y:execute-silent(echo {} | pbcopy)", "ctrl-a:select-all"]
+//! ```
+use serde::Deserialize;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SkimConfig {
+    pub bind: Vec<String>,
+    pub color: Option<String>,
+    pub delimiter: Option<String>,
+    pub exact: Option<bool>,
+    pub header: Option<String>,
+    pub height: Option<String>,
+    pub inline_info: Option<bool>,
+    pub layout: Option<String>,
+    pub margin: Option<String>,
+    pub multi: Option<bool>,
+    pub no_hscroll: Option<bool>,
+    pub no_mouse: Option<bool>,
+    pub no_sort: Option<bool>,
+    pub preview: Option<String>,
+    pub preview_window: Option<String>,
+    pub prompt: Option<String>,
+    pub regex: Option<bool>,
+    pub tac: Option<bool>,
+    pub tiebreak: Option<String>,
+}
+
+impl SkimConfig {
+    /// Parse the content of a config file, the unknown keys and the values of a wrong type are
+    /// errors.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|err| err.to_string().trim_end().to_string())
+    }
+
+    /// Drop the value of the config file that the command line option `name`(the long name) sets,
+    /// so that the option takes precedence. The bindings are kept, those of the option are bound
+    /// after them.
+    pub fn forget(&mut self, name: &str) {
+        match name {
+            "color" => self.color = None,
+            "delimiter" => self.delimiter = None,
+            "exact" => self.exact = None,
+            "header" => self.header = None,
+            "height" | "no-height" => self.height = None,
+            "inline-info" => self.inline_info = None,
+            "layout" | "reverse" => self.layout = None,
+            "margin" => self.margin = None,
+            "multi" | "no-multi" | "multi-max" => self.multi = None,
+            "no-hscroll" => self.no_hscroll = None,
+            "no-mouse" => self.no_mouse = None,
+            "no-sort" => self.no_sort = None,
+            "preview" => self.preview = None,
+            "preview-window" => self.preview_window = None,
+            "prompt" => self.prompt = None,
+            "regex" => self.regex = None,
+            "tac" => self.tac = None,
+            "tiebreak" => self.tiebreak = None,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::SkimOptionsBuilder;
+
+    #[test]
+    fn test_from_toml() {
+        let config = SkimConfig::from_toml(
+            r#"
+            # comments are allowed
+            multi = true
+            preview-window = "down:40%"
+            bind = ["ctrl-y:execute(echo {})"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            SkimConfig {
+                multi: Some(true),
+                preview_window: Some("down:40%".to_string()),
+                bind: vec!["ctrl-y:execute(echo {})".to_string()],
+                ..SkimConfig::default()
+            },
+            config
+        );
+        assert_eq!(Ok(SkimConfig::default()), SkimConfig::from_toml(""));
+
+        assert!(SkimConfig::from_toml("mutli = true")
+            .unwrap_err()
+            .contains("unknown field `mutli`"));
+        assert!(SkimConfig::from_toml("multi = \"yes\"").is_err());
+        assert!(SkimConfig::from_toml("bind = \"ctrl-y:accept\"").is_err());
+    }
+
+    #[test]
+    fn test_merge_config() {
+        let config =
+            SkimConfig::from_toml("multi = true\nprompt = '$ '\nbind = ['ctrl-y:accept', 'ctrl-a:up']").unwrap();
+        let options = SkimOptionsBuilder::default()
+            .bind(vec!["ctrl-y:abort"])
+            .layout("reverse")
+            .build()
+            .unwrap()
+            .merge_config(&config);
+        assert!(options.multi);
+        assert_eq!(Some("$ "), options.prompt);
+        assert_eq!("reverse", options.layout);
+        assert_eq!(vec!["ctrl-y:accept", "ctrl-a:up", "ctrl-y:abort"], options.bind);
+    }
+
+    #[test]
+    fn test_forget() {
+        let mut config = SkimConfig::from_toml("multi = true\nlayout = 'reverse'\nbind = ['ctrl-y:accept']").unwrap();
+        config.forget("no-multi");
+        config.forget("reverse");
+        config.forget("bind");
+        assert_eq!(None, config.multi);
+        assert_eq!(None, config.layout);
+        assert_eq!(vec!["ctrl-y:accept".to_string()], config.bind);
+    }
+}
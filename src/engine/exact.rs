@@ -1,8 +1,7 @@
-use crate::engine::util::{contains_upper, regex_match};
+use crate::engine::util::{char_boundary_range, regex_match};
 use crate::item::RankBuilder;
 use crate::{CaseMatching, MatchEngine, MatchRange, MatchResult, SkimItem};
 use regex::{escape, Regex};
-use std::cmp::min;
 use std::fmt::{Display, Error, Formatter};
 use std::sync::Arc;
 
@@ -28,11 +27,7 @@ pub struct ExactEngine {
 
 impl ExactEngine {
     pub fn builder(query: &str, param: ExactMatchingParam) -> Self {
-        let case_sensitive = match param.case {
-            CaseMatching::Respect => true,
-            CaseMatching::Ignore => false,
-            CaseMatching::Smart => contains_upper(query),
-        };
+        let case_sensitive = param.case.is_sensitive(query);
 
         let mut query_builder = String::new();
         if !case_sensitive {
@@ -79,8 +74,7 @@ impl MatchEngine for ExactEngine {
         let item_text = item.text();
         let default_range = [(0, item_text.len())];
         for &(start, end) in item.get_matching_ranges().unwrap_or(&default_range) {
-            let start = min(start, item_text.len());
-            let end = min(end, item_text.len());
+            let (start, end) = char_boundary_range(&item_text, start, end);
             if self.query_regex.is_none() {
                 matched_result = Some((0, 0));
                 break;
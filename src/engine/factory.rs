@@ -6,6 +6,7 @@ use crate::engine::regexp::RegexEngine;
 use crate::item::RankBuilder;
 use crate::{CaseMatching, MatchEngine, MatchEngineFactory};
 use regex::Regex;
+use std::borrow::Cow;
 use std::sync::Arc;
 
 lazy_static! {
@@ -16,6 +17,9 @@ lazy_static! {
 // Exact engine factory
 pub struct ExactOrFuzzyEngineFactory {
     exact_mode: bool,
+    literal: bool,
+    path_mode: bool,
+    concat_fields: bool,
     fuzzy_algorithm: FuzzyAlgorithm,
     rank_builder: Arc<RankBuilder>,
 }
@@ -24,6 +28,9 @@ impl ExactOrFuzzyEngineFactory {
     pub fn builder() -> Self {
         Self {
             exact_mode: false,
+            literal: false,
+            path_mode: false,
+            concat_fields: false,
             fuzzy_algorithm: FuzzyAlgorithm::SkimV2,
             rank_builder: Default::default(),
         }
@@ -34,6 +41,25 @@ impl ExactOrFuzzyEngineFactory {
         self
     }
 
+    /// treat the whole query as literal text, i.e. `'`, `^`, `$` and `!` have no special meaning
+    /// NOTE: `AndOrEngineFactory` should not be used to split the query in literal mode
+    pub fn literal(mut self, literal: bool) -> Self {
+        self.literal = literal;
+        self
+    }
+
+    /// treat the items as paths in fuzzy matching
+    pub fn path_mode(mut self, path_mode: bool) -> Self {
+        self.path_mode = path_mode;
+        self
+    }
+
+    /// match the fields of `--nth` as a whole in fuzzy matching
+    pub fn concat_fields(mut self, concat_fields: bool) -> Self {
+        self.concat_fields = concat_fields;
+        self
+    }
+
     pub fn fuzzy_algorithm(mut self, fuzzy_algorithm: FuzzyAlgorithm) -> Self {
         self.fuzzy_algorithm = fuzzy_algorithm;
         self
@@ -58,32 +84,29 @@ impl MatchEngineFactory for ExactOrFuzzyEngineFactory {
         // !^abc => items not starting with "abc"
         // !abc$ => items not ending with "abc"
         // !^abc$ => not "abc"
+        //
+        // The modifiers apply to this term only, so every alternative of an OR group carries its
+        // own: `'` flips the term between fuzzy and exact relative to `--exact`, `!` may come
+        // before or after it, and inverse or anchored terms are always exact.
 
         let mut query = query;
-        let mut exact = false;
+        let mut quoted = false;
         let mut param = ExactMatchingParam::default();
         param.case = case;
 
-        if query.starts_with('\'') {
-            if self.exact_mode {
-                return Box::new(
-                    FuzzyEngine::builder()
-                        .query(&query[1..])
-                        .algorithm(self.fuzzy_algorithm)
-                        .case(case)
-                        .rank_builder(self.rank_builder.clone())
-                        .build(),
-                );
-            } else {
-                exact = true;
-                query = &query[1..];
-            }
+        if self.literal {
+            return self.build_single(query, self.exact_mode, param);
         }
 
-        if query.starts_with('!') {
+        loop {
+            if !quoted && query.starts_with('\'') {
+                quoted = true;
+            } else if !param.inverse && query.starts_with('!') {
+                param.inverse = true;
+            } else {
+                break;
+            }
             query = &query[1..];
-            exact = true;
-            param.inverse = true;
         }
 
         if query.is_empty() {
@@ -97,21 +120,28 @@ impl MatchEngineFactory for ExactOrFuzzyEngineFactory {
 
         if query.starts_with('^') {
             query = &query[1..];
-            exact = true;
             param.prefix = true;
         }
 
         if query.ends_with('$') {
             query = &query[..(query.len() - 1)];
-            exact = true;
             param.postfix = true;
         }
 
-        if self.exact_mode {
-            exact = true;
-        }
+        let exact = param.inverse || param.prefix || param.postfix || (self.exact_mode != quoted);
+        self.build_single(query, exact, param)
+    }
+}
 
-        if exact {
+impl ExactOrFuzzyEngineFactory {
+    fn build_single(&self, query: &str, exact: bool, param: ExactMatchingParam) -> Box<dyn MatchEngine> {
+        if query.is_empty() {
+            Box::new(
+                MatchAllEngine::builder()
+                    .rank_builder(self.rank_builder.clone())
+                    .build(),
+            )
+        } else if exact {
             Box::new(
                 ExactEngine::builder(query, param)
                     .rank_builder(self.rank_builder.clone())
@@ -122,7 +152,9 @@ impl MatchEngineFactory for ExactOrFuzzyEngineFactory {
                 FuzzyEngine::builder()
                     .query(query)
                     .algorithm(self.fuzzy_algorithm)
-                    .case(case)
+                    .case(param.case)
+                    .path_mode(self.path_mode)
+                    .concat_fields(self.concat_fields)
                     .rank_builder(self.rank_builder.clone())
                     .build(),
             )
@@ -196,6 +228,22 @@ impl MatchEngineFactory for AndOrEngineFactory {
     fn create_engine_with_case(&self, query: &str, case: CaseMatching) -> Box<dyn MatchEngine> {
         self.parse_or(query, case)
     }
+
+    // the terms are separated by any number of spaces, except the escaped ones
+    fn normalize_query<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        let masked = self.mask_escape_space(query);
+        let normalized = masked
+            .split(' ')
+            .filter(|term| !term.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if normalized.is_empty() {
+            // a query of spaces is passed to the inner factory as is
+            Cow::Borrowed(query)
+        } else {
+            Cow::Owned(normalized.replace('\0', "\\ "))
+        }
+    }
 }
 
 //------------------------------------------------------------------------------
@@ -259,6 +307,17 @@ mod test {
         let x = exact_or_fuzzy.create_engine("!^abc$");
         assert_eq!(format!("{}", x), "(Exact|!(?i)^abc$)");
 
+        let literal = ExactOrFuzzyEngineFactory::builder().literal(true).build();
+        let x = literal.create_engine("!^a'bc$ | d");
+        assert_eq!(format!("{}", x), "(Fuzzy: !^a'bc$ | d)");
+
+        let literal_exact = ExactOrFuzzyEngineFactory::builder()
+            .literal(true)
+            .exact_mode(true)
+            .build();
+        let x = literal_exact.create_engine("^a.c$");
+        assert_eq!(format!("{}", x), "(Exact|(?i)\\^a\\.c\\$)");
+
         let regex_factory = RegexEngineFactory::builder();
         let and_or_factory = AndOrEngineFactory::new(exact_or_fuzzy);
 
@@ -271,4 +330,54 @@ mod test {
         let x = regex_factory.create_engine("'abc | def ^gh ij | kl mn");
         assert_eq!(format!("{}", x), "(Regex: 'abc | def ^gh ij | kl mn)");
     }
+
+    #[test]
+    fn test_normalize_query() {
+        use super::*;
+        let and_or_factory = AndOrEngineFactory::new(ExactOrFuzzyEngineFactory::builder().build());
+        assert_eq!("abc", and_or_factory.normalize_query("abc "));
+        assert_eq!("abc def", and_or_factory.normalize_query("  abc   def "));
+        assert_eq!("a | b", and_or_factory.normalize_query("a  |  b"));
+        assert_eq!("abc\\ ", and_or_factory.normalize_query("abc\\  "));
+        assert_eq!("a\\  b", and_or_factory.normalize_query("a\\   b"));
+        assert_eq!("  ", and_or_factory.normalize_query("  "));
+
+        let regex_factory = RegexEngineFactory::builder();
+        assert_eq!("abc ", regex_factory.normalize_query("abc "));
+    }
+
+    #[test]
+    fn test_or_group_modifiers() {
+        use super::*;
+        let and_or_factory = AndOrEngineFactory::new(ExactOrFuzzyEngineFactory::builder().build());
+
+        let x = and_or_factory.create_engine("'foo | bar");
+        assert_eq!(format!("{}", x), "(Or: (And: (Exact|(?i)foo)), (And: (Fuzzy: bar)))");
+
+        let x = and_or_factory.create_engine("foo | 'bar");
+        assert_eq!(format!("{}", x), "(Or: (And: (Fuzzy: foo)), (And: (Exact|(?i)bar)))");
+
+        let x = and_or_factory.create_engine("a 'foo | bar$ | !'baz");
+        assert_eq!(
+            format!("{}", x),
+            "(Or: (And: (Fuzzy: a), (Exact|(?i)foo)), (And: (Exact|(?i)bar$)), (And: (Exact|!(?i)baz)))"
+        );
+
+        let x = and_or_factory.create_engine("!'foo | '!bar");
+        assert_eq!(
+            format!("{}", x),
+            "(Or: (And: (Exact|!(?i)foo)), (And: (Exact|!(?i)bar)))"
+        );
+
+        let exact_factory = AndOrEngineFactory::new(ExactOrFuzzyEngineFactory::builder().exact_mode(true).build());
+
+        let x = exact_factory.create_engine("'foo | bar");
+        assert_eq!(format!("{}", x), "(Or: (And: (Fuzzy: foo)), (And: (Exact|(?i)bar)))");
+
+        let x = exact_factory.create_engine("foo | '^bar | '!baz");
+        assert_eq!(
+            format!("{}", x),
+            "(Or: (And: (Exact|(?i)foo)), (And: (Exact|(?i)^bar)), (And: (Exact|!(?i)baz)))"
+        );
+    }
 }
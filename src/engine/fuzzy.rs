@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::fmt::{Display, Error, Formatter};
 use std::sync::Arc;
 
@@ -5,10 +6,11 @@ use fuzzy_matcher::clangd::ClangdMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
 
+use crate::engine::prefilter::is_subsequence;
+use crate::engine::util::{byte_to_char_index, char_boundary_range, fold_case, is_folded_subsequence};
 use crate::item::RankBuilder;
 use crate::{CaseMatching, MatchEngine};
 use crate::{MatchRange, MatchResult, SkimItem};
-use bitflags::_core::cmp::min;
 
 //------------------------------------------------------------------------------
 #[derive(Debug, Copy, Clone)]
@@ -19,6 +21,22 @@ pub enum FuzzyAlgorithm {
 }
 
 impl FuzzyAlgorithm {
+    pub const ALL: [FuzzyAlgorithm; 3] = [FuzzyAlgorithm::SkimV1, FuzzyAlgorithm::SkimV2, FuzzyAlgorithm::Clangd];
+
+    /// the name accepted by `of`
+    pub fn name(&self) -> &'static str {
+        match self {
+            FuzzyAlgorithm::SkimV1 => "skim_v1",
+            FuzzyAlgorithm::SkimV2 => "skim_v2",
+            FuzzyAlgorithm::Clangd => "clangd",
+        }
+    }
+
+    /// the names accepted by `of`, without the aliases
+    pub fn names() -> Vec<&'static str> {
+        Self::ALL.iter().map(FuzzyAlgorithm::name).collect()
+    }
+
     pub fn of(algorithm: &str) -> Self {
         match algorithm.to_ascii_lowercase().as_ref() {
             "skim_v1" => FuzzyAlgorithm::SkimV1,
@@ -37,6 +55,15 @@ impl Default for FuzzyAlgorithm {
 
 const BYTES_1M: usize = 1024 * 1024 * 1024;
 
+// bonus of path mode for the matches that are all inside the basename
+const BASENAME_BONUS: i64 = 32;
+
+// bonus of the concatenated fields for the matches that are all inside a single field
+const FIELD_BONUS: i64 = 32;
+
+// joins the concatenated fields, the queries never contain it so it breaks the matches
+const FIELD_SEPARATOR: char = '\0';
+
 //------------------------------------------------------------------------------
 // Fuzzy engine
 #[derive(Default)]
@@ -44,6 +71,8 @@ pub struct FuzzyEngineBuilder {
     query: String,
     case: CaseMatching,
     algorithm: FuzzyAlgorithm,
+    path_mode: bool,
+    concat_fields: bool,
     rank_builder: Arc<RankBuilder>,
 }
 
@@ -63,6 +92,18 @@ impl FuzzyEngineBuilder {
         self
     }
 
+    /// treat the items as paths, see `FuzzyEngine::path_match`
+    pub fn path_mode(mut self, path_mode: bool) -> Self {
+        self.path_mode = path_mode;
+        self
+    }
+
+    /// match the fields(`SkimItem::get_matching_ranges`) as a whole, see `FuzzyEngine::concat_match`
+    pub fn concat_fields(mut self, concat_fields: bool) -> Self {
+        self.concat_fields = concat_fields;
+        self
+    }
+
     pub fn rank_builder(mut self, rank_builder: Arc<RankBuilder>) -> Self {
         self.rank_builder = rank_builder;
         self
@@ -71,31 +112,46 @@ impl FuzzyEngineBuilder {
     #[allow(deprecated)]
     pub fn build(self) -> FuzzyEngine {
         use fuzzy_matcher::skim::SkimMatcher;
+        // the smart case of the matchers only looks for ASCII upper case chars
+        let case_sensitive = self.case.is_sensitive(&self.query);
         let matcher: Box<dyn FuzzyMatcher> = match self.algorithm {
             FuzzyAlgorithm::SkimV1 => Box::new(SkimMatcher::default()),
             FuzzyAlgorithm::SkimV2 => {
                 let matcher = SkimMatcherV2::default().element_limit(BYTES_1M);
-                let matcher = match self.case {
-                    CaseMatching::Respect => matcher.respect_case(),
-                    CaseMatching::Ignore => matcher.ignore_case(),
-                    CaseMatching::Smart => matcher.smart_case(),
+                let matcher = if case_sensitive {
+                    matcher.respect_case()
+                } else {
+                    matcher.ignore_case()
                 };
                 Box::new(matcher)
             }
             FuzzyAlgorithm::Clangd => {
                 let matcher = ClangdMatcher::default();
-                let matcher = match self.case {
-                    CaseMatching::Respect => matcher.respect_case(),
-                    CaseMatching::Ignore => matcher.ignore_case(),
-                    CaseMatching::Smart => matcher.smart_case(),
+                let matcher = if case_sensitive {
+                    matcher.respect_case()
+                } else {
+                    matcher.ignore_case()
                 };
                 Box::new(matcher)
             }
         };
 
+        let query = if case_sensitive {
+            self.query
+        } else {
+            fold_case(&self.query).into_owned()
+        };
+
+        // the case of skim_v1 is not up to us, and the components of a path are matched apart
+        let prefilter = cfg!(feature = "simd") && !self.path_mode && !matches!(self.algorithm, FuzzyAlgorithm::SkimV1);
+
         FuzzyEngine {
             matcher,
-            query: self.query,
+            case_sensitive,
+            prefilter,
+            query,
+            path_mode: self.path_mode,
+            concat_fields: self.concat_fields,
             rank_builder: self.rank_builder,
         }
     }
@@ -104,6 +160,12 @@ impl FuzzyEngineBuilder {
 pub struct FuzzyEngine {
     query: String,
     matcher: Box<dyn FuzzyMatcher>,
+    // the non-ASCII chars of the query and the choices are folded if not
+    case_sensitive: bool,
+    // reject the choices without the chars of an ASCII query in order, see `is_subsequence`
+    prefilter: bool,
+    path_mode: bool,
+    concat_fields: bool,
     rank_builder: Arc<RankBuilder>,
 }
 
@@ -119,7 +181,140 @@ impl FuzzyEngine {
             return None;
         }
 
-        self.matcher.fuzzy_indices(choice, pattern)
+        let choice = if self.case_sensitive {
+            Cow::Borrowed(choice)
+        } else {
+            // the `/` of the pattern separates the components in path mode instead of matching
+            let pattern_chars = pattern.chars().filter(|&ch| !self.path_mode || ch != '/');
+            if !choice.is_ascii() && !is_folded_subsequence(choice, pattern_chars) {
+                return None;
+            }
+            fold_case(choice)
+        };
+
+        if self.prefilter
+            && pattern.is_ascii()
+            && !is_subsequence(choice.as_bytes(), pattern.as_bytes(), !self.case_sensitive)
+        {
+            return None;
+        }
+
+        if self.path_mode {
+            self.path_match(&choice, pattern)
+        } else {
+            self.matcher.fuzzy_indices(&choice, pattern)
+        }
+    }
+
+    /// Match `choice` as a path:
+    /// - a pattern without `/` is matched against the whole path, matches that lie entirely in
+    ///   the basename (the part after the last `/`) get a bonus.
+    /// - a pattern with `/` is split into segments, each segment should match inside a single
+    ///   component of the path and the components should be in the same order as the segments,
+    ///   e.g. `sr/eng/fz` matches `src/engine/fuzzy.rs` but not `src/fuzzy/engine.rs`.
+    fn path_match(&self, choice: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+        // (char offset, component)
+        let mut components = Vec::new();
+        let mut offset = 0;
+        for component in choice.split('/') {
+            components.push((offset, component));
+            offset += component.chars().count() + 1;
+        }
+
+        // the basename of "dir/" is "dir"
+        let basename_idx = components
+            .iter()
+            .rposition(|(_, component)| !component.is_empty())
+            .unwrap_or(0);
+
+        if !pattern.contains('/') {
+            let (base_offset, basename) = components[basename_idx];
+            let in_path = self.matcher.fuzzy_indices(choice, pattern);
+            let in_basename = self.matcher.fuzzy_indices(basename, pattern).map(|(score, indices)| {
+                let indices = indices.into_iter().map(|idx| idx + base_offset).collect();
+                (score + BASENAME_BONUS, indices)
+            });
+
+            return match (in_path, in_basename) {
+                (Some(path), Some(base)) if path.0 > base.0 => Some(path),
+                (path, None) => path,
+                (_, base) => base,
+            };
+        }
+
+        // match the segments from right to left, so that the last segment goes to the rightmost
+        // component that matches it.
+        let mut score = 0;
+        let mut indices = Vec::new();
+        let mut next_component = components.len();
+        let mut last_matched = None;
+        for segment in pattern.rsplit('/').filter(|segment| !segment.is_empty()) {
+            let (idx, (segment_score, segment_indices)) = components[..next_component]
+                .iter()
+                .enumerate()
+                .rev()
+                .find_map(|(idx, (_, component))| self.matcher.fuzzy_indices(component, segment).map(|m| (idx, m)))?;
+
+            let component_offset = components[idx].0;
+            score += segment_score;
+            indices.extend(segment_indices.into_iter().rev().map(|i| i + component_offset));
+            next_component = idx;
+            last_matched.get_or_insert(idx);
+        }
+
+        if last_matched == Some(basename_idx) {
+            score += BASENAME_BONUS;
+        }
+
+        indices.reverse();
+        Some((score, indices))
+    }
+
+    /// Match the field `text[start..end]`, the indices are the chars of `text`
+    fn field_match(&self, text: &str, start: usize, end: usize) -> Option<(i64, Vec<usize>)> {
+        let (start, end) = char_boundary_range(text, start, end);
+        self.fuzzy_match(&text[start..end], &self.query).map(|(s, vec)| {
+            if start != 0 {
+                let start_char = &byte_to_char_index(text, start);
+                (s, vec.iter().map(|x| x + start_char).collect())
+            } else {
+                (s, vec)
+            }
+        })
+    }
+
+    /// Match the fields of `text` joined by `FIELD_SEPARATOR`, so that the query could spread over
+    /// the fields(e.g. `--nth=1,3` of a table) but a run of matched chars can't cross the fields.
+    /// The matches that are all inside a single field get a bonus.
+    fn concat_match(&self, text: &str, ranges: &[(usize, usize)]) -> Option<(i64, Vec<usize>)> {
+        let mut concat = String::new();
+        // the char index of `text` for each char of `concat`
+        let mut char_indices = Vec::new();
+        for (idx, &(start, end)) in ranges.iter().enumerate() {
+            let (start, end) = char_boundary_range(text, start, end);
+            if idx > 0 {
+                concat.push(FIELD_SEPARATOR);
+                char_indices.push(0);
+            }
+            let start_char = byte_to_char_index(text, start);
+            concat.push_str(&text[start..end]);
+            char_indices.extend(start_char..start_char + text[start..end].chars().count());
+        }
+
+        let across = self
+            .fuzzy_match(&concat, &self.query)
+            .map(|(score, indices)| (score, indices.into_iter().map(|idx| char_indices[idx]).collect()));
+        let in_field = ranges
+            .iter()
+            .filter_map(|&(start, end)| self.field_match(text, start, end))
+            .max_by_key(|(score, _)| *score)
+            .map(|(score, indices)| (score + FIELD_BONUS, indices));
+
+        match (across, in_field) {
+            (Some(across), Some(field)) if across.0 > field.0 => Some(across),
+            (across, None) => across,
+            (_, field) => field,
+        }
     }
 }
 
@@ -129,20 +324,15 @@ impl MatchEngine for FuzzyEngine {
         let mut matched_result = None;
         let item_text = item.text();
         let default_range = [(0, item_text.len())];
-        for &(start, end) in item.get_matching_ranges().unwrap_or(&default_range) {
-            let start = min(start, item_text.len());
-            let end = min(end, item_text.len());
-            matched_result = self.fuzzy_match(&item_text[start..end], &self.query).map(|(s, vec)| {
-                if start != 0 {
-                    let start_char = &item_text[..start].chars().count();
-                    (s, vec.iter().map(|x| x + start_char).collect())
-                } else {
-                    (s, vec)
+        let ranges = item.get_matching_ranges().unwrap_or(&default_range);
+        if self.concat_fields && ranges.len() > 1 {
+            matched_result = self.concat_match(&item_text, ranges);
+        } else {
+            for &(start, end) in ranges {
+                matched_result = self.field_match(&item_text, start, end);
+                if matched_result.is_some() {
+                    break;
                 }
-            });
-
-            if matched_result.is_some() {
-                break;
             }
         }
 
@@ -168,3 +358,93 @@ impl Display for FuzzyEngine {
         write!(f, "(Fuzzy: {})", self.query)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_engine(query: &str) -> FuzzyEngine {
+        FuzzyEngine::builder().query(query).path_mode(true).build()
+    }
+
+    #[test]
+    fn test_algorithm_names() {
+        for algorithm in FuzzyAlgorithm::ALL {
+            assert_eq!(FuzzyAlgorithm::of(algorithm.name()).name(), algorithm.name());
+        }
+        assert_eq!(FuzzyAlgorithm::names(), vec!["skim_v1", "skim_v2", "clangd"]);
+    }
+
+    #[test]
+    fn test_path_mode_basename_bonus() {
+        let engine = path_engine("mod");
+        let (in_basename, indices) = engine.fuzzy_match("src/engine/mod.rs", "mod").unwrap();
+        assert_eq!(indices, vec![11, 12, 13]);
+        let (in_dir, _) = engine.fuzzy_match("src/mod/engine.rs", "mod").unwrap();
+        assert!(in_basename > in_dir);
+
+        // trailing slash of directories
+        let (_, indices) = engine.fuzzy_match("src/mod/", "mod").unwrap();
+        assert_eq!(indices, vec![4, 5, 6]);
+
+        // matches across components are still allowed without `/` in the query
+        assert!(engine.fuzzy_match("src/engine/fuzzy.rs", "sefz").is_some());
+    }
+
+    #[test]
+    fn test_path_mode_segments() {
+        let engine = path_engine("sr/eng/fz");
+        let (_, indices) = engine.fuzzy_match("src/engine/fuzzy.rs", "sr/eng/fz").unwrap();
+        assert_eq!(indices, vec![0, 1, 4, 5, 6, 11, 13]);
+        assert!(engine.fuzzy_match("src/fuzzy/engine.rs", "sr/eng/fz").is_none());
+        // a segment should not cross components
+        assert!(engine.fuzzy_match("s/rc/engine/fuzzy.rs", "sr/fz").is_none());
+        // empty segments are ignored
+        assert!(engine.fuzzy_match("src/engine/fuzzy.rs", "/src//fuzzy").is_some());
+
+        let (last, _) = engine.fuzzy_match("a/src/fuzzy.rs", "src/fz").unwrap();
+        let (middle, _) = engine.fuzzy_match("src/fuzzy/a.rs", "src/fz").unwrap();
+        assert!(last > middle);
+    }
+
+    #[test]
+    fn test_concat_fields() {
+        let engine = |query| FuzzyEngine::builder().query(query).concat_fields(true).build();
+        let text = "john smith doe";
+        let fields = [(0, 4), (11, 14)];
+
+        // spread over the fields
+        let (_, indices) = engine("jdoe").concat_match(text, &fields).unwrap();
+        assert_eq!(indices, vec![0, 11, 12, 13]);
+        // the fields out of `--nth` are not matched
+        assert!(engine("js").concat_match(text, &fields).is_none());
+        // a run can't cross the separator
+        let (across, _) = engine("nd").concat_match(text, &fields).unwrap();
+        let (inside, _) = engine("nd").concat_match("john doe", &[(0, 8)]).unwrap();
+        assert!(across < inside);
+
+        // the matches inside a single field are preferred
+        let (in_field, indices) = engine("do").concat_match("dx ox dox", &[(0, 5), (6, 9)]).unwrap();
+        assert_eq!(indices, vec![6, 7]);
+        let (spread, _) = engine("do").concat_match("dx ox xxx", &[(0, 5), (6, 9)]).unwrap();
+        assert!(in_field > spread);
+    }
+
+    #[test]
+    fn test_unicode_case() {
+        let engine = |query| FuzzyEngine::builder().query(query).build();
+        let (_, indices) = engine("ä").fuzzy_match("xÄy", &engine("ä").query).unwrap();
+        assert_eq!(indices, vec![1]);
+        assert!(engine("istanbul").fuzzy_match("İstanbul", "istanbul").is_some());
+        assert!(engine("ωm").fuzzy_match("Ωmega", "ωm").is_some());
+
+        // smart case respects the non-ASCII upper case chars as well
+        let engine = engine("Ä");
+        assert!(engine.fuzzy_match("xäy", &engine.query).is_none());
+        assert!(engine.fuzzy_match("xÄy", &engine.query).is_some());
+
+        // a folded query matches the upper case chars
+        let engine = FuzzyEngine::builder().query("ÄB").case(CaseMatching::Ignore).build();
+        assert!(engine.fuzzy_match("xäby", &engine.query).is_some());
+    }
+}
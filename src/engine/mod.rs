@@ -3,5 +3,6 @@ pub mod andor;
 pub mod exact;
 pub mod factory;
 pub mod fuzzy;
+pub mod prefilter;
 pub mod regexp;
-mod util;
+pub mod util;
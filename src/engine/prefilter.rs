@@ -0,0 +1,105 @@
+//! A quick test that the chars of an ASCII query appear in order in the choice, which is required
+//! by the fuzzy matchers. Most of the choices are rejected here before the matchers collect their
+//! chars and fill the score matrix.
+//!
+//! Only used with the `simd` feature, which searches the bytes by `memchr`, vectorized where the
+//! CPU supports it.
+
+/// Whether the bytes of the ASCII `pattern` appear in order in `choice`, the ASCII letters are
+/// compared case insensitively if `ignore_case`. Since the bytes of multi-byte UTF-8 chars are
+/// never ASCII, `choice` could be any UTF-8 text.
+pub fn is_subsequence(choice: &[u8], pattern: &[u8], ignore_case: bool) -> bool {
+    let mut rest = choice;
+    for &byte in pattern {
+        let (lower, upper) = if ignore_case {
+            (byte.to_ascii_lowercase(), byte.to_ascii_uppercase())
+        } else {
+            (byte, byte)
+        };
+        match find_either(rest, lower, upper) {
+            Some(idx) => rest = &rest[idx + 1..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// The index of the first byte in `haystack` that is `a` or `b`
+#[cfg(feature = "simd")]
+fn find_either(haystack: &[u8], a: u8, b: u8) -> Option<usize> {
+    memchr::memchr2(a, b, haystack)
+}
+
+#[cfg(not(feature = "simd"))]
+fn find_either(haystack: &[u8], a: u8, b: u8) -> Option<usize> {
+    haystack.iter().position(|&byte| byte == a || byte == b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_is_subsequence() {
+        assert!(is_subsequence(b"src/engine/fuzzy.rs", b"sefz", false));
+        assert!(is_subsequence(b"anything", b"", false));
+        assert!(!is_subsequence(b"src/engine/fuzzy.rs", b"zf", false));
+        assert!(!is_subsequence(b"", b"a", true));
+
+        assert!(!is_subsequence(b"Fuzzy", b"fz", false));
+        assert!(is_subsequence(b"Fuzzy", b"fZ", true));
+        // only the ASCII letters have the other case
+        assert!(!is_subsequence(b"a[b", b"{", true));
+        assert!(is_subsequence("中文 mixed Ä".as_bytes(), b"mx", false));
+
+        // across and at the edges of the SIMD chunks
+        let mut long = vec![b'x'; 100];
+        for &idx in &[0, 15, 16, 31, 32, 63, 99] {
+            long[idx] = b'a';
+            assert!(is_subsequence(&long, b"a", false));
+            assert_eq!(is_subsequence(&long, b"xA", true), idx > 0);
+            long[idx] = b'x';
+        }
+        long[40] = b'a';
+        long[70] = b'b';
+        assert!(is_subsequence(&long, b"ab", false));
+        assert!(!is_subsequence(&long, b"ba", false));
+    }
+
+    #[test]
+    fn test_find_either() {
+        let haystack: Vec<u8> = (0..200).map(|i| b'a' + (i % 26) as u8).collect();
+        for a in b'a'..=b'z' {
+            for start in 0..70 {
+                let expected = haystack[start..].iter().position(|&byte| byte == a);
+                assert_eq!(find_either(&haystack[start..], a, b'#'), expected);
+                assert_eq!(find_either(&haystack[start..], b'#', a), expected);
+            }
+        }
+    }
+
+    /// `cargo test --release [--features simd] bench_is_subsequence -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_is_subsequence() {
+        let line: String = "some/long/path/with_many_components/".repeat(30);
+        let choices: Vec<String> = (0..100_000).map(|i| format!("{}{}.rs", line, i)).collect();
+
+        for pattern in [&b"zq"[..], b"withq", b"mcpwq"] {
+            let started = Instant::now();
+            let matched = choices
+                .iter()
+                .filter(|choice| is_subsequence(choice.as_bytes(), pattern, true))
+                .count();
+            println!(
+                "{:>6} over {} lines of {} bytes: {:?}, matched {}",
+                String::from_utf8_lossy(pattern),
+                choices.len(),
+                choices[0].len(),
+                started.elapsed(),
+                matched
+            );
+        }
+    }
+}
@@ -3,11 +3,10 @@ use std::sync::Arc;
 
 use regex::Regex;
 
-use crate::engine::util::regex_match;
+use crate::engine::util::{char_boundary_range, regex_match};
 use crate::item::RankBuilder;
 use crate::{CaseMatching, MatchEngine};
 use crate::{MatchRange, MatchResult, SkimItem};
-use std::cmp::min;
 
 //------------------------------------------------------------------------------
 // Regular Expression engine
@@ -51,8 +50,7 @@ impl MatchEngine for RegexEngine {
         let item_text = item.text();
         let default_range = [(0, item_text.len())];
         for &(start, end) in item.get_matching_ranges().unwrap_or(&default_range) {
-            let start = min(start, item_text.len());
-            let end = min(end, item_text.len());
+            let (start, end) = char_boundary_range(&item_text, start, end);
             if self.query_regex.is_none() {
                 matched_result = Some((0, 0));
                 break;
@@ -88,3 +86,43 @@ impl Display for RegexEngine {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    struct RangedItem {
+        text: &'static str,
+        ranges: Vec<(usize, usize)>,
+    }
+
+    impl SkimItem for RangedItem {
+        fn text(&self) -> Cow<'_, str> {
+            Cow::Borrowed(self.text)
+        }
+
+        fn get_matching_ranges(&self) -> Option<&[(usize, usize)]> {
+            Some(&self.ranges)
+        }
+    }
+
+    #[test]
+    fn test_matching_ranges_in_the_middle_of_chars() {
+        let text = "中文:😀emoji:ありがとう";
+        let engine = RegexEngine::builder("emoji", CaseMatching::Respect).build();
+        for start in 0..text.len() {
+            for end in start..text.len() + 2 {
+                let item = Arc::new(RangedItem {
+                    text,
+                    ranges: vec![(start, end)],
+                });
+                if let Some(result) = engine.match_item(item) {
+                    let indices = result.range_char_indices(text);
+                    let matched: String = text.chars().skip(indices[0]).take(indices.len()).collect();
+                    assert_eq!(matched, "emoji");
+                }
+            }
+        }
+    }
+}
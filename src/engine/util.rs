@@ -1,4 +1,6 @@
 use regex::Regex;
+use std::borrow::Cow;
+use std::cmp::{max, min};
 
 pub fn regex_match(choice: &str, pattern: &Option<Regex>) -> Option<(usize, usize)> {
     match *pattern {
@@ -10,11 +12,129 @@ pub fn regex_match(choice: &str, pattern: &Option<Regex>) -> Option<(usize, usiz
     }
 }
 
+/// Clamp the `(start_byte, end_byte)` range given by `SkimItem::get_matching_ranges` to `text`
+/// and widen it to the char boundaries, so that slicing `text` with it won't panic on multi-byte
+/// characters.
+pub fn char_boundary_range(text: &str, start: usize, end: usize) -> (usize, usize) {
+    let mut start = min(start, text.len());
+    let mut end = max(start, min(end, text.len()));
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+    (start, end)
+}
+
+/// Convert the byte offset of `text` to the char index, offset in the middle of a char is mapped
+/// to the char.
+pub fn byte_to_char_index(text: &str, byte_idx: usize) -> usize {
+    let (start, _) = char_boundary_range(text, byte_idx, byte_idx);
+    text[..start].chars().count()
+}
+
 pub fn contains_upper(string: &str) -> bool {
-    for ch in string.chars() {
-        if ch.is_ascii_uppercase() {
-            return true;
+    string.chars().any(char::is_uppercase)
+}
+
+/// Lowercase the non-ASCII chars of `text`, e.g. `Ä` or the Turkish dotted `İ`, one char for each
+/// char so that the char indices are kept. The fuzzy matchers compare only the ASCII chars case
+/// insensitively, which are left as they are to keep their camel case bonus.
+///
+/// It is called for every item on every query, so the text is only copied if some char is folded.
+pub fn fold_case(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+
+    match text.char_indices().find(|&(_, ch)| fold_char(ch) != ch) {
+        None => Cow::Borrowed(text),
+        Some((idx, _)) => {
+            let mut owned = String::with_capacity(text.len());
+            owned.push_str(&text[..idx]);
+            owned.extend(text[idx..].chars().map(fold_char));
+            Cow::Owned(owned)
+        }
+    }
+}
+
+fn fold_char(ch: char) -> char {
+    match ch {
+        ch if ch.is_ascii() => ch,
+        ch => ch.to_lowercase().next().unwrap_or(ch),
+    }
+}
+
+/// Whether the chars of `pattern` appear in order in `fold_case(text)`, the ASCII chars compared
+/// case insensitively, which the fuzzy matchers require. The text is folded char by char without
+/// a copy, so that the items that could not match are rejected before `fold_case` copies them.
+pub fn is_folded_subsequence(text: &str, pattern: impl IntoIterator<Item = char>) -> bool {
+    let mut chars = text.chars().map(fold_char);
+    pattern
+        .into_iter()
+        .all(|pat_ch| chars.any(|ch| ch.eq_ignore_ascii_case(&pat_ch)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXTS: &[&str] = &["", "abc", "中文字符", "a中b文c", "😀ab🎉", "ありがとう😀 thx"];
+
+    #[test]
+    fn test_char_boundary_range() {
+        for text in TEXTS {
+            for start in 0..text.len() + 3 {
+                for end in 0..text.len() + 3 {
+                    let (s, e) = char_boundary_range(text, start, end);
+                    assert!(s <= e && e <= text.len());
+                    assert!(text.is_char_boundary(s) && text.is_char_boundary(e));
+                    assert!(s <= min(start, text.len()));
+                    assert!(e >= min(max(start, end), text.len()));
+                    let _ = &text[s..e];
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_case_folding() {
+        assert!(contains_upper("aBc"));
+        assert!(contains_upper("straße Ä"));
+        assert!(!contains_upper("ä中文字符"));
+
+        assert!(matches!(fold_case("aBc中文"), Cow::Borrowed("aBc中文")));
+        assert!(matches!(fold_case("straße"), Cow::Borrowed("straße")));
+        assert_eq!(fold_case("abc Äb"), "abc äb");
+        assert_eq!(fold_case("ÄBC Ωmega"), "äBC ωmega");
+        assert_eq!(fold_case("İstanbul"), "istanbul");
+        for text in TEXTS {
+            assert_eq!(fold_case(text).chars().count(), text.chars().count());
+        }
+    }
+
+    #[test]
+    fn test_is_folded_subsequence() {
+        assert!(is_folded_subsequence("ÄBC Ωmega", "äbω".chars()));
+        assert!(is_folded_subsequence("İstanbul", "ib".chars()));
+        assert!(is_folded_subsequence("中文", "".chars()));
+        assert!(!is_folded_subsequence("ÄBC Ωmega", "ωä".chars()));
+        assert!(!is_folded_subsequence("straße", "ss".chars()));
+        for text in TEXTS {
+            assert!(is_folded_subsequence(text, fold_case(text).chars()));
+        }
+    }
+
+    #[test]
+    fn test_byte_to_char_index() {
+        for text in TEXTS {
+            for (char_idx, (byte_idx, ch)) in text.char_indices().enumerate() {
+                for offset in 0..ch.len_utf8() {
+                    assert_eq!(byte_to_char_index(text, byte_idx + offset), char_idx);
+                }
+            }
+            assert_eq!(byte_to_char_index(text, text.len() + 1), text.chars().count());
         }
     }
-    false
 }
@@ -14,9 +14,13 @@ pub enum Event {
     EvHeartBeat,
 
     // user bind actions
-    EvActAbort,
+    /// abort with the exit code, e.g. `abort(1)`, the default one(130) if `None`
+    EvActAbort(Option<i32>),
     EvActAccept(Option<String>),
+    EvActAcceptOrPrintQuery,
     EvActAddChar(char),
+    /// the text of a bracketed paste, inserted into the query at once
+    EvActAddStr(String),
     EvActAppendAndSelect,
     EvActBackwardChar,
     EvActBackwardDeleteChar,
@@ -24,14 +28,19 @@ pub enum Event {
     EvActBackwardWord,
     EvActBeginningOfLine,
     EvActCancel,
+    EvActClearHeader,
     EvActClearScreen,
+    EvActCopyToClipboard,
     EvActDeleteChar,
     EvActDeleteCharEOF,
+    EvActDeselect(String),
     EvActDeselectAll,
     EvActDown(i32),
     EvActEndOfLine,
     EvActExecute(String),
     EvActExecuteSilent(String),
+    EvActExecuteToQuery(String),
+    EvActExecuteAppendItems(String),
     EvActForwardChar,
     EvActForwardWord,
     EvActIfQueryEmpty(String),
@@ -45,38 +54,209 @@ pub enum Event {
     EvActHalfPageUp(i32),
     EvActPageDown(i32),
     EvActPageUp(i32),
+    EvActParent,
     EvActPreviewUp(i32),
     EvActPreviewDown(i32),
     EvActPreviewLeft(i32),
     EvActPreviewRight(i32),
     EvActPreviewPageUp(i32),
     EvActPreviewPageDown(i32),
+    EvActPreSelectPat(String),
     EvActPreviousHistory,
     EvActRedraw,
     EvActRefreshCmd,
     EvActRefreshPreview,
+    EvActReloadTheme,
     EvActRotateMode,
     EvActScrollLeft(i32),
     EvActScrollRight(i32),
+    EvActScrollTo(usize),
+    EvActSelect(String),
     EvActSelectAll,
     EvActSelectRow(usize),
+    EvActSetHeader(String),
+    EvActShowHelp,
+    EvActSuspend,
     EvActToggle,
     EvActToggleAll,
+    EvActToggleFollow,
     EvActToggleIn,
     EvActToggleInteractive,
+    EvActToggleMouse,
     EvActToggleOut,
     EvActTogglePreview,
     EvActTogglePreviewWrap,
     EvActToggleSort,
+    EvActTransposeChars,
     EvActUnixLineDiscard,
     EvActUnixWordRubout,
     EvActUp(i32),
     EvActYank,
+    EvActYankPop,
 
     #[doc(hidden)]
     __Nonexhaustive,
 }
 
+/// The actions that could be bound to keys, see `Keymap`.
+/// The arguments are the same as the ones of the `--bind` option, e.g. `Action::Up(1)` is `up`
+/// and `Action::IfQueryEmpty("abort".to_string())` is `if-query-empty(abort)`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum Action {
+    Abort(Option<i32>),
+    Accept(Option<String>),
+    AcceptOrPrintQuery,
+    AppendAndSelect,
+    BackwardChar,
+    BackwardDeleteChar,
+    BackwardKillWord,
+    BackwardWord,
+    BeginningOfLine,
+    Cancel,
+    ClearHeader,
+    ClearScreen,
+    CopyToClipboard,
+    DeleteChar,
+    DeleteCharEOF,
+    Deselect(String),
+    DeselectAll,
+    Down(i32),
+    EndOfLine,
+    Execute(String),
+    ExecuteSilent(String),
+    ExecuteToQuery(String),
+    ExecuteAppendItems(String),
+    ForwardChar,
+    ForwardWord,
+    IfNonMatched(String),
+    IfQueryEmpty(String),
+    IfQueryNotEmpty(String),
+    Ignore,
+    KillLine,
+    KillWord,
+    NextHistory,
+    HalfPageDown(i32),
+    HalfPageUp(i32),
+    PageDown(i32),
+    PageUp(i32),
+    Parent,
+    PreviewUp(i32),
+    PreviewDown(i32),
+    PreviewLeft(i32),
+    PreviewRight(i32),
+    PreviewPageUp(i32),
+    PreviewPageDown(i32),
+    PreSelectPat(String),
+    PreviousHistory,
+    RefreshCmd,
+    RefreshPreview,
+    ReloadTheme,
+    RotateMode,
+    ScrollLeft(i32),
+    ScrollRight(i32),
+    Select(String),
+    SelectAll,
+    SetHeader(String),
+    ShowHelp,
+    Suspend,
+    Toggle,
+    ToggleAll,
+    ToggleFollow,
+    ToggleIn,
+    ToggleInteractive,
+    ToggleMouse,
+    ToggleOut,
+    TogglePreview,
+    TogglePreviewWrap,
+    ToggleSort,
+    TransposeChars,
+    UnixLineDiscard,
+    UnixWordRubout,
+    Up(i32),
+    Yank,
+    YankPop,
+}
+
+impl From<Action> for Event {
+    #[rustfmt::skip]
+    fn from(action: Action) -> Self {
+        match action {
+            Action::Abort(arg)              => Event::EvActAbort(arg),
+            Action::Accept(arg)             => Event::EvActAccept(arg),
+            Action::AcceptOrPrintQuery      => Event::EvActAcceptOrPrintQuery,
+            Action::AppendAndSelect         => Event::EvActAppendAndSelect,
+            Action::BackwardChar            => Event::EvActBackwardChar,
+            Action::BackwardDeleteChar      => Event::EvActBackwardDeleteChar,
+            Action::BackwardKillWord        => Event::EvActBackwardKillWord,
+            Action::BackwardWord            => Event::EvActBackwardWord,
+            Action::BeginningOfLine         => Event::EvActBeginningOfLine,
+            Action::Cancel                  => Event::EvActCancel,
+            Action::ClearHeader             => Event::EvActClearHeader,
+            Action::ClearScreen             => Event::EvActClearScreen,
+            Action::CopyToClipboard         => Event::EvActCopyToClipboard,
+            Action::DeleteChar              => Event::EvActDeleteChar,
+            Action::DeleteCharEOF           => Event::EvActDeleteCharEOF,
+            Action::Deselect(arg)           => Event::EvActDeselect(arg),
+            Action::DeselectAll             => Event::EvActDeselectAll,
+            Action::Down(arg)               => Event::EvActDown(arg),
+            Action::EndOfLine               => Event::EvActEndOfLine,
+            Action::Execute(arg)            => Event::EvActExecute(arg),
+            Action::ExecuteSilent(arg)      => Event::EvActExecuteSilent(arg),
+            Action::ExecuteToQuery(arg)     => Event::EvActExecuteToQuery(arg),
+            Action::ExecuteAppendItems(arg) => Event::EvActExecuteAppendItems(arg),
+            Action::ForwardChar             => Event::EvActForwardChar,
+            Action::ForwardWord             => Event::EvActForwardWord,
+            Action::IfNonMatched(arg)       => Event::EvActIfNonMatched(arg),
+            Action::IfQueryEmpty(arg)       => Event::EvActIfQueryEmpty(arg),
+            Action::IfQueryNotEmpty(arg)    => Event::EvActIfQueryNotEmpty(arg),
+            Action::Ignore                  => Event::EvActIgnore,
+            Action::KillLine                => Event::EvActKillLine,
+            Action::KillWord                => Event::EvActKillWord,
+            Action::NextHistory             => Event::EvActNextHistory,
+            Action::HalfPageDown(arg)       => Event::EvActHalfPageDown(arg),
+            Action::HalfPageUp(arg)         => Event::EvActHalfPageUp(arg),
+            Action::PageDown(arg)           => Event::EvActPageDown(arg),
+            Action::PageUp(arg)             => Event::EvActPageUp(arg),
+            Action::Parent                  => Event::EvActParent,
+            Action::PreviewUp(arg)          => Event::EvActPreviewUp(arg),
+            Action::PreviewDown(arg)        => Event::EvActPreviewDown(arg),
+            Action::PreviewLeft(arg)        => Event::EvActPreviewLeft(arg),
+            Action::PreviewRight(arg)       => Event::EvActPreviewRight(arg),
+            Action::PreviewPageUp(arg)      => Event::EvActPreviewPageUp(arg),
+            Action::PreviewPageDown(arg)    => Event::EvActPreviewPageDown(arg),
+            Action::PreSelectPat(arg)       => Event::EvActPreSelectPat(arg),
+            Action::PreviousHistory         => Event::EvActPreviousHistory,
+            Action::RefreshCmd              => Event::EvActRefreshCmd,
+            Action::RefreshPreview          => Event::EvActRefreshPreview,
+            Action::ReloadTheme             => Event::EvActReloadTheme,
+            Action::RotateMode              => Event::EvActRotateMode,
+            Action::ScrollLeft(arg)         => Event::EvActScrollLeft(arg),
+            Action::ScrollRight(arg)        => Event::EvActScrollRight(arg),
+            Action::Select(arg)             => Event::EvActSelect(arg),
+            Action::SelectAll               => Event::EvActSelectAll,
+            Action::SetHeader(arg)          => Event::EvActSetHeader(arg),
+            Action::ShowHelp                => Event::EvActShowHelp,
+            Action::Suspend                 => Event::EvActSuspend,
+            Action::Toggle                  => Event::EvActToggle,
+            Action::ToggleAll               => Event::EvActToggleAll,
+            Action::ToggleFollow            => Event::EvActToggleFollow,
+            Action::ToggleIn                => Event::EvActToggleIn,
+            Action::ToggleInteractive       => Event::EvActToggleInteractive,
+            Action::ToggleMouse             => Event::EvActToggleMouse,
+            Action::ToggleOut               => Event::EvActToggleOut,
+            Action::TogglePreview           => Event::EvActTogglePreview,
+            Action::TogglePreviewWrap       => Event::EvActTogglePreviewWrap,
+            Action::ToggleSort              => Event::EvActToggleSort,
+            Action::TransposeChars          => Event::EvActTransposeChars,
+            Action::UnixLineDiscard         => Event::EvActUnixLineDiscard,
+            Action::UnixWordRubout          => Event::EvActUnixWordRubout,
+            Action::Up(arg)                 => Event::EvActUp(arg),
+            Action::Yank                    => Event::EvActYank,
+            Action::YankPop                 => Event::EvActYankPop,
+        }
+    }
+}
+
 bitflags! {
     /// `Effect` is the effect of a text
     pub struct UpdateScreen: u8 {
@@ -90,11 +270,106 @@ pub trait EventHandler {
     fn handle(&mut self, event: &Event) -> UpdateScreen;
 }
 
+/// The argument taken by an action of `--bind`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionArg {
+    /// e.g. `abort`
+    Nothing,
+    /// e.g. `accept` or `accept(key)`
+    Optional,
+    /// a number that defaults to 1, e.g. `up` or `up(3)`
+    Number,
+    /// an optional exit code, e.g. `abort` or `abort(3)`
+    ExitCode,
+    /// e.g. `execute(echo {})`
+    Required,
+}
+
+/// The actions accepted by `parse_event` and their arguments
+#[rustfmt::skip]
+pub const ACTION_ARGS: &[(&str, ActionArg)] = &[
+    ("abort",                ActionArg::ExitCode),
+    ("accept",               ActionArg::Optional),
+    ("accept-or-print-query", ActionArg::Nothing),
+    ("append-and-select",    ActionArg::Nothing),
+    ("backward-char",        ActionArg::Nothing),
+    ("backward-delete-char", ActionArg::Nothing),
+    ("backward-kill-word",   ActionArg::Nothing),
+    ("backward-word",        ActionArg::Nothing),
+    ("beginning-of-line",    ActionArg::Nothing),
+    ("cancel",               ActionArg::Nothing),
+    ("clear-header",         ActionArg::Nothing),
+    ("clear-screen",         ActionArg::Nothing),
+    ("copy-to-clipboard",    ActionArg::Nothing),
+    ("delete-char",          ActionArg::Nothing),
+    ("delete-charEOF",       ActionArg::Nothing),
+    ("deselect",             ActionArg::Required),
+    ("deselect-all",         ActionArg::Nothing),
+    ("down",                 ActionArg::Number),
+    ("end-of-line",          ActionArg::Nothing),
+    ("execute",              ActionArg::Required),
+    ("execute-silent",       ActionArg::Required),
+    ("execute-to-query",     ActionArg::Required),
+    ("execute-append-items", ActionArg::Required),
+    ("forward-char",         ActionArg::Nothing),
+    ("forward-word",         ActionArg::Nothing),
+    ("if-non-matched",       ActionArg::Required),
+    ("if-query-empty",       ActionArg::Required),
+    ("if-query-not-empty",   ActionArg::Required),
+    ("ignore",               ActionArg::Nothing),
+    ("kill-line",            ActionArg::Nothing),
+    ("kill-word",            ActionArg::Nothing),
+    ("next-history",         ActionArg::Nothing),
+    ("half-page-down",       ActionArg::Number),
+    ("half-page-up",         ActionArg::Number),
+    ("page-down",            ActionArg::Number),
+    ("page-up",              ActionArg::Number),
+    ("parent",               ActionArg::Nothing),
+    ("preview-up",           ActionArg::Number),
+    ("preview-down",         ActionArg::Number),
+    ("preview-left",         ActionArg::Number),
+    ("preview-right",        ActionArg::Number),
+    ("preview-page-up",      ActionArg::Number),
+    ("preview-page-down",    ActionArg::Number),
+    ("pre-select-pat",       ActionArg::Optional),
+    ("previous-history",     ActionArg::Nothing),
+    ("refresh-cmd",          ActionArg::Nothing),
+    ("refresh-preview",      ActionArg::Nothing),
+    ("reload-theme",         ActionArg::Nothing),
+    ("scroll-left",          ActionArg::Number),
+    ("scroll-right",         ActionArg::Number),
+    ("select",               ActionArg::Required),
+    ("select-all",           ActionArg::Nothing),
+    ("set-header",           ActionArg::Optional),
+    ("show-help",            ActionArg::Nothing),
+    ("suspend",              ActionArg::Nothing),
+    ("toggle",               ActionArg::Nothing),
+    ("toggle-all",           ActionArg::Nothing),
+    ("toggle-follow",        ActionArg::Nothing),
+    ("toggle-in",            ActionArg::Nothing),
+    ("toggle-interactive",   ActionArg::Nothing),
+    ("toggle-mouse",         ActionArg::Nothing),
+    ("toggle-out",           ActionArg::Nothing),
+    ("toggle-preview",       ActionArg::Nothing),
+    ("toggle-preview-wrap",  ActionArg::Nothing),
+    ("toggle-sort",          ActionArg::Nothing),
+    ("transpose-chars",      ActionArg::Nothing),
+    ("unix-line-discard",    ActionArg::Nothing),
+    ("unix-word-rubout",     ActionArg::Nothing),
+    ("up",                   ActionArg::Number),
+    ("yank",                 ActionArg::Nothing),
+    ("yank-pop",             ActionArg::Nothing),
+];
+
 #[rustfmt::skip]
 pub fn parse_event(action: &str, arg: Option<String>) -> Option<Event> {
     match action {
-        "abort"                =>   Some(Event::EvActAbort),
+        "abort"                =>   match arg {
+                                        Some(s) => s.parse::<u8>().ok().map(|code| Event::EvActAbort(Some(code.into()))),
+                                        None => Some(Event::EvActAbort(None)),
+                                    },
         "accept"               =>   Some(Event::EvActAccept(arg)),
+        "accept-or-print-query" =>  Some(Event::EvActAcceptOrPrintQuery),
         "append-and-select"    =>   Some(Event::EvActAppendAndSelect),
         "backward-char"        =>   Some(Event::EvActBackwardChar),
         "backward-delete-char" =>   Some(Event::EvActBackwardDeleteChar),
@@ -102,19 +377,24 @@ pub fn parse_event(action: &str, arg: Option<String>) -> Option<Event> {
         "backward-word"        =>   Some(Event::EvActBackwardWord),
         "beginning-of-line"    =>   Some(Event::EvActBeginningOfLine),
         "cancel"               =>   Some(Event::EvActCancel),
+        "clear-header"         =>   Some(Event::EvActClearHeader),
         "clear-screen"         =>   Some(Event::EvActClearScreen),
+        "copy-to-clipboard"    =>   Some(Event::EvActCopyToClipboard),
         "delete-char"          =>   Some(Event::EvActDeleteChar),
         "delete-charEOF"       =>   Some(Event::EvActDeleteCharEOF),
+        "deselect"             =>   arg.map(Event::EvActDeselect),
         "deselect-all"         =>   Some(Event::EvActDeselectAll),
         "down"                 =>   Some(Event::EvActDown(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
         "end-of-line"          =>   Some(Event::EvActEndOfLine),
-        "execute"              =>   Some(Event::EvActExecute(arg.expect("execute event should have argument"))),
-        "execute-silent"       =>   Some(Event::EvActExecuteSilent(arg.expect("execute-silent event should have argument"))),
+        "execute"              =>   arg.map(Event::EvActExecute),
+        "execute-silent"       =>   arg.map(Event::EvActExecuteSilent),
+        "execute-to-query"     =>   arg.map(Event::EvActExecuteToQuery),
+        "execute-append-items" =>   arg.map(Event::EvActExecuteAppendItems),
         "forward-char"         =>   Some(Event::EvActForwardChar),
         "forward-word"         =>   Some(Event::EvActForwardWord),
-        "if-non-matched"       =>   Some(Event::EvActIfNonMatched(arg.expect("no arg specified for event if-non-matched"))),
-        "if-query-empty"       =>   Some(Event::EvActIfQueryEmpty(arg.expect("no arg specified for event if-query-empty"))),
-        "if-query-not-empty"   =>   Some(Event::EvActIfQueryNotEmpty(arg.expect("no arg specified for event if-query-not-empty"))),
+        "if-non-matched"       =>   arg.map(Event::EvActIfNonMatched),
+        "if-query-empty"       =>   arg.map(Event::EvActIfQueryEmpty),
+        "if-query-not-empty"   =>   arg.map(Event::EvActIfQueryNotEmpty),
         "ignore"               =>   Some(Event::EvActIgnore),
         "kill-line"            =>   Some(Event::EvActKillLine),
         "kill-word"            =>   Some(Event::EvActKillWord),
@@ -123,30 +403,122 @@ pub fn parse_event(action: &str, arg: Option<String>) -> Option<Event> {
         "half-page-up"         =>   Some(Event::EvActHalfPageUp(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
         "page-down"            =>   Some(Event::EvActPageDown(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
         "page-up"              =>   Some(Event::EvActPageUp(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
+        "parent"               =>   Some(Event::EvActParent),
         "preview-up"           =>   Some(Event::EvActPreviewUp(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
         "preview-down"         =>   Some(Event::EvActPreviewDown(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
         "preview-left"         =>   Some(Event::EvActPreviewLeft(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
         "preview-right"        =>   Some(Event::EvActPreviewRight(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
         "preview-page-up"      =>   Some(Event::EvActPreviewPageUp(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
         "preview-page-down"    =>   Some(Event::EvActPreviewPageDown(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
+        "pre-select-pat"       =>   Some(Event::EvActPreSelectPat(arg.unwrap_or_default())),
         "previous-history"     =>   Some(Event::EvActPreviousHistory),
         "refresh-cmd"          =>   Some(Event::EvActRefreshCmd),
         "refresh-preview"      =>   Some(Event::EvActRefreshPreview),
+        "reload-theme"         =>   Some(Event::EvActReloadTheme),
         "scroll-left"          =>   Some(Event::EvActScrollLeft(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
         "scroll-right"         =>   Some(Event::EvActScrollRight(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
+        "select"               =>   arg.map(Event::EvActSelect),
         "select-all"           =>   Some(Event::EvActSelectAll),
+        "set-header"           =>   Some(Event::EvActSetHeader(arg.unwrap_or_default())),
+        "show-help"            =>   Some(Event::EvActShowHelp),
+        "suspend"              =>   Some(Event::EvActSuspend),
         "toggle"               =>   Some(Event::EvActToggle),
         "toggle-all"           =>   Some(Event::EvActToggleAll),
+        "toggle-follow"        =>   Some(Event::EvActToggleFollow),
         "toggle-in"            =>   Some(Event::EvActToggleIn),
         "toggle-interactive"   =>   Some(Event::EvActToggleInteractive),
+        "toggle-mouse"         =>   Some(Event::EvActToggleMouse),
         "toggle-out"           =>   Some(Event::EvActToggleOut),
         "toggle-preview"       =>   Some(Event::EvActTogglePreview),
         "toggle-preview-wrap"  =>   Some(Event::EvActTogglePreviewWrap),
         "toggle-sort"          =>   Some(Event::EvActToggleSort),
+        "transpose-chars"      =>   Some(Event::EvActTransposeChars),
         "unix-line-discard"    =>   Some(Event::EvActUnixLineDiscard),
         "unix-word-rubout"     =>   Some(Event::EvActUnixWordRubout),
         "up"                   =>   Some(Event::EvActUp(arg.and_then(|s|s.parse().ok()).unwrap_or(1))),
         "yank"                 =>   Some(Event::EvActYank),
+        "yank-pop"             =>   Some(Event::EvActYankPop),
         _ => None
     }
 }
+
+/// The action and its argument of an event, the reverse of `parse_event`. `None` if the event
+/// could not be bound, e.g. `EvHeartBeat`.
+#[rustfmt::skip]
+pub fn event_action(event: &Event) -> Option<(&'static str, Option<String>)> {
+    let (action, arg) = match event {
+        Event::EvActAbort(code)             => ("abort", code.map(|code| code.to_string())),
+        Event::EvActAccept(arg)             => ("accept", arg.clone()),
+        Event::EvActAcceptOrPrintQuery      => ("accept-or-print-query", None),
+        Event::EvActAppendAndSelect         => ("append-and-select", None),
+        Event::EvActBackwardChar            => ("backward-char", None),
+        Event::EvActBackwardDeleteChar      => ("backward-delete-char", None),
+        Event::EvActBackwardKillWord        => ("backward-kill-word", None),
+        Event::EvActBackwardWord            => ("backward-word", None),
+        Event::EvActBeginningOfLine         => ("beginning-of-line", None),
+        Event::EvActCancel                  => ("cancel", None),
+        Event::EvActClearHeader             => ("clear-header", None),
+        Event::EvActClearScreen             => ("clear-screen", None),
+        Event::EvActCopyToClipboard         => ("copy-to-clipboard", None),
+        Event::EvActDeleteChar              => ("delete-char", None),
+        Event::EvActDeleteCharEOF           => ("delete-charEOF", None),
+        Event::EvActDeselect(arg)           => ("deselect", Some(arg.clone())),
+        Event::EvActDeselectAll             => ("deselect-all", None),
+        Event::EvActDown(count)             => ("down", Some(count.to_string())),
+        Event::EvActEndOfLine               => ("end-of-line", None),
+        Event::EvActExecute(arg)            => ("execute", Some(arg.clone())),
+        Event::EvActExecuteSilent(arg)      => ("execute-silent", Some(arg.clone())),
+        Event::EvActExecuteToQuery(arg)     => ("execute-to-query", Some(arg.clone())),
+        Event::EvActExecuteAppendItems(arg) => ("execute-append-items", Some(arg.clone())),
+        Event::EvActForwardChar             => ("forward-char", None),
+        Event::EvActForwardWord             => ("forward-word", None),
+        Event::EvActIfNonMatched(arg)       => ("if-non-matched", Some(arg.clone())),
+        Event::EvActIfQueryEmpty(arg)       => ("if-query-empty", Some(arg.clone())),
+        Event::EvActIfQueryNotEmpty(arg)    => ("if-query-not-empty", Some(arg.clone())),
+        Event::EvActIgnore                  => ("ignore", None),
+        Event::EvActKillLine                => ("kill-line", None),
+        Event::EvActKillWord                => ("kill-word", None),
+        Event::EvActNextHistory             => ("next-history", None),
+        Event::EvActHalfPageDown(count)     => ("half-page-down", Some(count.to_string())),
+        Event::EvActHalfPageUp(count)       => ("half-page-up", Some(count.to_string())),
+        Event::EvActPageDown(count)         => ("page-down", Some(count.to_string())),
+        Event::EvActPageUp(count)           => ("page-up", Some(count.to_string())),
+        Event::EvActParent                  => ("parent", None),
+        Event::EvActPreviewUp(count)        => ("preview-up", Some(count.to_string())),
+        Event::EvActPreviewDown(count)      => ("preview-down", Some(count.to_string())),
+        Event::EvActPreviewLeft(count)      => ("preview-left", Some(count.to_string())),
+        Event::EvActPreviewRight(count)     => ("preview-right", Some(count.to_string())),
+        Event::EvActPreviewPageUp(count)    => ("preview-page-up", Some(count.to_string())),
+        Event::EvActPreviewPageDown(count)  => ("preview-page-down", Some(count.to_string())),
+        Event::EvActPreSelectPat(arg)       => ("pre-select-pat", Some(arg.clone())),
+        Event::EvActPreviousHistory         => ("previous-history", None),
+        Event::EvActRefreshCmd              => ("refresh-cmd", None),
+        Event::EvActRefreshPreview          => ("refresh-preview", None),
+        Event::EvActReloadTheme             => ("reload-theme", None),
+        Event::EvActScrollLeft(count)       => ("scroll-left", Some(count.to_string())),
+        Event::EvActScrollRight(count)      => ("scroll-right", Some(count.to_string())),
+        Event::EvActSelect(arg)             => ("select", Some(arg.clone())),
+        Event::EvActSelectAll               => ("select-all", None),
+        Event::EvActSetHeader(arg)          => ("set-header", Some(arg.clone())),
+        Event::EvActShowHelp                => ("show-help", None),
+        Event::EvActSuspend                 => ("suspend", None),
+        Event::EvActToggle                  => ("toggle", None),
+        Event::EvActToggleAll               => ("toggle-all", None),
+        Event::EvActToggleFollow            => ("toggle-follow", None),
+        Event::EvActToggleIn                => ("toggle-in", None),
+        Event::EvActToggleInteractive       => ("toggle-interactive", None),
+        Event::EvActToggleMouse             => ("toggle-mouse", None),
+        Event::EvActToggleOut               => ("toggle-out", None),
+        Event::EvActTogglePreview           => ("toggle-preview", None),
+        Event::EvActTogglePreviewWrap       => ("toggle-preview-wrap", None),
+        Event::EvActToggleSort              => ("toggle-sort", None),
+        Event::EvActTransposeChars          => ("transpose-chars", None),
+        Event::EvActUnixLineDiscard         => ("unix-line-discard", None),
+        Event::EvActUnixWordRubout          => ("unix-word-rubout", None),
+        Event::EvActUp(count)               => ("up", Some(count.to_string())),
+        Event::EvActYank                    => ("yank", None),
+        Event::EvActYankPop                 => ("yank-pop", None),
+        _ => return None,
+    };
+    Some((action, arg))
+}
@@ -17,6 +17,9 @@ lazy_static! {
     };
 }
 
+/// The number of the current run of the command, the same command(with the same arguments) gets
+/// the same number. Together with the index of an item in the run it identifies the item, see
+/// `ItemIdentity`.
 pub fn current_run_num() -> u32 {
     RUN_NUM.load(Ordering::SeqCst)
 }
@@ -29,6 +32,11 @@ pub fn mark_new_run(query: &str) -> u32 {
     run_num
 }
 
+/// go back to a former run, e.g. the items of the previous level of `--drill-cmd`
+pub fn restore_run(run_num: u32) {
+    RUN_NUM.store(run_num, Ordering::SeqCst);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -44,5 +52,7 @@ mod tests {
         assert_eq!(1, current_run_num());
         mark_new_run("");
         assert_eq!(0, current_run_num());
+        restore_run(2);
+        assert_eq!(2, current_run_num());
     }
 }
@@ -2,6 +2,7 @@
 use crate::ansi::{ANSIParser, AnsiString};
 use crate::event::UpdateScreen;
 use crate::event::{Event, EventHandler};
+use crate::input::split_expect_keys;
 use crate::item::ItemPool;
 use crate::theme::ColorTheme;
 use crate::theme::DEFAULT_THEME;
@@ -14,6 +15,8 @@ use tuikit::prelude::*;
 
 pub struct Header {
     header: Vec<AnsiString<'static>>,
+    // the keys of `--expect` and their meanings(`--expect-hint`)
+    expect_hint: Option<String>,
     tabstop: usize,
     reverse: bool,
     theme: Arc<ColorTheme>,
@@ -26,6 +29,7 @@ impl Header {
     pub fn empty() -> Self {
         Self {
             header: vec![],
+            expect_hint: None,
             tabstop: 8,
             reverse: false,
             theme: Arc::new(*DEFAULT_THEME),
@@ -43,6 +47,10 @@ impl Header {
         self
     }
 
+    pub fn set_theme(&mut self, theme: Arc<ColorTheme>) {
+        self.theme = theme;
+    }
+
     pub fn with_options(mut self, options: &SkimOptions) -> Self {
         if let Some(tabstop_str) = options.tabstop {
             let tabstop = tabstop_str.parse::<usize>().unwrap_or(8);
@@ -53,19 +61,40 @@ impl Header {
             self.reverse = true;
         }
 
-        match options.header {
-            None => {}
-            Some("") => {}
-            Some(header) => {
-                let mut parser = ANSIParser::default();
-                self.header = str_lines(header).into_iter().map(|l| parser.parse_ansi(l)).collect();
-            }
+        if let Some(header) = options.header {
+            self.set_header(header);
+        }
+
+        if let (true, Some(expect)) = (options.expect_hint, options.expect.as_deref()) {
+            self.set_expect_hint(expect);
         }
         self
     }
 
+    /// show the keys of `--expect`, e.g. `ctrl-e:edit,alt-s` as `ctrl-e: edit  alt-s`
+    fn set_expect_hint(&mut self, expect: &str) {
+        let hints: Vec<String> = split_expect_keys(expect)
+            .into_iter()
+            .map(|(key, meaning)| match meaning {
+                Some(meaning) => format!("{}: {}", key, meaning),
+                None => key.to_string(),
+            })
+            .collect();
+        self.expect_hint = Some(hints.join("  ")).filter(|hint| !hint.is_empty());
+    }
+
+    /// replace the lines specified by `--header`, an empty header removes them
+    pub fn set_header(&mut self, header: &str) {
+        if header.is_empty() {
+            self.header.clear();
+        } else {
+            let mut parser = ANSIParser::default();
+            self.header = str_lines(header).into_iter().map(|l| parser.parse_ansi(l)).collect();
+        }
+    }
+
     fn lines_of_header(&self) -> usize {
-        self.header.len() + self.item_pool.reserved().len()
+        self.header.len() + self.expect_hint.iter().count() + self.item_pool.reserved().len()
     }
 
     fn adjust_row(&self, index: usize, screen_height: usize) -> usize {
@@ -107,7 +136,23 @@ impl Draw for Header {
             }
         }
 
-        let lines_used = self.header.len();
+        let mut lines_used = self.header.len();
+
+        if let Some(hint) = self.expect_hint.as_ref() {
+            let mut printer = LinePrinter::builder()
+                .row(self.adjust_row(lines_used, screen_height))
+                .col(2)
+                .tabstop(self.tabstop)
+                .container_width(screen_width - 2)
+                .shift(0)
+                .text_width(screen_width - 2)
+                .build();
+
+            for ch in hint.chars() {
+                printer.print_char(canvas, ch, self.theme.info(), false);
+            }
+            lines_used += 1;
+        }
 
         // print "reserved" header lines (--header-lines)
         for (idx, item) in self.item_pool.reserved().iter().enumerate() {
@@ -142,7 +187,13 @@ impl Widget<Event> for Header {
 }
 
 impl EventHandler for Header {
-    fn handle(&mut self, _event: &Event) -> UpdateScreen {
-        UpdateScreen::DONT_REDRAW
+    fn handle(&mut self, event: &Event) -> UpdateScreen {
+        match event {
+            Event::EvActClearHeader => {
+                self.set_header("");
+                UpdateScreen::REDRAW
+            }
+            _ => UpdateScreen::DONT_REDRAW,
+        }
     }
 }
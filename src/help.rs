@@ -0,0 +1,148 @@
+//! the overlay of the `show-help` action, which lists the key bindings
+use crate::event::{Event, EventHandler, UpdateScreen};
+use crate::theme::{ColorTheme, DEFAULT_THEME};
+use crate::util::clear_canvas;
+use std::cmp::{max, min};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tuikit::prelude::*;
+
+const TITLE: &str = "Key bindings";
+
+pub struct Help {
+    // the keys and their descriptions
+    bindings: Vec<(String, String)>,
+    key_width: usize,
+    shown: bool,
+    offset: usize,
+    // the rows of the bindings on the last draw
+    height: AtomicUsize,
+    theme: Arc<ColorTheme>,
+}
+
+impl Help {
+    pub fn new(bindings: Vec<(String, String)>) -> Self {
+        let key_width = bindings.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+        Self {
+            bindings,
+            key_width,
+            shown: false,
+            offset: 0,
+            height: AtomicUsize::new(0),
+            theme: Arc::new(*DEFAULT_THEME),
+        }
+    }
+
+    pub fn theme(mut self, theme: Arc<ColorTheme>) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn set_theme(&mut self, theme: Arc<ColorTheme>) {
+        self.theme = theme;
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.shown
+    }
+
+    pub fn hide(&mut self) {
+        self.shown = false;
+    }
+
+    /// whether the event scrolls the help while it is shown
+    pub fn scrolls_on(&self, event: &Event) -> bool {
+        use crate::event::Event::*;
+        self.shown && matches!(event, EvActUp(_) | EvActDown(_) | EvActPageUp(_) | EvActPageDown(_))
+    }
+
+    fn scroll_down(&mut self, diff: i32) {
+        let height = max(1, self.height.load(Ordering::Relaxed));
+        let max_offset = self.bindings.len().saturating_sub(height);
+        let offset = self.offset as i32 + diff;
+        self.offset = min(max(offset, 0) as usize, max_offset);
+    }
+}
+
+impl Draw for Help {
+    fn draw(&self, canvas: &mut dyn Canvas) -> DrawResult<()> {
+        let (screen_width, screen_height) = canvas.size()?;
+        canvas.clear()?;
+        clear_canvas(canvas)?;
+        if screen_width < 3 || screen_height < 2 {
+            return Ok(());
+        }
+
+        let height = screen_height - 1;
+        self.height.store(height, Ordering::Relaxed);
+        let last = min(self.offset + height, self.bindings.len());
+        let title = format!(
+            " {} {}-{}/{}",
+            TITLE,
+            min(self.offset + 1, last),
+            last,
+            self.bindings.len()
+        );
+        canvas.print_with_attr(0, 0, &title, self.theme.info())?;
+
+        let bindings = self.bindings.iter().skip(self.offset).take(height);
+        for (row, (key, description)) in bindings.enumerate() {
+            let col = canvas.print_with_attr(row + 1, 2, key, self.theme.prompt())?;
+            let col = 2 + max(col, self.key_width) + 2;
+            canvas.print_with_attr(row + 1, col, description, self.theme.normal())?;
+        }
+        Ok(())
+    }
+}
+
+impl Widget<Event> for Help {}
+
+impl EventHandler for Help {
+    fn handle(&mut self, event: &Event) -> UpdateScreen {
+        use crate::event::Event::*;
+        let height = self.height.load(Ordering::Relaxed) as i32;
+        match event {
+            EvActShowHelp => {
+                self.shown = !self.shown;
+                self.offset = 0;
+            }
+            _ if !self.shown => return UpdateScreen::DONT_REDRAW,
+            // the bindings are listed from the top, unlike the items
+            EvActUp(diff) => self.scroll_down(-*diff),
+            EvActDown(diff) => self.scroll_down(*diff),
+            EvActPageUp(diff) => self.scroll_down(-height * *diff),
+            EvActPageDown(diff) => self.scroll_down(height * *diff),
+            _ => return UpdateScreen::DONT_REDRAW,
+        }
+        UpdateScreen::REDRAW
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_help() {
+        let bindings = (0..10).map(|i| (format!("f{}", i), "abort".to_string())).collect();
+        let mut help = Help::new(bindings);
+        assert!(!help.scrolls_on(&Event::EvActDown(1)));
+        assert_eq!(help.handle(&Event::EvActDown(1)), UpdateScreen::DONT_REDRAW);
+
+        help.handle(&Event::EvActShowHelp);
+        assert!(help.is_shown());
+        assert!(help.scrolls_on(&Event::EvActDown(1)));
+        help.height.store(4, Ordering::Relaxed);
+        help.handle(&Event::EvActDown(2));
+        assert_eq!(help.offset, 2);
+        help.handle(&Event::EvActPageDown(1));
+        assert_eq!(help.offset, 6);
+        help.handle(&Event::EvActDown(1));
+        assert_eq!(help.offset, 6);
+        help.handle(&Event::EvActPageUp(3));
+        assert_eq!(help.offset, 0);
+
+        help.handle(&Event::EvActShowHelp);
+        assert!(!help.is_shown());
+    }
+}
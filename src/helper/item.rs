@@ -3,7 +3,12 @@ use crate::field::{parse_matching_fields, parse_transform_fields, FieldRange};
 use crate::{AnsiString, DisplayContext, Matches, SkimItem};
 use regex::Regex;
 use std::borrow::Cow;
+use std::collections::VecDeque;
 use tuikit::prelude::Attr;
+use unicode_width::UnicodeWidthStr;
+
+/// The number of the recent lines whose field widths decide the width of the aligned columns
+const ALIGN_SAMPLE_SIZE: usize = 100;
 
 //------------------------------------------------------------------------------
 /// An item will store everything that one line input will need to be operated and displayed.
@@ -26,21 +31,29 @@ pub struct DefaultSkimItem {
     /// The text that will be shown on screen and matched.
     text: AnsiString<'static>,
 
+    /// strip the ANSI codes of the output, they are kept by default unless the ANSI codes are
+    /// parsed for display
+    strip_output: bool,
+
     // Option<Box<_>> to reduce memory use in normal cases where no matching ranges are specified.
     #[allow(clippy::box_collection)]
     matching_ranges: Option<Box<Vec<(usize, usize)>>>,
 }
 
 impl DefaultSkimItem {
+    /// `ansi_output` keeps the ANSI codes in the output when they are parsed(`ansi_enabled`),
+    /// `strip_ansi_output` strips them from the output in any case
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         orig_text: String,
         ansi_enabled: bool,
+        ansi_output: bool,
+        strip_ansi_output: bool,
         trans_fields: &[FieldRange],
         matching_fields: &[FieldRange],
         delimiter: &Regex,
+        aligner: Option<&mut ColumnAligner>,
     ) -> Self {
-        let using_transform_fields = !trans_fields.is_empty();
-
         //        transformed | ANSI             | output
         //------------------------------------------------------
         //                    +- T -> trans+ANSI | ANSI
@@ -50,17 +63,33 @@ impl DefaultSkimItem {
         //      +- F -> orig  +- T -> ANSI     ==| ANSI
         //                    |                  |
         //                    +- F -> orig       | orig
+        //
+        // aligning the columns counts as a transformation
 
         let mut ansi_parser: ANSIParser = Default::default();
 
-        let (orig_text, text) = if using_transform_fields && ansi_enabled {
+        let transformed = if trans_fields.is_empty() {
+            None
+        } else {
+            Some(parse_transform_fields(delimiter, &orig_text, trans_fields))
+        };
+
+        let transformed = match aligner {
+            Some(aligner) => Some(aligner.align(transformed.as_ref().unwrap_or(&orig_text), delimiter, ansi_enabled)),
+            None => transformed,
+        };
+
+        let (orig_text, text) = if let (Some(transformed), true) = (transformed.as_ref(), ansi_enabled) {
             // ansi and transform
-            let transformed = ansi_parser.parse_ansi(&parse_transform_fields(delimiter, &orig_text, trans_fields));
+            let transformed = ansi_parser.parse_ansi(transformed);
             (Some(orig_text), transformed)
-        } else if using_transform_fields {
+        } else if let Some(transformed) = transformed {
             // transformed, not ansi
-            let transformed = parse_transform_fields(delimiter, &orig_text, trans_fields).into();
-            (Some(orig_text), transformed)
+            (Some(orig_text), transformed.into())
+        } else if ansi_enabled && ansi_output {
+            // not transformed, ansi, keep the raw text for output
+            let text = ansi_parser.parse_ansi(&orig_text);
+            (Some(orig_text), text)
         } else if ansi_enabled {
             // not transformed, ansi
             (None, ansi_parser.parse_ansi(&orig_text))
@@ -82,6 +111,7 @@ impl DefaultSkimItem {
         DefaultSkimItem {
             orig_text,
             text,
+            strip_output: strip_ansi_output || (ansi_enabled && !ansi_output),
             matching_ranges,
         }
     }
@@ -94,16 +124,16 @@ impl SkimItem for DefaultSkimItem {
     }
 
     fn output(&self) -> Cow<str> {
-        if self.orig_text.is_some() {
-            if self.text.has_attrs() {
-                let mut ansi_parser: ANSIParser = Default::default();
-                let text = ansi_parser.parse_ansi(self.orig_text.as_ref().unwrap());
-                text.into_inner()
-            } else {
-                Cow::Borrowed(self.orig_text.as_ref().unwrap())
-            }
+        let output = match self.orig_text {
+            Some(ref orig_text) => orig_text,
+            None => self.text.stripped(),
+        };
+
+        if self.strip_output && output.contains('\x1b') {
+            let mut ansi_parser: ANSIParser = Default::default();
+            ansi_parser.parse_ansi(output).into_inner()
         } else {
-            Cow::Borrowed(self.text.stripped())
+            Cow::Borrowed(output)
         }
     }
 
@@ -130,3 +160,177 @@ impl SkimItem for DefaultSkimItem {
         ret
     }
 }
+
+/// Pads the fields of the lines into aligned columns. The width of a column is the max width of
+/// the field in the recent `ALIGN_SAMPLE_SIZE` lines, so that the lines are aligned as they are
+/// read, and a few long lines only widen the columns of the lines near them.
+#[derive(Debug, Default)]
+pub struct ColumnAligner {
+    recent_widths: VecDeque<Vec<usize>>,
+}
+
+impl ColumnAligner {
+    /// The fields of `text` are padded with spaces after their delimiters, the whitespace
+    /// delimiters are squeezed into one space. The last field is not padded.
+    pub fn align(&mut self, text: &str, delimiter: &Regex, ansi_enabled: bool) -> String {
+        let mut fields = Vec::new();
+        let mut last = 0;
+        for mat in delimiter.find_iter(text) {
+            let delimiter = if mat.as_str().trim().is_empty() {
+                " "
+            } else {
+                mat.as_str()
+            };
+            fields.push((&text[last..mat.start()], delimiter));
+            last = mat.end();
+        }
+        fields.push((&text[last..], ""));
+
+        let width_of = |field: &str| {
+            if ansi_enabled {
+                ANSIParser::default().parse_ansi(field).stripped().width()
+            } else {
+                field.width()
+            }
+        };
+        let widths: Vec<usize> = fields
+            .iter()
+            .map(|(field, delimiter)| width_of(field) + delimiter.width())
+            .collect();
+
+        if self.recent_widths.len() >= ALIGN_SAMPLE_SIZE {
+            self.recent_widths.pop_front();
+        }
+        self.recent_widths.push_back(widths);
+        let widths = self.recent_widths.back().unwrap();
+
+        let mut ret = String::with_capacity(text.len());
+        for (idx, (field, delimiter)) in fields.iter().enumerate() {
+            ret.push_str(field);
+            ret.push_str(delimiter);
+            if idx + 1 < fields.len() {
+                let column_width = self
+                    .recent_widths
+                    .iter()
+                    .filter_map(|widths| widths.get(idx))
+                    .max()
+                    .copied()
+                    .unwrap_or(0);
+                ret.push_str(&" ".repeat(column_width - widths[idx]));
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::factory::{AndOrEngineFactory, ExactOrFuzzyEngineFactory, RegexEngineFactory};
+    use crate::{MatchEngineFactory, MatchRange};
+    use std::sync::Arc;
+    use tuikit::prelude::{Color, Effect};
+
+    const HIGHLIGHT: Attr = Attr {
+        fg: Color::Default,
+        bg: Color::Default,
+        effect: Effect::UNDERLINE,
+    };
+
+    /// the indices of the highlighted chars of the item displayed with the matches of `query`
+    fn highlighted(item: DefaultSkimItem, factory: &dyn MatchEngineFactory, query: &str) -> Option<Vec<usize>> {
+        let item: Arc<dyn SkimItem> = Arc::new(item);
+        let result = factory.create_engine(query).match_item(item.clone())?;
+        let text = item.text();
+        let matches = match result.matched_range {
+            MatchRange::Chars(ref indices) => Matches::CharIndices(indices),
+            MatchRange::ByteRange(start, end) => Matches::ByteRange(start, end),
+        };
+        let context = DisplayContext {
+            text: &text,
+            score: 0,
+            matches,
+            container_width: 80,
+            highlight_attr: HIGHLIGHT,
+        };
+        let display = item.display(context);
+        Some(
+            display
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, attr))| attr.effect.contains(Effect::UNDERLINE))
+                .map(|(idx, _)| idx)
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_highlight_matching_ranges() {
+        let delimiter = Regex::new(",").unwrap();
+        let nth = |ranges: &str| -> Vec<FieldRange> { ranges.split(',').filter_map(FieldRange::from_str).collect() };
+        let item = |text: &str, ansi: bool, nth: &[FieldRange], with_nth: &[FieldRange]| {
+            DefaultSkimItem::new(text.to_string(), ansi, false, false, with_nth, nth, &delimiter, None)
+        };
+        let fuzzy = AndOrEngineFactory::new(ExactOrFuzzyEngineFactory::builder().build());
+        let regex = RegexEngineFactory::builder();
+
+        // the match is in the field of `--nth`, not the first occurrence in the text
+        // 中ab,x中ab,ab
+        // 0123456789A
+        let text = "中ab,x中ab,\x1b[31mab\x1b[0m";
+        assert_eq!(
+            Some(vec![9, 10]),
+            highlighted(item(text, true, &nth("3"), &[]), &fuzzy, "ab")
+        );
+        assert_eq!(
+            Some(vec![6, 7]),
+            highlighted(item(text, true, &nth("2"), &[]), &fuzzy, "'ab")
+        );
+        assert_eq!(
+            Some(vec![5, 6, 7]),
+            highlighted(item(text, true, &nth("2"), &[]), &regex, "中a.")
+        );
+        assert_eq!(
+            Some(vec![4, 6, 7]),
+            highlighted(item(text, true, &nth("2"), &[]), &fuzzy, "x ab")
+        );
+        assert_eq!(None, highlighted(item(text, true, &nth("3"), &[]), &fuzzy, "x"));
+
+        // the fields of `--nth` are those of the transformed text
+        let item_nth = item(text, true, &nth("2"), &nth("2,3"));
+        assert_eq!("x中ab,ab", item_nth.text());
+        assert_eq!(Some(vec![5, 6]), highlighted(item_nth, &fuzzy, "ab"));
+        let item_nth = item(text, true, &nth("1"), &nth("2,3"));
+        assert_eq!(Some(vec![1, 2, 3]), highlighted(item_nth, &regex, "中ab"));
+    }
+
+    #[test]
+    fn test_column_aligner() {
+        let spaces = Regex::new(r"[\t\n ]+").unwrap();
+        let mut aligner = ColumnAligner::default();
+        assert_eq!("PID CMD", aligner.align("PID CMD", &spaces, false));
+        assert_eq!("12345 sk --align", aligner.align("12345\tsk --align", &spaces, false));
+        assert_eq!("1     vim x", aligner.align("1  vim x", &spaces, false));
+        assert_eq!("中文  a", aligner.align("中文 a", &spaces, false));
+
+        let comma = Regex::new(",").unwrap();
+        let mut aligner = ColumnAligner::default();
+        assert_eq!("long,b", aligner.align("long,b", &comma, false));
+        assert_eq!(
+            "a,   \x1b[31mb\x1b[0m,c",
+            aligner.align("a,\x1b[31mb\x1b[0m,c", &comma, true)
+        );
+    }
+
+    #[test]
+    fn test_column_aligner_sample() {
+        let spaces = Regex::new(" ").unwrap();
+        let mut aligner = ColumnAligner::default();
+        aligner.align("long_field x", &spaces, false);
+        for _ in 0..ALIGN_SAMPLE_SIZE - 1 {
+            assert_eq!("a          x", aligner.align("a x", &spaces, false));
+        }
+        // the long line is out of the sample now
+        assert_eq!("a x", aligner.align("a x", &spaces, false));
+    }
+}
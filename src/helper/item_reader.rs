@@ -1,7 +1,9 @@
 /// helper for turn a BufRead into a skim stream
+use std::borrow::Cow;
 use std::env;
 use std::error::Error;
-use std::io::{BufRead, BufReader};
+use std::fmt::{Debug, Formatter};
+use std::io::{BufRead, BufReader, Cursor};
 use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -11,13 +13,13 @@ use crossbeam::channel::{bounded, Receiver, Sender};
 use regex::Regex;
 
 use crate::field::FieldRange;
-use crate::helper::item::DefaultSkimItem;
+use crate::helper::item::{ColumnAligner, DefaultSkimItem};
 use crate::reader::CommandCollector;
+use crate::util::{CommandEnv, DEFAULT_DELIMITER};
 use crate::{SkimItem, SkimItemReceiver, SkimItemSender};
 
 const CMD_CHANNEL_SIZE: usize = 1024;
 const ITEM_CHANNEL_SIZE: usize = 10240;
-const DELIMITER_STR: &str = r"[\t\n ]+";
 const READ_BUFFER_SIZE: usize = 1024;
 
 pub enum CollectorInput {
@@ -25,15 +27,35 @@ pub enum CollectorInput {
     Command(String),
 }
 
+type LineTransformFn = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+/// Transform a line before it is turned into an item, `None` to drop the line.
+#[derive(Clone)]
+struct LineTransform(Arc<LineTransformFn>);
+
+impl Debug for LineTransform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LineTransform")
+    }
+}
+
 #[derive(Debug)]
 pub struct SkimItemReaderOption {
     buf_size: usize,
     use_ansi_color: bool,
+    ansi_output: bool,
+    strip_ansi_output: bool,
     transform_fields: Vec<FieldRange>,
     matching_fields: Vec<FieldRange>,
     delimiter: Regex,
     line_ending: u8,
     show_error: bool,
+    trim_trailing: bool,
+    skip_blank: bool,
+    skip_pattern: Option<Regex>,
+    command_env: CommandEnv,
+    line_transform: Option<LineTransform>,
+    align: bool,
 }
 
 impl Default for SkimItemReaderOption {
@@ -42,10 +64,18 @@ impl Default for SkimItemReaderOption {
             buf_size: READ_BUFFER_SIZE,
             line_ending: b'\n',
             use_ansi_color: false,
+            ansi_output: false,
+            strip_ansi_output: false,
             transform_fields: Vec::new(),
             matching_fields: Vec::new(),
-            delimiter: Regex::new(DELIMITER_STR).unwrap(),
+            delimiter: DEFAULT_DELIMITER.clone(),
             show_error: false,
+            trim_trailing: false,
+            skip_blank: false,
+            skip_pattern: None,
+            command_env: CommandEnv::default(),
+            line_transform: None,
+            align: false,
         }
     }
 }
@@ -66,9 +96,23 @@ impl SkimItemReaderOption {
         self
     }
 
+    /// output the original line with the ANSI codes on accept instead of the stripped one,
+    /// only takes effect with `ansi`
+    pub fn ansi_output(mut self, enable: bool) -> Self {
+        self.ansi_output = enable;
+        self
+    }
+
+    /// strip the ANSI codes of the line on accept whether or not they are parsed for display,
+    /// takes precedence over `ansi_output`
+    pub fn strip_ansi_output(mut self, enable: bool) -> Self {
+        self.strip_ansi_output = enable;
+        self
+    }
+
     pub fn delimiter(mut self, delimiter: &str) -> Self {
         if !delimiter.is_empty() {
-            self.delimiter = Regex::new(delimiter).unwrap_or_else(|_| Regex::new(DELIMITER_STR).unwrap());
+            self.delimiter = Regex::new(delimiter).unwrap_or_else(|_| DEFAULT_DELIMITER.clone());
         }
         self
     }
@@ -97,6 +141,12 @@ impl SkimItemReaderOption {
         self
     }
 
+    /// pad the fields (after `with_nth`) into aligned columns, see `ColumnAligner`
+    pub fn align(mut self, enable: bool) -> Self {
+        self.align = enable;
+        self
+    }
+
     pub fn read0(mut self, enable: bool) -> Self {
         if enable {
             self.line_ending = b'\0';
@@ -111,12 +161,90 @@ impl SkimItemReaderOption {
         self
     }
 
+    /// remove the trailing whitespaces of every line
+    pub fn trim_trailing(mut self, enable: bool) -> Self {
+        self.trim_trailing = enable;
+        self
+    }
+
+    /// skip the lines that contain only whitespaces
+    pub fn skip_blank(mut self, enable: bool) -> Self {
+        self.skip_blank = enable;
+        self
+    }
+
+    /// skip the lines that match the regex, e.g. `^#` for comments
+    pub fn skip_pattern(mut self, pattern: &str) -> Self {
+        if !pattern.is_empty() {
+            self.skip_pattern = Regex::new(pattern).ok();
+        }
+        self
+    }
+
+    /// the working directory of the invoked commands
+    pub fn cmd_cwd(mut self, cwd: &str) -> Self {
+        self.command_env.cwd = CommandEnv::new(Some(cwd), &[]).cwd;
+        self
+    }
+
+    /// extra environment variables(`KEY=VALUE`) of the invoked commands
+    pub fn cmd_env(mut self, vars: &[&str]) -> Self {
+        self.command_env.vars = CommandEnv::new(None, vars).vars;
+        self
+    }
+
+    /// Normalize, annotate or drop(by returning `None`) the lines during ingestion. The
+    /// transform runs in the reader thread, after the other filters.
+    ///
+    /// ```
+    /// use skim::prelude::*;
+    ///
+    /// let option = SkimItemReaderOption::default()
+    ///     .line_transform(|line| if line.starts_with("//") { None } else { Some(line.to_uppercase()) })
+    ///     .build();
+    /// ```
+    pub fn line_transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.line_transform = Some(LineTransform(Arc::new(transform)));
+        self
+    }
+
     pub fn build(self) -> Self {
         self
     }
 
+    /// apply the ingestion filters, `None` if the line should be skipped
+    fn filter_line<'a>(&self, line: Cow<'a, str>) -> Option<Cow<'a, str>> {
+        let line = if self.trim_trailing && line.ends_with(char::is_whitespace) {
+            Cow::Owned(line.trim_end().to_string())
+        } else {
+            line
+        };
+
+        if self.skip_blank && line.trim_start().is_empty() {
+            return None;
+        }
+
+        if let Some(ref pattern) = self.skip_pattern {
+            if pattern.is_match(&line) {
+                return None;
+            }
+        }
+
+        match self.line_transform {
+            Some(LineTransform(ref transform)) => transform(&line).map(Cow::Owned),
+            None => Some(line),
+        }
+    }
+
     pub fn is_simple(&self) -> bool {
-        !self.use_ansi_color && self.matching_fields.is_empty() && self.transform_fields.is_empty()
+        !self.use_ansi_color
+            && !self.strip_ansi_output
+            && self.matching_fields.is_empty()
+            && self.transform_fields.is_empty()
+            && !self.align
     }
 }
 
@@ -158,26 +286,24 @@ impl SkimItemReader {
     /// helper: convert bufread into SkimItemReceiver
     fn raw_bufread(&self, mut source: impl BufRead + Send + 'static) -> SkimItemReceiver {
         let (tx_item, rx_item): (SkimItemSender, SkimItemReceiver) = bounded(self.option.buf_size);
-        let line_ending = self.option.line_ending;
+        let option = self.option.clone();
         thread::spawn(move || {
             let mut buffer = Vec::with_capacity(1024);
             loop {
                 buffer.clear();
                 // start reading
-                match source.read_until(line_ending, &mut buffer) {
+                match source.read_until(option.line_ending, &mut buffer) {
                     Ok(n) => {
                         if n == 0 {
                             break;
                         }
 
-                        if buffer.ends_with(&[b'\r', b'\n']) {
-                            buffer.pop();
-                            buffer.pop();
-                        } else if buffer.ends_with(&[b'\n']) || buffer.ends_with(&[b'\0']) {
-                            buffer.pop();
-                        }
+                        strip_line_ending(&mut buffer, option.line_ending);
 
-                        let string = String::from_utf8_lossy(&buffer);
+                        let string = match option.filter_line(String::from_utf8_lossy(&buffer)) {
+                            Some(string) => string,
+                            None => continue,
+                        };
                         let result = tx_item.send(Arc::new(string.into_owned()));
                         if result.is_err() {
                             break;
@@ -199,7 +325,18 @@ impl SkimItemReader {
     ) -> (Receiver<Arc<dyn SkimItem>>, Sender<i32>) {
         let (command, mut source) = match input {
             CollectorInput::Pipe(pipe) => (None, pipe),
-            CollectorInput::Command(cmd) => get_command_output(&cmd).expect("command not found"),
+            CollectorInput::Command(cmd) => match get_command_output(&cmd, &self.option.command_env) {
+                Ok(output) => output,
+                Err(err) => {
+                    // e.g. a missing `--cmd-cwd`, the error is shown as the only item
+                    warn!("collector: failed to run {:?}: {}", cmd, err);
+                    let message = format!("[sk] failed to run the command: {}", err);
+                    (
+                        None,
+                        Box::new(Cursor::new(message.into_bytes())) as Box<dyn BufRead + Send>,
+                    )
+                }
+            },
         };
 
         let (tx_interrupt, rx_interrupt) = bounded(CMD_CHANNEL_SIZE);
@@ -254,6 +391,7 @@ impl SkimItemReader {
             started_clone.store(true, Ordering::SeqCst); // notify parent that it is started
 
             let mut buffer = Vec::with_capacity(option.buf_size);
+            let mut aligner = ColumnAligner::default();
             loop {
                 buffer.clear();
 
@@ -264,21 +402,22 @@ impl SkimItemReader {
                             break;
                         }
 
-                        if buffer.ends_with(&[b'\r', b'\n']) {
-                            buffer.pop();
-                            buffer.pop();
-                        } else if buffer.ends_with(&[b'\n']) || buffer.ends_with(&[b'\0']) {
-                            buffer.pop();
-                        }
+                        strip_line_ending(&mut buffer, option.line_ending);
 
-                        let line = String::from_utf8_lossy(&buffer).to_string();
+                        let line = match option.filter_line(String::from_utf8_lossy(&buffer)) {
+                            Some(line) => line.into_owned(),
+                            None => continue,
+                        };
 
                         let raw_item = DefaultSkimItem::new(
                             line,
                             option.use_ansi_color,
+                            option.ansi_output,
+                            option.strip_ansi_output,
                             &option.transform_fields,
                             &option.matching_fields,
                             &option.delimiter,
+                            if option.align { Some(&mut aligner) } else { None },
                         );
 
                         match tx_item.send(Arc::new(raw_item)) {
@@ -314,9 +453,11 @@ impl CommandCollector for SkimItemReader {
 
 type CommandOutput = (Option<Child>, Box<dyn BufRead + Send>);
 
-fn get_command_output(cmd: &str) -> Result<CommandOutput, Box<dyn Error>> {
+fn get_command_output(cmd: &str, command_env: &CommandEnv) -> Result<CommandOutput, Box<dyn Error>> {
     let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
-    let mut command: Child = Command::new(shell)
+    let mut command = Command::new(shell);
+    command_env.apply(&mut command);
+    let mut command: Child = command
         .arg("-c")
         .arg(cmd)
         .stdout(Stdio::piped())
@@ -330,3 +471,139 @@ fn get_command_output(cmd: &str) -> Result<CommandOutput, Box<dyn Error>> {
 
     Ok((Some(command), Box::new(BufReader::new(stdout))))
 }
+
+/// Strip the line ending of a line read by `read_until`: `\n` or `\r\n` for lines, and only the
+/// `\0` for `--read0`, where the `\r` and `\n` are part of the items.
+fn strip_line_ending(buffer: &mut Vec<u8>, line_ending: u8) {
+    if buffer.last() == Some(&line_ending) {
+        buffer.pop();
+        if line_ending == b'\n' && buffer.last() == Some(&b'\r') {
+            buffer.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_lines(option: SkimItemReaderOption, input: &'static str) -> Vec<String> {
+        let reader = SkimItemReader::new(option);
+        let rx_item = reader.of_bufread(BufReader::new(input.as_bytes()));
+        rx_item.iter().map(|item| item.text().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_ingestion_filters() {
+        let input = "a  \n  \n# comment\nb\t\n\n";
+        assert_eq!(
+            read_lines(SkimItemReaderOption::default(), input),
+            vec!["a  ", "  ", "# comment", "b\t", ""]
+        );
+
+        let option = SkimItemReaderOption::default()
+            .trim_trailing(true)
+            .skip_blank(true)
+            .skip_pattern("^#")
+            .build();
+        assert_eq!(read_lines(option, input), vec!["a", "b"]);
+
+        // filters are applied on the non-simple path as well
+        let option = SkimItemReaderOption::default()
+            .nth("1")
+            .skip_blank(true)
+            .skip_pattern("^#")
+            .build();
+        assert_eq!(read_lines(option, input), vec!["a  ", "b\t"]);
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let input = "a\r\nb\nc\r\n\r\nd\re\r\nf";
+        let expected = vec!["a", "b", "c", "", "d\re", "f"];
+        assert_eq!(read_lines(SkimItemReaderOption::default(), input), expected);
+        assert_eq!(
+            read_lines(SkimItemReaderOption::default().nth("1").build(), input),
+            expected
+        );
+
+        let input = "a\r\n\0b\n\0c\r\n";
+        let expected = vec!["a\r\n", "b\n", "c\r\n"];
+        assert_eq!(read_lines(SkimItemReaderOption::default().read0(true), input), expected);
+        assert_eq!(
+            read_lines(SkimItemReaderOption::default().read0(true).nth("1").build(), input),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_ansi_output() {
+        let input = "\x1b[31mred\x1b[0m blue\n";
+        let output = |option: SkimItemReaderOption| -> Vec<String> {
+            let reader = SkimItemReader::new(option);
+            let rx_item = reader.of_bufread(BufReader::new(input.as_bytes()));
+            rx_item.iter().map(|item| item.output().into_owned()).collect()
+        };
+
+        assert_eq!(output(SkimItemReaderOption::default().ansi(true)), vec!["red blue"]);
+        assert_eq!(
+            output(SkimItemReaderOption::default().ansi(true).ansi_output(true)),
+            vec!["\x1b[31mred\x1b[0m blue"]
+        );
+        assert_eq!(
+            output(
+                SkimItemReaderOption::default()
+                    .ansi(true)
+                    .ansi_output(true)
+                    .with_nth("2")
+            ),
+            vec!["\x1b[31mred\x1b[0m blue"]
+        );
+
+        assert_eq!(output(SkimItemReaderOption::default()), vec!["\x1b[31mred\x1b[0m blue"]);
+        assert_eq!(
+            output(SkimItemReaderOption::default().strip_ansi_output(true)),
+            vec!["red blue"]
+        );
+        assert_eq!(
+            output(
+                SkimItemReaderOption::default()
+                    .ansi(true)
+                    .ansi_output(true)
+                    .strip_ansi_output(true)
+            ),
+            vec!["red blue"]
+        );
+        assert_eq!(
+            output(SkimItemReaderOption::default().strip_ansi_output(true).with_nth("2")),
+            vec!["red blue"]
+        );
+    }
+
+    #[test]
+    fn test_command_error_as_item() {
+        let reader = SkimItemReader::new(SkimItemReaderOption::default().cmd_cwd("/no/such/dir").build());
+        let (rx_item, _tx_interrupt) = reader.read_and_collect_from_command(
+            Arc::new(AtomicUsize::new(0)),
+            CollectorInput::Command("echo a".to_string()),
+        );
+        let items: Vec<_> = rx_item.iter().map(|item| item.text().into_owned()).collect();
+        assert_eq!(items.len(), 1);
+        assert!(items[0].starts_with("[sk] failed to run the command: "));
+    }
+
+    #[test]
+    fn test_line_transform() {
+        let input = "a\n# comment\nb\n";
+        let option = SkimItemReaderOption::default()
+            .line_transform(|line| {
+                if line.starts_with('#') {
+                    None
+                } else {
+                    Some(format!("[{}]", line))
+                }
+            })
+            .build();
+        assert_eq!(read_lines(option, input), vec!["[a]", "[b]"]);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod item;
 pub mod item_reader;
 pub mod selector;
+#[cfg(feature = "tokio")]
+pub mod tokio_source;
@@ -1,14 +1,19 @@
 use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::RwLock;
 
 use regex::Regex;
 
 use crate::{Selector, SkimItem};
 
+/// The selector of the `--pre-select-*` options, the regex and the preset items could be updated
+/// while skim is running, e.g. by the `pre-select-pat` action
 #[derive(Debug, Default)]
 pub struct DefaultSkimSelector {
     first_n: usize,
-    regex: Option<Regex>,
-    preset: Option<HashSet<String>>,
+    regex: RwLock<Option<Regex>>,
+    preset: RwLock<Option<HashSet<String>>>,
 }
 
 impl DefaultSkimSelector {
@@ -18,23 +23,37 @@ impl DefaultSkimSelector {
         self
     }
 
-    pub fn preset(mut self, preset: impl IntoIterator<Item = String>) -> Self {
-        if self.preset.is_none() {
-            self.preset = Some(HashSet::new())
-        }
+    pub fn preset(self, preset: impl IntoIterator<Item = String>) -> Self {
+        self.preset
+            .write()
+            .unwrap()
+            .get_or_insert_with(HashSet::new)
+            .extend(preset);
+        self
+    }
 
-        if let Some(set) = self.preset.as_mut() {
-            set.extend(preset)
-        }
+    /// preset the lines of the file(`--pre-select-file`), a missing file presets nothing
+    pub fn preset_file(self, filename: &str) -> Self {
+        let lines = File::open(filename)
+            .map(|file| BufReader::new(file).lines().map_while(Result::ok).collect())
+            .unwrap_or_else(|_| Vec::new());
+        self.preset(lines)
+    }
+
+    pub fn regex(self, regex: &str) -> Self {
+        self.set_regex(regex);
         self
     }
 
-    pub fn regex(mut self, regex: &str) -> Self {
+    /// replace the regex, an empty or invalid one selects nothing
+    pub fn set_regex(&self, regex: &str) {
         trace!("select regex: {}", regex);
-        if !regex.is_empty() {
-            self.regex = Regex::new(regex).ok();
-        }
-        self
+        *self.regex.write().unwrap() = if regex.is_empty() { None } else { Regex::new(regex).ok() };
+    }
+
+    /// replace the preset items
+    pub fn set_preset(&self, preset: impl IntoIterator<Item = String>) {
+        *self.preset.write().unwrap() = Some(preset.into_iter().collect());
     }
 }
 
@@ -44,22 +63,34 @@ impl Selector for DefaultSkimSelector {
             return true;
         }
 
-        if self.preset.is_some()
-            && self
-                .preset
-                .as_ref()
-                .map(|preset| preset.contains(item.text().as_ref()))
-                .unwrap_or(false)
+        if self
+            .preset
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|preset| preset.contains(item.text().as_ref()))
+            .unwrap_or(false)
         {
             return true;
         }
 
-        if self.regex.is_some() && self.regex.as_ref().map(|re| re.is_match(&item.text())).unwrap_or(false) {
+        if self
+            .regex
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|re| re.is_match(&item.text()))
+            .unwrap_or(false)
+        {
             return true;
         }
 
         false
     }
+
+    fn set_pattern(&self, pattern: &str) {
+        self.set_regex(pattern);
+    }
 }
 
 #[cfg(test)]
@@ -106,4 +137,19 @@ mod tests {
         assert!(selector.should_select(2, &"c"));
         assert!(!selector.should_select(3, &"d"));
     }
+
+    #[test]
+    pub fn test_update() {
+        let selector = DefaultSkimSelector::default().regex("a").preset(vec!["b".to_string()]);
+        selector.set_pattern("c");
+        assert!(!selector.should_select(0, &"a"));
+        assert!(selector.should_select(0, &"c"));
+
+        selector.set_preset(vec!["d".to_string()]);
+        assert!(!selector.should_select(0, &"b"));
+        assert!(selector.should_select(0, &"d"));
+
+        selector.set_regex("");
+        assert!(!selector.should_select(0, &"c"));
+    }
 }
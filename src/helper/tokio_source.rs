@@ -0,0 +1,160 @@
+//! Feed skim from async code(the `tokio` feature), and follow it by a stream of `SkimEvent`s.
+//!
+//! The items are forwarded by a bridging thread into a bounded `SkimItemReceiver`, so a slow
+//! skim blocks the thread, which in turn stops polling the async source. Once skim drops the
+//! receiver, the thread drops the source, and the async producer sees the channel closed on its
+//! next send.
+use std::sync::Arc;
+use std::thread;
+
+use crossbeam::channel::bounded;
+use tokio::runtime::{Builder, Handle};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{SkimEvent, SkimEventReceiver, SkimItem, SkimItemReceiver, SkimItemSender};
+
+const ITEM_CHANNEL_SIZE: usize = 1024;
+
+/// turn the receiving half of a tokio channel into the source of skim
+pub fn from_tokio_receiver(mut rx: mpsc::Receiver<Arc<dyn SkimItem>>) -> SkimItemReceiver {
+    let (tx_item, rx_item): (SkimItemSender, SkimItemReceiver) = bounded(ITEM_CHANNEL_SIZE);
+    thread::spawn(move || {
+        while let Some(item) = rx.blocking_recv() {
+            if tx_item.send(item).is_err() {
+                break;
+            }
+        }
+    });
+    rx_item
+}
+
+/// turn a stream of items into the source of skim
+///
+/// The stream is polled by a task spawned on the current tokio runtime if there is one, so it
+/// could rely on the runtime's timers and IO, otherwise on a runtime of its own. The task only
+/// makes progress while the runtime is driven, so don't block the thread of a current_thread
+/// runtime on skim, run it by `Skim::run_async` or in `spawn_blocking` instead.
+pub fn from_stream<S>(mut stream: S) -> SkimItemReceiver
+where
+    S: Stream<Item = Arc<dyn SkimItem>> + Send + Unpin + 'static,
+{
+    if let Ok(handle) = Handle::try_current() {
+        let (tx, rx) = mpsc::channel(ITEM_CHANNEL_SIZE);
+        handle.spawn(async move {
+            while let Some(item) = stream.next().await {
+                if tx.send(item).await.is_err() {
+                    break;
+                }
+            }
+        });
+        return from_tokio_receiver(rx);
+    }
+
+    let (tx_item, rx_item): (SkimItemSender, SkimItemReceiver) = bounded(ITEM_CHANNEL_SIZE);
+    thread::spawn(move || {
+        let forward = async move {
+            while let Some(item) = stream.next().await {
+                if tx_item.send(item).is_err() {
+                    break;
+                }
+            }
+        };
+
+        match Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => runtime.block_on(forward),
+            Err(err) => error!("failed to start a runtime for the stream: {}", err),
+        }
+    });
+    rx_item
+}
+
+/// turn the events of `Skim::run_async` into a stream, which ends after `SkimEvent::Finished`
+pub fn event_stream(rx_event: SkimEventReceiver) -> impl Stream<Item = SkimEvent> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    thread::spawn(move || {
+        for event in rx_event {
+            if tx.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    UnboundedReceiverStream::new(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(rx_item: SkimItemReceiver) -> Vec<String> {
+        rx_item.iter().map(|item| item.text().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_from_tokio_receiver() {
+        let (tx, rx) = mpsc::channel(2);
+        let producer = thread::spawn(move || {
+            for i in 0..100 {
+                tx.blocking_send(Arc::new(i.to_string()) as Arc<dyn SkimItem>).unwrap();
+            }
+        });
+        let expected: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        assert_eq!(collect(from_tokio_receiver(rx)), expected);
+        producer.join().unwrap();
+    }
+
+    #[test]
+    fn test_from_tokio_receiver_shutdown() {
+        let (tx, rx) = mpsc::channel(1);
+        drop(from_tokio_receiver(rx));
+
+        // the producer is stopped soon after skim is gone
+        let mut sent = 0;
+        while tx.blocking_send(Arc::new("item".to_string())).is_ok() {
+            sent += 1;
+            assert!(sent < 10);
+        }
+        assert!(tx.is_closed());
+    }
+
+    #[test]
+    fn test_from_stream() {
+        let items = (0..3).map(|i| Arc::new(i.to_string()) as Arc<dyn SkimItem>);
+        assert_eq!(
+            collect(from_stream(tokio_stream::iter(items.clone()))),
+            vec!["0", "1", "2"]
+        );
+
+        let runtime = Builder::new_current_thread().build().unwrap();
+        let collected = runtime.block_on(async {
+            let rx_item = from_stream(tokio_stream::iter(items));
+            tokio::task::spawn_blocking(move || collect(rx_item)).await.unwrap()
+        });
+        assert_eq!(collected, vec!["0", "1", "2"]);
+    }
+
+    #[test]
+    fn test_from_stream_with_timer() {
+        // the runtime of its own has the timers
+        let items = tokio_stream::iter(0..3).then(|i| async move {
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+            Arc::new(i.to_string()) as Arc<dyn SkimItem>
+        });
+        assert_eq!(collect(from_stream(Box::pin(items))), vec!["0", "1", "2"]);
+    }
+
+    #[test]
+    fn test_event_stream() {
+        let (tx_event, rx_event) = crossbeam::channel::unbounded();
+        tx_event.send(SkimEvent::Query("a".to_string())).unwrap();
+        tx_event.send(SkimEvent::Finished(None)).unwrap();
+        drop(tx_event);
+
+        let runtime = Builder::new_current_thread().build().unwrap();
+        let events: Vec<SkimEvent> = runtime.block_on(event_stream(rx_event).collect());
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], SkimEvent::Query(query) if query == "a"));
+        assert!(matches!(events[1], SkimEvent::Finished(None)));
+    }
+}
@@ -0,0 +1,116 @@
+//! Highlight the lines of the files previewed by `--preview-builtin=file`, by the syntaxes and
+//! themes bundled with `syntect`(the `highlight` feature). Without the feature, the lines are
+//! left plain.
+use std::path::Path;
+
+use crate::ansi::AnsiString;
+
+#[cfg(feature = "highlight")]
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{FontStyle, Theme, ThemeSet},
+    parsing::SyntaxSet,
+};
+#[cfg(feature = "highlight")]
+use tuikit::attr::{Attr, Color, Effect};
+
+// a longer line is likely minified, whose highlighting is slow and hardly helps
+#[cfg(feature = "highlight")]
+const MAX_HIGHLIGHT_LINE_LEN: usize = 4096;
+
+#[cfg(feature = "highlight")]
+lazy_static! {
+    static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_nonewlines();
+    static ref THEME: Theme = ThemeSet::load_defaults()
+        .themes
+        .remove("base16-ocean.dark")
+        .expect("the default themes should contain base16-ocean.dark");
+}
+
+pub struct Highlighter {
+    #[cfg(feature = "highlight")]
+    lines: Option<HighlightLines<'static>>,
+}
+
+impl Highlighter {
+    /// The syntax is found by the extension or the name of `path`, then by the `first_line` of the
+    /// file(e.g. a shebang). The lines are left plain if none is found.
+    #[cfg(feature = "highlight")]
+    pub fn new(path: &Path, first_line: &str) -> Self {
+        let by_name = |name: Option<&std::ffi::OsStr>| {
+            name.and_then(|name| name.to_str())
+                .and_then(|name| SYNTAX_SET.find_syntax_by_extension(name))
+        };
+        let syntax = by_name(path.extension())
+            .or_else(|| by_name(path.file_name()))
+            .or_else(|| SYNTAX_SET.find_syntax_by_first_line(first_line));
+        Self {
+            lines: syntax.map(|syntax| HighlightLines::new(syntax, &THEME)),
+        }
+    }
+
+    #[cfg(not(feature = "highlight"))]
+    pub fn new(_path: &Path, _first_line: &str) -> Self {
+        Self {}
+    }
+
+    /// Highlight the next `line` of the file, without the line break.
+    #[cfg(feature = "highlight")]
+    pub fn highlight(&mut self, line: &str) -> AnsiString<'static> {
+        if line.len() > MAX_HIGHLIGHT_LINE_LEN {
+            // the state of the syntax is lost, the rest is left plain too
+            self.lines = None;
+        }
+        let ranges = match self.lines.as_mut().map(|lines| lines.highlight_line(line, &SYNTAX_SET)) {
+            Some(Ok(ranges)) => ranges,
+            Some(Err(err)) => {
+                debug!("highlight: failed to highlight the line: {}", err);
+                self.lines = None;
+                return AnsiString::parse(line);
+            }
+            None => return AnsiString::parse(line),
+        };
+
+        let mut fragments = vec![];
+        let mut start = 0;
+        for (style, text) in ranges {
+            let end = start + text.chars().count() as u32;
+            let mut effect = Effect::empty();
+            if style.font_style.contains(FontStyle::BOLD) {
+                effect |= Effect::BOLD;
+            }
+            if style.font_style.contains(FontStyle::UNDERLINE) {
+                effect |= Effect::UNDERLINE;
+            }
+            let fg = Color::Rgb(style.foreground.r, style.foreground.g, style.foreground.b);
+            fragments.push((Attr::from(fg).effect(effect), (start, end)));
+            start = end;
+        }
+        AnsiString::new_string(line.to_string(), fragments)
+    }
+
+    #[cfg(not(feature = "highlight"))]
+    pub fn highlight(&mut self, line: &str) -> AnsiString<'static> {
+        AnsiString::parse(line)
+    }
+}
+
+#[cfg(all(test, feature = "highlight"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight() {
+        let mut highlighter = Highlighter::new(Path::new("src/main.rs"), "fn main() {");
+        let line = highlighter.highlight("fn main() {");
+        assert_eq!(line.stripped(), "fn main() {");
+        assert!(line.has_attrs());
+
+        // by the shebang
+        let mut highlighter = Highlighter::new(Path::new("script"), "#!/bin/bash");
+        assert!(highlighter.highlight("echo hi").has_attrs());
+
+        let mut highlighter = Highlighter::new(Path::new("notes"), "plain text");
+        assert!(!highlighter.highlight("plain text").has_attrs());
+    }
+}
@@ -0,0 +1,74 @@
+//! The icons given by `--icons`, looked up by the extension of the items
+use std::collections::HashMap;
+
+use tuikit::prelude::*;
+
+use crate::theme::parse_color;
+use crate::ItemIcon;
+
+/// The icon used when no extension matches
+const FALLBACK_KEY: &str = "*";
+
+#[derive(Default)]
+pub struct IconMap {
+    icons: HashMap<String, (String, Attr)>,
+}
+
+impl IconMap {
+    /// parse icons in the form of `EXT:GLYPH[:COLOR],...`, e.g. `rs:R:208,md:M:#5599ff,*:-`
+    pub fn parse(icons: &str) -> Self {
+        let icons = icons
+            .split(',')
+            .filter_map(|icon| {
+                let mut parts = icon.splitn(3, ':');
+                let ext = parts.next()?.trim();
+                let glyph = parts.next()?;
+                if ext.is_empty() || glyph.is_empty() {
+                    return None;
+                }
+                let attr = Attr {
+                    fg: parts.next().map(parse_color).unwrap_or(Color::Default),
+                    ..Attr::default()
+                };
+                Some((ext.to_lowercase(), (glyph.to_string(), attr)))
+            })
+            .collect();
+        Self { icons }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.icons.is_empty()
+    }
+
+    /// the icon of the extension of the file name in `text`, or the fallback icon
+    pub fn lookup(&self, text: &str) -> Option<ItemIcon> {
+        let file_name = text.trim_end().rsplit('/').next().unwrap_or("");
+        file_name
+            .rfind('.')
+            .filter(|&dot| dot > 0)
+            .and_then(|dot| self.icons.get(&file_name[dot + 1..].to_lowercase()))
+            .or_else(|| self.icons.get(FALLBACK_KEY))
+            .map(|(glyph, attr)| ItemIcon::new(glyph.as_str(), *attr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icon_lookup() {
+        let icons = IconMap::parse("rs:R:208,md:M,*:-");
+        let glyph = |text| icons.lookup(text).map(|icon| icon.glyph);
+        assert_eq!(Some("R".to_string()), glyph("src/main.rs"));
+        assert_eq!(Some("M".to_string()), glyph("README.MD"));
+        assert_eq!(Some("-".to_string()), glyph("Cargo.toml"));
+        assert_eq!(Some("-".to_string()), glyph(".rs"));
+        assert_eq!(Some("-".to_string()), glyph("a.rs/b"));
+        assert_eq!(Color::AnsiValue(208), icons.lookup("a.rs").unwrap().attr.fg);
+
+        let icons = IconMap::parse("rs:R");
+        assert!(icons.lookup("Cargo.toml").is_none());
+        assert!(IconMap::parse("rs,:x").is_empty());
+    }
+}
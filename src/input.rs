@@ -1,8 +1,7 @@
 ///! Input will listens to user input, modify the query string, send special
 ///! keystrokes(such as Enter, Ctrl-p, Ctrl-n, etc) to the controller.
-use crate::event::{parse_event, Event};
-use regex::Regex;
-use std::collections::HashMap;
+use crate::event::{event_action, parse_event, Action, ActionArg, Event, ACTION_ARGS};
+use std::collections::{HashMap, HashSet};
 use tuikit::event::Event as TermEvent;
 use tuikit::key::{from_keyname, Key};
 
@@ -10,17 +9,42 @@ pub type ActionChain = Vec<Event>;
 
 pub struct Input {
     keymap: HashMap<Key, ActionChain>,
+    // the descriptions of `--bind`, e.g. `ctrl-o:execute(open {})#Open in Finder`
+    descriptions: HashMap<Key, String>,
+    // the text of the bracketed paste so far, where the keys are the text pasted instead of the
+    // bindings. The text is inserted at once when the paste ends.
+    pasting: Option<String>,
 }
 
 impl Input {
     pub fn new() -> Self {
         Input {
             keymap: get_default_key_map(),
+            descriptions: HashMap::new(),
+            pasting: None,
         }
     }
 
-    pub fn translate_event(&self, event: TermEvent) -> (Key, ActionChain) {
+    pub fn translate_event(&mut self, event: TermEvent) -> (Key, ActionChain) {
         match event {
+            TermEvent::Key(key @ Key::BracketedPasteStart) => {
+                self.pasting = Some(String::new());
+                (key, vec![])
+            }
+            TermEvent::Key(key @ Key::BracketedPasteEnd) => match self.pasting.take() {
+                Some(pasted) if !pasted.is_empty() => (key, vec![Event::EvActAddStr(pasted)]),
+                _ => (key, vec![]),
+            },
+            TermEvent::Key(key) if self.pasting.is_some() => {
+                let pasted = self.pasting.as_mut().expect("pasting");
+                match key {
+                    Key::Char(ch) => pasted.push(ch),
+                    // the query is a single line, the line breaks of the paste are taken as spaces
+                    Key::Enter | Key::Tab | Key::Ctrl('j') => pasted.push(' '),
+                    _ => {}
+                }
+                (key, vec![])
+            }
             // search event from keymap
             TermEvent::Key(key) => (
                 key,
@@ -38,15 +62,19 @@ impl Input {
     }
 
     pub fn bind(&mut self, key: &str, action_chain: ActionChain) {
-        let key = from_keyname(key);
-        if key == None || action_chain.is_empty() {
-            return;
+        if let Some(key) = from_keyname(key) {
+            self.bind_key(key, action_chain);
         }
+    }
 
-        let key = key.unwrap();
+    pub fn bind_key(&mut self, key: Key, action_chain: ActionChain) {
+        if action_chain.is_empty() {
+            return;
+        }
 
         // remove the key for existing keymap;
         let _ = self.keymap.remove(&key);
+        let _ = self.descriptions.remove(&key);
         self.keymap.entry(key).or_insert(action_chain);
     }
 
@@ -59,66 +87,324 @@ impl Input {
     // key_action is comma separated: 'ctrl-j:accept,ctrl-k:kill-line'
     pub fn parse_keymap(&mut self, key_action: &str) {
         debug!("got key_action: {:?}", key_action);
-        for (key, action_chain) in parse_key_action(key_action).into_iter() {
+        for (key, action_chain, description) in parse_key_bindings(key_action).into_iter() {
             debug!("parsed key_action: {:?}: {:?}", key, action_chain);
-            let action_chain = action_chain
+            let action_chain: ActionChain = action_chain
                 .into_iter()
                 .filter_map(|(action, arg)| parse_event(action, arg))
                 .collect();
+            let bound = !action_chain.is_empty();
             self.bind(key, action_chain);
+            if let (true, Some(key), Some(description)) = (bound, from_keyname(key), description) {
+                self.descriptions.insert(key, description.to_string());
+            }
+        }
+    }
+
+    pub fn bind_keymap(&mut self, keymap: &Keymap) {
+        for (key, action_chain) in keymap.bindings.iter() {
+            self.bind_key(*key, action_chain.clone());
         }
     }
 
     pub fn parse_expect_keys(&mut self, keys: Option<&str>) {
         if let Some(keys) = keys {
-            for key in keys.split(',') {
+            for (key, meaning) in split_expect_keys(keys) {
                 self.bind(key, vec![Event::EvActAccept(Some(key.to_string()))]);
+                if let (Some(key), Some(meaning)) = (from_keyname(key), meaning) {
+                    self.descriptions.insert(key, meaning.to_string());
+                }
             }
         }
     }
+
+    /// The bound keys and their descriptions, or their actions if not described, sorted by the
+    /// keys. These are listed by the `show-help` action.
+    pub fn describe_bindings(&self) -> Vec<(String, String)> {
+        let names: HashMap<Key, String> = key_names()
+            .into_iter()
+            .filter_map(|name| from_keyname(&name).map(|key| (key, name)))
+            .collect();
+
+        // the keys that could not be bound by name, e.g. `Key::Null`, are left out
+        let mut bindings: Vec<(String, String)> = self
+            .keymap
+            .iter()
+            .filter_map(|(key, action_chain)| {
+                let name = match key {
+                    Key::Char(ch) => ch.to_string(),
+                    key => names.get(key)?.clone(),
+                };
+                let description = match self.descriptions.get(key) {
+                    Some(description) => description.clone(),
+                    None => describe_action_chain(action_chain),
+                };
+                Some((name, description))
+            })
+            .collect();
+        bindings.sort();
+        bindings
+    }
+}
+
+/// split the keys of `--expect` and their meanings, e.g. `ctrl-e:edit,ctrl-o:open,alt-s`
+pub fn split_expect_keys(keys: &str) -> Vec<(&str, Option<&str>)> {
+    keys.split(',')
+        .filter(|key| !key.is_empty())
+        .map(|key| {
+            // the key itself could be `:`
+            let key_len = key.chars().next().map(char::len_utf8).unwrap_or(0);
+            match key[key_len..].find(':') {
+                Some(idx) => (&key[..key_len + idx], Some(&key[key_len + idx + 1..])),
+                None => (key, None),
+            }
+        })
+        .collect()
+}
+
+/// the `--bind` form of the actions, e.g. `toggle+down` of `[EvActToggle, EvActDown(1)]`
+fn describe_action_chain(action_chain: &[Event]) -> String {
+    action_chain.iter().map(describe_event).collect::<Vec<_>>().join("+")
+}
+
+fn describe_event(event: &Event) -> String {
+    let (name, arg) = match event_action(event) {
+        Some(action) => action,
+        None => return format!("{:?}", event),
+    };
+    let expected = ACTION_ARGS
+        .iter()
+        .find(|(action, _)| *action == name)
+        .map(|(_, expected)| *expected);
+
+    match (expected, arg) {
+        (Some(ActionArg::Nothing), _) | (_, None) => name.to_string(),
+        (Some(ActionArg::Number), Some(arg)) if arg == "1" => name.to_string(),
+        (_, Some(arg)) if arg.is_empty() => name.to_string(),
+        (_, Some(arg)) => format!("{}({})", name, arg),
+    }
+}
+
+/// Key bindings constructed with typed actions, which are applied after the string bindings
+/// (`bind` of `SkimOptions`).
+///
+/// ```
+/// use skim::prelude::*;
+///
+/// let mut keymap = Keymap::new();
+/// keymap
+///     .bind(Key::Ctrl('j'), Action::Accept(None))
+///     .bind_chain(Key::Ctrl('t'), vec![Action::Toggle, Action::Down(1)]);
+/// let options = SkimOptionsBuilder::default().keymap(keymap).build().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    bindings: Vec<(Key, ActionChain)>,
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// bind the key to a single action, overrides the existing bindings of the key
+    pub fn bind(&mut self, key: Key, action: Action) -> &mut Self {
+        self.bind_chain(key, vec![action])
+    }
+
+    /// bind the key to actions that are executed in order, like `ctrl-t:toggle+down`
+    pub fn bind_chain(&mut self, key: Key, actions: Vec<Action>) -> &mut Self {
+        self.bindings
+            .push((key, actions.into_iter().map(Event::from).collect()));
+        self
+    }
 }
 
-type KeyActions<'a> = (&'a str, Vec<(&'a str, Option<String>)>);
+type Actions<'a> = Vec<(&'a str, Option<String>)>;
+type KeyActions<'a> = (&'a str, Actions<'a>);
+type KeyBinding<'a> = (&'a str, Actions<'a>, Option<&'a str>);
 
 /// parse key action string to `(key, action, argument)` tuple
 /// key_action is comma separated: 'ctrl-j:accept,ctrl-k:kill-line'
+///
+/// The argument of an action could be quoted by `(...)`, `[...]`, `{...}`, `"..."` or `'...'`,
+/// e.g. `ctrl-x:execute(echo a,b:c)+abort`, brackets of the same kind could be nested inside.
+/// The argument could also follow a colon: `execute:...`, which ends at the next comma.
 pub fn parse_key_action(key_action: &str) -> Vec<KeyActions> {
-    lazy_static! {
-        // match `key:action` or `key:action:arg` or `key:action(arg)` etc.
-        static ref RE: Regex =
-            Regex::new(r#"(?si)([^:]+?):((?:\+?[a-z-]+?(?:"[^"]*?"|'[^']*?'|\([^\)]*?\)|\[[^\]]*?\]|:[^:]*?)?\s*)+)(?:,|$)"#)
-                .unwrap();
-        // grab key, action and arg out.
-        static ref RE_BIND: Regex = Regex::new(r#"(?si)([a-z-]+)("[^"]+?"|'[^']+?'|\([^\)]+?\)|\[[^\]]+?\]|:[^:]+?)?(?:\+|$)"#).unwrap();
-    }
-
-    RE.captures_iter(key_action)
-        .map(|caps| {
-            debug!("RE: caps: {:?}", caps);
-            let key = caps.get(1).unwrap().as_str();
-            let actions = RE_BIND
-                .captures_iter(caps.get(2).unwrap().as_str())
-                .map(|caps| {
-                    debug!("RE_BIND: caps: {:?}", caps);
-                    (
-                        caps.get(1).unwrap().as_str(),
-                        caps.get(2).map(|s| {
-                            // (arg) => arg, :end_arg => arg
-                            let action = s.as_str();
-                            if let Some(stripped) = action.strip_prefix(':') {
-                                stripped.to_owned()
-                            } else {
-                                action[1..action.len() - 1].to_string()
-                            }
-                        }),
-                    )
-                })
-                .collect();
-            (key, actions)
-        })
+    parse_key_bindings(key_action)
+        .into_iter()
+        .map(|(key, actions, _description)| (key, actions))
         .collect()
 }
 
+/// like `parse_key_action`, also return the description after the actions of a key, e.g.
+/// `Open in Finder` of `ctrl-o:execute(open {})#Open in Finder`, which ends at the next comma.
+fn parse_key_bindings(key_action: &str) -> Vec<KeyBinding<'_>> {
+    let mut ret = Vec::new();
+    let mut rest = key_action;
+    while !rest.is_empty() {
+        // the key itself could be `:` or `,`
+        let key_len = rest.chars().next().map(char::len_utf8).unwrap_or(0);
+        let key_end = match rest[key_len..].find(':') {
+            Some(idx) => key_len + idx,
+            None => break,
+        };
+
+        let key = rest[..key_end].trim();
+        let (actions, description, remaining) = parse_action_chain(&rest[key_end + 1..]);
+        debug!("parsed key: {:?}, actions: {:?}", key, actions);
+        ret.push((key, actions, description));
+        rest = remaining;
+    }
+    ret
+}
+
+/// parse the actions of a key till the next `,`, return the actions, the description after `#`
+/// and the text after the `,`
+fn parse_action_chain(text: &str) -> (Actions<'_>, Option<&str>, &str) {
+    let mut actions = Vec::new();
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start();
+        let name_len = rest
+            .find(|c: char| !(c.is_ascii_alphabetic() || c == '-'))
+            .unwrap_or(rest.len());
+        let action = &rest[..name_len];
+        let (arg, remaining) = split_action_arg(&rest[name_len..]);
+        if !action.is_empty() {
+            actions.push((action, arg));
+        }
+
+        rest = remaining.trim_start();
+        if let Some(next_action) = rest.strip_prefix('+') {
+            rest = next_action;
+        } else {
+            // skip the invalid text till the next key
+            let (text, remaining) = match rest.find(',') {
+                Some(idx) => (&rest[..idx], &rest[idx + 1..]),
+                None => (rest, ""),
+            };
+            let description = text.strip_prefix('#').map(str::trim).filter(|text| !text.is_empty());
+            return (actions, description, remaining);
+        }
+    }
+}
+
+/// split the argument (if any) at the start of the text, return the argument and the rest text.
+/// An argument that is not closed extends to the end of the text.
+fn split_action_arg(text: &str) -> (Option<String>, &str) {
+    let open = match text.chars().next() {
+        Some(ch) => ch,
+        None => return (None, text),
+    };
+
+    if open == ':' {
+        let end = text.find(',').unwrap_or(text.len());
+        return (Some(text[1..end].to_string()), &text[end..]);
+    }
+
+    let close = match open {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        '"' | '\'' => open,
+        _ => return (None, text),
+    };
+
+    let mut depth = 0;
+    for (idx, ch) in text.char_indices().skip(1) {
+        if ch == close && depth == 0 {
+            return (Some(text[1..idx].to_string()), &text[idx + 1..]);
+        } else if ch == close {
+            depth -= 1;
+        } else if ch == open {
+            depth += 1;
+        }
+    }
+
+    (Some(text[1..].to_string()), "")
+}
+
+/// The names of the actions that could be bound by `--bind`
+pub fn action_names() -> Vec<&'static str> {
+    ACTION_ARGS.iter().map(|(name, _)| *name).collect()
+}
+
+/// The names of the keys that could be bound by `--bind`, without the synonyms (e.g. `ctrl-m` of
+/// `enter`), and the single characters.
+pub fn key_names() -> Vec<String> {
+    #[rustfmt::skip]
+    const NAMED_KEYS: &[&str] = &[
+        "enter", "space", "bspace", "tab", "btab", "esc", "del",
+        "up", "down", "left", "right", "home", "end", "pgup", "pgdn",
+        "shift-up", "shift-down", "shift-left", "shift-right",
+        "alt-up", "alt-down", "alt-left", "alt-right", "alt-enter", "alt-space", "alt-bspace", "alt-/",
+        "alt-shift-up", "alt-shift-down", "alt-shift-left", "alt-shift-right",
+        "ctrl-space",
+    ];
+
+    let mut names: Vec<String> = NAMED_KEYS.iter().map(|name| name.to_string()).collect();
+    names.extend(('a'..='z').map(|ch| format!("ctrl-{}", ch)));
+    names.extend(('a'..='z').map(|ch| format!("ctrl-alt-{}", ch)));
+    names.extend(
+        ('a'..='z')
+            .chain('A'..='Z')
+            .chain('0'..='9')
+            .map(|ch| format!("alt-{}", ch)),
+    );
+    names.extend((1..=12).map(|num| format!("f{}", num)));
+
+    let mut keys = HashSet::new();
+    names.retain(|name| from_keyname(name).map(|key| keys.insert(key)).unwrap_or(false));
+    names
+}
+
+/// Check the key bindings of `--bind`, e.g. 'ctrl-j:accept,ctrl-k:kill-line', return the
+/// descriptions of the unknown keys, the unknown actions and the invalid arguments.
+pub fn check_keymap(key_action: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (key, actions) in parse_key_action(key_action) {
+        if from_keyname(key).is_none() {
+            errors.push(format!("unknown key `{}`", key));
+        }
+        check_actions(key, &actions, &mut errors);
+    }
+    errors
+}
+
+fn check_actions(key: &str, actions: &[(&str, Option<String>)], errors: &mut Vec<String>) {
+    for (action, arg) in actions {
+        let expected = match ACTION_ARGS.iter().find(|(name, _)| name == action) {
+            Some((_, expected)) => *expected,
+            None => {
+                errors.push(format!(
+                    "unknown action `{}` for key `{}`, valid actions are: {}",
+                    action,
+                    key,
+                    action_names().join(", ")
+                ));
+                continue;
+            }
+        };
+
+        let invalid_arg = match (expected, arg) {
+            (ActionArg::Nothing, Some(_)) => Some("takes no argument"),
+            (ActionArg::Number, Some(arg)) if arg.parse::<i32>().is_err() => Some("expects a number"),
+            (ActionArg::ExitCode, Some(arg)) if arg.parse::<u8>().is_err() => Some("expects an exit code(0-255)"),
+            (ActionArg::Required, None) => Some("requires an argument"),
+            _ => None,
+        };
+        if let Some(reason) = invalid_arg {
+            errors.push(format!("action `{}` for key `{}` {}", action, key, reason));
+        }
+
+        // the argument of `if-*` is an action itself
+        if let (true, Some(arg)) = (action.starts_with("if-"), arg) {
+            check_actions(key, &parse_action_chain(arg).0, errors);
+        }
+    }
+}
+
 /// e.g. execute(...) => Some(Event::EvActExecute, Box::new(Option("...")))
 pub fn parse_action_arg(action_arg: &str) -> Option<Event> {
     // construct a fake key_action: `fake_key:action(arg)`
@@ -138,9 +424,9 @@ pub fn parse_action_arg(action_arg: &str) -> Option<Event> {
 #[rustfmt::skip]
 fn get_default_key_map() -> HashMap<Key, ActionChain> {
     let mut ret = HashMap::new();
-    ret.insert(Key::ESC,          vec![Event::EvActAbort]);
-    ret.insert(Key::Ctrl('c'),    vec![Event::EvActAbort]);
-    ret.insert(Key::Ctrl('g'),    vec![Event::EvActAbort]);
+    ret.insert(Key::ESC,          vec![Event::EvActAbort(None)]);
+    ret.insert(Key::Ctrl('c'),    vec![Event::EvActAbort(None)]);
+    ret.insert(Key::Ctrl('g'),    vec![Event::EvActAbort(None)]);
     ret.insert(Key::Enter,        vec![Event::EvActAccept(None)]);
     ret.insert(Key::Left,         vec![Event::EvActBackwardChar]);
     ret.insert(Key::Ctrl('b'),    vec![Event::EvActBackwardChar]);
@@ -175,6 +461,7 @@ fn get_default_key_map() -> HashMap<Key, ActionChain> {
     ret.insert(Key::Alt('l'),     vec![Event::EvActScrollRight(1)]);
     ret.insert(Key::Tab,          vec![Event::EvActToggle, Event::EvActDown(1)]);
     ret.insert(Key::Ctrl('q'),    vec![Event::EvActToggleInteractive]);
+    ret.insert(Key::Ctrl('t'),    vec![Event::EvActTransposeChars]);
     ret.insert(Key::BackTab,      vec![Event::EvActToggle, Event::EvActUp(1)]);
     ret.insert(Key::Ctrl('u'),    vec![Event::EvActUnixLineDiscard]);
     ret.insert(Key::Ctrl('w'),    vec![Event::EvActUnixWordRubout]);
@@ -182,7 +469,9 @@ fn get_default_key_map() -> HashMap<Key, ActionChain> {
     ret.insert(Key::Ctrl('k'),    vec![Event::EvActUp(1)]);
     ret.insert(Key::Up,           vec![Event::EvActUp(1)]);
     ret.insert(Key::Ctrl('y'),    vec![Event::EvActYank]);
-    ret.insert(Key::Null,         vec![Event::EvActAbort]);
+    ret.insert(Key::Alt('y'),     vec![Event::EvActYankPop]);
+    ret.insert(Key::Ctrl('z'),    vec![Event::EvActSuspend]);
+    ret.insert(Key::Null,         vec![Event::EvActAbort(None)]);
     ret
 }
 
@@ -242,4 +531,249 @@ mod test {
             key_action[1]
         );
     }
+
+    #[test]
+    fn typed_keymap_should_override_bindings() {
+        let mut input = Input::new();
+        input.parse_keymap("ctrl-j:down,ctrl-t:toggle");
+
+        let mut keymap = Keymap::new();
+        keymap
+            .bind(Key::Ctrl('j'), Action::Accept(Some("ctrl-j".to_string())))
+            .bind_chain(
+                Key::Ctrl('x'),
+                vec![Action::Execute("echo a,b".to_string()), Action::Abort(None)],
+            )
+            .bind_chain(Key::Ctrl('t'), vec![]);
+        input.bind_keymap(&keymap);
+
+        let mut translate = |key| input.translate_event(TermEvent::Key(key)).1;
+        assert_eq!(
+            translate(Key::Ctrl('j')),
+            vec![Event::EvActAccept(Some("ctrl-j".to_string()))]
+        );
+        assert_eq!(
+            translate(Key::Ctrl('x')),
+            vec![Event::EvActExecute("echo a,b".to_string()), Event::EvActAbort(None)]
+        );
+        // empty chain is ignored
+        assert_eq!(translate(Key::Ctrl('t')), vec![Event::EvActToggle]);
+    }
+
+    #[test]
+    fn abort_should_carry_exit_code() {
+        assert_eq!(
+            Some(Event::EvActAbort(Some(2))),
+            parse_event("abort", Some("2".to_string()))
+        );
+        assert_eq!(Some(Event::EvActAbort(None)), parse_event("abort", None));
+        assert_eq!(None, parse_event("abort", Some("x".to_string())));
+        assert_eq!(None, parse_event("abort", Some("256".to_string())));
+        assert_eq!(None, parse_event("abort", Some("-1".to_string())));
+
+        let mut input = Input::new();
+        let mut translate = |key| input.translate_event(TermEvent::Key(key)).1;
+        assert_eq!(translate(Key::ESC), vec![Event::EvActAbort(None)]);
+        assert_eq!(translate(Key::Ctrl('g')), vec![Event::EvActAbort(None)]);
+        assert_eq!(translate(Key::Ctrl('c')), vec![Event::EvActAbort(None)]);
+    }
+
+    #[test]
+    fn paste_should_be_taken_as_text() {
+        let mut input = Input::new();
+        input.parse_keymaps(&["a:toggle"]);
+        let mut translate = |key| input.translate_event(TermEvent::Key(key)).1;
+        assert_eq!(translate(Key::Char('a')), vec![Event::EvActToggle]);
+
+        // the paste is inserted at once when it ends
+        assert_eq!(translate(Key::BracketedPasteStart), vec![]);
+        assert_eq!(translate(Key::Char('a')), vec![]);
+        assert_eq!(translate(Key::Enter), vec![]);
+        assert_eq!(translate(Key::ESC), vec![]);
+        assert_eq!(translate(Key::Char('b')), vec![]);
+        assert_eq!(
+            translate(Key::BracketedPasteEnd),
+            vec![Event::EvActAddStr("a b".to_string())]
+        );
+        assert_eq!(translate(Key::Enter), vec![Event::EvActAccept(None)]);
+
+        translate(Key::BracketedPasteStart);
+        assert_eq!(translate(Key::BracketedPasteEnd), vec![]);
+    }
+
+    #[test]
+    fn action_args_should_be_parsed() {
+        for (action, expected) in ACTION_ARGS {
+            let arg = match expected {
+                ActionArg::Nothing => None,
+                ActionArg::Optional => None,
+                ActionArg::Number => Some("3".to_string()),
+                ActionArg::ExitCode => Some("3".to_string()),
+                ActionArg::Required => Some("abort".to_string()),
+            };
+            assert!(parse_event(action, arg).is_some(), "{}", action);
+        }
+    }
+
+    #[test]
+    fn events_should_be_mapped_back_to_actions() {
+        for (action, expected) in ACTION_ARGS {
+            let arg = match expected {
+                ActionArg::Nothing => None,
+                ActionArg::Optional => Some("x".to_string()),
+                ActionArg::Number | ActionArg::ExitCode => Some("3".to_string()),
+                ActionArg::Required => Some("abort".to_string()),
+            };
+            let event = parse_event(action, arg.clone()).unwrap();
+            assert_eq!(event_action(&event), Some((*action, arg)), "{}", action);
+        }
+        assert_eq!(event_action(&Event::EvHeartBeat), None);
+    }
+
+    #[test]
+    fn key_names_should_be_bindable() {
+        let names = key_names();
+        assert!(names.contains(&"enter".to_string()));
+        assert!(!names.contains(&"ctrl-m".to_string()));
+        assert!(names.contains(&"f12".to_string()));
+        for name in names {
+            assert!(from_keyname(&name).is_some(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn invalid_bindings_should_be_reported() {
+        assert!(check_keymap("ctrl-j:accept,tab:toggle+down,ctrl-p:preview-up(3),ctrl-x:execute(ls)").is_empty());
+        assert!(check_keymap("ctrl-e:if-query-empty(abort)+accept(ctrl-e)").is_empty());
+
+        let errors = check_keymap("ctrl-j:acept,ctrl-p:preview-up(x),ctrl-x:execute,ctrl-q:toggle(1),nokey:abort");
+        assert_eq!(5, errors.len());
+        assert!(errors[0].starts_with("unknown action `acept` for key `ctrl-j`, valid actions are: abort, accept,"));
+        assert_eq!("action `preview-up` for key `ctrl-p` expects a number", errors[1]);
+        assert_eq!("action `execute` for key `ctrl-x` requires an argument", errors[2]);
+        assert_eq!("action `toggle` for key `ctrl-q` takes no argument", errors[3]);
+        assert_eq!("unknown key `nokey`", errors[4]);
+
+        let errors = check_keymap("esc:abort(x),ctrl-g:abort(5),ctrl-q:abort(256),ctrl-z:abort(-1)");
+        assert_eq!(
+            vec![
+                "action `abort` for key `esc` expects an exit code(0-255)",
+                "action `abort` for key `ctrl-q` expects an exit code(0-255)",
+                "action `abort` for key `ctrl-z` expects an exit code(0-255)",
+            ],
+            errors
+        );
+
+        let errors = check_keymap("ctrl-e:if-query-empty(abrt)");
+        assert_eq!(1, errors.len());
+        assert!(errors[0].starts_with("unknown action `abrt` for key `ctrl-e`"));
+    }
+
+    #[test]
+    fn bracket_arguments_should_be_parsed() {
+        let key_action = parse_key_action("ctrl-x:execute(echo a,b:c),ctrl-y:up");
+        assert_eq!(
+            ("ctrl-x", vec![("execute", Some("echo a,b:c".to_string()))]),
+            key_action[0]
+        );
+        assert_eq!(("ctrl-y", vec![("up", None)]), key_action[1]);
+
+        let key_action = parse_key_action("ctrl-x:execute[echo [a],b]+abort,ctrl-y:execute{echo $(date) {}}");
+        assert_eq!(
+            (
+                "ctrl-x",
+                vec![("execute", Some("echo [a],b".to_string())), ("abort", None)]
+            ),
+            key_action[0]
+        );
+        assert_eq!(
+            ("ctrl-y", vec![("execute", Some("echo $(date) {}".to_string()))]),
+            key_action[1]
+        );
+
+        let key_action = parse_key_action("ctrl-x:execute(echo $(date +%s)),alt-a:execute'echo (a'");
+        assert_eq!(
+            ("ctrl-x", vec![("execute", Some("echo $(date +%s)".to_string()))]),
+            key_action[0]
+        );
+        assert_eq!(("alt-a", vec![("execute", Some("echo (a".to_string()))]), key_action[1]);
+
+        // the key could be `,` or `:`
+        let key_action = parse_key_action(",:up,::down");
+        assert_eq!((",", vec![("up", None)]), key_action[0]);
+        assert_eq!((":", vec![("down", None)]), key_action[1]);
+
+        // unclosed argument extends to the end
+        let key_action = parse_key_action("ctrl-x:execute(echo a, b");
+        assert_eq!(
+            ("ctrl-x", vec![("execute", Some("echo a, b".to_string()))]),
+            key_action[0]
+        );
+    }
+
+    #[test]
+    fn expect_keys_should_be_split() {
+        assert_eq!(
+            split_expect_keys("ctrl-e:edit,ctrl-o:open,alt-s,::colon,"),
+            vec![
+                ("ctrl-e", Some("edit")),
+                ("ctrl-o", Some("open")),
+                ("alt-s", None),
+                (":", Some("colon"))
+            ]
+        );
+
+        let mut input = Input::new();
+        input.parse_expect_keys(Some("ctrl-e:edit,alt-s"));
+        let mut translate = |key| input.translate_event(TermEvent::Key(key)).1;
+        assert_eq!(
+            translate(Key::Ctrl('e')),
+            vec![Event::EvActAccept(Some("ctrl-e".to_string()))]
+        );
+        assert_eq!(
+            translate(Key::Alt('s')),
+            vec![Event::EvActAccept(Some("alt-s".to_string()))]
+        );
+        let bindings = input.describe_bindings();
+        assert!(bindings.contains(&("ctrl-e".to_string(), "edit".to_string())));
+        assert!(bindings.contains(&("alt-s".to_string(), "accept(alt-s)".to_string())));
+    }
+
+    #[test]
+    fn bindings_should_be_described() {
+        let bindings = parse_key_bindings("ctrl-o:execute(open {})#Open in Finder,ctrl-y:up # Up ,ctrl-z:down#");
+        assert_eq!(
+            (
+                "ctrl-o",
+                vec![("execute", Some("open {}".to_string()))],
+                Some("Open in Finder")
+            ),
+            bindings[0]
+        );
+        assert_eq!(("ctrl-y", vec![("up", None)], Some("Up")), bindings[1]);
+        assert_eq!(("ctrl-z", vec![("down", None)], None), bindings[2]);
+
+        let mut input = Input::new();
+        input.parse_keymap("ctrl-o:execute(open {})#Open in Finder,ctrl-y:toggle+up(2),?:show-help#Help");
+        input.parse_keymap("ctrl-g:abort(3)#Quit,ctrl-g:abort");
+        let bindings = input.describe_bindings();
+        let describe = |name: &str| {
+            bindings
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, description)| description.as_str())
+        };
+        assert_eq!(describe("ctrl-o"), Some("Open in Finder"));
+        assert_eq!(describe("ctrl-y"), Some("toggle+up(2)"));
+        assert_eq!(describe("?"), Some("Help"));
+        // the description is dropped along with the actions
+        assert_eq!(describe("ctrl-g"), Some("abort"));
+        assert_eq!(describe("esc"), Some("abort"));
+        assert_eq!(describe("down"), Some("down"));
+        assert_eq!(describe("enter"), Some("accept"));
+
+        let mut sorted = bindings.clone();
+        sorted.sort();
+        assert_eq!(bindings, sorted);
+    }
 }
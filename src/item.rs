@@ -1,13 +1,16 @@
 ///! An item is line of text that read from `find` command or stdin together with
 ///! the internal states, such as selected or not
+use std::borrow::Cow;
 use std::cmp::min;
 use std::default::Default;
 use std::ops::Deref;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
+use tuikit::attr::Attr;
+
 use crate::spinlock::{SpinLock, SpinLockGuard};
-use crate::{MatchRange, Rank, SkimItem};
+use crate::{AnsiString, DisplayContext, ItemIcon, ItemPreview, MatchRange, Matches, PreviewContext, Rank, SkimItem};
 
 //------------------------------------------------------------------------------
 
@@ -26,6 +29,11 @@ impl Default for RankBuilder {
 
 impl RankBuilder {
     pub fn new(mut criterion: Vec<RankCriteria>) -> Self {
+        // the indices are unique, the criteria after index never take effect
+        if let Some(pos) = criterion.iter().position(RankCriteria::is_index) {
+            criterion.truncate(pos + 1);
+        }
+
         if !criterion.contains(&RankCriteria::Score) && !criterion.contains(&RankCriteria::NegScore) {
             criterion.insert(0, RankCriteria::Score);
         }
@@ -34,6 +42,11 @@ impl RankBuilder {
         Self { criterion }
     }
 
+    /// whether the items that appear later in the input are preferred when the ranks tie
+    pub fn reverse_index(&self) -> bool {
+        self.criterion.contains(&RankCriteria::NegIndex)
+    }
+
     /// score: the greater the better
     pub fn build_rank(&self, score: i32, begin: usize, end: usize, length: usize) -> Rank {
         let mut rank = [0; 4];
@@ -51,6 +64,8 @@ impl RankBuilder {
                 RankCriteria::NegEnd => -end,
                 RankCriteria::Length => length,
                 RankCriteria::NegLength => -length,
+                // the ties are kept in input order, see `OrderedVec`
+                RankCriteria::Index | RankCriteria::NegIndex => 0,
             };
 
             rank[index] = value;
@@ -58,6 +73,33 @@ impl RankBuilder {
 
         rank
     }
+
+    /// fill in the values that `rank` was built with, the reverse of `build_rank`
+    pub fn decode_rank(&self, rank: &Rank, values: &mut RankValues) {
+        for (criteria, &value) in self.criterion.iter().zip(rank.iter()) {
+            match criteria {
+                RankCriteria::Score => values.score = -value,
+                RankCriteria::NegScore => values.score = value,
+                RankCriteria::Begin => values.begin = value as usize,
+                RankCriteria::NegBegin => values.begin = -value as usize,
+                RankCriteria::End => values.end = value as usize,
+                RankCriteria::NegEnd => values.end = -value as usize,
+                RankCriteria::Length => values.length = value as usize,
+                RankCriteria::NegLength => values.length = -value as usize,
+                RankCriteria::Index | RankCriteria::NegIndex => {}
+            }
+        }
+    }
+}
+
+/// The values that the rank of a matched item is built from, for the `{score}` and `{rank.*}`
+/// placeholders of the commands
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RankValues {
+    pub score: i32,
+    pub begin: usize,
+    pub end: usize,
+    pub length: usize,
 }
 
 //------------------------------------------------------------------------------
@@ -65,11 +107,33 @@ impl RankBuilder {
 pub struct MatchedItem {
     pub item: Arc<dyn SkimItem>,
     pub rank: Rank,
-    pub matched_range: Option<MatchRange>, // range of chars that matched the pattern
+    // range of chars that matched the pattern, boxed to keep the matched items small, None if compact
+    pub matched_range: Option<Box<MatchRange>>,
     pub item_idx: u32,
 }
 
-impl MatchedItem {}
+impl MatchedItem {
+    /// the values of the rank, the ones not in the rank are taken from the matched range and the
+    /// text, the positions are unknown if neither has them(e.g. the compact matched range)
+    pub fn rank_values(&self, rank_builder: &RankBuilder) -> RankValues {
+        let (begin, end) = match self.matched_range.as_deref() {
+            Some(MatchRange::ByteRange(begin, end)) => (*begin, *end),
+            Some(MatchRange::Chars(indices)) => (
+                indices.first().copied().unwrap_or(0),
+                indices.last().copied().unwrap_or(0),
+            ),
+            None => (0, 0),
+        };
+        let mut values = RankValues {
+            score: 0,
+            begin,
+            end,
+            length: self.item.text().len(),
+        };
+        rank_builder.decode_rank(&self.rank, &mut values);
+        values
+    }
+}
 
 use std::cmp::Ordering as CmpOrd;
 
@@ -101,10 +165,15 @@ pub struct ItemPool {
     pool: SpinLock<Vec<Arc<dyn SkimItem>>>,
     /// number of items that was `take`n
     taken: AtomicUsize,
+    /// bumped by `clear`, the items(and their indices) of different generations never mix
+    generation: AtomicUsize,
 
     /// reverse first N lines as header
     reserved_items: SpinLock<Vec<Arc<dyn SkimItem>>>,
     lines_to_reserve: usize,
+
+    /// match the items against their display text
+    match_display: bool,
 }
 
 impl ItemPool {
@@ -113,8 +182,10 @@ impl ItemPool {
             length: AtomicUsize::new(0),
             pool: SpinLock::new(Vec::with_capacity(ITEM_POOL_CAPACITY)),
             taken: AtomicUsize::new(0),
+            generation: AtomicUsize::new(0),
             reserved_items: SpinLock::new(Vec::new()),
             lines_to_reserve: 0,
+            match_display: false,
         }
     }
 
@@ -123,6 +194,11 @@ impl ItemPool {
         self
     }
 
+    pub fn match_display(mut self, match_display: bool) -> Self {
+        self.match_display = match_display;
+        self
+    }
+
     pub fn len(&self) -> usize {
         self.length.load(Ordering::SeqCst)
     }
@@ -135,6 +211,11 @@ impl ItemPool {
         self.taken.load(Ordering::SeqCst)
     }
 
+    pub fn generation(&self) -> usize {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// start a new generation of items, the allocation is kept for the new items
     pub fn clear(&self) {
         let mut items = self.pool.lock();
         items.clear();
@@ -142,6 +223,7 @@ impl ItemPool {
         header_items.clear();
         self.taken.store(0, Ordering::SeqCst);
         self.length.store(0, Ordering::SeqCst);
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 
     pub fn reset(&self) {
@@ -152,6 +234,10 @@ impl ItemPool {
 
     /// append the items and return the new_size of the pool
     pub fn append(&self, mut items: Vec<Arc<dyn SkimItem>>) -> usize {
+        if self.match_display {
+            items = items.into_iter().map(DisplayTextItem::wrap).collect();
+        }
+
         let len = items.len();
         trace!("item pool, append {} items", len);
         let mut pool = self.pool.lock();
@@ -176,12 +262,101 @@ impl ItemPool {
         ItemPoolGuard { guard, start: taken }
     }
 
+    /// the items except the reserved ones, to be put back by `set_items`
+    pub fn items(&self) -> Vec<Arc<dyn SkimItem>> {
+        self.pool.lock().clone()
+    }
+
+    /// replace the items returned by `items`, the reserved items are kept. The indices are
+    /// reused, so it starts a new generation as `clear` does.
+    pub fn set_items(&self, items: Vec<Arc<dyn SkimItem>>) {
+        let mut pool = self.pool.lock();
+        *pool = items;
+        self.taken.store(0, Ordering::SeqCst);
+        self.length.store(pool.len(), Ordering::SeqCst);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
     pub fn reserved(&self) -> ItemPoolGuard<Arc<dyn SkimItem>> {
         let guard = self.reserved_items.lock();
         ItemPoolGuard { guard, start: 0 }
     }
 }
 
+/// An item that is matched against the text it displays (without ANSI attributes) instead of
+/// its `text`. The display text is computed once when the item is added to the pool.
+struct DisplayTextItem {
+    inner: Arc<dyn SkimItem>,
+    display_text: String,
+}
+
+impl DisplayTextItem {
+    // width that is large enough for the item to be displayed completely
+    const DISPLAY_WIDTH: usize = u16::MAX as usize;
+
+    /// wrap the item if its display text differs from its text
+    fn wrap(item: Arc<dyn SkimItem>) -> Arc<dyn SkimItem> {
+        let display_text = {
+            let text = item.text();
+            let context = DisplayContext {
+                text: &text,
+                score: 0,
+                matches: Matches::None,
+                container_width: Self::DISPLAY_WIDTH,
+                highlight_attr: Attr::default(),
+            };
+            let display_text = item.display(context).stripped().to_string();
+            if display_text == text {
+                return item;
+            }
+            display_text
+        };
+
+        Arc::new(Self {
+            inner: item,
+            display_text,
+        })
+    }
+}
+
+impl SkimItem for DisplayTextItem {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.display_text)
+    }
+
+    fn display<'a>(&'a self, context: DisplayContext<'a>) -> AnsiString<'a> {
+        self.inner.display(context)
+    }
+
+    fn preview(&self, context: PreviewContext) -> ItemPreview {
+        self.inner.preview(context)
+    }
+
+    fn icon(&self) -> Option<ItemIcon> {
+        self.inner.icon()
+    }
+
+    fn identity(&self) -> Option<String> {
+        self.inner.identity()
+    }
+
+    fn output(&self) -> Cow<'_, str> {
+        self.inner.output()
+    }
+
+    fn output_fields(&self) -> Vec<Cow<'_, str>> {
+        self.inner.output_fields()
+    }
+}
+
+/// get back the item that was added to the item pool, so that it could be downcasted
+pub fn unwrap_item(item: Arc<dyn SkimItem>) -> Arc<dyn SkimItem> {
+    match item.as_any().downcast_ref::<DisplayTextItem>() {
+        Some(wrapped) => wrapped.inner.clone(),
+        None => item,
+    }
+}
+
 pub struct ItemPoolGuard<'a, T: Sized + 'a> {
     guard: SpinLockGuard<'a, Vec<T>>,
     start: usize,
@@ -206,6 +381,14 @@ pub enum RankCriteria {
     NegEnd,
     Length,
     NegLength,
+    Index,
+    NegIndex,
+}
+
+impl RankCriteria {
+    fn is_index(&self) -> bool {
+        matches!(self, RankCriteria::Index | RankCriteria::NegIndex)
+    }
 }
 
 pub fn parse_criteria(text: &str) -> Option<RankCriteria> {
@@ -218,6 +401,120 @@ pub fn parse_criteria(text: &str) -> Option<RankCriteria> {
         "-end" => Some(RankCriteria::NegEnd),
         "length" => Some(RankCriteria::Length),
         "-length" => Some(RankCriteria::NegLength),
+        "index" => Some(RankCriteria::Index),
+        "-index" => Some(RankCriteria::NegIndex),
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ItemIdentity;
+
+    struct Labeled(&'static str, &'static str);
+
+    impl SkimItem for Labeled {
+        fn text(&self) -> Cow<'_, str> {
+            Cow::Borrowed(self.0)
+        }
+
+        fn display<'a>(&'a self, _context: DisplayContext<'a>) -> AnsiString<'a> {
+            AnsiString::parse(self.1)
+        }
+
+        fn identity(&self) -> Option<String> {
+            Some(self.0.to_string())
+        }
+
+        fn output_fields(&self) -> Vec<Cow<'_, str>> {
+            vec![Cow::Borrowed(self.0), Cow::Borrowed(self.1)]
+        }
+    }
+
+    #[test]
+    fn test_index_criteria() {
+        let rank_builder = RankBuilder::new(vec![RankCriteria::Begin, RankCriteria::NegIndex, RankCriteria::End]);
+        assert!(rank_builder.reverse_index());
+        assert_eq!(rank_builder.build_rank(10, 1, 2, 3), [-10, 1, 0, 0]);
+
+        let rank_builder = RankBuilder::new(vec![RankCriteria::Index, RankCriteria::NegScore]);
+        assert!(!rank_builder.reverse_index());
+        assert_eq!(rank_builder.build_rank(10, 1, 2, 3), [-10, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_rank_values() {
+        let values = RankValues {
+            score: 42,
+            begin: 1,
+            end: 3,
+            length: 5,
+        };
+        let rank_builder = RankBuilder::default();
+        let mut matched = MatchedItem {
+            item: Arc::new("abcde".to_string()),
+            rank: rank_builder.build_rank(42, 1, 3, 5),
+            matched_range: None,
+            item_idx: 0,
+        };
+        assert_eq!(matched.rank_values(&rank_builder), values);
+
+        let rank_builder = RankBuilder::new(vec![RankCriteria::NegBegin, RankCriteria::NegScore]);
+        matched.rank = rank_builder.build_rank(42, 1, 3, 5);
+        matched.matched_range = Some(Box::new(MatchRange::Chars(vec![1, 2, 3])));
+        assert_eq!(matched.rank_values(&rank_builder), values);
+
+        // the item, the rank and the index, the matched range is a pointer
+        assert!(std::mem::size_of::<MatchedItem>() <= 48);
+    }
+
+    #[test]
+    fn test_clear_item_pool() {
+        let item_pool = ItemPool::new();
+        let items: Vec<Arc<dyn SkimItem>> = (0..5000)
+            .map(|i| Arc::new(i.to_string()) as Arc<dyn SkimItem>)
+            .collect();
+        item_pool.append(items.clone());
+        let _ = item_pool.take();
+        assert_eq!(item_pool.generation(), 0);
+
+        item_pool.clear();
+        assert_eq!(item_pool.len(), 0);
+        assert_eq!(item_pool.num_taken(), 0);
+        assert_eq!(item_pool.generation(), 1);
+
+        // the items are dropped while the allocation is kept for the next generation
+        assert_eq!(Arc::strong_count(&items[0]), 1);
+        assert!(item_pool.pool.lock().capacity() >= 5000);
+
+        item_pool.append(vec![Arc::new("a".to_string())]);
+        item_pool.clear();
+        assert_eq!(item_pool.generation(), 2);
+    }
+
+    #[test]
+    fn test_match_display() {
+        let item_pool = ItemPool::new().match_display(true);
+        item_pool.append(vec![
+            Arc::new(Labeled("id-1", "\x1b[31mapple\x1b[0m")),
+            Arc::new("plain".to_string()),
+        ]);
+
+        let items = item_pool.take();
+        assert_eq!(items[0].text(), "apple");
+        assert_eq!(items[0].output(), "id-1");
+        assert_eq!(items[0].output_fields(), vec!["id-1", "\x1b[31mapple\x1b[0m"]);
+        assert_eq!(items[1].text(), "plain");
+        assert_eq!(
+            ItemIdentity::of(items[0].as_ref(), 3, 0),
+            ItemIdentity::Key("id-1".to_string())
+        );
+        assert_eq!(ItemIdentity::of(items[1].as_ref(), 3, 1), ItemIdentity::Index(3, 1));
+
+        let original = unwrap_item(items[0].clone());
+        assert!(original.as_any().downcast_ref::<Labeled>().is_some());
+        let plain = unwrap_item(items[1].clone());
+        assert!(plain.as_any().downcast_ref::<String>().is_some());
+    }
+}
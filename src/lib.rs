@@ -10,32 +10,48 @@ use std::sync::mpsc::channel;
 use std::sync::Arc;
 use std::thread;
 
-use crossbeam::channel::{Receiver, Sender};
+use crossbeam::channel::{unbounded, Receiver, Sender};
 use tuikit::prelude::{Event as TermEvent, *};
 
+pub use crate::abort::AbortHandle;
 pub use crate::ansi::AnsiString;
+#[cfg(feature = "config")]
+pub use crate::config::SkimConfig;
 pub use crate::engine::fuzzy::FuzzyAlgorithm;
+use crate::engine::util::contains_upper;
 use crate::event::{EventReceiver, EventSender};
+pub use crate::global::current_run_num;
+pub use crate::input::{action_names, check_keymap, key_names};
 use crate::model::Model;
+pub use crate::notify::{SkimEvent, SkimEventReceiver, SkimEventSender};
 pub use crate::options::SkimOptions;
 pub use crate::output::SkimOutput;
 use crate::reader::Reader;
+use crate::util::{parse_margin, set_bracketed_paste};
 
+mod abort;
 mod ansi;
+#[cfg(feature = "config")]
+mod config;
 mod engine;
 mod event;
 pub mod field;
 mod global;
 mod header;
+mod help;
 mod helper;
+mod highlight;
+mod icon;
 mod input;
 mod item;
 mod matcher;
 mod model;
+mod notify;
 mod options;
 mod orderedvec;
 mod output;
 pub mod prelude;
+mod preview_rules;
 mod previewer;
 mod query;
 mod reader;
@@ -115,6 +131,19 @@ pub trait SkimItem: AsAny + Send + Sync + 'static {
         ItemPreview::Global
     }
 
+    /// The icon shown in a column before the item text, e.g. the devicon of the file type.
+    /// Default to `None`, which falls back to the icons given by the `icons` option.
+    fn icon(&self) -> Option<ItemIcon> {
+        None
+    }
+
+    /// A key that identifies the item across the runs of the command, e.g. a file path or an id
+    /// in a database, so that the item stays selected when it's read again. Default to `None`,
+    /// which identifies the item by the run number and its index in the run, see `ItemIdentity`.
+    fn identity(&self) -> Option<String> {
+        None
+    }
+
     /// Get output text(after accept), default to `text()`
     /// Note that this function is intended to be used by the caller of skim and will not be used by
     /// skim. And since skim will return the item back in `SkimOutput`, if string is not what you
@@ -123,6 +152,13 @@ pub trait SkimItem: AsAny + Send + Sync + 'static {
         self.text()
     }
 
+    /// The fields of the output, e.g. the id, the path and the kind of a structured item. The
+    /// binary prints them separated by tabs, and `{o1}`, `{o2}`, ... of the commands are replaced
+    /// by them. Default to the single field of `output()`.
+    fn output_fields(&self) -> Vec<Cow<'_, str>> {
+        vec![self.output()]
+    }
+
     /// we could limit the matching ranges of the `get_text` of the item.
     /// providing (start_byte, end_byte) of the range
     fn get_matching_ranges(&self) -> Option<&[(usize, usize)]> {
@@ -216,6 +252,23 @@ pub enum ItemPreview {
     Global,
 }
 
+//------------------------------------------------------------------------------
+// Icon
+pub struct ItemIcon {
+    /// The glyph(s) of the icon, the icon column is as wide as the widest visible icon
+    pub glyph: String,
+    pub attr: Attr,
+}
+
+impl ItemIcon {
+    pub fn new(glyph: impl Into<String>, attr: Attr) -> Self {
+        Self {
+            glyph: glyph.into(),
+            attr,
+        }
+    }
+}
+
 //==============================================================================
 // A match engine will execute the matching algorithm
 
@@ -232,6 +285,18 @@ impl Default for CaseMatching {
     }
 }
 
+impl CaseMatching {
+    /// whether `query` should be matched case sensitively, smart case is sensitive only if the
+    /// query contains upper case characters
+    pub fn is_sensitive(&self, query: &str) -> bool {
+        match self {
+            CaseMatching::Respect => true,
+            CaseMatching::Ignore => false,
+            CaseMatching::Smart => contains_upper(query),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Debug)]
 #[allow(dead_code)]
 pub enum MatchRange {
@@ -259,25 +324,113 @@ impl MatchResult {
             MatchRange::Chars(vec) => vec.clone(),
         }
     }
+
+    /// The matched `(start, end)` byte ranges of `text`, the adjacent characters are merged into
+    /// one range
+    pub fn range_byte_ranges(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut char_indices = self.range_char_indices(text);
+        char_indices.sort_unstable();
+
+        let mut char_indices = char_indices.into_iter().peekable();
+        let mut ranges: Vec<(usize, usize)> = vec![];
+        for (char_idx, (byte_idx, ch)) in text.char_indices().enumerate() {
+            while char_indices.next_if(|&idx| idx < char_idx).is_some() {}
+            if char_indices.next_if_eq(&char_idx).is_none() {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some((_, end)) if *end == byte_idx => *end += ch.len_utf8(),
+                _ => ranges.push((byte_idx, byte_idx + ch.len_utf8())),
+            }
+        }
+        ranges
+    }
 }
 
 pub trait MatchEngine: Sync + Send + Display {
     fn match_item(&self, item: Arc<dyn SkimItem>) -> Option<MatchResult>;
 }
 
-pub trait MatchEngineFactory {
+pub trait MatchEngineFactory: Send + Sync {
     fn create_engine_with_case(&self, query: &str, case: CaseMatching) -> Box<dyn MatchEngine>;
     fn create_engine(&self, query: &str) -> Box<dyn MatchEngine> {
         self.create_engine_with_case(query, CaseMatching::default())
     }
+
+    /// The canonical form of the query, the queries of the same form should create the same
+    /// engines, so that the items are not matched again when e.g. only a trailing space is typed
+    fn normalize_query<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(query)
+    }
 }
 
 //------------------------------------------------------------------------------
 // Preselection
 
 /// A selector that determines whether an item should be "pre-selected" in multi-selection mode
-pub trait Selector {
+pub trait Selector: Send + Sync {
     fn should_select(&self, index: usize, item: &dyn SkimItem) -> bool;
+
+    /// Replace the pattern of the items to select, by the `pre-select-pat(REGEX)` action.
+    /// Ignored by default.
+    fn set_pattern(&self, _pattern: &str) {}
+}
+
+//------------------------------------------------------------------------------
+// Identity
+
+/// Identifies an item across the matcher runs, the selected items are remembered by it.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ItemIdentity {
+    /// The key given by `SkimItem::identity`
+    Key(String),
+    /// The run number(see `current_run_num`) and the index of the item in the run
+    Index(u32, u32),
+}
+
+impl ItemIdentity {
+    pub fn of(item: &dyn SkimItem, run_num: u32, item_idx: u32) -> Self {
+        match item.identity() {
+            Some(key) => ItemIdentity::Key(key),
+            None => ItemIdentity::Index(run_num, item_idx),
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+// Viewport
+
+/// The part of the matched items that is visible on the screen
+#[derive(Clone, Default)]
+pub struct Viewport {
+    /// index(in the matched items) of the first visible item
+    pub start: usize,
+    /// index of the item after the last visible one
+    pub end: usize,
+    /// index of the current(highlighted) item
+    pub cursor: usize,
+    /// the visible items, from `start` to `end`
+    pub items: Vec<Arc<dyn SkimItem>>,
+}
+
+impl Viewport {
+    pub(crate) fn same_as(&self, other: &Viewport) -> bool {
+        self.start == other.start
+            && self.end == other.end
+            && self.cursor == other.cursor
+            && self.items.len() == other.items.len()
+            && self
+                .items
+                .iter()
+                .zip(other.items.iter())
+                .all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+/// Notified whenever the viewport changes, e.g. the user scrolls or the items are matched again,
+/// so that custom side panels could follow the visible items.
+pub trait ViewportListener: Send + Sync {
+    fn on_viewport_change(&self, viewport: &Viewport);
 }
 
 //------------------------------------------------------------------------------
@@ -296,16 +449,12 @@ impl Skim {
     /// - None: on internal errors.
     /// - SkimOutput: the collected key, event, query, selected items, etc.
     pub fn run_with(options: &SkimOptions, source: Option<SkimItemReceiver>) -> Option<SkimOutput> {
-        let min_height = options
-            .min_height
-            .map(Skim::parse_height_string)
-            .expect("min_height should have default values");
-        let height = options
-            .height
-            .map(Skim::parse_height_string)
-            .expect("height should have default values");
+        let (min_height, height) = Skim::term_heights(options);
 
         let (tx, rx): (EventSender, EventReceiver) = channel();
+        if let Some(abort_handle) = options.abort_handle.as_ref() {
+            abort_handle.attach(tx.clone());
+        }
         let term = Arc::new(
             Term::with_options(
                 TermOptions::default()
@@ -314,7 +463,7 @@ impl Skim {
                     .clear_on_exit(!options.no_clear)
                     .disable_alternate_screen(options.no_clear_start)
                     .clear_on_start(!options.no_clear_start)
-                    .hold(options.select1 || options.exit0 || options.sync),
+                    .hold(options.select1 || options.exit0 || options.sync || options.accept_nth.is_some()),
             )
             .unwrap(),
         );
@@ -326,7 +475,9 @@ impl Skim {
         // input
         let mut input = input::Input::new();
         input.parse_keymaps(&options.bind);
+        input.bind_keymap(&options.keymap);
         input.parse_expect_keys(options.expect.as_deref());
+        let key_bindings = input.describe_bindings();
 
         let tx_clone = tx.clone();
         let term_clone = term.clone();
@@ -351,12 +502,93 @@ impl Skim {
         //------------------------------------------------------------------------------
         // model + previewer
         let mut model = Model::new(rx, tx, reader, term.clone(), options);
+        model.set_key_bindings(key_bindings);
+        // a paste comes in one piece, instead of the keys that take their actions
+        let _ = set_bracketed_paste(true);
         let ret = model.start();
+        let _ = set_bracketed_paste(false);
+        if let Some(abort_handle) = options.abort_handle.as_ref() {
+            abort_handle.detach();
+        }
         let _ = term.send_event(TermEvent::User(())); // interrupt the input thread
         let _ = input_thread.join();
         ret
     }
 
+    /// The factory of the engines that `run_with` matches the items with by the options, e.g.
+    /// to time the matching apart from the interface
+    pub fn engine_factory(options: &SkimOptions) -> Arc<dyn MatchEngineFactory> {
+        Model::engine_factory(options, Model::rank_builder(options))
+    }
+
+    /// Run skim on a thread of its own, so that the caller could go on with its work and follow
+    /// skim by the returned receiver: the query, focus and selection changes as they happen, and
+    /// `SkimEvent::Finished` with the output of `run_with` at last, after which the receiver is
+    /// disconnected. `options.event_sender` is replaced by the sender of the receiver.
+    ///
+    /// The options live as long as the thread, thus the `'static` lifetime.
+    pub fn run_async(mut options: SkimOptions<'static>, source: Option<SkimItemReceiver>) -> SkimEventReceiver {
+        let (tx_event, rx_event) = unbounded();
+        options.event_sender = Some(tx_event.clone());
+        thread::spawn(move || {
+            let output = Skim::run_with(&options, source);
+            let _ = tx_event.send(SkimEvent::Finished(output));
+        });
+        rx_event
+    }
+
+    /// `run_async` as a stream of the events(the `tokio` feature)
+    #[cfg(feature = "tokio")]
+    pub fn run_stream(
+        options: SkimOptions<'static>,
+        source: Option<SkimItemReceiver>,
+    ) -> impl tokio_stream::Stream<Item = SkimEvent> {
+        helper::tokio_source::event_stream(Skim::run_async(options, source))
+    }
+
+    /// The `(min_height, height)` of the window, `no_height` is the full screen whatever the
+    /// heights are. The fixed heights are raised to the working minimum, the terminal caps them.
+    fn term_heights(options: &SkimOptions) -> (TermHeight, TermHeight) {
+        // too small a height leaves no room for the query or the items
+        let working_height = Skim::working_min_height(options);
+        let clamp = |height| match height {
+            TermHeight::Fixed(h) if h < working_height => {
+                warn!("height is too small, use {} lines instead", working_height);
+                TermHeight::Fixed(working_height)
+            }
+            height => height,
+        };
+
+        let min_height = options
+            .min_height
+            .map(Skim::parse_height_string)
+            .map(clamp)
+            .expect("min_height should have default values");
+        if options.no_height {
+            return (min_height, TermHeight::Percent(100));
+        }
+
+        let height = options
+            .height
+            .map(Skim::parse_height_string)
+            .map(clamp)
+            .expect("height should have default values");
+        (min_height, height)
+    }
+
+    /// The least number of lines that skim needs: the query line, the info line and an item,
+    /// plus the fixed top and bottom margins. Margins in percent are not counted since they
+    /// shrink along with the height.
+    fn working_min_height(options: &SkimOptions) -> usize {
+        let (margin_top, _, margin_bottom, _) = parse_margin(options.margin.unwrap_or("0"));
+        let fixed_margin = |margin| match margin {
+            Size::Fixed(margin) => margin,
+            _ => 0,
+        };
+        let info_lines = if options.inline_info || options.no_info { 0 } else { 1 };
+        2 + info_lines + fixed_margin(margin_top) + fixed_margin(margin_bottom)
+    }
+
     // 10 -> TermHeight::Fixed(10)
     // 10% -> TermHeight::Percent(10)
     fn parse_height_string(string: &str) -> TermHeight {
@@ -367,3 +599,92 @@ impl Skim {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::SkimOptionsBuilder;
+
+    #[test]
+    fn test_range_byte_ranges() {
+        let result = |matched_range| MatchResult {
+            rank: [0; 4],
+            matched_range,
+        };
+        let text = "a中b文c";
+        assert_eq!(
+            vec![(1, 5), (8, 9)],
+            result(MatchRange::Chars(vec![4, 2, 1])).range_byte_ranges(text)
+        );
+        assert_eq!(
+            vec![(1, 4)],
+            result(MatchRange::ByteRange(1, 4)).range_byte_ranges(text)
+        );
+        assert!(result(MatchRange::Chars(vec![])).range_byte_ranges(text).is_empty());
+    }
+
+    #[test]
+    fn test_options_are_send() {
+        // the options could be built on one thread and run on another
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+        let options = SkimOptions::default();
+        assert_send_sync(&options);
+        thread::spawn(move || assert!(options.engine_factory.is_none()))
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_no_height() {
+        let height = |options: &SkimOptions| match Skim::term_heights(options).1 {
+            TermHeight::Fixed(h) => format!("{}", h),
+            TermHeight::Percent(h) => format!("{}%", h),
+        };
+
+        let options = SkimOptionsBuilder::default().height(Some("40%")).build().unwrap();
+        assert_eq!(height(&options), "40%");
+        let options = SkimOptionsBuilder::default()
+            .no_height(true)
+            .height(Some("10"))
+            .build()
+            .unwrap();
+        assert_eq!(height(&options), "100%");
+
+        // the options set after the builder
+        let options = SkimOptions {
+            no_height: true,
+            height: Some("10"),
+            ..SkimOptions::default()
+        };
+        assert_eq!(height(&options), "100%");
+    }
+
+    #[test]
+    fn test_small_height() {
+        let heights = |options: &SkimOptions| match Skim::term_heights(options) {
+            (TermHeight::Fixed(min_height), TermHeight::Fixed(height)) => (min_height, height),
+            _ => unreachable!(),
+        };
+
+        let options = SkimOptionsBuilder::default()
+            .height(Some("1"))
+            .min_height(Some("2"))
+            .build()
+            .unwrap();
+        assert_eq!(heights(&options), (3, 3));
+        let options = SkimOptionsBuilder::default()
+            .height(Some("2"))
+            .min_height(Some("1"))
+            .inline_info(true)
+            .margin(Some("1,0"))
+            .build()
+            .unwrap();
+        assert_eq!(heights(&options), (4, 4));
+        let options = SkimOptionsBuilder::default()
+            .height(Some("20"))
+            .min_height(Some("10"))
+            .build()
+            .unwrap();
+        assert_eq!(heights(&options), (10, 20));
+    }
+}
@@ -1,3 +1,4 @@
+use std::cmp::{max, min};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
@@ -7,16 +8,22 @@ use rayon::prelude::*;
 
 use crate::item::{ItemPool, MatchedItem};
 use crate::spinlock::SpinLock;
-use crate::{CaseMatching, MatchEngineFactory};
+use crate::{CaseMatching, MatchEngine, MatchEngineFactory, MatchRange};
 use defer_drop::DeferDrop;
-use std::rc::Rc;
+
+const MIN_CHUNK_SIZE: usize = 1024;
+// bounds the time to match a chunk, so that the best matches of huge pools are shown early
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
 
 //==============================================================================
 pub struct MatcherControl {
     stopped: Arc<AtomicBool>,
     processed: Arc<AtomicUsize>,
     matched: Arc<AtomicUsize>,
-    items: Arc<SpinLock<Vec<MatchedItem>>>,
+    // sorted runs of the matched items, in input order, and the number of items taken out
+    items: Arc<SpinLock<Vec<Vec<MatchedItem>>>>,
+    taken: usize,
+    engine: Arc<dyn MatchEngine>,
     thread_matcher: JoinHandle<()>,
 }
 
@@ -25,8 +32,9 @@ impl MatcherControl {
         self.processed.load(Ordering::Relaxed)
     }
 
+    /// the number of matched items that are not taken by `take_runs` yet
     pub fn get_num_matched(&self) -> usize {
-        self.matched.load(Ordering::Relaxed)
+        self.matched.load(Ordering::Relaxed) - self.taken
     }
 
     pub fn kill(self) {
@@ -38,23 +46,33 @@ impl MatcherControl {
         self.stopped.load(Ordering::Relaxed)
     }
 
-    pub fn into_items(self) -> Arc<SpinLock<Vec<MatchedItem>>> {
-        while !self.stopped.load(Ordering::Relaxed) {}
-        self.items
+    /// the engine that matched the items, to find the dropped positions of compact matches
+    pub fn engine(&self) -> Arc<dyn MatchEngine> {
+        self.engine.clone()
+    }
+
+    /// Take out the sorted runs of the chunks matched so far. The runs are made available in
+    /// input order while the matcher is running, all of them are available once it `stopped`.
+    pub fn take_runs(&mut self) -> Vec<Vec<MatchedItem>> {
+        let runs = std::mem::take(&mut *self.items.lock());
+        self.taken += runs.iter().map(Vec::len).sum::<usize>();
+        runs
     }
 }
 
 //==============================================================================
 pub struct Matcher {
-    engine_factory: Rc<dyn MatchEngineFactory>,
+    engine_factory: Arc<dyn MatchEngineFactory>,
     case_matching: CaseMatching,
+    compact: bool,
 }
 
 impl Matcher {
-    pub fn builder(engine_factory: Rc<dyn MatchEngineFactory>) -> Self {
+    pub fn builder(engine_factory: Arc<dyn MatchEngineFactory>) -> Self {
         Self {
             engine_factory,
             case_matching: CaseMatching::default(),
+            compact: false,
         }
     }
 
@@ -63,15 +81,30 @@ impl Matcher {
         self
     }
 
+    /// Do not keep the matched positions of the characters, so that large results take less
+    /// memory. The positions are matched again when the items are drawn.
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
     pub fn build(self) -> Self {
         self
     }
 
+    /// whether the queries create the same engine, see `MatchEngineFactory::normalize_query`
+    pub fn is_same_query(&self, query: &str, other: &str) -> bool {
+        self.engine_factory.normalize_query(query) == self.engine_factory.normalize_query(other)
+    }
+
     pub fn run<C>(&self, query: &str, item_pool: Arc<DeferDrop<ItemPool>>, callback: C) -> MatcherControl
     where
-        C: Fn(Arc<SpinLock<Vec<MatchedItem>>>) + Send + 'static,
+        C: Fn(Arc<SpinLock<Vec<Vec<MatchedItem>>>>) + Send + 'static,
     {
-        let matcher_engine = self.engine_factory.create_engine_with_case(query, self.case_matching);
+        let matcher_engine: Arc<dyn MatchEngine> =
+            Arc::from(self.engine_factory.create_engine_with_case(query, self.case_matching));
+        let matcher_engine_clone = matcher_engine.clone();
+        let compact = self.compact;
         debug!("engine: {}", matcher_engine);
         let stopped = Arc::new(AtomicBool::new(false));
         let stopped_clone = stopped.clone();
@@ -86,36 +119,66 @@ impl Matcher {
             let num_taken = item_pool.num_taken();
             let items = item_pool.take();
 
-            // 1. use rayon for parallel
-            // 2. return Err to skip iteration
-            //    check https://doc.rust-lang.org/std/result/enum.Result.html#method.from_iter
+            // 1. use rayon for parallel, every thread takes the next chunk to match and sort
+            // 2. the sorted runs are published as soon as the chunks before them are done, so
+            //    that the runs stay in input order and the model could show them early
+            // 3. return Err to stop the threads
 
             trace!("matcher start, total: {}", items.len());
-            let result: Result<Vec<_>, _> = items
-                .into_par_iter()
-                .enumerate()
-                .filter_map(|(index, item)| {
+            let num_threads = rayon::current_num_threads();
+            let chunk_size = max(MIN_CHUNK_SIZE, items.len() / (num_threads * 4) + 1);
+            let chunk_size = min(chunk_size, MAX_CHUNK_SIZE);
+            let num_chunks = items.len().div_ceil(chunk_size);
+            let next_chunk = AtomicUsize::new(0);
+            // the index of the next run to publish, and the runs done but not published yet
+            let done_runs: SpinLock<(usize, Vec<Option<Vec<MatchedItem>>>)> =
+                SpinLock::new((0, (0..num_chunks).map(|_| None).collect()));
+
+            let result: Result<(), _> = (0..num_threads).into_par_iter().try_for_each(|_| loop {
+                let chunk_idx = next_chunk.fetch_add(1, Ordering::Relaxed);
+                if chunk_idx >= num_chunks {
+                    return Ok(());
+                }
+
+                let chunk_start = chunk_idx * chunk_size;
+                let chunk = &items[chunk_start..min(chunk_start + chunk_size, items.len())];
+                let chunk_start = num_taken + chunk_start;
+                let mut run = Vec::new();
+                for (index, item) in chunk.iter().enumerate() {
                     processed.fetch_add(1, Ordering::Relaxed);
                     if stopped.load(Ordering::Relaxed) {
-                        Some(Err("matcher killed"))
+                        return Err("matcher killed");
                     } else if let Some(match_result) = matcher_engine.match_item(item.clone()) {
                         matched.fetch_add(1, Ordering::Relaxed);
-                        Some(Ok(MatchedItem {
+                        let matched_range = match match_result.matched_range {
+                            MatchRange::Chars(_) if compact => None,
+                            matched_range => Some(Box::new(matched_range)),
+                        };
+                        run.push(MatchedItem {
                             item: item.clone(),
                             rank: match_result.rank,
-                            matched_range: Some(match_result.matched_range),
-                            item_idx: (num_taken + index) as u32,
-                        }))
-                    } else {
-                        None
+                            matched_range,
+                            item_idx: (chunk_start + index) as u32,
+                        });
                     }
-                })
-                .collect();
-
-            if let Ok(items) = result {
-                let mut pool = matched_items.lock();
-                *pool = items;
-                trace!("matcher stop, total matched: {}", pool.len());
+                }
+                // stable sort, items with the same rank stay in input order
+                run.sort();
+                if compact {
+                    run.shrink_to_fit();
+                }
+
+                let mut done_runs = done_runs.lock();
+                let (next_run, runs) = &mut *done_runs;
+                runs[chunk_idx] = Some(run);
+                while let Some(run) = runs.get_mut(*next_run).and_then(Option::take) {
+                    matched_items.lock().push(run);
+                    *next_run += 1;
+                }
+            });
+
+            if result.is_ok() {
+                trace!("matcher stop, total matched: {}", matched.load(Ordering::Relaxed));
             }
 
             callback(matched_items.clone());
@@ -127,7 +190,64 @@ impl Matcher {
             matched: matched_clone,
             processed: processed_clone,
             items: matched_items_clone,
+            taken: 0,
+            engine: matcher_engine_clone,
             thread_matcher,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::factory::ExactOrFuzzyEngineFactory;
+    use crate::SkimItem;
+
+    #[test]
+    fn test_runs_in_input_order() {
+        let items: Vec<Arc<dyn SkimItem>> = (0..20_000)
+            .map(|i| Arc::new(format!("item {}", i)) as Arc<dyn SkimItem>)
+            .collect();
+        let item_pool = Arc::new(DeferDrop::new(ItemPool::new()));
+        item_pool.append(items);
+
+        let matcher = Matcher::builder(Arc::new(ExactOrFuzzyEngineFactory::builder().build())).build();
+        let mut ctrl = matcher.run("item", item_pool, |_| {});
+        let mut runs = Vec::new();
+        while !ctrl.stopped() {
+            runs.append(&mut ctrl.take_runs());
+        }
+        runs.append(&mut ctrl.take_runs());
+        assert_eq!(ctrl.get_num_matched(), 0);
+
+        let first_indices: Vec<u32> = runs
+            .iter()
+            .map(|run| run.iter().map(|m| m.item_idx).min().unwrap())
+            .collect();
+        assert!(runs.len() > 1);
+        assert!(first_indices.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(runs.iter().map(Vec::len).sum::<usize>(), 20_000);
+    }
+
+    #[test]
+    fn test_compact() {
+        let items: Vec<Arc<dyn SkimItem>> = (0..5_000)
+            .map(|i| Arc::new(format!("item {}", i)) as Arc<dyn SkimItem>)
+            .collect();
+        let item_pool = Arc::new(DeferDrop::new(ItemPool::new()));
+        item_pool.append(items);
+
+        let matcher = Matcher::builder(Arc::new(ExactOrFuzzyEngineFactory::builder().build()))
+            .compact(true)
+            .build();
+        let mut ctrl = matcher.run("im", item_pool, |_| {});
+        let mut runs = Vec::new();
+        while !ctrl.stopped() {
+            runs.append(&mut ctrl.take_runs());
+        }
+        runs.append(&mut ctrl.take_runs());
+        assert_eq!(runs.iter().map(Vec::len).sum::<usize>(), 5_000);
+        assert!(runs.iter().all(|run| run.capacity() == run.len()));
+        assert!(runs.iter().flatten().all(|matched| matched.matched_range.is_none()));
+    }
+}
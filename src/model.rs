@@ -1,47 +1,65 @@
 use std::borrow::Cow;
 use std::env;
 
-use std::process::Command;
-use std::rc::Rc;
+use std::os::unix::io::RawFd;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Arc;
+use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
 use chrono::Duration as TimerDuration;
 use defer_drop::DeferDrop;
+use nix::fcntl::OFlag;
+use nix::sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::unistd::{self, Pid};
 use regex::Regex;
 use timer::{Guard as TimerGuard, Timer};
 use tuikit::prelude::{Event as TermEvent, *};
 
 use crate::engine::factory::{AndOrEngineFactory, ExactOrFuzzyEngineFactory, RegexEngineFactory};
 use crate::event::{Event, EventHandler, EventReceiver, EventSender};
-use crate::global::current_run_num;
+use crate::global::{current_run_num, mark_new_run, restore_run};
 use crate::header::Header;
+use crate::help::Help;
 use crate::input::parse_action_arg;
-use crate::item::{parse_criteria, ItemPool, MatchedItem, RankBuilder, RankCriteria};
+use crate::item::{parse_criteria, unwrap_item, ItemPool, MatchedItem, RankBuilder, RankCriteria};
 use crate::matcher::{Matcher, MatcherControl};
+use crate::notify::{Notification, Notifier};
 use crate::options::SkimOptions;
 use crate::output::SkimOutput;
-use crate::previewer::Previewer;
+use crate::preview_rules::PreviewRules;
+use crate::previewer::{BuiltinPreview, Previewer};
 use crate::query::Query;
 use crate::reader::{Reader, ReaderControl};
 use crate::selection::Selection;
 use crate::spinlock::SpinLock;
 use crate::theme::ColorTheme;
 use crate::util::clear_canvas;
-use crate::util::{depends_on_items, inject_command, margin_string_to_size, parse_margin, InjectContext};
-use crate::{FuzzyAlgorithm, MatchEngineFactory, MatchRange, SkimItem};
+use crate::util::{
+    copy_to_clipboard, depends_on_items, inject_command, inject_text, margin_string_to_size, parse_margin,
+    set_bracketed_paste, CommandEnv, InjectContext, DEFAULT_DELIMITER, TERMINAL_IN_USE_ENV,
+};
+use crate::{FuzzyAlgorithm, MatchEngineFactory, MatchRange, SkimItem, Viewport, ViewportListener};
 use std::cmp::max;
 
-const REFRESH_DURATION: i64 = 100;
+const REFRESH_DURATION: i64 = 50;
 const SPINNER_DURATION: u32 = 200;
-// const SPINNERS: [char; 8] = ['-', '\\', '|', '/', '-', '\\', '|', '/'];
+// how long the selected count flashes when a selection is refused by --multi-max
+const FLASH_DURATION: i64 = 300;
+const SPINNERS_ASCII: [char; 4] = ['-', '\\', '|', '/'];
 const SPINNERS_INLINE: [char; 2] = ['-', '<'];
 const SPINNERS_UNICODE: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
-const DELIMITER_STR: &str = r"[\t\n ]+";
+// the item list needs the room of the cursor, the selection mark and a character
+const MIN_WIDTH: usize = 3;
+
+// the write end of the pipe that the SIGUSR1 handler notifies, -1 if not listening
+static SIGUSR1_PIPE: AtomicI32 = AtomicI32::new(-1);
 
 lazy_static! {
     static ref RE_FIELDS: Regex = Regex::new(r"\\?(\{-?[0-9.,q]*?})").unwrap();
-    static ref RE_PREVIEW_OFFSET: Regex = Regex::new(r"^\+([0-9]+|\{-?[0-9]+\})(-[0-9]+|-/[1-9][0-9]*)?$").unwrap();
+    static ref RE_PREVIEW_OFFSET: Regex =
+        Regex::new(r"^\+([0-9]+|\{-?[0-9.]+\})([-+*/]([0-9]+|\{-?[0-9.]+\}))*(-/[1-9][0-9]*)?$").unwrap();
     static ref DEFAULT_CRITERION: Vec<RankCriteria> =
         vec![RankCriteria::Score, RankCriteria::Begin, RankCriteria::End,];
 }
@@ -53,6 +71,7 @@ pub struct Model {
     num_options: usize,
     select1: bool,
     exit0: bool,
+    accept_nth: Option<usize>,
     sync: bool,
 
     use_regex: bool,
@@ -60,6 +79,7 @@ pub struct Model {
     matcher: Matcher,
 
     term: Arc<Term>,
+    mouse_enabled: bool,
 
     item_pool: Arc<DeferDrop<ItemPool>>,
 
@@ -73,9 +93,13 @@ pub struct Model {
     matcher_control: Option<MatcherControl>,
 
     header: Header,
+    help: Help,
 
     preview_hidden: bool,
+    // hide the preview on terminals narrower than it(`:auto-hide<COLS`)
+    preview_auto_hide: Option<usize>,
     previewer: Option<Previewer>,
+    capture_preview: bool,
     preview_direction: Direction,
     preview_size: Size,
 
@@ -87,61 +111,97 @@ pub struct Model {
     layout: String,
     delimiter: Regex,
     inline_info: bool,
+    no_info: bool,
+    no_unicode: bool,
+    command_env: CommandEnv,
     no_clear_if_empty: bool,
     theme: Arc<ColorTheme>,
+    color: Option<String>,
 
     // timer thread for scheduled events
     timer: Timer,
     hb_timer_guard: Option<TimerGuard>,
+    redraw_timer_guard: Option<TimerGuard>,
+    flash_timer_guard: Option<TimerGuard>,
+    // the selected count flashes till then
+    flash_until: Option<Instant>,
+
+    // minimal interval between the redraws caused by heart beats, zero for no limit, except for
+    // the heart beat that finds the matcher stopped, whose result is drawn at once
+    frame_interval: Duration,
+    last_redraw: Instant,
+    matcher_stopped: bool,
 
     // for AppendAndSelect action
     rank_builder: Arc<RankBuilder>,
+
+    // report the state changes for --notify-fd
+    notifier: Option<Notifier>,
+    last_focus: Option<(usize, String)>,
+    last_selection_version: u64,
+
+    viewport_listener: Option<Arc<dyn ViewportListener>>,
+    last_viewport: Viewport,
+
+    // the levels to go back by the `parent` action(--drill-cmd), the cursor to restore once the
+    // items of a level are matched, and the command of the next level that is still running
+    drill_cmd: Option<String>,
+    drill_stack: Vec<DrillLevel>,
+    drill_cursor: Option<usize>,
+    pending_drill: Option<PendingDrill>,
+
+    // the key of an `accept-or-print-query` waiting for the matcher to finish
+    pending_accept_or_print_query: Option<Key>,
+
+    // narrow down the items of the last command run when the cmd query is extended
+    // (--reuse-items-on-superset), the cmd query the items are of
+    reuse_items_on_superset: bool,
+    items_cmd_query: Option<String>,
+
+    // sends `reload-theme` on SIGUSR1 until the model is dropped(--reload-theme-on-sigusr1)
+    _sigusr1_listener: Option<SigUsr1Listener>,
 }
 
 impl Model {
     pub fn new(rx: EventReceiver, tx: EventSender, reader: Reader, term: Arc<Term>, options: &SkimOptions) -> Self {
-        let default_command = match env::var("SKIM_DEFAULT_COMMAND").as_ref().map(String::as_ref) {
-            Ok("") | Err(_) => "find .".to_owned(),
-            Ok(val) => val.to_owned(),
-        };
+        let default_command = options.default_command();
+        let tx_reader = tx.clone();
 
         let theme = Arc::new(ColorTheme::init_from_options(options));
+        let sigusr1_listener = if options.reload_theme_on_sigusr1 {
+            listen_sigusr1(tx.clone())
+        } else {
+            None
+        };
         let query = Query::from_options(options)
             .replace_base_cmd_if_not_set(&default_command)
             .theme(theme.clone())
             .build();
 
-        let criterion = if let Some(ref tie_breaker) = options.tiebreak {
-            tie_breaker.split(',').filter_map(parse_criteria).collect()
-        } else {
-            DEFAULT_CRITERION.clone()
-        };
+        let rank_builder = Self::rank_builder(options);
 
-        let rank_builder = Arc::new(RankBuilder::new(criterion));
-
-        let selection = Selection::with_options(options).theme(theme.clone());
-        let regex_engine: Rc<dyn MatchEngineFactory> =
-            Rc::new(RegexEngineFactory::builder().rank_builder(rank_builder.clone()).build());
-        let regex_matcher = Matcher::builder(regex_engine).build();
-
-        let matcher = if let Some(engine_factory) = options.engine_factory.as_ref() {
-            // use provided engine
-            Matcher::builder(engine_factory.clone()).case(options.case).build()
-        } else {
-            let fuzzy_engine_factory: Rc<dyn MatchEngineFactory> = Rc::new(AndOrEngineFactory::new(
-                ExactOrFuzzyEngineFactory::builder()
-                    .exact_mode(options.exact)
-                    .rank_builder(rank_builder.clone())
-                    .build(),
-            ));
-            Matcher::builder(fuzzy_engine_factory).case(options.case).build()
-        };
+        let selection = Selection::with_options(options)
+            .theme(theme.clone())
+            .reverse_index(rank_builder.reverse_index());
+        let regex_engine: Arc<dyn MatchEngineFactory> =
+            Arc::new(RegexEngineFactory::builder().rank_builder(rank_builder.clone()).build());
+        let regex_matcher = Matcher::builder(regex_engine).compact(options.compact_match).build();
+
+        let matcher = Matcher::builder(Self::engine_factory(options, rank_builder.clone()))
+            .case(options.case)
+            .compact(options.compact_match)
+            .build();
 
-        let item_pool = Arc::new(DeferDrop::new(ItemPool::new().lines_to_reserve(options.header_lines)));
+        let item_pool = Arc::new(DeferDrop::new(
+            ItemPool::new()
+                .lines_to_reserve(options.header_lines)
+                .match_display(options.match_display),
+        ));
         let header = Header::empty()
             .with_options(options)
             .item_pool(item_pool.clone())
             .theme(theme.clone());
+        let help = Help::new(Vec::new()).theme(theme.clone());
 
         let margins = options
             .margin
@@ -150,17 +210,21 @@ impl Model {
         let (margin_top, margin_right, margin_bottom, margin_left) = margins;
 
         let mut ret = Model {
-            reader,
+            reader: reader.on_done(move || {
+                let _ = tx_reader.send((Key::Null, Event::EvHeartBeat));
+            }),
             query,
             selection,
             num_options: 0,
             select1: false,
             exit0: false,
+            accept_nth: None,
             sync: false,
             use_regex: options.regex,
             regex_matcher,
             matcher,
             term,
+            mouse_enabled: !options.no_mouse,
             item_pool,
 
             rx,
@@ -172,8 +236,11 @@ impl Model {
             fuzzy_algorithm: FuzzyAlgorithm::default(),
 
             header,
+            help,
             preview_hidden: true,
+            preview_auto_hide: None,
             previewer: None,
+            capture_preview: false,
             preview_direction: Direction::Right,
             preview_size: Size::Default,
 
@@ -183,30 +250,100 @@ impl Model {
             margin_left,
 
             layout: "default".to_string(),
-            delimiter: Regex::new(DELIMITER_STR).unwrap(),
+            delimiter: DEFAULT_DELIMITER.clone(),
             inline_info: false,
+            no_info: false,
+            no_unicode: false,
+            command_env: CommandEnv::default(),
             no_clear_if_empty: false,
             theme,
+            color: options.color.map(str::to_string),
             timer: Timer::new(),
             hb_timer_guard: None,
+            redraw_timer_guard: None,
+            flash_timer_guard: None,
+            flash_until: None,
+            frame_interval: Duration::from_millis(0),
+            last_redraw: Instant::now(),
+            matcher_stopped: false,
 
             rank_builder,
+
+            notifier: Notifier::new(options.notify_fd, options.event_sender.clone()),
+            last_focus: None,
+            last_selection_version: 0,
+
+            viewport_listener: options.viewport_listener.clone(),
+            last_viewport: Viewport::default(),
+
+            drill_cmd: options.drill_cmd.map(str::to_string),
+            drill_stack: Vec::new(),
+            drill_cursor: None,
+            pending_drill: None,
+            pending_accept_or_print_query: None,
+
+            reuse_items_on_superset: options.reuse_items_on_superset,
+            items_cmd_query: None,
+
+            _sigusr1_listener: sigusr1_listener,
         };
         ret.parse_options(options);
         ret
     }
 
+    /// the rank builder of the `--tiebreak` criteria
+    pub fn rank_builder(options: &SkimOptions) -> Arc<RankBuilder> {
+        let criterion = if let Some(ref tie_breaker) = options.tiebreak {
+            tie_breaker.split(',').filter_map(parse_criteria).collect()
+        } else {
+            DEFAULT_CRITERION.clone()
+        };
+        Arc::new(RankBuilder::new(criterion))
+    }
+
+    /// The factory of the engines that match the items, the one given by the options or the
+    /// fuzzy(or exact) engines built by them, e.g. `--exact` and `--algo`
+    pub fn engine_factory(options: &SkimOptions, rank_builder: Arc<RankBuilder>) -> Arc<dyn MatchEngineFactory> {
+        if let Some(engine_factory) = options.engine_factory.as_ref() {
+            // use provided engine
+            return engine_factory.clone();
+        }
+
+        let exact_or_fuzzy = ExactOrFuzzyEngineFactory::builder()
+            .fuzzy_algorithm(options.algorithm)
+            .exact_mode(options.exact)
+            .literal(options.literal)
+            .path_mode(options.path_mode)
+            .concat_fields(options.nth_concat)
+            .rank_builder(rank_builder)
+            .build();
+        // literal query is not split into terms
+        if options.literal {
+            Arc::new(exact_or_fuzzy)
+        } else {
+            Arc::new(AndOrEngineFactory::new(exact_or_fuzzy))
+        }
+    }
+
     fn parse_options(&mut self, options: &SkimOptions) {
         if let Some(delimiter) = options.delimiter {
-            self.delimiter = Regex::new(delimiter).unwrap_or_else(|_| Regex::new(DELIMITER_STR).unwrap());
+            self.delimiter = Regex::new(delimiter).unwrap_or_else(|_| DEFAULT_DELIMITER.clone());
         }
 
         self.layout = options.layout.to_string();
 
+        self.no_unicode = options.no_unicode;
+
         if options.inline_info {
             self.inline_info = true;
         }
 
+        if options.no_info {
+            self.no_info = true;
+        }
+
+        self.command_env = CommandEnv::new(options.cmd_cwd, &options.cmd_env);
+
         if options.regex {
             self.use_regex = true;
         }
@@ -221,15 +358,30 @@ impl Model {
         self.preview_direction = preview_direction;
         self.preview_size = preview_size;
         self.preview_hidden = !preview_shown;
+        self.preview_auto_hide = options.preview_window.and_then(Self::parse_preview_auto_hide);
+        self.capture_preview = options.capture_preview;
+
+        if options.max_fps > 0 {
+            self.frame_interval = Duration::from_secs(1) / options.max_fps as u32;
+        }
 
-        if let Some(preview_cmd) = options.preview {
+        let preview_rules = options.preview_rules.map(PreviewRules::parse).unwrap_or_default();
+        let preview_builtin = options.preview_builtin.and_then(BuiltinPreview::parse);
+        if options.preview.is_some() || !preview_rules.is_empty() || preview_builtin.is_some() {
             let tx = Arc::new(SpinLock::new(self.tx.clone()));
             self.previewer = Some(
-                Previewer::new(Some(preview_cmd.to_string()), move || {
+                Previewer::new(options.preview.map(|cmd| cmd.to_string()), move || {
                     let _ = tx.lock().send((Key::Null, Event::EvHeartBeat));
                 })
                 .wrap(preview_wrap)
                 .delimiter(self.delimiter.clone())
+                .command_env(self.command_env.clone())
+                .keep_scroll(options.preview_keep_scroll)
+                .timeout(options.preview_timeout.map(Duration::from_millis))
+                .debug(options.preview_debug)
+                .preview_rules(preview_rules)
+                .builtin(preview_builtin)
+                .shell_init(options.preview_shell_init)
                 .preview_offset(
                     options
                         .preview_window
@@ -241,6 +393,7 @@ impl Model {
 
         self.select1 = options.select1;
         self.exit0 = options.exit0;
+        self.accept_nth = options.accept_nth.filter(|&n| n > 0);
         self.sync = options.sync;
         self.no_clear_if_empty = options.no_clear_if_empty;
     }
@@ -292,36 +445,73 @@ impl Model {
         "".to_string()
     }
 
+    // e.g. `auto-hide<80` -> 80
+    fn parse_preview_auto_hide(preview_window: &str) -> Option<usize> {
+        preview_window
+            .split(':')
+            .rev()
+            .find_map(|token| token.to_lowercase().strip_prefix("auto-hide<")?.parse().ok())
+    }
+
+    /// whether the preview is shown, it is hidden by `toggle-preview` or on narrow terminals
+    fn preview_shown(&self) -> bool {
+        let too_narrow = self.preview_auto_hide.is_some_and(|min_width| {
+            self.term
+                .term_size()
+                .map(|(width, _)| width < min_width)
+                .unwrap_or(false)
+        });
+        !self.preview_hidden && !too_narrow
+    }
+
     fn act_heart_beat(&mut self, env: &mut ModelEnv) {
-        // save the processed items
-        let matcher_stopped = self
-            .matcher_control
-            .as_ref()
-            .map(|ctrl| ctrl.stopped())
-            .unwrap_or(false);
+        self.poll_drill_down(env);
 
-        if matcher_stopped {
+        // save the processed items, the runs matched so far are shown before the matcher stops
+        if let Some(ctrl) = self.matcher_control.as_mut() {
+            let matcher_stopped = ctrl.stopped();
+            let matched = ctrl.take_runs();
+            let num_matched: usize = matched.iter().map(Vec::len).sum();
             let reader_stopped = self.reader_control.as_ref().map(ReaderControl::is_done).unwrap_or(true);
-            let ctrl = self.matcher_control.take().unwrap();
-            let lock = ctrl.into_items();
-            let mut items = lock.lock();
-            let matched = std::mem::take(&mut *items);
 
             match env.clear_selection {
                 ClearStrategy::DontClear => {}
                 ClearStrategy::Clear => {
-                    self.selection.clear();
-                    env.clear_selection = ClearStrategy::DontClear;
+                    if matcher_stopped || num_matched > 0 {
+                        self.selection.clear();
+                        env.clear_selection = ClearStrategy::DontClear;
+                    }
                 }
                 ClearStrategy::ClearIfNotNull => {
-                    if (!self.no_clear_if_empty && reader_stopped) || !matched.is_empty() {
+                    if (!self.no_clear_if_empty && reader_stopped && matcher_stopped) || num_matched > 0 {
                         self.selection.clear();
                         env.clear_selection = ClearStrategy::DontClear;
                     }
                 }
             };
-            self.num_options += matched.len();
-            self.selection.append_sorted_items(matched);
+
+            if matcher_stopped || num_matched > 0 {
+                self.selection.set_match_engine(ctrl.engine());
+                self.selection.set_item_generation(self.item_pool.generation());
+                self.selection
+                    .set_highlight_query(if self.use_regex { "" } else { &env.query });
+                self.num_options += num_matched;
+                self.selection.append_sorted_items(matched);
+            }
+
+            if matcher_stopped {
+                self.matcher_control = None;
+                self.matcher_stopped = true;
+            }
+        }
+
+        if self.matcher_control.is_none() {
+            if let Some(cursor) = self.drill_cursor.take() {
+                self.selection.act_move_cursor_to(cursor);
+            }
+            if let Some(key) = self.pending_accept_or_print_query {
+                let _ = self.tx.send((key, Event::EvActAcceptOrPrintQuery));
+            }
         }
 
         let items_consumed = self.item_pool.num_not_taken() == 0;
@@ -334,7 +524,7 @@ impl Model {
         }
 
         // send next heart beat if matcher is still running or there are items not been processed.
-        if self.matcher_control.is_some() || !processed {
+        if self.matcher_control.is_some() || !processed || self.pending_drill.is_some() {
             let tx = self.tx.clone();
             let hb_timer_guard =
                 self.timer
@@ -345,6 +535,47 @@ impl Model {
         }
     }
 
+    /// copy the outputs of the selected items, or the current item if none is selected
+    fn act_copy_to_clipboard(&self) {
+        let mut items = self.selection.get_selected_items();
+        if items.is_empty() {
+            items.extend(self.selection.get_current_item());
+        }
+        if items.is_empty() {
+            return;
+        }
+
+        let text = items.iter().map(|item| item.output()).collect::<Vec<_>>().join("\n");
+        if let Err(err) = copy_to_clipboard(&text) {
+            debug!("failed to copy to clipboard: {}", err);
+        }
+    }
+
+    /// release the mouse to the terminal(e.g. for its native text selection) or capture it again
+    fn act_toggle_mouse(&mut self) {
+        let result = if self.mouse_enabled {
+            self.term.disable_mouse_support()
+        } else {
+            self.term.enable_mouse_support()
+        };
+        if result.is_ok() {
+            self.mouse_enabled = !self.mouse_enabled;
+        }
+    }
+
+    fn act_reload_theme(&mut self) {
+        self.theme = Arc::new(ColorTheme::from_color(self.color.as_deref()));
+        self.query.set_theme(self.theme.clone());
+        self.selection.set_theme(self.theme.clone());
+        self.header.set_theme(self.theme.clone());
+        self.help.set_theme(self.theme.clone());
+    }
+
+    /// the keys and their descriptions listed by the `show-help` action
+    pub fn set_key_bindings(&mut self, bindings: Vec<(String, String)>) {
+        self.help = Help::new(bindings).theme(self.theme.clone());
+    }
+
     fn act_rotate_mode(&mut self, env: &mut ModelEnv) {
         self.use_regex = !self.use_regex;
 
@@ -359,14 +590,94 @@ impl Model {
         self.restart_matcher();
     }
 
+    /// run `--drill-cmd` on the current item by the reader, the items are replaced by its output
+    /// once it finishes, see `poll_drill_down`
+    fn act_drill_down(&mut self, cmd: &str) {
+        if let Some(pending) = self.pending_drill.take() {
+            pending.control.kill();
+        }
+        if depends_on_items(cmd) && self.selection.get_current_item().is_none() {
+            return;
+        }
+
+        let cmd = self.with_inject_context(|context| inject_command(cmd, context).to_string());
+        let run_num = current_run_num();
+        self.pending_drill = Some(PendingDrill {
+            control: self.reader.run(&cmd),
+            items: Vec::new(),
+            run_num,
+        });
+        let _ = self.tx.send((Key::Null, Event::EvHeartBeat));
+    }
+
+    /// Enter the level of the items read by `--drill-cmd` if it finished and printed some lines,
+    /// otherwise the item is accepted as usual.
+    fn poll_drill_down(&mut self, env: &mut ModelEnv) {
+        let pending = match self.pending_drill.as_mut() {
+            Some(pending) => pending,
+            None => return,
+        };
+        pending.items.append(&mut pending.control.take());
+        if !pending.control.is_done() {
+            return;
+        }
+
+        let pending = self.pending_drill.take().expect("pending drill");
+        if pending.items.is_empty() {
+            restore_run(pending.run_num);
+            let _ = self.tx.send((Key::Null, Event::EvActAccept(None)));
+            return;
+        }
+
+        if let Some(ctrl) = self.reader_control.take() {
+            ctrl.kill();
+        }
+        self.items_cmd_query = None;
+        self.drill_stack.push(DrillLevel {
+            items: self.item_pool.items(),
+            query: env.query.clone(),
+            cursor: self.selection.get_current_item_idx(),
+            run_num: pending.run_num,
+        });
+        self.item_pool.set_items(Vec::new());
+        let _ = self.item_pool.append(pending.items);
+        self.enter_drill_level(env, "", 0);
+    }
+
+    fn act_parent(&mut self, env: &mut ModelEnv) {
+        if let Some(level) = self.drill_stack.pop() {
+            restore_run(level.run_num);
+            self.item_pool.set_items(level.items);
+            self.enter_drill_level(env, &level.query, level.cursor);
+        }
+    }
+
+    /// match the new items of the pool against `query`, the cursor is moved to `cursor` after
+    fn enter_drill_level(&mut self, env: &mut ModelEnv, query: &str, cursor: usize) {
+        if let Some(ctrl) = self.matcher_control.take() {
+            ctrl.kill();
+        }
+
+        self.query.set_query(query);
+        env.query = self.query.get_fz_query();
+        self.notify(Notification::Query(&env.query));
+
+        env.clear_selection = ClearStrategy::Clear;
+        self.num_options = 0;
+        self.drill_cursor = Some(cursor);
+        self.restart_matcher();
+    }
+
     fn handle_select1_or_exit0(&mut self) {
-        if !self.select1 && !self.exit0 && !self.sync {
+        if !self.select1 && !self.exit0 && !self.sync && self.accept_nth.is_none() {
             return;
         }
 
         let items_consumed = self.item_pool.num_not_taken() == 0;
         let reader_stopped = self.reader_control.as_ref().map(|c| c.is_done()).unwrap_or(true);
-        let matcher_stopped = self.matcher_control.as_ref().map(|ctrl| ctrl.stopped()).unwrap_or(true);
+        // the matcher is dropped once its matches are moved into the selection, a stopped one may
+        // still hold some
+        let matcher_stopped = self.matcher_control.is_none();
 
         let processed = reader_stopped && items_consumed && matcher_stopped;
         let num_matched = self.selection.get_num_options();
@@ -374,13 +685,19 @@ impl Model {
             if num_matched == 1 && self.select1 {
                 debug!("select-1 triggered, accept");
                 let _ = self.tx.send((Key::Null, Event::EvActAccept(None)));
+            } else if self.accept_nth.map(|nth| num_matched >= nth).unwrap_or(false) {
+                let nth = self.accept_nth.unwrap();
+                debug!("accept-nth triggered, accept the {}th match", nth);
+                self.selection.act_set_cursor(nth - 1);
+                let _ = self.tx.send((Key::Null, Event::EvActAccept(None)));
             } else if num_matched == 0 && self.exit0 {
                 debug!("exit-0 triggered, accept");
-                let _ = self.tx.send((Key::Null, Event::EvActAbort));
+                let _ = self.tx.send((Key::Null, Event::EvActAbort(None)));
             } else {
                 // no longer need need to handle select-1, exit-1, sync, etc.
                 self.select1 = false;
                 self.exit0 = false;
+                self.accept_nth = None;
                 self.sync = false;
                 let _ = self.term.restart();
             }
@@ -388,6 +705,10 @@ impl Model {
     }
 
     fn on_cmd_query_change(&mut self, env: &mut ModelEnv) {
+        if self.reuse_items_on_superset && self.filter_items_by_cmd_query(env) {
+            return;
+        }
+
         // stop matcher
         if let Some(ctrl) = self.reader_control.take() {
             ctrl.kill();
@@ -404,9 +725,56 @@ impl Model {
         self.reader_control.replace(self.reader.run(&env.cmd));
         self.restart_matcher();
         self.reader_timer = Instant::now();
+        self.items_cmd_query = Some(env.cmd_query.clone());
     }
 
-    fn on_query_change(&mut self, env: &mut ModelEnv) {
+    /// Keep the items containing the cmd query instead of running the command again, if the
+    /// command has finished and the cmd query extends the one it ran with, e.g. `rg -F {}` after
+    /// `foo` is typed on to `foob`. Returns false if the command should run.
+    fn filter_items_by_cmd_query(&mut self, env: &mut ModelEnv) -> bool {
+        let extended = match self.items_cmd_query.as_deref() {
+            // the command may print nothing for an empty query
+            Some(prev) if !prev.is_empty() => env.cmd_query.len() > prev.len() && env.cmd_query.starts_with(prev),
+            _ => false,
+        };
+        let finished = self.reader_control.as_ref().is_none_or(|ctrl| ctrl.is_done());
+        if !extended || !finished {
+            return false;
+        }
+
+        if let Some(ctrl) = self.matcher_control.take() {
+            ctrl.kill();
+        }
+        // the items are indexed as if the command ran
+        mark_new_run(&env.cmd);
+        let items = self
+            .item_pool
+            .items()
+            .into_iter()
+            .filter(|item| item.text().contains(env.cmd_query.as_str()))
+            .collect();
+        self.item_pool.set_items(items);
+
+        env.clear_selection = ClearStrategy::ClearIfNotNull;
+        self.num_options = 0;
+        self.restart_matcher();
+        self.items_cmd_query = Some(env.cmd_query.clone());
+        true
+    }
+
+    fn on_query_change(&mut self, env: &mut ModelEnv, new_query: String) {
+        let matcher = if self.use_regex {
+            &self.regex_matcher
+        } else {
+            &self.matcher
+        };
+        // e.g. a trailing space does not change the results
+        let same_query = matcher.is_same_query(&new_query, &env.query);
+        env.query = new_query;
+        if same_query {
+            return;
+        }
+
         // restart matcher
         if let Some(ctrl) = self.matcher_control.take() {
             ctrl.kill();
@@ -417,28 +785,114 @@ impl Model {
         self.restart_matcher();
     }
 
+    fn selected_items(&self) -> Vec<Arc<dyn SkimItem>> {
+        let (_, items) = self.selection.get_selected_indices_and_items();
+        items.into_iter().map(unwrap_item).collect()
+    }
+
+    fn captured_preview_lines(&self) -> Vec<String> {
+        match self.previewer.as_ref() {
+            Some(previewer) if self.capture_preview && self.preview_shown() => previewer.visible_lines(),
+            _ => Vec::new(),
+        }
+    }
+
     fn act_execute(&mut self, cmd: &str) {
-        let item = self.selection.get_current_item();
-        if depends_on_items(cmd) && item.is_none() {
-            debug!("act_execute: command refers to items and there is no item for now");
-            debug!("command to execute: [{}]", cmd);
-            return;
+        if let Some(mut command) = self.prepare_command(cmd) {
+            // the command takes over the terminal, so it is free to run skim again
+            command.env_remove(TERMINAL_IN_USE_ENV);
+            self.pause_term();
+            let _ = command.status();
+            self.restart_term();
         }
+    }
+
+    /// suspend skim to the shell(as SIGTSTP does), the terminal is restored on `fg`
+    fn act_suspend(&mut self) {
+        self.pause_term();
+        // the terminal is in raw mode, so ctrl-z is not turned into SIGTSTP by the tty
+        let _ = signal::kill(Pid::from_raw(0), Signal::SIGTSTP);
+        // resumed by SIGCONT
+        self.restart_term();
+        let _ = self.term.clear();
+    }
 
+    /// give the terminal to other programs, without the bracketed paste mode they may not know
+    fn pause_term(&self) {
+        let _ = set_bracketed_paste(false);
         let _ = self.term.pause();
-        self.act_execute_silent(cmd);
+    }
+
+    fn restart_term(&self) {
         let _ = self.term.restart();
+        let _ = set_bracketed_paste(true);
     }
 
     fn act_execute_silent(&mut self, cmd: &str) {
-        let current_index = self.selection.get_current_item_idx();
-        let current_item = self.selection.get_current_item();
-        if depends_on_items(cmd) && current_item.is_none() {
-            debug!("act_execute_silent: command refers to items and there is no item for now");
-            debug!("command to execute: [{}]", cmd);
+        if let Some(mut command) = self.prepare_command(cmd) {
+            let _ = command.status();
+        }
+    }
+
+    /// execute the command and replace the query with the first line of its output
+    fn act_execute_to_query(&mut self, cmd: &str) {
+        if let Some(output) = self.capture_command_output(cmd) {
+            self.query.set_query(output.lines().next().unwrap_or(""));
+        }
+    }
+
+    /// execute the command and append the lines of its output as new items
+    fn act_execute_append_items(&mut self, env: &mut ModelEnv, cmd: &str) {
+        let output = match self.capture_command_output(cmd) {
+            Some(output) => output,
+            None => return,
+        };
+
+        let items: Vec<Arc<dyn SkimItem>> = output
+            .lines()
+            .map(|line| Arc::new(line.to_string()) as Arc<dyn SkimItem>)
+            .collect();
+        if items.is_empty() {
             return;
         }
 
+        // the new items are matched on next heart beat
+        let _ = self.item_pool.append(items);
+        self.act_heart_beat(env);
+    }
+
+    fn capture_command_output(&self, cmd: &str) -> Option<String> {
+        let output = self.prepare_command(cmd)?.stderr(Stdio::null()).output().ok()?;
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// inject the placeholders of the command, `None` if the command refers to items while there
+    /// is no item for now
+    fn prepare_command(&self, cmd: &str) -> Option<Command> {
+        if depends_on_items(cmd) && self.selection.get_current_item().is_none() {
+            debug!("prepare_command: command refers to items and there is no item for now");
+            debug!("command to execute: [{}]", cmd);
+            return None;
+        }
+
+        let cmd = self.with_inject_context(|context| inject_command(cmd, context).to_string());
+        let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+        let mut command = Command::new(shell);
+        self.command_env.apply(&mut command);
+        command.arg("-c").arg(cmd);
+        Some(command)
+    }
+
+    /// The command of interactive mode, `replstr` is replaced by the command query as is while
+    /// the other placeholders(e.g. `{q}`, `{cq}`, `{1}`) are quoted as in the preview command.
+    fn interactive_cmd(&self) -> String {
+        self.with_inject_context(|context| self.query.get_cmd_with(|part| inject_command(part, context)))
+    }
+
+    /// call `action` with the current query and selection to inject into commands
+    fn with_inject_context<R>(&self, action: impl FnOnce(InjectContext) -> R) -> R {
+        let current_index = self.selection.get_current_item_idx();
+        let current_item = self.selection.get_current_item();
         let current_selection = current_item
             .as_ref()
             .map(|item| item.output())
@@ -449,6 +903,11 @@ impl Model {
         let (indices, selections) = self.selection.get_selected_indices_and_items();
         let tmp: Vec<Cow<str>> = selections.iter().map(|item| item.text()).collect();
         let selected_texts: Vec<&str> = tmp.iter().map(|cow| cow.as_ref()).collect();
+        let tmp_fields: Vec<Cow<str>> = current_item
+            .as_ref()
+            .map(|item| item.output_fields())
+            .unwrap_or_default();
+        let output_fields: Vec<&str> = tmp_fields.iter().map(|cow| cow.as_ref()).collect();
 
         let context = InjectContext {
             current_index,
@@ -458,11 +917,11 @@ impl Model {
             indices: &indices,
             query: &query,
             cmd_query: &cmd_query,
+            output_fields: &output_fields,
+            rank: self.selection.get_current_rank_values(&self.rank_builder),
         };
 
-        let cmd = inject_command(cmd, context).to_string();
-        let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
-        let _ = Command::new(shell).arg("-c").arg(cmd).status();
+        action(context)
     }
 
     #[allow(clippy::trivial_regex)]
@@ -479,7 +938,7 @@ impl Model {
         let matched_item = MatchedItem {
             item,
             rank: self.rank_builder.build_rank(0, 0, 0, item_len),
-            matched_range: Some(MatchRange::ByteRange(0, 0)),
+            matched_range: Some(Box::new(MatchRange::ByteRange(0, 0))),
             item_idx,
         };
 
@@ -490,7 +949,7 @@ impl Model {
 
     pub fn start(&mut self) -> Option<SkimOutput> {
         let mut env = ModelEnv {
-            cmd: self.query.get_cmd(),
+            cmd: self.interactive_cmd(),
             query: self.query.get_fz_query(),
             cmd_query: self.query.get_cmd_query(),
             in_query_mode: self.query.in_query_mode(),
@@ -498,6 +957,7 @@ impl Model {
         };
 
         self.reader_control = Some(self.reader.run(&env.cmd));
+        self.items_cmd_query = Some(env.cmd_query.clone());
 
         // In the event loop, there might need
         let mut next_event = Some((Key::Null, Event::EvHeartBeat));
@@ -506,6 +966,19 @@ impl Model {
 
             debug!("handle event: {:?}", ev);
 
+            // while the help is shown, a key scrolls or closes it instead of taking its actions
+            if self.help.is_shown() && key != Key::Null {
+                if self.help.scrolls_on(&ev) {
+                    self.help.handle(&ev);
+                } else {
+                    self.help.hide();
+                    next_event = self.consume_events_of_key(key);
+                }
+                let _ = self.do_with_widget(|root| self.term.draw(&root));
+                let _ = self.term.present();
+                continue;
+            }
+
             match ev {
                 Event::EvHeartBeat => {
                     // consume following HeartBeat event
@@ -541,11 +1014,54 @@ impl Model {
                     self.preview_hidden = !self.preview_hidden;
                 }
 
+                Event::EvActToggleMouse => {
+                    self.act_toggle_mouse();
+                }
+
+                Event::EvActCopyToClipboard => {
+                    self.act_copy_to_clipboard();
+                }
+
+                Event::EvActReloadTheme => {
+                    self.act_reload_theme();
+                }
+
                 Event::EvActRotateMode => {
                     self.act_rotate_mode(&mut env);
                 }
 
-                Event::EvActAccept(accept_key) => {
+                Event::EvActShowHelp => {
+                    self.help.handle(&ev);
+                }
+
+                // with --drill-cmd, the item accepted by a key is replaced by its children if it
+                // has any, while the ones accepted by skim(e.g. --select-1) come with `Key::Null`
+                Event::EvActAccept(None) if key != Key::Null && self.drill_cmd.is_some() => {
+                    let cmd = self.drill_cmd.clone().unwrap_or_default();
+                    self.act_drill_down(&cmd);
+                }
+
+                Event::EvActParent => {
+                    self.act_parent(&mut env);
+                }
+
+                Event::EvActAcceptOrPrintQuery
+                    if self.matcher_control.is_some() && self.pending_accept_or_print_query.is_none() =>
+                {
+                    // decide once the items read so far are matched
+                    self.pending_accept_or_print_query = Some(key);
+                    continue;
+                }
+                Event::EvActAcceptOrPrintQuery if self.selection.get_num_options() > 0 => {
+                    next_event = Some((key, Event::EvActAccept(None)));
+                    continue;
+                }
+
+                Event::EvActAccept(_) | Event::EvActAcceptOrPrintQuery => {
+                    let (accept_key, is_query_accepted) = match ev {
+                        Event::EvActAccept(ref accept_key) => (accept_key.clone(), false),
+                        _ => (None, true),
+                    };
                     if let Some(ctrl) = self.reader_control.take() {
                         ctrl.kill();
                     }
@@ -555,15 +1071,22 @@ impl Model {
 
                     return Some(SkimOutput {
                         is_abort: false,
+                        is_query_accepted,
                         final_event: Event::EvActAccept(accept_key),
                         final_key: key,
                         query: self.query.get_fz_query(),
                         cmd: self.query.get_cmd_query(),
-                        selected_items: self.selection.get_selected_indices_and_items().1,
+                        selected_items: if is_query_accepted {
+                            Vec::new()
+                        } else {
+                            self.selected_items()
+                        },
+                        num_matched: self.selection.get_num_options(),
+                        preview_lines: self.captured_preview_lines(),
                     });
                 }
 
-                Event::EvActAbort => {
+                Event::EvActAbort(_) => {
                     if let Some(ctrl) = self.reader_control.take() {
                         ctrl.kill();
                     }
@@ -573,17 +1096,20 @@ impl Model {
 
                     return Some(SkimOutput {
                         is_abort: true,
+                        is_query_accepted: false,
                         final_event: ev.clone(),
                         final_key: key,
                         query: self.query.get_fz_query(),
                         cmd: self.query.get_cmd_query(),
-                        selected_items: self.selection.get_selected_indices_and_items().1,
+                        selected_items: self.selected_items(),
+                        num_matched: self.selection.get_num_options(),
+                        preview_lines: self.captured_preview_lines(),
                     });
                 }
 
                 Event::EvActDeleteCharEOF => {
                     if env.in_query_mode && env.query.is_empty() || !env.in_query_mode && env.cmd_query.is_empty() {
-                        next_event = Some((key, Event::EvActAbort));
+                        next_event = Some((key, Event::EvActAbort(None)));
                         continue;
                     }
                 }
@@ -596,6 +1122,18 @@ impl Model {
                     self.act_execute_silent(cmd);
                 }
 
+                Event::EvActExecuteToQuery(ref cmd) => {
+                    self.act_execute_to_query(cmd);
+                }
+
+                Event::EvActExecuteAppendItems(ref cmd) => {
+                    self.act_execute_append_items(&mut env, cmd);
+                }
+
+                Event::EvActSuspend => {
+                    self.act_suspend();
+                }
+
                 Event::EvActAppendAndSelect => {
                     self.act_append_and_select(&mut env);
                 }
@@ -603,7 +1141,10 @@ impl Model {
                 Event::EvInputKey(key) => {
                     // dispatch key(normally the mouse keys) to sub-widgets
                     self.do_with_widget(|root| {
-                        let (width, height) = self.term.term_size().unwrap();
+                        let (width, height) = match self.term.term_size() {
+                            Ok(size) => size,
+                            Err(_) => return,
+                        };
                         let rect = Rectangle {
                             top: 0,
                             left: 0,
@@ -618,6 +1159,7 @@ impl Model {
                 }
 
                 Event::EvActRefreshCmd => {
+                    env.cmd = self.interactive_cmd();
                     self.on_cmd_query_change(&mut env);
                 }
 
@@ -625,6 +1167,11 @@ impl Model {
                     self.draw_preview(&env, true);
                 }
 
+                Event::EvActSetHeader(ref header) => {
+                    let header = self.with_inject_context(|context| inject_text(header, context).into_owned());
+                    self.header.set_header(&header);
+                }
+
                 _ => {}
             }
 
@@ -633,21 +1180,36 @@ impl Model {
             self.header.handle(&ev);
 
             self.query.handle(&ev);
-            env.cmd_query = self.query.get_cmd_query();
-
+            let new_cmd_query = self.query.get_cmd_query();
             let new_query = self.query.get_fz_query();
-            let new_cmd = self.query.get_cmd();
+
+            // the command is expanded only when the queries change, so that moving the cursor
+            // does not re-run a command that refers to the current item
+            let new_cmd = if new_cmd_query != env.cmd_query || new_query != env.query {
+                self.interactive_cmd()
+            } else {
+                env.cmd.clone()
+            };
+            env.cmd_query = new_cmd_query;
 
             // re-run reader & matcher if needed;
             if new_cmd != env.cmd {
                 env.cmd = new_cmd;
+                if new_query != env.query {
+                    env.query = new_query;
+                    self.notify(Notification::Query(&env.query));
+                }
                 self.on_cmd_query_change(&mut env);
             } else if new_query != env.query {
-                env.query = new_query;
-                self.on_query_change(&mut env);
+                self.on_query_change(&mut env, new_query);
+                self.notify(Notification::Query(&env.query));
             }
 
             self.selection.handle(&ev);
+            if self.selection.take_limit_hit() {
+                self.flash_selected_count();
+            }
+            self.notify_selection_changes();
 
             if let Some(previewer) = self.previewer.as_mut() {
                 previewer.handle(&ev);
@@ -655,25 +1217,108 @@ impl Model {
 
             self.draw_preview(&env, false);
 
-            let _ = self.do_with_widget(|root| self.term.draw(&root));
-            let _ = self.term.present();
+            if ev != Event::EvHeartBeat || std::mem::take(&mut self.matcher_stopped) || self.redraw_due() {
+                let _ = self.do_with_widget(|root| self.term.draw(&root));
+                let _ = self.term.present();
+                self.last_redraw = Instant::now();
+                self.notify_viewport_change();
+            }
+        }
+    }
+
+    fn notify(&mut self, notification: Notification) {
+        if let Some(notifier) = self.notifier.as_mut() {
+            notifier.notify(notification);
+        }
+    }
+
+    /// flash the selected count for a while, the heart beat afterwards ends the flash
+    fn flash_selected_count(&mut self) {
+        let duration = TimerDuration::milliseconds(FLASH_DURATION);
+        self.flash_until = duration.to_std().ok().map(|duration| Instant::now() + duration);
+        let tx = self.tx.clone();
+        let flash_timer_guard = self.timer.schedule_with_delay(duration, move || {
+            let _ = tx.send((Key::Null, Event::EvHeartBeat));
+        });
+        self.flash_timer_guard.replace(flash_timer_guard);
+    }
+
+    /// Report the focused item and the selected items if they changed.
+    fn notify_selection_changes(&mut self) {
+        if self.notifier.is_none() {
+            return;
+        }
+
+        let focus = self.selection.get_current_item().map(|item| {
+            let index = self.selection.get_current_item_idx();
+            (index, item.output().into_owned())
+        });
+        if focus != self.last_focus {
+            if let Some((index, text)) = focus.as_ref() {
+                self.notify(Notification::Focus(*index, text));
+            }
+            self.last_focus = focus;
+        }
+
+        let version = self.selection.selection_version();
+        if version != self.last_selection_version {
+            self.last_selection_version = version;
+            let selected = self
+                .selection
+                .get_selected_items()
+                .into_iter()
+                .map(|item| item.output().into_owned())
+                .collect();
+            self.notify(Notification::Selection(selected));
+        }
+    }
+
+    fn notify_viewport_change(&mut self) {
+        if let Some(listener) = self.viewport_listener.as_ref() {
+            let viewport = self.selection.viewport();
+            if !viewport.same_as(&self.last_viewport) {
+                listener.on_viewport_change(&viewport);
+                self.last_viewport = viewport;
+            }
+        }
+    }
+
+    /// Whether a heart beat should redraw the screen, so that redraws happen at most once per
+    /// `frame_interval`. Otherwise a heart beat is scheduled for the end of the interval to draw
+    /// the latest state.
+    fn redraw_due(&mut self) -> bool {
+        let elapsed = self.last_redraw.elapsed();
+        if elapsed >= self.frame_interval {
+            return true;
         }
+
+        let delay = TimerDuration::from_std(self.frame_interval - elapsed).unwrap_or_else(|_| TimerDuration::zero());
+        let tx = self.tx.clone();
+        let redraw_timer_guard = self.timer.schedule_with_delay(delay, move || {
+            let _ = tx.send((Key::Null, Event::EvHeartBeat));
+        });
+        self.redraw_timer_guard.replace(redraw_timer_guard);
+        false
     }
 
     fn draw_preview(&mut self, env: &ModelEnv, force: bool) {
-        if self.preview_hidden {
+        if !self.preview_shown() {
             return;
         }
 
         // re-draw
         let item_index = self.selection.get_current_item_idx();
+        let item_identity = self.selection.get_current_item_identity();
         let item = self.selection.get_current_item();
+        let rank = self.selection.get_current_rank_values(&self.rank_builder);
         if let Some(previewer) = self.previewer.as_mut() {
             let selections = &self.selection;
             let get_selected_items = || selections.get_selected_indices_and_items();
             previewer.on_item_change(
                 item_index,
+                item_identity,
                 item,
+                rank,
                 env.query.to_string(),
                 env.cmd_query.to_string(),
                 selections.get_num_of_selected_exclude_current(),
@@ -697,6 +1342,22 @@ impl Model {
         rx_try_iter.next()
     }
 
+    /// consume the rest of the action chain of the key, the other events that came in between
+    /// (e.g. heart beats) are kept: the first one is returned and the rest are sent again
+    fn consume_events_of_key(&self, key: Key) -> Option<(Key, Event)> {
+        let mut others = self
+            .rx
+            .try_iter()
+            .filter(|(ev_key, _)| *ev_key != key)
+            .collect::<Vec<_>>()
+            .into_iter();
+        let next = others.next();
+        for event in others {
+            let _ = self.tx.send(event);
+        }
+        next
+    }
+
     fn restart_matcher(&mut self) {
         self.matcher_timer = Instant::now();
         let query = self.query.get_fz_query();
@@ -759,6 +1420,8 @@ impl Model {
             matcher_running,
             multi_selection: self.selection.is_multi_selection(),
             selected: self.selection.get_num_selected(),
+            multi_max: self.selection.get_multi_max(),
+            flash_selected: self.flash_until.is_some_and(|until| Instant::now() < until),
             current_item_idx: self.selection.get_current_item_idx(),
             hscroll_offset: self.selection.get_hscroll_offset(),
             reading: !self.reader_control.as_ref().map(|c| c.is_done()).unwrap_or(true),
@@ -767,21 +1430,31 @@ impl Model {
             matcher_mode,
             theme: self.theme.clone(),
             inline_info: self.inline_info,
+            no_unicode: self.no_unicode,
         };
         let status_inline = status.clone();
 
+        // --no-info removes the status line from the layout, the query takes its own line
+        // the info is not inlined with the two rows of --split-prompt either
+        let query_rows = self.query.num_rows();
+        let inline_info = self.inline_info && !self.no_info && query_rows == 1;
+
+        // only the query is drawn until the screen grows large enough for the rest
+        let status_rows = if inline_info || self.no_info { 0 } else { 1 };
+        let header_rows = self.header.size_hint().1.unwrap_or(0);
+        let (screen_width, screen_height) = self.term.term_size().unwrap_or((MIN_WIDTH, usize::MAX));
+        if screen_width < MIN_WIDTH || screen_height < query_rows + status_rows + header_rows + 1 {
+            return action(Box::new(Win::new(&self.query)));
+        }
         let win_selection = Win::new(&self.selection);
         let win_query = Win::new(&self.query)
-            .basis(if self.inline_info { 0 } else { 1 })
-            .grow(0)
-            .shrink(0);
-        let win_status = Win::new(status)
-            .basis(if self.inline_info { 0 } else { 1 })
+            .basis(if inline_info { 0 } else { query_rows })
             .grow(0)
             .shrink(0);
+        let win_status = Win::new(status).basis(status_rows).grow(0).shrink(0);
         let win_header = Win::new(&self.header).grow(0).shrink(0);
         let win_query_status = HSplit::default()
-            .basis(if self.inline_info { 1 } else { 0 })
+            .basis(if inline_info { 1 } else { 0 })
             .grow(0)
             .shrink(0)
             .split(Win::new(&self.query).grow(0).shrink(0))
@@ -809,26 +1482,32 @@ impl Model {
                 .split(win_query_status),
         };
 
-        let screen: Box<dyn Widget<Event>> = if !self.preview_hidden && self.previewer.is_some() {
+        let screen: Box<dyn Widget<Event>> = if self.help.is_shown() {
+            Box::new(&self.help)
+        } else if self.preview_shown() && self.previewer.is_some() {
             let previewer = self.previewer.as_ref().unwrap();
-            let win = Win::new(previewer)
-                .basis(self.preview_size)
-                .grow(0)
-                .shrink(0)
-                .border_attr(self.theme.border());
-
-            let win_preview = match self.preview_direction {
-                Direction::Up => win.border_bottom(true),
-                Direction::Right => win.border_left(true),
-                Direction::Down => win.border_top(true),
-                Direction::Left => win.border_right(true),
-            };
-
-            match self.preview_direction {
-                Direction::Up => Box::new(VSplit::default().split(win_preview).split(win_main)),
-                Direction::Right => Box::new(HSplit::default().split(win_main).split(win_preview)),
-                Direction::Down => Box::new(VSplit::default().split(win_main).split(win_preview)),
-                Direction::Left => Box::new(HSplit::default().split(win_preview).split(win_main)),
+            if self.no_unicode {
+                self.split_preview_ascii(win_main, previewer)
+            } else {
+                let win = Win::new(previewer)
+                    .basis(self.preview_size)
+                    .grow(0)
+                    .shrink(0)
+                    .border_attr(self.theme.border());
+
+                let win_preview = match self.preview_direction {
+                    Direction::Up => win.border_bottom(true),
+                    Direction::Right => win.border_left(true),
+                    Direction::Down => win.border_top(true),
+                    Direction::Left => win.border_right(true),
+                };
+
+                match self.preview_direction {
+                    Direction::Up => Box::new(VSplit::default().split(win_preview).split(win_main)),
+                    Direction::Right => Box::new(HSplit::default().split(win_main).split(win_preview)),
+                    Direction::Down => Box::new(VSplit::default().split(win_main).split(win_preview)),
+                    Direction::Left => Box::new(HSplit::default().split(win_preview).split(win_main)),
+                }
             }
         } else {
             Box::new(win_main)
@@ -842,6 +1521,166 @@ impl Model {
 
         action(Box::new(root))
     }
+
+    /// The borders of `Win` are drawn with box-drawing characters, place an ASCII one between
+    /// the main window and the preview instead.
+    fn split_preview_ascii<'a>(
+        &'a self,
+        win_main: impl Split<Event> + 'a,
+        previewer: &'a Previewer,
+    ) -> Box<dyn Widget<Event> + 'a> {
+        let win = Win::new(previewer);
+        let border_char = match self.preview_direction {
+            Direction::Up | Direction::Down => '-',
+            Direction::Left | Direction::Right => '|',
+        };
+        let border = Win::new(AsciiBorder::new(border_char, self.theme.border()))
+            .basis(1)
+            .grow(0)
+            .shrink(0);
+
+        match self.preview_direction {
+            Direction::Up => Box::new(
+                VSplit::default()
+                    .split(
+                        VSplit::default()
+                            .basis(self.preview_size)
+                            .grow(0)
+                            .shrink(0)
+                            .split(win)
+                            .split(border),
+                    )
+                    .split(win_main),
+            ),
+            Direction::Right => Box::new(
+                HSplit::default().split(win_main).split(
+                    HSplit::default()
+                        .basis(self.preview_size)
+                        .grow(0)
+                        .shrink(0)
+                        .split(border)
+                        .split(win),
+                ),
+            ),
+            Direction::Down => Box::new(
+                VSplit::default().split(win_main).split(
+                    VSplit::default()
+                        .basis(self.preview_size)
+                        .grow(0)
+                        .shrink(0)
+                        .split(border)
+                        .split(win),
+                ),
+            ),
+            Direction::Left => Box::new(
+                HSplit::default()
+                    .split(
+                        HSplit::default()
+                            .basis(self.preview_size)
+                            .grow(0)
+                            .shrink(0)
+                            .split(win)
+                            .split(border),
+                    )
+                    .split(win_main),
+            ),
+        }
+    }
+}
+
+extern "C" fn on_sigusr1(_: i32) {
+    // only async-signal-safe calls here, the listening thread does the rest
+    let fd = SIGUSR1_PIPE.load(Ordering::SeqCst);
+    if fd >= 0 {
+        let _ = unistd::write(fd, &[0]);
+    }
+}
+
+/// send `reload-theme` to `tx` on SIGUSR1, replacing the previous listener(of another run), until
+/// the returned listener is dropped
+fn listen_sigusr1(tx: EventSender) -> Option<SigUsr1Listener> {
+    // not inherited by the commands, or they would keep the pipe open after skim closes it
+    let (read_fd, write_fd): (RawFd, RawFd) = match unistd::pipe2(OFlag::O_CLOEXEC) {
+        Ok(fds) => fds,
+        Err(err) => {
+            debug!("failed to listen to SIGUSR1: {}", err);
+            return None;
+        }
+    };
+
+    // closing the write end stops the previous listening thread
+    let prev_fd = SIGUSR1_PIPE.swap(write_fd, Ordering::SeqCst);
+    if prev_fd >= 0 {
+        let _ = unistd::close(prev_fd);
+    }
+
+    let action = SigAction::new(SigHandler::Handler(on_sigusr1), SaFlags::SA_RESTART, SigSet::empty());
+    let prev_action = match unsafe { signal::sigaction(Signal::SIGUSR1, &action) } {
+        Ok(prev_action) => prev_action,
+        Err(err) => {
+            debug!("failed to listen to SIGUSR1: {}", err);
+            SIGUSR1_PIPE.store(-1, Ordering::SeqCst);
+            let _ = unistd::close(write_fd);
+            let _ = unistd::close(read_fd);
+            return None;
+        }
+    };
+
+    let thread = thread::spawn(move || {
+        let mut buf = [0u8; 1];
+        while let Ok(1) = unistd::read(read_fd, &mut buf) {
+            if tx.send((Key::Null, Event::EvActReloadTheme)).is_err() {
+                break;
+            }
+        }
+        let _ = unistd::close(read_fd);
+    });
+
+    Some(SigUsr1Listener {
+        prev_action,
+        write_fd,
+        thread: Some(thread),
+    })
+}
+
+/// the listener of `--reload-theme-on-sigusr1`, which restores the previous handler of SIGUSR1 and
+/// stops its thread once dropped, i.e. the run is over
+struct SigUsr1Listener {
+    prev_action: SigAction,
+    write_fd: RawFd,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for SigUsr1Listener {
+    fn drop(&mut self) {
+        // unless replaced by another run, which restores the handler itself
+        if SIGUSR1_PIPE
+            .compare_exchange(self.write_fd, -1, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return;
+        }
+        let _ = unsafe { signal::sigaction(Signal::SIGUSR1, &self.prev_action) };
+        let _ = unistd::close(self.write_fd);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// the items of a level of `--drill-cmd`, restored by the `parent` action
+struct DrillLevel {
+    items: Vec<Arc<dyn SkimItem>>,
+    query: String,
+    cursor: usize,
+    run_num: u32,
+}
+
+/// the output of `--drill-cmd` read so far, and the run of the current items
+struct PendingDrill {
+    control: ReaderControl,
+    items: Vec<Arc<dyn SkimItem>>,
+    run_num: u32,
 }
 
 struct ModelEnv {
@@ -860,6 +1699,8 @@ struct Status {
     matcher_running: bool,
     multi_selection: bool,
     selected: usize,
+    multi_max: Option<usize>,
+    flash_selected: bool,
     current_item_idx: usize,
     hscroll_offset: i64,
     reading: bool,
@@ -868,6 +1709,7 @@ struct Status {
     matcher_mode: String,
     theme: Arc<ColorTheme>,
     inline_info: bool,
+    no_unicode: bool,
 }
 
 #[allow(unused_assignments)]
@@ -899,6 +1741,8 @@ impl Draw for Status {
         let mut col = 0;
         let spinner_set: &[char] = if self.inline_info {
             &SPINNERS_INLINE
+        } else if self.no_unicode {
+            &SPINNERS_ASCII
         } else {
             &SPINNERS_UNICODE
         };
@@ -937,9 +1781,22 @@ impl Draw for Status {
             )?;
         }
 
-        // selected number
-        if self.multi_selection && self.selected > 0 {
-            col += canvas.print_with_attr(0, col, format!(" [{}]", self.selected).as_ref(), info_attr_bold)?;
+        // selected number, and the limit of --multi-max
+        if self.multi_selection && (self.selected > 0 || self.flash_selected) {
+            let selected = match self.multi_max {
+                Some(max) => format!("[{}/{}]", self.selected, max),
+                None => format!("[{}]", self.selected),
+            };
+            let attr = if self.flash_selected {
+                Attr {
+                    effect: Effect::BOLD | Effect::REVERSE,
+                    ..info_attr_bold
+                }
+            } else {
+                info_attr_bold
+            };
+            col += canvas.print_with_attr(0, col, " ", info_attr)?;
+            col += canvas.print_with_attr(0, col, &selected, attr)?;
         }
 
         // item cursor
@@ -949,7 +1806,9 @@ impl Draw for Status {
             self.hscroll_offset,
             if self.matcher_running { '.' } else { ' ' }
         );
-        canvas.print_with_attr(0, screen_width - line_num_str.len(), &line_num_str, info_attr_bold)?;
+        if let Some(line_num_col) = screen_width.checked_sub(line_num_str.len()) {
+            canvas.print_with_attr(0, line_num_col, &line_num_str, info_attr_bold)?;
+        }
 
         Ok(())
     }
@@ -957,6 +1816,32 @@ impl Draw for Status {
 
 impl Widget<Event> for Status {}
 
+/// The border of the preview window that fills the area with an ASCII character
+struct AsciiBorder {
+    ch: char,
+    attr: Attr,
+}
+
+impl AsciiBorder {
+    fn new(ch: char, attr: Attr) -> Self {
+        Self { ch, attr }
+    }
+}
+
+impl Draw for AsciiBorder {
+    fn draw(&self, canvas: &mut dyn Canvas) -> DrawResult<()> {
+        let (width, height) = canvas.size()?;
+        for row in 0..height {
+            for col in 0..width {
+                canvas.put_char_with_attr(row, col, self.ch, self.attr)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Widget<Event> for AsciiBorder {}
+
 #[derive(PartialEq, Eq, Clone, Debug, Copy)]
 enum Direction {
     Up,
@@ -971,3 +1856,54 @@ enum ClearStrategy {
     Clear,
     ClearIfNotNull,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tuikit::screen::Screen;
+
+    fn status(no_unicode: bool, time_since_read: Duration) -> Status {
+        Status {
+            total: 10,
+            matched: 10,
+            processed: 10,
+            matcher_running: false,
+            multi_selection: false,
+            selected: 0,
+            multi_max: None,
+            flash_selected: false,
+            current_item_idx: 0,
+            hscroll_offset: 0,
+            reading: true,
+            time_since_read,
+            time_since_match: Duration::from_millis(0),
+            matcher_mode: String::new(),
+            theme: Arc::new(ColorTheme::from_color(None)),
+            inline_info: false,
+            no_unicode,
+        }
+    }
+
+    fn spinner(no_unicode: bool, time_since_read: Duration) -> char {
+        let mut screen = Screen::new(40, 1);
+        status(no_unicode, time_since_read).draw(&mut screen).unwrap();
+        screen.iter_cell().next().unwrap().2.ch
+    }
+
+    #[test]
+    fn test_no_unicode_spinner() {
+        for step in 1..12 {
+            let time_since_read = Duration::from_millis((step * SPINNER_DURATION) as u64);
+            assert!(SPINNERS_ASCII.contains(&spinner(true, time_since_read)));
+            assert!(SPINNERS_UNICODE.contains(&spinner(false, time_since_read)));
+        }
+    }
+
+    #[test]
+    fn test_ascii_border() {
+        let mut screen = Screen::new(3, 2);
+        AsciiBorder::new('|', Attr::default()).draw(&mut screen).unwrap();
+        assert!(screen.iter_cell().all(|(_, _, cell)| cell.ch == '|'));
+        assert_eq!(6, screen.iter_cell().count());
+    }
+}
@@ -0,0 +1,147 @@
+//! Notify the state changes of skim as JSON lines, so that other programs(e.g. editor plugins)
+//! could follow the focused item, the selection and the query, or as `SkimEvent`s to the
+//! programs that embed skim.
+use std::os::unix::io::RawFd;
+
+use crossbeam::channel::{Receiver, Sender};
+
+use crate::SkimOutput;
+
+/// The state changes of skim, sent to `SkimOptions::event_sender`
+pub enum SkimEvent {
+    /// The query is changed
+    Query(String),
+    /// The item of the index(in the matched items) and the text is focused
+    Focus(usize, String),
+    /// The texts of the selected items after the selection is changed
+    Selection(Vec<String>),
+    /// Skim quits with the output of `Skim::run_with`, sent by `Skim::run_async` only
+    Finished(Option<SkimOutput>),
+}
+
+pub type SkimEventSender = Sender<SkimEvent>;
+pub type SkimEventReceiver = Receiver<SkimEvent>;
+
+pub enum Notification<'a> {
+    Query(&'a str),
+    Focus(usize, &'a str),
+    Selection(Vec<String>),
+}
+
+impl Notification<'_> {
+    pub fn to_json(&self) -> String {
+        match self {
+            Notification::Query(query) => format!(r#"{{"event":"query","query":{}}}"#, json_string(query)),
+            Notification::Focus(index, text) => {
+                format!(r#"{{"event":"focus","index":{},"text":{}}}"#, index, json_string(text))
+            }
+            Notification::Selection(texts) => {
+                let texts: Vec<String> = texts.iter().map(|text| json_string(text)).collect();
+                format!(r#"{{"event":"selection","selected":[{}]}}"#, texts.join(","))
+            }
+        }
+    }
+
+    pub fn to_event(&self) -> SkimEvent {
+        match self {
+            Notification::Query(query) => SkimEvent::Query(query.to_string()),
+            Notification::Focus(index, text) => SkimEvent::Focus(*index, text.to_string()),
+            Notification::Selection(texts) => SkimEvent::Selection(texts.clone()),
+        }
+    }
+}
+
+pub struct Notifier {
+    fd: Option<RawFd>,
+    events: Option<SkimEventSender>,
+}
+
+impl Notifier {
+    /// Write the notifications to the file descriptor `fd`, which should be opened by the caller,
+    /// and/or send them to `events`. None if there is nowhere to notify.
+    pub fn new(fd: Option<RawFd>, events: Option<SkimEventSender>) -> Option<Self> {
+        if fd.is_none() && events.is_none() {
+            return None;
+        }
+        Some(Self { fd, events })
+    }
+
+    pub fn notify(&mut self, notification: Notification) {
+        if let Some(fd) = self.fd {
+            let line = notification.to_json() + "\n";
+            if let Err(err) = nix::unistd::write(fd, line.as_bytes()) {
+                debug!("notify: failed to write {}: {}", line.trim_end(), err);
+            }
+        }
+
+        // the receiver might be gone, skim goes on without it
+        if let Some(events) = self.events.as_ref() {
+            let _ = events.send(notification.to_event());
+        }
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len() + 2);
+    ret.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => ret.push(ch),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(r#""abc""#, json_string("abc"));
+        assert_eq!(r#""a\"b\\c\n\u0001""#, json_string("a\"b\\c\n\u{1}"));
+    }
+
+    #[test]
+    fn test_notification_to_json() {
+        assert_eq!(r#"{"event":"query","query":"ab"}"#, Notification::Query("ab").to_json());
+        assert_eq!(
+            r#"{"event":"focus","index":3,"text":"x y"}"#,
+            Notification::Focus(3, "x y").to_json()
+        );
+        assert_eq!(
+            r#"{"event":"selection","selected":["a","b"]}"#,
+            Notification::Selection(vec!["a".to_string(), "b".to_string()]).to_json()
+        );
+        assert_eq!(
+            r#"{"event":"selection","selected":[]}"#,
+            Notification::Selection(vec![]).to_json()
+        );
+    }
+
+    #[test]
+    fn test_notify_events() {
+        assert!(Notifier::new(None, None).is_none());
+
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let mut notifier = Notifier::new(None, Some(tx)).unwrap();
+        notifier.notify(Notification::Query("ab"));
+        notifier.notify(Notification::Focus(2, "x"));
+        notifier.notify(Notification::Selection(vec!["x".to_string()]));
+        assert!(matches!(rx.try_recv(), Ok(SkimEvent::Query(query)) if query == "ab"));
+        assert!(matches!(rx.try_recv(), Ok(SkimEvent::Focus(2, text)) if text == "x"));
+        assert!(matches!(rx.try_recv(), Ok(SkimEvent::Selection(texts)) if texts == ["x"]));
+        assert!(rx.try_recv().is_err());
+
+        // skim goes on after the receiver is dropped
+        drop(rx);
+        notifier.notify(Notification::Query("abc"));
+    }
+}
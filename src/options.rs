@@ -1,33 +1,50 @@
-use std::rc::Rc;
-
 use derive_builder::Builder;
 
+#[cfg(feature = "config")]
+use crate::config::SkimConfig;
 use crate::helper::item_reader::SkimItemReader;
+use crate::input::Keymap;
 use crate::reader::CommandCollector;
-use crate::{CaseMatching, FuzzyAlgorithm, MatchEngineFactory, Selector};
-use std::cell::RefCell;
+use crate::selection::check_line_numbers;
+use crate::util::default_command;
+use crate::{
+    AbortHandle, CaseMatching, FuzzyAlgorithm, MatchEngineFactory, Selector, SkimEventSender, ViewportListener,
+};
+use std::sync::{Arc, Mutex};
 
 #[derive(Builder)]
 #[builder(build_fn(name = "final_build"))]
 #[builder(default)]
 pub struct SkimOptions<'a> {
     pub bind: Vec<&'a str>,
+    pub keymap: Keymap,
     pub multi: bool,
+    pub multi_max: Option<usize>,
     pub prompt: Option<&'a str>,
     pub cmd_prompt: Option<&'a str>,
     pub expect: Option<String>,
+    pub expect_hint: bool,
     pub tac: bool,
     pub nosort: bool,
+    pub follow: bool,
     pub tiebreak: Option<String>,
     pub exact: bool,
+    pub literal: bool,
+    pub path_mode: bool,
+    pub nth_concat: bool,
+    pub match_display: bool,
     pub cmd: Option<&'a str>,
+    pub exclude: Option<&'a str>,
     pub interactive: bool,
+    pub split_prompt: bool,
+    pub filepath_word: bool,
     pub query: Option<&'a str>,
     pub cmd_query: Option<&'a str>,
     pub regex: bool,
     pub delimiter: Option<&'a str>,
     pub replstr: Option<&'a str>,
     pub color: Option<&'a str>,
+    pub reload_theme_on_sigusr1: bool,
     pub margin: Option<&'a str>,
     pub no_height: bool,
     pub no_clear: bool,
@@ -38,47 +55,83 @@ pub struct SkimOptions<'a> {
     pub preview_window: Option<&'a str>,
     pub reverse: bool,
     pub tabstop: Option<&'a str>,
+    pub max_fps: usize,
     pub no_hscroll: bool,
     pub no_mouse: bool,
     pub inline_info: bool,
+    pub no_info: bool,
+    pub capture_preview: bool,
+    pub notify_fd: Option<i32>,
+    pub icons: Option<&'a str>,
+    pub scrollbar: Option<&'a str>,
+    pub highlight_all: bool,
+    pub compact_match: bool,
+    pub no_unicode: bool,
+    pub preview_keep_scroll: bool,
+    pub preview_timeout: Option<u64>,
+    pub preview_debug: bool,
+    pub preview_rules: Option<&'a str>,
+    pub preview_builtin: Option<&'a str>,
+    pub preview_shell_init: Option<&'a str>,
     pub header: Option<&'a str>,
     pub header_lines: usize,
     pub layout: &'a str,
     pub algorithm: FuzzyAlgorithm,
     pub case: CaseMatching,
-    pub engine_factory: Option<Rc<dyn MatchEngineFactory>>,
+    pub engine_factory: Option<Arc<dyn MatchEngineFactory>>,
     pub query_history: &'a [String],
     pub cmd_history: &'a [String],
-    pub cmd_collector: Rc<RefCell<dyn CommandCollector>>,
+    pub cmd_collector: Arc<Mutex<dyn CommandCollector>>,
     pub keep_right: bool,
     pub skip_to_pattern: &'a str,
     pub select1: bool,
     pub exit0: bool,
+    pub accept_nth: Option<usize>,
     pub sync: bool,
-    pub selector: Option<Rc<dyn Selector>>,
+    pub selector: Option<Arc<dyn Selector>>,
+    pub viewport_listener: Option<Arc<dyn ViewportListener>>,
+    pub abort_handle: Option<AbortHandle>,
+    pub event_sender: Option<SkimEventSender>,
     pub no_clear_if_empty: bool,
+    pub line_numbers: Option<&'a str>,
+    pub cmd_cwd: Option<&'a str>,
+    pub cmd_env: Vec<&'a str>,
+    pub drill_cmd: Option<&'a str>,
+    pub reuse_items_on_superset: bool,
 }
 
 impl<'a> Default for SkimOptions<'a> {
     fn default() -> Self {
         Self {
             bind: vec![],
+            keymap: Keymap::default(),
             multi: false,
+            multi_max: None,
             prompt: Some("> "),
             cmd_prompt: Some("c> "),
             expect: None,
+            expect_hint: false,
             tac: false,
             nosort: false,
+            follow: false,
             tiebreak: None,
             exact: false,
+            literal: false,
+            path_mode: false,
+            nth_concat: false,
+            match_display: false,
             cmd: None,
+            exclude: None,
             interactive: false,
+            split_prompt: false,
+            filepath_word: false,
             query: None,
             cmd_query: None,
             regex: false,
             delimiter: None,
             replstr: Some("{}"),
             color: None,
+            reload_theme_on_sigusr1: false,
             margin: Some("0,0,0,0"),
             no_height: false,
             no_clear: false,
@@ -89,9 +142,24 @@ impl<'a> Default for SkimOptions<'a> {
             preview_window: Some("right:50%"),
             reverse: false,
             tabstop: None,
+            max_fps: 30,
             no_hscroll: false,
             no_mouse: false,
             inline_info: false,
+            no_info: false,
+            capture_preview: false,
+            notify_fd: None,
+            icons: None,
+            scrollbar: None,
+            highlight_all: false,
+            compact_match: false,
+            no_unicode: false,
+            preview_keep_scroll: false,
+            preview_timeout: None,
+            preview_debug: false,
+            preview_rules: None,
+            preview_builtin: None,
+            preview_shell_init: None,
             header: None,
             header_lines: 0,
             layout: "",
@@ -100,18 +168,70 @@ impl<'a> Default for SkimOptions<'a> {
             engine_factory: None,
             query_history: &[],
             cmd_history: &[],
-            cmd_collector: Rc::new(RefCell::new(SkimItemReader::new(Default::default()))),
+            cmd_collector: Arc::new(Mutex::new(SkimItemReader::new(Default::default()))),
             keep_right: false,
             skip_to_pattern: "",
             select1: false,
             exit0: false,
+            accept_nth: None,
             sync: false,
             selector: None,
+            viewport_listener: None,
+            abort_handle: None,
+            event_sender: None,
             no_clear_if_empty: false,
+            line_numbers: None,
+            cmd_cwd: None,
+            cmd_env: vec![],
+            drill_cmd: None,
+            reuse_items_on_superset: false,
         }
     }
 }
 
+impl<'a> SkimOptions<'a> {
+    /// The command to fetch the items when neither the input nor `cmd` is given:
+    /// `$SKIM_DEFAULT_COMMAND` if set, otherwise `find .` that skips the `exclude` patterns.
+    pub fn default_command(&self) -> String {
+        default_command(self.exclude)
+    }
+
+    /// Apply the options of a config file, which replace the ones set so far. The bindings are
+    /// put before the ones set so far, which take precedence when they bind the same keys.
+    #[cfg(feature = "config")]
+    pub fn merge_config(mut self, config: &'a SkimConfig) -> Self {
+        let mut bind: Vec<&'a str> = config.bind.iter().map(String::as_str).collect();
+        bind.append(&mut self.bind);
+        self.bind = bind;
+
+        fn merge<T>(option: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *option = value;
+            }
+        }
+
+        merge(&mut self.color, config.color.as_deref().map(Some));
+        merge(&mut self.delimiter, config.delimiter.as_deref().map(Some));
+        merge(&mut self.exact, config.exact);
+        merge(&mut self.header, config.header.as_deref().map(Some));
+        merge(&mut self.height, config.height.as_deref().map(Some));
+        merge(&mut self.inline_info, config.inline_info);
+        merge(&mut self.layout, config.layout.as_deref());
+        merge(&mut self.margin, config.margin.as_deref().map(Some));
+        merge(&mut self.multi, config.multi);
+        merge(&mut self.no_hscroll, config.no_hscroll);
+        merge(&mut self.no_mouse, config.no_mouse);
+        merge(&mut self.nosort, config.no_sort);
+        merge(&mut self.preview, config.preview.as_deref().map(Some));
+        merge(&mut self.preview_window, config.preview_window.as_deref().map(Some));
+        merge(&mut self.prompt, config.prompt.as_deref().map(Some));
+        merge(&mut self.regex, config.regex);
+        merge(&mut self.tac, config.tac);
+        merge(&mut self.tiebreak, config.tiebreak.clone().map(Some));
+        self
+    }
+}
+
 impl<'a> SkimOptionsBuilder<'a> {
     pub fn build(&mut self) -> Result<SkimOptions<'a>, SkimOptionsBuilderError> {
         if let Some(true) = self.no_height {
@@ -122,6 +242,11 @@ impl<'a> SkimOptionsBuilder<'a> {
             self.layout = Some("reverse");
         }
 
+        if let Some(Some(line_numbers)) = self.line_numbers {
+            check_line_numbers(line_numbers)
+                .map_err(|err| SkimOptionsBuilderError::ValidationError(format!("invalid line numbers: {}", err)))?;
+        }
+
         self.final_build()
     }
 }
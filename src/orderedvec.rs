@@ -1,6 +1,11 @@
 // ordered container
 // Normally, user will only care about the first several options. So we only keep several of them
-// in order. Other items are kept unordered and are sorted on demand.
+// in order. Other items are kept in sorted runs and are merged on demand.
+//
+// The items are always sorted in ascending order, the flags only decide the order of the items
+// that compare equal (the order they are appended, i.e. the input order):
+// - `tac`: the input is reversed, later items go first. With `nosort`, all the items are reversed.
+// - `reverse_index`: later items go first (the `-index` tiebreak), cancels out with `tac`.
 
 use defer_drop::DeferDrop;
 use rayon::prelude::ParallelSliceMut;
@@ -8,7 +13,6 @@ use std::cell::{Ref, RefCell};
 use std::cmp::Ordering;
 
 const ORDERED_SIZE: usize = 300;
-const MAX_MOVEMENT: usize = 100;
 
 pub struct OrderedVec<T: Send + Ord + 'static> {
     // sorted vectors for merge, reverse ordered, last one is the smallest one
@@ -17,6 +21,7 @@ pub struct OrderedVec<T: Send + Ord + 'static> {
     sorted: RefCell<DeferDrop<Vec<T>>>,
     tac: bool,
     nosort: bool,
+    reverse_index: bool,
 }
 
 impl<T: Send + Ord + 'static> OrderedVec<T> {
@@ -26,6 +31,7 @@ impl<T: Send + Ord + 'static> OrderedVec<T> {
             sorted: RefCell::new(DeferDrop::new(Vec::with_capacity(ORDERED_SIZE))),
             tac: false,
             nosort: false,
+            reverse_index: false,
         }
     }
 
@@ -39,65 +45,85 @@ impl<T: Send + Ord + 'static> OrderedVec<T> {
         self
     }
 
+    pub fn reverse_index(&mut self, reverse_index: bool) -> &mut Self {
+        self.reverse_index = reverse_index;
+        self
+    }
+
+    /// whether the items appended later go first among the equal ones
+    #[inline]
+    fn later_first(&self) -> bool {
+        self.tac != self.reverse_index
+    }
+
+    /// Sort the items and append them as one run, see `append_sorted_runs`
+    #[allow(dead_code)]
     pub fn append(&mut self, mut items: Vec<T>) {
         trace!("orderedvec append: new vec size: {}", items.len());
+        if !self.nosort {
+            items.par_sort();
+        }
+        self.append_sorted_runs(vec![items]);
+    }
+
+    /// Append runs that are already sorted in ascending order, e.g. the chunks sorted by each
+    /// matcher thread. The runs are merged lazily on `get` instead of being sorted again.
+    ///
+    /// Items that compare equal keep the order in which they were appended (reversed if
+    /// `later_first`), both within a run and across runs.
+    pub fn append_sorted_runs(&mut self, runs: Vec<Vec<T>>) {
         if self.nosort {
-            self.sorted.borrow_mut().append(&mut items);
+            let mut sorted = self.sorted.borrow_mut();
+            for mut run in runs {
+                sorted.append(&mut run);
+            }
             return;
         }
 
-        self.sort_vector(&mut items, false);
         let mut sorted = self.sorted.borrow_mut();
+        let mut vectors = self.sub_vectors.borrow_mut();
 
-        let mut items_smaller = Vec::new();
-        if !sorted.is_empty() {
-            // move the ones <= sorted to sorted
-            while items_smaller.len() < MAX_MOVEMENT
-                && !items.is_empty()
-                && self.compare_item(items.last().unwrap(), sorted.last().unwrap()) == Ordering::Less
-            {
-                items_smaller.push(items.pop().unwrap());
+        for mut run in runs {
+            if run.is_empty() {
+                continue;
             }
-        }
-
-        if !items.is_empty() {
-            self.sub_vectors.borrow_mut().push(items);
-        }
 
-        let too_many_moved = items_smaller.len() >= ORDERED_SIZE;
-        trace!("append_ordered: num_moved: {}", items_smaller.len());
+            // sub vectors are reverse ordered, the last one is the next to be merged
+            run.reverse();
+            if self.later_first() {
+                reverse_equal_groups(&mut run);
+            }
 
-        sorted.append(&mut items_smaller);
-        if too_many_moved {
-            // means the current sorted vector contains item that's large
-            // so we'll move the sorted vector to partially sorted candidates.
-            self.sort_vector(&mut sorted, false);
-            let old_vec = self.sorted.replace(DeferDrop::new(Vec::new()));
-            self.sub_vectors.borrow_mut().push(DeferDrop::into_inner(old_vec));
-        } else {
-            self.sort_vector(&mut sorted, true);
-        }
+            // the merged items should stay a prefix of the final order, if the new run contains
+            // items that should go before them, put them back and merge again on demand.
+            let should_unmerge = match (sorted.last(), run.last()) {
+                (Some(merged), Some(head)) => self.precedes(head, merged),
+                _ => false,
+            };
 
-        trace!(
-            "orderedvec done append: sub_vector size: {}",
-            self.sub_vectors.borrow().len()
-        );
-    }
+            if should_unmerge {
+                let mut merged = std::mem::take(&mut **sorted);
+                merged.reverse();
+                if self.later_first() {
+                    vectors.push(merged);
+                } else {
+                    vectors.insert(0, merged);
+                }
+            }
 
-    fn sort_vector(&self, vec: &mut Vec<T>, asc: bool) {
-        let asc = asc ^ self.tac;
-        vec.par_sort();
-        if !asc {
-            vec.reverse();
+            vectors.push(run);
         }
+
+        trace!("orderedvec done append: sub_vector size: {}", vectors.len());
     }
 
+    /// whether item `a` should be placed before item `b` when `a` was appended after `b`
     #[inline]
-    fn compare_item(&self, a: &T, b: &T) -> Ordering {
-        if !self.tac {
-            a.cmp(b)
-        } else {
-            b.cmp(a)
+    fn precedes(&self, a: &T, b: &T) -> bool {
+        match a.cmp(b) {
+            Ordering::Less => true,
+            Ordering::Equal => self.later_first(),
+            Ordering::Greater => false,
         }
     }
 
@@ -110,12 +136,15 @@ impl<T: Send + Ord + 'static> OrderedVec<T> {
         }
 
         while index >= sorted.len() {
+            // k-way merge, the vectors are kept in the order they are appended
             let o_min_index = vectors
                 .iter()
-                .map(|v| v.last())
                 .enumerate()
-                .filter(|(_idx, item)| item.is_some())
-                .min_by(|(_, a), (_, b)| self.compare_item(a.unwrap(), b.unwrap()))
+                .filter_map(|(idx, v)| v.last().map(|item| (idx, item)))
+                .fold(None, |min: Option<(usize, &T)>, (idx, item)| match min {
+                    Some((_, min_item)) if !self.precedes(item, min_item) => min,
+                    _ => Some((idx, item)),
+                })
                 .map(|(idx, _)| idx);
             if o_min_index.is_none() {
                 break;
@@ -171,6 +200,46 @@ impl<T: Send + Ord + 'static> OrderedVec<T> {
             index: 0,
         }
     }
+
+    /// visit all the items in no particular order, without merging them
+    pub fn for_each_unordered(&self, mut f: impl FnMut(&T)) {
+        self.sorted.borrow().iter().for_each(&mut f);
+        for vector in self.sub_vectors.borrow().iter() {
+            vector.iter().for_each(&mut f);
+        }
+    }
+
+    /// an empty vector with the same flags
+    pub fn empty_like(&self) -> Self {
+        OrderedVec {
+            tac: self.tac,
+            nosort: self.nosort,
+            reverse_index: self.reverse_index,
+            ..OrderedVec::new()
+        }
+    }
+}
+
+impl<T: Send + Ord + Clone + 'static> Clone for OrderedVec<T> {
+    fn clone(&self) -> Self {
+        OrderedVec {
+            sub_vectors: RefCell::new(DeferDrop::new((**self.sub_vectors.borrow()).clone())),
+            sorted: RefCell::new(DeferDrop::new((**self.sorted.borrow()).clone())),
+            tac: self.tac,
+            nosort: self.nosort,
+            reverse_index: self.reverse_index,
+        }
+    }
+}
+
+/// reverse the order of the consecutive items that compare equal
+fn reverse_equal_groups<T: Ord>(items: &mut [T]) {
+    let mut start = 0;
+    while start < items.len() {
+        let end = start + items[start..].iter().take_while(|item| **item == items[start]).count();
+        items[start..end].reverse();
+        start = end;
+    }
 }
 
 struct OrderedVecIter<'a, T: Send + Ord + 'static> {
@@ -221,17 +290,18 @@ mod tests {
         let mut ordered_vec = OrderedVec::new();
         ordered_vec.tac(true);
 
+        // the items are still sorted in ascending order
         ordered_vec.append(a);
-        assert_eq!(*ordered_vec.get(0).unwrap(), 7);
+        assert_eq!(*ordered_vec.get(0).unwrap(), 1);
 
         ordered_vec.append(b);
-        assert_eq!(*ordered_vec.get(1).unwrap(), 8);
-        assert_eq!(*ordered_vec.get(2).unwrap(), 7);
+        assert_eq!(*ordered_vec.get(1).unwrap(), 3);
+        assert_eq!(*ordered_vec.get(2).unwrap(), 4);
         assert_eq!(*ordered_vec.get(3).unwrap(), 5);
 
         ordered_vec.append(c);
         for (idx, item) in ordered_vec.iter().enumerate() {
-            assert_eq!(10 - idx, *item)
+            assert_eq!(idx + 1, *item)
         }
     }
 
@@ -279,4 +349,75 @@ mod tests {
             assert_eq!(*a, *b);
         }
     }
+
+    // items with the same rank, only compared by the first element
+    #[derive(Debug)]
+    struct Tie(i32, usize);
+
+    impl PartialEq for Tie {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Tie {}
+
+    impl PartialOrd for Tie {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tie {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    #[test]
+    fn test_sorted_runs() {
+        let mut ordered_vec = OrderedVec::new();
+        ordered_vec.append_sorted_runs(vec![vec![1, 4, 7], vec![2, 5, 8], vec![]]);
+        assert_eq!(*ordered_vec.get(0).unwrap(), 1);
+        assert_eq!(*ordered_vec.get(1).unwrap(), 2);
+
+        // smaller than the merged items
+        ordered_vec.append_sorted_runs(vec![vec![0, 3, 6, 9]]);
+        for (idx, item) in ordered_vec.iter().enumerate() {
+            assert_eq!(idx, *item)
+        }
+        assert_eq!(ordered_vec.len(), 10);
+    }
+
+    #[test]
+    fn test_keep_order_of_ties() {
+        let mut ordered_vec = OrderedVec::new();
+        ordered_vec.append_sorted_runs(vec![vec![Tie(1, 0), Tie(2, 1)], vec![Tie(1, 2), Tie(2, 3)]]);
+        assert_eq!(ordered_vec.get(0).unwrap().1, 0);
+        ordered_vec.append_sorted_runs(vec![vec![Tie(1, 4)]]);
+
+        let order: Vec<usize> = ordered_vec.iter().map(|tie| tie.1).collect();
+        assert_eq!(order, vec![0, 2, 4, 1, 3]);
+    }
+
+    fn tie_order(tac: bool, reverse_index: bool) -> Vec<usize> {
+        let mut ordered_vec = OrderedVec::new();
+        ordered_vec.tac(tac).reverse_index(reverse_index);
+        ordered_vec.append_sorted_runs(vec![vec![Tie(1, 0), Tie(1, 1), Tie(2, 2)], vec![Tie(1, 3), Tie(2, 4)]]);
+        // merge some of the items before appending more
+        let _ = ordered_vec.get(0);
+        ordered_vec.append_sorted_runs(vec![vec![Tie(0, 5), Tie(1, 6)]]);
+        ordered_vec.append(vec![Tie(2, 7), Tie(1, 8)]);
+        ordered_vec.iter().map(|tie| tie.1).collect()
+    }
+
+    #[test]
+    fn test_keep_order_of_ties_tac() {
+        assert_eq!(tie_order(false, false), vec![5, 0, 1, 3, 6, 8, 2, 4, 7]);
+        // later items go first among the equal ones, the best item is still the first one
+        assert_eq!(tie_order(true, false), vec![5, 8, 6, 3, 1, 0, 7, 4, 2]);
+        assert_eq!(tie_order(false, true), vec![5, 8, 6, 3, 1, 0, 7, 4, 2]);
+        // `-index` with `--tac` prefers the earlier items
+        assert_eq!(tie_order(true, true), vec![5, 0, 1, 3, 6, 8, 2, 4, 7]);
+    }
 }
@@ -6,12 +6,17 @@ use tuikit::key::Key;
 pub struct SkimOutput {
     /// The final event that makes skim accept/quit.
     /// Was designed to determine if skim quit or accept.
-    /// Typically there are only two options: `Event::EvActAbort` | `Event::EvActAccept`
+    /// Typically there are only two options: `Event::EvActAbort` | `Event::EvActAccept`, the
+    /// exit code of the abort is the one given by `abort(N)`, and `final_key` tells the keys apart
     pub final_event: Event,
 
     /// quick pass for judging if skim aborts.
     pub is_abort: bool,
 
+    /// The query is accepted as the result by `accept-or-print-query` as no item matched it,
+    /// `selected_items` is empty then.
+    pub is_query_accepted: bool,
+
     /// The final key that makes skim accept/quit.
     /// Note that it might be Key::Null if it is triggered by skim.
     pub final_key: Key,
@@ -24,4 +29,11 @@ pub struct SkimOutput {
 
     /// The selected items.
     pub selected_items: Vec<Arc<dyn SkimItem>>,
+
+    /// The number of items that matched the final query.
+    pub num_matched: usize,
+
+    /// The lines shown in the preview window when skim exits, without ANSI attributes.
+    /// Only captured if `capture_preview` is set in the options, empty otherwise.
+    pub preview_lines: Vec<String>,
 }
@@ -1,15 +1,21 @@
 pub use crate::ansi::AnsiString;
+pub use crate::engine::util::{byte_to_char_index, char_boundary_range, contains_upper, regex_match};
 pub use crate::engine::{factory::*, fuzzy::FuzzyAlgorithm};
-pub use crate::event::Event;
+pub use crate::event::{Action, Event};
 pub use crate::helper::item_reader::{SkimItemReader, SkimItemReaderOption};
 pub use crate::helper::selector::DefaultSkimSelector;
-pub use crate::options::{SkimOptions, SkimOptionsBuilder};
+#[cfg(feature = "tokio")]
+pub use crate::helper::tokio_source::{event_stream, from_stream, from_tokio_receiver};
+pub use crate::input::Keymap;
+pub use crate::item::{RankBuilder, RankCriteria};
+pub use crate::options::{SkimOptions, SkimOptionsBuilder, SkimOptionsBuilderError};
 pub use crate::output::SkimOutput;
 pub use crate::*;
 pub use crossbeam::channel::{bounded, unbounded, Receiver, Sender};
+pub use fuzzy_matcher::{clangd::ClangdMatcher, skim::SkimMatcherV2, FuzzyMatcher};
 pub use std::borrow::Cow;
 pub use std::cell::RefCell;
 pub use std::rc::Rc;
 pub use std::sync::atomic::{AtomicUsize, Ordering};
-pub use std::sync::Arc;
+pub use std::sync::{Arc, Mutex};
 pub use tuikit::event::Key;
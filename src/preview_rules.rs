@@ -0,0 +1,167 @@
+//! Choose the preview command by the type of the file an item names, e.g. `ls` for directories
+//! and `xxd` for binaries, configured by `--preview-rules 'dir:ls -la {},bin:xxd {}'`.
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+// the number of the leading bytes checked for NUL, same as git
+const BINARY_CHECK_LEN: u64 = 8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Dir,
+    Bin,
+    File,
+}
+
+impl FileKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dir" => Some(FileKind::Dir),
+            "bin" => Some(FileKind::Bin),
+            "file" => Some(FileKind::File),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct PreviewRules {
+    rules: Vec<(FileKind, String)>,
+}
+
+impl PreviewRules {
+    /// Parse the comma separated `KIND:COMMAND` rules, the kinds are `dir`, `bin` and `file`. A
+    /// comma is only a separator if a kind follows, so that the commands could contain commas.
+    pub fn parse(spec: &str) -> Self {
+        let mut rules: Vec<(FileKind, String)> = vec![];
+        let mut rest = spec;
+        while !rest.is_empty() {
+            let (rule, next) = match next_separator(rest) {
+                Some(idx) => (&rest[..idx], &rest[idx + 1..]),
+                None => (rest, ""),
+            };
+            rest = next;
+
+            match rule.split_once(':') {
+                Some((kind, cmd)) if !cmd.is_empty() => match FileKind::from_name(kind.trim()) {
+                    // the first rule of a kind wins
+                    Some(kind) if !rules.iter().any(|(k, _)| *k == kind) => rules.push((kind, cmd.to_string())),
+                    Some(_) => {}
+                    None => warn!("preview rules: unknown kind in {:?}", rule),
+                },
+                _ => warn!("preview rules: invalid rule {:?}", rule),
+            }
+        }
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The commands of all the kinds
+    pub fn commands(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|(_, cmd)| cmd.as_str())
+    }
+
+    /// The same rules with the commands mapped by `f`, e.g. with the fields of an item injected
+    pub fn map_commands(&self, f: impl Fn(&str) -> String) -> Self {
+        let rules = self.rules.iter().map(|(kind, cmd)| (*kind, f(cmd))).collect();
+        Self { rules }
+    }
+
+    /// The command for the file named by `text`, relative to `cwd` if given. None if `text` is
+    /// not an existing file or no rule is given for its kind.
+    pub fn command_for(&self, text: &str, cwd: Option<&str>) -> Option<&str> {
+        if self.rules.is_empty() || text.is_empty() {
+            return None;
+        }
+
+        let path = match cwd {
+            Some(cwd) => Path::new(cwd).join(text),
+            None => Path::new(text).to_path_buf(),
+        };
+        let metadata = fs::metadata(&path).ok()?;
+        let kind = if metadata.is_dir() {
+            FileKind::Dir
+        } else if self.rule(FileKind::Bin).is_some() && is_binary(&path) {
+            FileKind::Bin
+        } else {
+            FileKind::File
+        };
+        self.rule(kind)
+    }
+
+    fn rule(&self, kind: FileKind) -> Option<&str> {
+        self.rules.iter().find(|(k, _)| *k == kind).map(|(_, cmd)| cmd.as_str())
+    }
+}
+
+/// the index of the next comma that is followed by `KIND:`
+fn next_separator(spec: &str) -> Option<usize> {
+    spec.match_indices(',').map(|(idx, _)| idx).find(|&idx| {
+        spec[idx + 1..]
+            .split_once(':')
+            .is_some_and(|(kind, _)| FileKind::from_name(kind.trim()).is_some())
+    })
+}
+
+/// whether the leading bytes of the file contain NUL
+pub fn is_binary(path: &Path) -> bool {
+    let mut buf = vec![];
+    match File::open(path).and_then(|file| file.take(BINARY_CHECK_LEN).read_to_end(&mut buf)) {
+        Ok(_) => buf.contains(&0),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_parse() {
+        let rules = PreviewRules::parse("dir:ls -la {},bin:xxd {}");
+        assert_eq!(rules.rule(FileKind::Dir), Some("ls -la {}"));
+        assert_eq!(rules.rule(FileKind::Bin), Some("xxd {}"));
+        assert_eq!(rules.rule(FileKind::File), None);
+
+        // commas in the commands
+        let rules = PreviewRules::parse("file:bat --style=numbers,changes {}, dir: tree -L 1,2 {}");
+        assert_eq!(rules.rule(FileKind::File), Some("bat --style=numbers,changes {}"));
+        assert_eq!(rules.rule(FileKind::Dir), Some(" tree -L 1,2 {}"));
+
+        let rules = PreviewRules::parse("link:ls {},dir:,file:cat {},file:bat {}");
+        assert_eq!(rules.rules, vec![(FileKind::File, "cat {}".to_string())]);
+        assert!(PreviewRules::parse("").is_empty());
+    }
+
+    #[test]
+    fn test_command_for() {
+        let dir = env::temp_dir().join(format!("skim-preview-rules-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("text"), "hello\n").unwrap();
+        fs::write(dir.join("binary"), b"\x7fELF\x00\x01").unwrap();
+        let cwd = dir.to_str();
+
+        let rules = PreviewRules::parse("dir:ls {},bin:xxd {},file:cat {}");
+        assert_eq!(rules.command_for(".", cwd), Some("ls {}"));
+        assert_eq!(rules.command_for("text", cwd), Some("cat {}"));
+        assert_eq!(rules.command_for("binary", cwd), Some("xxd {}"));
+        assert_eq!(rules.command_for("missing", cwd), None);
+        assert_eq!(rules.command_for("", cwd), None);
+
+        // binaries are files without a rule of their own
+        let rules = PreviewRules::parse("file:cat {}");
+        assert_eq!(rules.command_for("binary", cwd), Some("cat {}"));
+        assert_eq!(rules.command_for(".", cwd), None);
+
+        let rules = PreviewRules::parse("dir:ls {},file:cat {}").map_commands(|cmd| cmd.replace("{}", "a"));
+        assert_eq!(rules.commands().collect::<Vec<_>>(), vec!["ls a", "cat a"]);
+        assert_eq!(rules.command_for(".", cwd), Some("ls a"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,26 +1,43 @@
 use std::borrow::Cow;
 use std::cmp::{max, min};
+use std::collections::VecDeque;
 use std::env;
-use std::process::{Command, Stdio};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::sync::Arc;
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use derive_builder::Builder;
 use nix::libc;
 use regex::Regex;
 use tuikit::prelude::{Event as TermEvent, *};
 
-use crate::ansi::{ANSIParser, AnsiString};
+use crate::ansi::{ANSIParser, ANSIScreen, AnsiString};
 use crate::event::{Event, EventHandler, UpdateScreen};
+use crate::highlight::Highlighter;
+use crate::item::RankValues;
+use crate::preview_rules::{is_binary, PreviewRules};
 use crate::spinlock::SpinLock;
-use crate::util::{atoi, clear_canvas, depends_on_items, inject_command, InjectContext};
-use crate::{ItemPreview, PreviewContext, PreviewPosition, SkimItem};
+use crate::util::{
+    atoi, clear_canvas, depends_on_items, depends_on_rank, eval_arithmetic, inject_command, inject_text,
+    unbalanced_quote, CommandEnv, InjectContext, DEFAULT_DELIMITER,
+};
+use crate::{ItemIdentity, ItemPreview, PreviewContext, PreviewPosition, SkimItem};
 
 const TAB_STOP: usize = 8;
-const DELIMITER_STR: &str = r"[\t\n ]+";
+const SCROLL_MEMORY_SIZE: usize = 1000;
+/// the interval of publishing the partial output of a running preview command
+const PREVIEW_HEARTBEAT: Duration = Duration::from_millis(50);
+const PREVIEW_POLL_INTERVAL: Duration = Duration::from_millis(5);
+// the lines read from a file by `--preview-builtin=file`, the rest is cut off
+const PREVIEW_FILE_MAX_LINES: usize = 10000;
 
 pub struct Previewer {
     tx_preview: Sender<PreviewEvent>,
@@ -33,6 +50,8 @@ pub struct Previewer {
     wrap: bool,
 
     prev_item: Option<Arc<dyn SkimItem>>,
+    prev_item_identity: Option<ItemIdentity>,
+    prev_rank: Option<RankValues>,
     prev_query: Option<String>,
     prev_cmd_query: Option<String>,
     prev_num_selected: usize,
@@ -40,6 +59,17 @@ pub struct Previewer {
     preview_cmd: Option<String>,
     preview_offset: String, // e.g. +SCROLL-OFFSET
     delimiter: Regex,
+    command_env: CommandEnv,
+    // the content of `--preview-shell-init`, read once and run before every preview command
+    shell_init: Option<Arc<String>>,
+    timeout: Option<Duration>,
+    // show the injected command above its output
+    debug: bool,
+    // the commands by the file kinds of the items, over `preview_cmd`
+    preview_rules: PreviewRules,
+    // read the files instead of running the commands, over `preview_rules`
+    builtin: Option<BuiltinPreview>,
+    scroll_memory: Option<ScrollMemory>,
     thread_previewer: Option<JoinHandle<()>>,
 }
 
@@ -61,17 +91,29 @@ impl Previewer {
         let hscroll_offset_clone = hscroll_offset.clone();
         let vscroll_offset_clone = vscroll_offset.clone();
         let thread_previewer = thread::spawn(move || {
-            run(rx_preview, move |lines, pos| {
-                let width = width_clone.load(Ordering::SeqCst);
-                let height = height_clone.load(Ordering::SeqCst);
-
-                let hscroll = pos.h_scroll.calc_fixed_size(lines.len(), 0);
-                let hoffset = pos.h_offset.calc_fixed_size(width, 0);
-                let vscroll = pos.v_scroll.calc_fixed_size(usize::MAX, 0);
-                let voffset = pos.v_offset.calc_fixed_size(height, 0);
-
-                hscroll_offset_clone.store(max(1, max(hscroll, hoffset) - hoffset), Ordering::SeqCst);
-                vscroll_offset_clone.store(max(1, max(vscroll, voffset) - voffset), Ordering::SeqCst);
+            run(rx_preview, move |update| {
+                let (lines, pos) = match update {
+                    PreviewUpdate::Replace(lines, pos) => (lines, pos),
+                    PreviewUpdate::Append(lines) => {
+                        content_clone.lock().extend(lines);
+                        callback();
+                        return;
+                    }
+                };
+                // the position is only given for the first lines of a preview, so that the
+                // lines streamed later won't reset the scroll of the user
+                if let Some(pos) = pos {
+                    let width = width_clone.load(Ordering::SeqCst);
+                    let height = height_clone.load(Ordering::SeqCst);
+
+                    let hscroll = pos.h_scroll.calc_fixed_size(lines.len(), 0);
+                    let hoffset = pos.h_offset.calc_fixed_size(width, 0);
+                    let vscroll = pos.v_scroll.calc_fixed_size(usize::MAX, 0);
+                    let voffset = pos.v_offset.calc_fixed_size(height, 0);
+
+                    hscroll_offset_clone.store(max(1, max(hscroll, hoffset) - hoffset), Ordering::SeqCst);
+                    vscroll_offset_clone.store(max(1, max(vscroll, voffset) - voffset), Ordering::SeqCst);
+                }
                 *content_clone.lock() = lines;
 
                 callback();
@@ -89,13 +131,22 @@ impl Previewer {
             wrap: false,
 
             prev_item: None,
+            prev_item_identity: None,
+            prev_rank: None,
             prev_query: None,
             prev_cmd_query: None,
             prev_num_selected: 0,
 
             preview_cmd,
             preview_offset: "".to_string(),
-            delimiter: Regex::new(DELIMITER_STR).unwrap(),
+            delimiter: DEFAULT_DELIMITER.clone(),
+            command_env: CommandEnv::default(),
+            shell_init: None,
+            timeout: None,
+            debug: false,
+            preview_rules: PreviewRules::default(),
+            builtin: None,
+            scroll_memory: None,
             thread_previewer: Some(thread_previewer),
         }
     }
@@ -110,17 +161,85 @@ impl Previewer {
         self
     }
 
+    pub fn command_env(mut self, command_env: CommandEnv) -> Self {
+        self.command_env = command_env;
+        self
+    }
+
+    /// run the shell script in `path` before the preview commands, e.g. to define the functions
+    /// they call. The file is read once, an unreadable file is ignored.
+    pub fn shell_init(mut self, path: Option<&str>) -> Self {
+        self.shell_init = path.and_then(|path| match fs::read_to_string(path) {
+            Ok(script) => Some(Arc::new(script)),
+            Err(err) => {
+                warn!("failed to read the preview shell init file {}: {}", path, err);
+                None
+            }
+        });
+        self
+    }
+
+    /// kill the preview command if it runs longer than `timeout`
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// show the command with the placeholders injected above its output
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// preview the items that name files by the command for their kinds, see `PreviewRules`
+    pub fn preview_rules(mut self, preview_rules: PreviewRules) -> Self {
+        self.preview_rules = preview_rules;
+        self
+    }
+
+    pub fn builtin(mut self, builtin: Option<BuiltinPreview>) -> Self {
+        self.builtin = builtin;
+        self
+    }
+
+    /// remember the scroll offsets of the recently previewed items and restore them when the
+    /// items are previewed again
+    pub fn keep_scroll(mut self, keep_scroll: bool) -> Self {
+        self.scroll_memory = if keep_scroll {
+            Some(ScrollMemory::default())
+        } else {
+            None
+        };
+        self
+    }
+
     // e.g. +SCROLL-OFFSET
     pub fn preview_offset(mut self, offset: String) -> Self {
         self.preview_offset = offset;
         self
     }
 
+    /// The lines of the preview content that are shown in the preview window, without ANSI
+    /// attributes.
+    pub fn visible_lines(&self) -> Vec<String> {
+        let height = self.height.load(Ordering::Relaxed);
+        let vscroll_offset = self.vscroll_offset.load(Ordering::SeqCst);
+        self.content_lines
+            .lock()
+            .iter()
+            .skip(max(1, vscroll_offset) - 1)
+            .take(height)
+            .map(|line| line.stripped().to_string())
+            .collect()
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn on_item_change(
         &mut self,
         new_item_index: usize,
+        new_item_identity: Option<ItemIdentity>,
         new_item: impl Into<Option<Arc<dyn SkimItem>>>,
+        new_rank: Option<RankValues>,
         new_query: impl Into<Option<String>>,
         new_cmd_query: impl Into<Option<String>>,
         num_selected: usize,
@@ -154,12 +273,36 @@ impl Previewer {
         };
 
         let selected_items_changed = self.prev_num_selected != num_selected;
-
-        if !force && !item_changed && !query_changed && !cmd_query_changed && !selected_items_changed {
+        // the same item is matched again after the query changes, only `{score}` and alike differ
+        let rank_changed = self.prev_rank != new_rank
+            && self
+                .preview_cmd
+                .iter()
+                .map(String::as_str)
+                .chain(self.preview_rules.commands())
+                .any(depends_on_rank);
+
+        if !force && !item_changed && !query_changed && !cmd_query_changed && !selected_items_changed && !rank_changed {
             return;
         }
 
+        let restored_offsets = match self.scroll_memory.as_mut() {
+            Some(memory) if item_changed => {
+                if let Some(prev_identity) = self.prev_item_identity.take() {
+                    let offsets = (
+                        self.hscroll_offset.load(Ordering::SeqCst),
+                        self.vscroll_offset.load(Ordering::SeqCst),
+                    );
+                    memory.remember(prev_identity, offsets);
+                }
+                new_item_identity.as_ref().and_then(|identity| memory.take(identity))
+            }
+            _ => None,
+        };
+
         self.prev_item = new_item.clone();
+        self.prev_rank = new_rank;
+        self.prev_item_identity = new_item_identity;
         self.prev_query = new_query;
         self.prev_cmd_query = new_cmd_query;
         self.prev_num_selected = num_selected;
@@ -177,6 +320,12 @@ impl Previewer {
         let (indices, selections) = get_selected_items();
         let tmp: Vec<Cow<str>> = selections.iter().map(|item| item.text()).collect();
         let selected_texts: Vec<&str> = tmp.iter().map(|cow| cow.as_ref()).collect();
+        let tmp_fields: Vec<Cow<str>> = self
+            .prev_item
+            .as_ref()
+            .map(|item| item.output_fields())
+            .unwrap_or_default();
+        let output_fields: Vec<&str> = tmp_fields.iter().map(|cow| cow.as_ref()).collect();
 
         let columns = self.width.load(Ordering::Relaxed);
         let lines = self.height.load(Ordering::Relaxed);
@@ -189,6 +338,8 @@ impl Previewer {
             indices: &indices,
             query,
             cmd_query,
+            output_fields: &output_fields,
+            rank: self.prev_rank,
         };
 
         let preview_context = PreviewContext {
@@ -202,40 +353,89 @@ impl Previewer {
             selections: &selected_texts,
         };
 
-        let preview_event = match new_item {
-            Some(item) => match (item.preview(preview_context), PreviewPosition::default()) {
+        // without an item, only the global preview command could be run
+        let item_preview = match new_item {
+            Some(item) => Some(item.preview(preview_context)),
+            None if self.preview_cmd.is_some() => Some(ItemPreview::Global),
+            None => None,
+        };
+
+        let preview_event = match item_preview {
+            Some(item_preview) => match (item_preview, PreviewPosition::default()) {
                 (ItemPreview::Text(text), pos) => PreviewEvent::PreviewPlainText(text, pos),
                 (ItemPreview::AnsiText(text), pos) => PreviewEvent::PreviewAnsiText(text, pos),
                 (ItemPreview::TextWithPos(text, pos), _) => PreviewEvent::PreviewPlainText(text, pos),
                 (ItemPreview::AnsiWithPos(text, pos), _) => PreviewEvent::PreviewAnsiText(text, pos),
                 (ItemPreview::Command(cmd), pos) | (ItemPreview::CommandWithPos(cmd, pos), _) => {
-                    if depends_on_items(&cmd) && self.prev_item.is_none() {
-                        debug!("the command for preview refers to items and currently there is no item");
-                        debug!("command to execute: [{}]", cmd);
-                        PreviewEvent::PreviewPlainText("no item matched".to_string(), Default::default())
-                    } else {
-                        let cmd = inject_command(&cmd, inject_context).to_string();
-                        let preview_command = PreviewCommand { cmd, columns, lines };
-                        PreviewEvent::PreviewCommand(preview_command, pos)
-                    }
+                    let cmd = inject_command(&cmd, inject_context).to_string();
+                    let preview_command = PreviewCommand {
+                        cmd,
+                        columns,
+                        lines,
+                        command_env: self.command_env.clone(),
+                        shell_init: self.shell_init.clone(),
+                        timeout: self.timeout,
+                        debug: self.debug,
+                    };
+                    PreviewEvent::PreviewCommand(preview_command, pos)
                 }
                 (ItemPreview::Global, _) => {
-                    let cmd = self.preview_cmd.clone().expect("previewer: not provided");
-                    if depends_on_items(&cmd) && self.prev_item.is_none() {
-                        debug!("the command for preview refers to items and currently there is no item");
-                        debug!("command to execute: [{}]", cmd);
-                        PreviewEvent::PreviewPlainText("no item matched".to_string(), Default::default())
-                    } else {
-                        let cmd = inject_command(&cmd, inject_context).to_string();
-                        let pos = self.eval_scroll_offset(inject_context);
-                        let preview_command = PreviewCommand { cmd, columns, lines };
-                        PreviewEvent::PreviewCommand(preview_command, pos)
+                    let fallback = {
+                        let cmd = self.preview_cmd.clone().unwrap_or_default();
+                        if self.prev_item.is_none() && !cmd.is_empty() && self.depends_on_items(&cmd) {
+                            debug!("the command for preview refers to items and currently there is no item");
+                            debug!("command to execute: [{}], offset: [{}]", cmd, self.preview_offset);
+                            PreviewEvent::PreviewPlainText("no item matched".to_string(), Default::default())
+                        } else {
+                            let pos = self.eval_scroll_offset(inject_context);
+                            let preview_command = PreviewCommand {
+                                cmd: inject_command(&cmd, inject_context).to_string(),
+                                columns,
+                                lines,
+                                command_env: self.command_env.clone(),
+                                shell_init: self.shell_init.clone(),
+                                timeout: self.timeout,
+                                debug: self.debug,
+                            };
+                            match self.prev_item.as_ref() {
+                                // the kind of the file is told by the preview thread, the file
+                                // system may be slow
+                                Some(item) if !self.preview_rules.is_empty() => {
+                                    let rules = self
+                                        .preview_rules
+                                        .map_commands(|cmd| inject_command(cmd, inject_context).to_string());
+                                    PreviewEvent::PreviewByRules(rules, item.text().into_owned(), preview_command, pos)
+                                }
+                                // no `--preview` to fall back to
+                                _ if cmd.is_empty() => {
+                                    PreviewEvent::PreviewPlainText("".to_string(), Default::default())
+                                }
+                                _ => PreviewEvent::PreviewCommand(preview_command, pos),
+                            }
+                        }
+                    };
+                    match self.builtin_file(inject_context) {
+                        Some(path) => {
+                            let pos = self.eval_scroll_offset(inject_context);
+                            PreviewEvent::PreviewFile(path, pos, Box::new(fallback))
+                        }
+                        None => fallback,
                     }
                 }
             },
             None => PreviewEvent::Noop,
         };
 
+        let preview_event = match restored_offsets {
+            Some((hscroll, vscroll)) => preview_event.with_position(PreviewPosition {
+                h_scroll: Size::Fixed(hscroll),
+                h_offset: Size::Fixed(0),
+                v_scroll: Size::Fixed(vscroll),
+                v_offset: Size::Fixed(0),
+            }),
+            None => preview_event,
+        };
+
         let _ = self.tx_preview.send(preview_event);
     }
 
@@ -265,6 +465,28 @@ impl Previewer {
         self.wrap = !self.wrap;
     }
 
+    /// The file to preview by `--preview-builtin=file`, named by the template of the fields. The
+    /// preview thread falls back to the commands unless it is an existing file that is not binary.
+    fn builtin_file(&self, context: InjectContext) -> Option<PathBuf> {
+        let builtin = self.builtin.as_ref()?;
+        self.prev_item.as_ref()?;
+        let text = inject_text(&builtin.path, context);
+        if text.is_empty() {
+            return None;
+        }
+
+        match self.command_env.cwd.as_ref() {
+            Some(cwd) => Some(Path::new(cwd).join(text.as_ref())),
+            None => Some(PathBuf::from(text.as_ref())),
+        }
+    }
+
+    /// whether the global preview command needs an item, either the command itself or the scroll
+    /// offset of `--preview-window` (e.g. `+{2}`) refers to the fields of the item
+    fn depends_on_items(&self, cmd: &str) -> bool {
+        depends_on_items(cmd) || depends_on_items(&self.preview_offset)
+    }
+
     fn eval_scroll_offset(&self, context: InjectContext) -> PreviewPosition {
         // currently, only h_scroll and h_offset is supported
         // The syntax follows fzf's
@@ -285,26 +507,23 @@ impl Previewer {
             return Default::default();
         }
 
-        let nums: Vec<&str> = offset_expr.split('-').collect();
-        let v_scroll = if nums.is_empty() {
-            Size::Default
-        } else {
-            Size::Fixed(atoi::<usize>(nums[0]).unwrap_or(0))
-        };
-
-        let v_offset = if nums.len() >= 2 {
-            let expr = nums[1];
-            if expr.starts_with('/') {
-                let num = atoi::<usize>(expr).unwrap_or(0);
-                Size::Percent(if num == 0 { 0 } else { 100 / num })
-            } else {
-                let num = atoi::<usize>(expr).unwrap_or(0);
-                Size::Fixed(num)
+        // SCROLL could be an arithmetic expression of the fields, e.g. `+{2}-5`, `+{2}/2`
+        // the trailing `-/INTEGER` is a fraction of the preview window height
+        let (scroll_expr, v_offset) = match offset_expr.rfind("-/") {
+            Some(idx) => {
+                let num = atoi::<usize>(&offset_expr[idx + 2..]).unwrap_or(0);
+                (
+                    &offset_expr[..idx],
+                    Size::Percent(100usize.checked_div(num).unwrap_or(0)),
+                )
             }
-        } else {
-            Size::Default
+            None => (&offset_expr[..], Size::Default),
         };
 
+        let v_scroll = eval_arithmetic(scroll_expr)
+            .map(|num| Size::Fixed(max(num, 0) as usize))
+            .unwrap_or(Size::Default);
+
         PreviewPosition {
             h_scroll: Default::default(),
             h_offset: Default::default(),
@@ -396,40 +615,118 @@ impl Widget<Event> for Previewer {
     }
 }
 
-#[derive(Debug, Ord, PartialOrd, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct PreviewCommand {
     pub cmd: String,
     pub lines: usize,
     pub columns: usize,
+    pub command_env: CommandEnv,
+    pub shell_init: Option<Arc<String>>,
+    pub timeout: Option<Duration>,
+    pub debug: bool,
 }
 
 #[derive(Debug)]
 enum PreviewEvent {
     PreviewCommand(PreviewCommand, PreviewPosition),
+    // the rules with the item injected, the item text, and the command if no rule applies
+    PreviewByRules(PreviewRules, String, PreviewCommand, PreviewPosition),
+    // the file of --preview-builtin, and the preview if it is not a text file
+    PreviewFile(PathBuf, PreviewPosition, Box<PreviewEvent>),
     PreviewPlainText(String, PreviewPosition),
     PreviewAnsiText(String, PreviewPosition),
     Noop,
     Abort,
 }
 
+impl PreviewEvent {
+    fn with_position(self, pos: PreviewPosition) -> Self {
+        match self {
+            PreviewEvent::PreviewCommand(cmd, _) => PreviewEvent::PreviewCommand(cmd, pos),
+            PreviewEvent::PreviewByRules(rules, text, cmd, _) => PreviewEvent::PreviewByRules(rules, text, cmd, pos),
+            PreviewEvent::PreviewFile(path, _, fallback) => {
+                PreviewEvent::PreviewFile(path, pos, Box::new(fallback.with_position(pos)))
+            }
+            PreviewEvent::PreviewPlainText(text, _) => PreviewEvent::PreviewPlainText(text, pos),
+            PreviewEvent::PreviewAnsiText(text, _) => PreviewEvent::PreviewAnsiText(text, pos),
+            event => event,
+        }
+    }
+}
+
+/// The (hscroll, vscroll) offsets of the recently previewed items, by their identities, so that
+/// an item keeps its offsets wherever it is moved by the queries.
+/// The least recently previewed item is forgotten first.
+#[derive(Default)]
+struct ScrollMemory {
+    offsets: VecDeque<(ItemIdentity, (usize, usize))>,
+}
+
+impl ScrollMemory {
+    fn remember(&mut self, identity: ItemIdentity, offsets: (usize, usize)) {
+        let _ = self.take(&identity);
+        if self.offsets.len() >= SCROLL_MEMORY_SIZE {
+            self.offsets.pop_front();
+        }
+        self.offsets.push_back((identity, offsets));
+    }
+
+    fn take(&mut self, identity: &ItemIdentity) -> Option<(usize, usize)> {
+        let pos = self.offsets.iter().position(|(other, _)| other == identity)?;
+        self.offsets.remove(pos).map(|(_, offsets)| offsets)
+    }
+}
+
 struct PreviewThread {
-    pid: u32,
+    // the process of the command, None for the builtin previews
+    pid: Option<u32>,
     thread: thread::JoinHandle<()>,
+    // set once the command exits, or by `kill`, after which nothing is published
     stopped: Arc<AtomicBool>,
 }
 
 impl PreviewThread {
     fn kill(self) {
-        if !self.stopped.load(Ordering::Relaxed) {
-            unsafe { libc::kill(self.pid as i32, libc::SIGKILL) };
+        if !self.stopped.swap(true, Ordering::SeqCst) {
+            if let Some(pid) = self.pid {
+                kill_process_group(pid);
+            }
         }
         self.thread.join().expect("Failed to join Preview process");
     }
 }
 
+/// The lines published by the preview thread
+enum PreviewUpdate {
+    /// the new content of the preview, positioned only when it is the first one of a preview
+    Replace(Vec<AnsiString<'static>>, Option<PreviewPosition>),
+    /// the lines streamed since the last update, appended to the content
+    Append(Vec<AnsiString<'static>>),
+}
+
+/// `--preview-builtin`, currently only `file[:TEMPLATE]`: the file named by the template of the
+/// fields(`{}` by default) is read and highlighted by skim, instead of running a command for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuiltinPreview {
+    path: String,
+}
+
+impl BuiltinPreview {
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (kind, path) = spec.split_once(':').unwrap_or((spec, "{}"));
+        match kind {
+            "file" if !path.is_empty() => Some(Self { path: path.to_string() }),
+            _ => {
+                warn!("unsupported builtin preview: {}", spec);
+                None
+            }
+        }
+    }
+}
+
 fn run<C>(rx_preview: Receiver<PreviewEvent>, on_return: C)
 where
-    C: Fn(Vec<AnsiString<'static>>, PreviewPosition) + Send + Sync + 'static,
+    C: Fn(PreviewUpdate) + Send + Sync + 'static,
 {
     let callback = Arc::new(on_return);
     let mut preview_thread: Option<PreviewThread> = None;
@@ -452,27 +749,68 @@ where
             }
         }
 
+        // the file system may be slow, so the files are checked here rather than by the UI thread
+        let event = match event {
+            PreviewEvent::PreviewFile(path, _, fallback) if !is_text_file(&path) => *fallback,
+            event => event,
+        };
+        let event = match event {
+            PreviewEvent::PreviewByRules(rules, text, mut preview_cmd, pos) => {
+                if let Some(cmd) = rules.command_for(&text, preview_cmd.command_env.cwd.as_deref()) {
+                    preview_cmd.cmd = cmd.to_string();
+                }
+                if preview_cmd.cmd.is_empty() {
+                    // no rule for the kind of the item, and no `--preview` to fall back to
+                    PreviewEvent::PreviewPlainText("".to_string(), Default::default())
+                } else {
+                    PreviewEvent::PreviewCommand(preview_cmd, pos)
+                }
+            }
+            event => event,
+        };
+
         match event {
             PreviewEvent::PreviewCommand(preview_cmd, pos) => {
                 let cmd = &preview_cmd.cmd;
+                let timeout = preview_cmd.timeout;
                 if cmd.is_empty() {
                     continue;
                 }
 
+                // e.g. an item with quotes injected into the quotes, the shell would only complain
+                // about the unexpected EOF
+                if let Some(quote) = unbalanced_quote(cmd) {
+                    warn!("preview: unbalanced {} in the command: {}", quote, cmd);
+                    let title = format!("unbalanced {} in the preview command", quote);
+                    callback(PreviewUpdate::Replace(command_error(&title, cmd), Some(pos)));
+                    continue;
+                }
+                let header = if preview_cmd.debug { command_lines(cmd) } else { vec![] };
+
+                // the init script runs in the same shell, so the functions it defines are visible
+                let script = match preview_cmd.shell_init.as_ref() {
+                    Some(init) => Cow::Owned(format!("{}\n{}", init, cmd)),
+                    None => Cow::Borrowed(cmd),
+                };
+
                 let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
-                let spawned = Command::new(shell)
+                let mut command = Command::new(shell);
+                preview_cmd.command_env.apply(&mut command);
+                // in its own process group, so that the children of the command are killed too
+                command.process_group(0);
+                let spawned = command
                     .env("LINES", preview_cmd.lines.to_string())
                     .env("COLUMNS", preview_cmd.columns.to_string())
                     .arg("-c")
-                    .arg(&cmd)
+                    .arg(script.as_ref())
                     .stdout(Stdio::piped())
                     .stderr(Stdio::piped())
                     .spawn();
 
                 match spawned {
                     Err(err) => {
-                        let astdout = AnsiString::parse(format!("Failed to spawn: {} / {}", cmd, err).as_str());
-                        callback(vec![astdout], pos);
+                        let title = format!("failed to spawn the preview command: {}", err);
+                        callback(PreviewUpdate::Replace(command_error(&title, cmd), Some(pos)));
                         preview_thread = None;
                     }
                     Ok(spawned) => {
@@ -481,56 +819,286 @@ where
                         let stopped_clone = stopped.clone();
                         let callback_clone = callback.clone();
                         let thread = thread::spawn(move || {
-                            wait(spawned, move |lines| {
-                                stopped_clone.store(true, Ordering::SeqCst);
-                                callback_clone(lines, pos);
+                            let mut positioned = false;
+                            wait(spawned, timeout, &stopped_clone, |lines, finished| {
+                                let first = !positioned;
+                                positioned = true;
+                                if first || finished {
+                                    let lines = header.iter().cloned().chain(lines).collect();
+                                    let pos = if first { Some(pos) } else { None };
+                                    callback_clone(PreviewUpdate::Replace(lines, pos));
+                                } else {
+                                    callback_clone(PreviewUpdate::Append(lines));
+                                }
                             })
                         });
-                        preview_thread = Some(PreviewThread { pid, thread, stopped });
+                        preview_thread = Some(PreviewThread {
+                            pid: Some(pid),
+                            thread,
+                            stopped,
+                        });
                     }
                 }
             }
+            PreviewEvent::PreviewFile(path, pos, _) => {
+                let stopped = Arc::new(AtomicBool::new(false));
+                let stopped_clone = stopped.clone();
+                let callback_clone = callback.clone();
+                let thread = thread::spawn(move || {
+                    let mut positioned = false;
+                    read_file(&path, &stopped_clone, |lines| {
+                        if positioned {
+                            callback_clone(PreviewUpdate::Append(lines));
+                        } else {
+                            positioned = true;
+                            callback_clone(PreviewUpdate::Replace(lines, Some(pos)));
+                        }
+                    })
+                });
+                preview_thread = Some(PreviewThread {
+                    pid: None,
+                    thread,
+                    stopped,
+                });
+            }
             PreviewEvent::PreviewPlainText(text, pos) => {
-                callback(text.lines().map(|line| line.to_string().into()).collect(), pos);
+                let lines = text.lines().map(|line| line.to_string().into()).collect();
+                callback(PreviewUpdate::Replace(lines, Some(pos)));
             }
             PreviewEvent::PreviewAnsiText(text, pos) => {
-                let mut parser = ANSIParser::default();
-                let color_lines = text.lines().map(|line| parser.parse_ansi(line)).collect();
-                callback(color_lines, pos);
+                let color_lines = if ANSIScreen::is_needed(&text) {
+                    ANSIScreen::default().parse_lines(&text)
+                } else {
+                    let mut parser = ANSIParser::default();
+                    text.lines().map(|line| parser.parse_ansi(line)).collect()
+                };
+                callback(PreviewUpdate::Replace(color_lines, Some(pos)));
             }
-            PreviewEvent::Noop => {}
+            // turned into the commands above
+            PreviewEvent::PreviewByRules(..) | PreviewEvent::Noop => {}
             PreviewEvent::Abort => return,
         };
     }
 }
 
-fn wait<C>(spawned: std::process::Child, callback: C)
+/// Stream the stdout of the preview command line by line, publishing the lines received since the
+/// last publish every `PREVIEW_HEARTBEAT`, then publish the complete output once the command exits.
+/// `callback(lines, finished)` is called with `finished == true` only for the complete output.
+/// The command is killed if it runs longer than `timeout`. Nothing is published once `stopped` is
+/// set by `PreviewThread::kill`, and `stopped` is set once the command exits.
+fn wait<C>(mut spawned: Child, timeout: Option<Duration>, stopped: &AtomicBool, mut callback: C)
 where
-    C: Fn(Vec<AnsiString<'static>>),
+    C: FnMut(Vec<AnsiString<'static>>, bool),
 {
-    let output = spawned.wait_with_output();
+    let started = Instant::now();
+    let stdout = spawned.stdout.take();
+    let stderr = spawned.stderr.take();
+
+    let timed_out = || timeout.map(|timeout| started.elapsed() >= timeout).unwrap_or(false);
+
+    // drain stderr meanwhile, or the command may block on a full pipe
+    let (tx_stderr, rx_stderr) = channel::<Vec<u8>>();
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_end(&mut output);
+        }
+        let _ = tx_stderr.send(output);
+    });
 
-    if output.is_err() {
-        return;
+    let (tx_line, rx_line) = channel::<Vec<u8>>();
+    let stdout_reader = thread::spawn(move || {
+        let mut reader = match stdout {
+            Some(stdout) => BufReader::new(stdout),
+            None => return,
+        };
+        loop {
+            let mut line = Vec::new();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if tx_line.send(line).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let mut stdout = Vec::new();
+    let mut lines = Vec::new();
+    // the colors may span several lines
+    let mut parser = ANSIParser::default();
+    let mut published = 0;
+    let mut last_published = Instant::now();
+    loop {
+        match rx_line.recv_timeout(PREVIEW_HEARTBEAT) {
+            Ok(line) => {
+                lines.push(parser.parse_ansi(String::from_utf8_lossy(&line).lines().next().unwrap_or("")));
+                stdout.extend(line);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if published < lines.len() && last_published.elapsed() >= PREVIEW_HEARTBEAT {
+            if !stopped.load(Ordering::SeqCst) {
+                callback(lines[published..].to_vec(), false);
+            }
+            published = lines.len();
+            last_published = Instant::now();
+        }
+
+        if timed_out() {
+            return time_out(spawned, lines, stopped, callback);
+        }
     }
 
-    let output = output.unwrap();
+    let _ = stdout_reader.join();
+
+    // the command may keep running, or its children keep stderr open, after closing stdout
+    let status = match timeout {
+        None => spawned.wait(),
+        Some(_) => loop {
+            match spawned.try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) if timed_out() => return time_out(spawned, lines, stopped, callback),
+                Ok(None) => thread::sleep(PREVIEW_POLL_INTERVAL),
+                Err(err) => break Err(err),
+            }
+        },
+    };
+    let status = match status {
+        Ok(status) => status,
+        Err(_) => return,
+    };
+    let stderr = loop {
+        match rx_stderr.recv_timeout(PREVIEW_HEARTBEAT) {
+            Ok(stderr) => break stderr,
+            Err(RecvTimeoutError::Disconnected) => break Vec::new(),
+            Err(RecvTimeoutError::Timeout) if timed_out() => return time_out(spawned, lines, stopped, callback),
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+    };
 
-    if output.status.code().is_none() {
+    // killed for another preview
+    if stopped.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    if status.code().is_none() {
         // On Unix it means the process is terminated by a signal
         // directly return to avoid flickering
         return;
     }
 
     // Capture stderr in case users want to debug ...
-    let out_str = String::from_utf8_lossy(if output.status.success() {
-        &output.stdout
+    let out_str = String::from_utf8_lossy(if status.success() { &stdout } else { &stderr });
+
+    // interpret the cursor movements of TUI-like programs in a virtual screen
+    let lines = if ANSIScreen::is_needed(&out_str) {
+        ANSIScreen::default().parse_lines(&out_str)
     } else {
-        &output.stderr
-    });
+        out_str.lines().map(AnsiString::parse).collect()
+    };
+    callback(lines, true);
+}
+
+/// Kill the timed out command of `wait` and publish the lines read so far, unless it was stopped.
+fn time_out<C>(mut spawned: Child, mut lines: Vec<AnsiString<'static>>, stopped: &AtomicBool, mut callback: C)
+where
+    C: FnMut(Vec<AnsiString<'static>>, bool),
+{
+    debug!("preview: command timed out, killing process group {}", spawned.id());
+    kill_process_group(spawned.id());
+    let _ = spawned.wait();
+    if !stopped.swap(true, Ordering::SeqCst) {
+        lines.push(AnsiString::parse("[preview timed out]"));
+        callback(lines, true);
+    }
+}
+
+/// Read and highlight the file for `--preview-builtin=file`, publishing the lines read since the
+/// last publish every `PREVIEW_HEARTBEAT` as `wait` does for the commands. Stop early once `stopped` is set, i.e.
+/// another item is previewed.
+fn read_file<C>(path: &Path, stopped: &AtomicBool, mut callback: C)
+where
+    C: FnMut(Vec<AnsiString<'static>>),
+{
+    let mut reader = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(err) => {
+            callback(vec![AnsiString::parse(&format!(
+                "[failed to read {}: {}]",
+                path.display(),
+                err
+            ))]);
+            return;
+        }
+    };
+
+    let mut lines = Vec::new();
+    let mut highlighter: Option<Highlighter> = None;
+    let mut published = 0;
+    let mut last_published = Instant::now();
+    let mut buf = Vec::new();
+    loop {
+        if stopped.load(Ordering::Relaxed) {
+            return;
+        }
+
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(err) => {
+                lines.push(AnsiString::parse(&format!(
+                    "[failed to read {}: {}]",
+                    path.display(),
+                    err
+                )));
+                break;
+            }
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(&['\n', '\r'][..]);
+        let highlighter = highlighter.get_or_insert_with(|| Highlighter::new(path, line));
+        lines.push(highlighter.highlight(line));
+
+        if lines.len() >= PREVIEW_FILE_MAX_LINES {
+            lines.push(AnsiString::parse("[preview truncated]"));
+            break;
+        }
+        if last_published.elapsed() >= PREVIEW_HEARTBEAT {
+            callback(lines[published..].to_vec());
+            published = lines.len();
+            last_published = Instant::now();
+        }
+    }
+    callback(lines.split_off(published));
+}
+
+fn is_text_file(path: &Path) -> bool {
+    let is_file = fs::metadata(path).map(|metadata| metadata.is_file()).unwrap_or(false);
+    is_file && !is_binary(path)
+}
+
+/// The injected command as shown in the preview window, as a shell prompt
+fn command_lines(cmd: &str) -> Vec<AnsiString<'static>> {
+    cmd.lines()
+        .enumerate()
+        .map(|(i, line)| AnsiString::parse(&format!("{} {}", if i == 0 { "$" } else { ">" }, line)))
+        .collect()
+}
 
-    let lines = out_str.lines().map(AnsiString::parse).collect();
-    callback(lines);
+fn command_error(title: &str, cmd: &str) -> Vec<AnsiString<'static>> {
+    let mut lines = vec![AnsiString::parse(&format!("[{}]", title))];
+    lines.extend(command_lines(cmd));
+    lines
+}
+
+fn kill_process_group(pid: u32) {
+    unsafe { libc::kill(-(pid as i32), libc::SIGKILL) };
 }
 
 #[derive(Builder, Default, Debug)]
@@ -630,3 +1198,193 @@ impl Printer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_memory() {
+        let identity = |index: usize| ItemIdentity::Index(1, index as u32);
+        let mut memory = ScrollMemory::default();
+        for index in 0..SCROLL_MEMORY_SIZE {
+            memory.remember(identity(index), (1, index));
+        }
+        memory.remember(identity(0), (2, 5));
+        memory.remember(identity(SCROLL_MEMORY_SIZE), (1, 1));
+
+        // item 1 is the least recently used one
+        assert_eq!(memory.take(&identity(1)), None);
+        assert_eq!(memory.take(&identity(0)), Some((2, 5)));
+        assert_eq!(memory.take(&identity(0)), None);
+        assert_eq!(memory.take(&identity(SCROLL_MEMORY_SIZE)), Some((1, 1)));
+
+        // the items of another run are different ones
+        memory.remember(ItemIdentity::Key("a".to_string()), (1, 3));
+        assert_eq!(memory.take(&ItemIdentity::Index(2, 1)), None);
+        assert_eq!(memory.take(&ItemIdentity::Key("a".to_string())), Some((1, 3)));
+    }
+
+    #[test]
+    fn test_visible_lines() {
+        let previewer = Previewer::new(None, || {});
+        *previewer.content_lines.lock() = ["\x1B[31ma\x1B[0m", "b", "c", "d"]
+            .iter()
+            .map(|line| AnsiString::parse(line))
+            .collect();
+        previewer.height.store(2, Ordering::Relaxed);
+        assert_eq!(vec!["a", "b"], previewer.visible_lines());
+
+        previewer.vscroll_offset.store(3, Ordering::SeqCst);
+        assert_eq!(vec!["c", "d"], previewer.visible_lines());
+
+        previewer.vscroll_offset.store(4, Ordering::SeqCst);
+        assert_eq!(vec!["d"], previewer.visible_lines());
+    }
+
+    #[test]
+    fn test_wait_streams_lines() {
+        let spawned = Command::new("sh")
+            .arg("-c")
+            .arg("echo a; sleep 0.5; echo b")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let published = std::cell::RefCell::new(vec![]);
+        wait(spawned, None, &AtomicBool::new(false), |lines, finished| {
+            let lines: Vec<String> = lines.iter().map(|line| line.stripped().to_string()).collect();
+            published.borrow_mut().push((lines, finished));
+        });
+
+        let published = published.into_inner();
+        assert_eq!(Some(&(vec!["a".to_string()], false)), published.first());
+        assert_eq!(Some(&(vec!["a".to_string(), "b".to_string()], true)), published.last());
+    }
+
+    #[test]
+    fn test_wait_streams_new_lines_only() {
+        let spawned = Command::new("sh")
+            .arg("-c")
+            .arg("printf '\\033[31ma\\n'; sleep 0.3; printf 'b\\033[0m\\n'; sleep 0.3")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut published = vec![];
+        wait(spawned, None, &AtomicBool::new(false), |lines, finished| {
+            if !finished {
+                published.push(lines);
+            }
+        });
+
+        let lines: Vec<&str> = published.iter().flatten().map(|line| line.stripped()).collect();
+        assert_eq!(lines, vec!["a", "b"]);
+        // the color of the first line is kept for the next one
+        assert!(published[1][0].has_attrs());
+    }
+
+    #[test]
+    fn test_wait_stopped() {
+        let spawned = Command::new("sh")
+            .arg("-c")
+            .arg("echo a; sleep 0.3; echo b")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut published = 0;
+        wait(spawned, None, &AtomicBool::new(true), |_, _| published += 1);
+        assert_eq!(0, published);
+    }
+
+    #[test]
+    fn test_wait_timeout() {
+        let spawned = Command::new("sh")
+            .arg("-c")
+            .arg("echo a; sleep 10 | cat; echo b")
+            .process_group(0)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let started = Instant::now();
+        let published = std::cell::RefCell::new(vec![]);
+        wait(
+            spawned,
+            Some(Duration::from_millis(300)),
+            &AtomicBool::new(false),
+            |lines, finished| {
+                let lines: Vec<String> = lines.iter().map(|line| line.stripped().to_string()).collect();
+                published.borrow_mut().push((lines, finished));
+            },
+        );
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let expected = vec!["a".to_string(), "[preview timed out]".to_string()];
+        assert_eq!(Some(&(expected, true)), published.into_inner().last());
+    }
+
+    #[test]
+    fn test_wait_timeout_after_stdout_closed() {
+        // still running, or stderr held by a child, once stdout is closed
+        for script in ["echo a; exec >&-; sleep 10", "echo a; sleep 10 >/dev/null &"] {
+            let spawned = Command::new("sh")
+                .arg("-c")
+                .arg(script)
+                .process_group(0)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .unwrap();
+
+            let started = Instant::now();
+            let mut published = vec![];
+            wait(
+                spawned,
+                Some(Duration::from_millis(300)),
+                &AtomicBool::new(false),
+                |lines, _| published = lines.iter().map(|line| line.stripped().to_string()).collect(),
+            );
+
+            assert!(started.elapsed() < Duration::from_secs(5));
+            assert_eq!(published, vec!["a", "[preview timed out]"]);
+        }
+    }
+
+    #[test]
+    fn test_builtin_preview_parse() {
+        assert_eq!(Some("{}"), BuiltinPreview::parse("file").map(|b| b.path).as_deref());
+        assert_eq!(
+            Some("{1}"),
+            BuiltinPreview::parse("file:{1}").map(|b| b.path).as_deref()
+        );
+        assert_eq!(None, BuiltinPreview::parse("file:"));
+        assert_eq!(None, BuiltinPreview::parse("dir"));
+    }
+
+    #[test]
+    fn test_read_file() {
+        let path = std::env::temp_dir().join(format!("skim-read-file-{}.txt", std::process::id()));
+        fs::write(&path, "a\r\nb\n\nc").unwrap();
+
+        let mut published = vec![];
+        read_file(&path, &AtomicBool::new(false), |lines| published.push(lines));
+        let lines: Vec<&str> = published.last().unwrap().iter().map(|line| line.stripped()).collect();
+        assert_eq!(lines, vec!["a", "b", "", "c"]);
+
+        // nothing is published once stopped
+        let mut published = vec![];
+        read_file(&path, &AtomicBool::new(true), |lines| published.push(lines));
+        assert!(published.is_empty());
+
+        fs::remove_file(&path).unwrap();
+        let mut published = vec![];
+        read_file(&path, &AtomicBool::new(false), |lines| published.push(lines));
+        assert!(published[0][0].stripped().starts_with("[failed to read"));
+    }
+}
@@ -1,14 +1,17 @@
-use std::mem;
+use std::borrow::Cow;
 use std::sync::Arc;
 
 use tuikit::prelude::*;
 use unicode_width::UnicodeWidthStr;
 
-use crate::event::{Event, EventHandler, UpdateScreen};
+use crate::event::{event_action, Event, EventHandler, UpdateScreen};
 use crate::options::SkimOptions;
 use crate::theme::{ColorTheme, DEFAULT_THEME};
 use crate::util::clear_canvas;
 
+/// the number of killed texts kept for `yank-pop`
+const KILL_RING_SIZE: usize = 10;
+
 #[derive(Clone, Copy, PartialEq)]
 enum QueryMode {
     Cmd,
@@ -20,9 +23,16 @@ pub struct Query {
     cmd_after: Vec<char>,
     fz_query_before: Vec<char>,
     fz_query_after: Vec<char>,
-    yank: Vec<char>,
+    // the killed texts, the latest one goes last
+    kill_ring: Vec<Vec<char>>,
+    // the index in the kill ring and the length of the text just yanked, for `yank-pop`
+    yanked: Option<(usize, usize)>,
+    // the words are path components(`--filepath-word`)
+    filepath_word: bool,
 
     mode: QueryMode,
+    // show the command query and the query in two rows(--split-prompt)
+    split_prompt: bool,
     base_cmd: String,
     replstr: String,
     query_prompt: String,
@@ -46,8 +56,11 @@ impl Query {
             cmd_after: Vec::new(),
             fz_query_before: Vec::new(),
             fz_query_after: Vec::new(),
-            yank: Vec::new(),
+            kill_ring: Vec::new(),
+            yanked: None,
+            filepath_word: false,
             mode: QueryMode::Query,
+            split_prompt: false,
             base_cmd: String::new(),
             replstr: "{}".to_string(),
             query_prompt: "> ".to_string(),
@@ -87,6 +100,10 @@ impl Query {
         self
     }
 
+    pub fn set_theme(&mut self, theme: Arc<ColorTheme>) {
+        self.theme = theme;
+    }
+
     pub fn cmd_history(mut self, mut history: Vec<String>) -> Self {
         self.cmd_history_before.append(&mut history);
         self
@@ -124,6 +141,9 @@ impl Query {
             self.mode = QueryMode::Cmd;
         }
 
+        self.split_prompt = options.split_prompt;
+        self.filepath_word = options.filepath_word;
+
         if let Some(query_prompt) = options.prompt {
             self.query_prompt = query_prompt.to_string();
         }
@@ -151,14 +171,19 @@ impl Query {
             .collect()
     }
 
-    pub fn get_cmd(&self) -> String {
-        let arg: String = self
-            .cmd_before
-            .iter()
-            .cloned()
-            .chain(self.cmd_after.iter().cloned().rev())
-            .collect();
-        self.base_cmd.replace(&self.replstr, &arg)
+    /// The command with `replstr` replaced by the command query as is, the rest of the command
+    /// is transformed by `inject`, e.g. to expand the other placeholders.
+    pub fn get_cmd_with<'a>(&'a self, inject: impl Fn(&'a str) -> Cow<'a, str>) -> String {
+        let cmd_query = self.get_cmd_query();
+        if self.replstr.is_empty() {
+            return inject(&self.base_cmd).into_owned();
+        }
+
+        self.base_cmd
+            .split(self.replstr.as_str())
+            .map(inject)
+            .collect::<Vec<_>>()
+            .join(&cmd_query)
     }
 
     pub fn get_cmd_query(&self) -> String {
@@ -176,22 +201,31 @@ impl Query {
         }
     }
 
-    fn get_before(&self) -> String {
-        match self.mode {
+    /// the number of rows taken by the prompts
+    pub fn num_rows(&self) -> usize {
+        if self.split_prompt {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn get_before(&self, mode: QueryMode) -> String {
+        match mode {
             QueryMode::Cmd => self.cmd_before.iter().cloned().collect(),
             QueryMode::Query => self.fz_query_before.iter().cloned().collect(),
         }
     }
 
-    fn get_after(&self) -> String {
-        match self.mode {
+    fn get_after(&self, mode: QueryMode) -> String {
+        match mode {
             QueryMode::Cmd => self.cmd_after.iter().cloned().rev().collect(),
             QueryMode::Query => self.fz_query_after.iter().cloned().rev().collect(),
         }
     }
 
-    fn get_prompt(&self) -> &str {
-        match self.mode {
+    fn get_prompt(&self, mode: QueryMode) -> &str {
+        match mode {
             QueryMode::Cmd => &self.cmd_prompt,
             QueryMode::Query => &self.query_prompt,
         }
@@ -216,12 +250,13 @@ impl Query {
             return;
         }
 
-        self.yank.clear();
-
         if reverse {
-            self.yank.append(&mut yank.into_iter().rev().collect());
-        } else {
-            self.yank.append(&mut yank);
+            yank.reverse();
+        }
+
+        self.kill_ring.push(yank);
+        if self.kill_ring.len() > KILL_RING_SIZE {
+            self.kill_ring.remove(0);
         }
     }
 
@@ -286,16 +321,17 @@ impl Query {
 
     pub fn act_backward_kill_word(&mut self) {
         let mut yank = Vec::new();
+        let filepath_word = self.filepath_word;
 
         {
             let (before, _) = self.get_query_ref();
-            // kill things other than alphanumeric
-            while !before.is_empty() && !before[before.len() - 1].is_alphanumeric() {
+            // kill things other than the word
+            while !before.is_empty() && !is_word_char(before[before.len() - 1], filepath_word) {
                 yank.push(before.pop().unwrap());
             }
 
-            // kill word until whitespace (not alphanumeric)
-            while !before.is_empty() && before[before.len() - 1].is_alphanumeric() {
+            // kill word until the non-word chars
+            while !before.is_empty() && is_word_char(before[before.len() - 1], filepath_word) {
                 yank.push(before.pop().unwrap());
             }
         }
@@ -305,16 +341,17 @@ impl Query {
 
     pub fn act_kill_word(&mut self) {
         let mut yank = Vec::new();
+        let filepath_word = self.filepath_word;
 
         {
             let (_, after) = self.get_query_ref();
 
-            // kill non alphanumeric
-            while !after.is_empty() && !after[after.len() - 1].is_alphanumeric() {
+            // kill non-word chars
+            while !after.is_empty() && !is_word_char(after[after.len() - 1], filepath_word) {
                 yank.push(after.pop().unwrap());
             }
-            // kill alphanumeric
-            while !after.is_empty() && after[after.len() - 1].is_alphanumeric() {
+            // kill word chars
+            while !after.is_empty() && is_word_char(after[after.len() - 1], filepath_word) {
                 yank.push(after.pop().unwrap());
             }
         }
@@ -322,16 +359,17 @@ impl Query {
     }
 
     pub fn act_backward_word(&mut self) {
+        let filepath_word = self.filepath_word;
         let (before, after) = self.get_query_ref();
-        // skip whitespace
-        while !before.is_empty() && !before[before.len() - 1].is_alphanumeric() {
+        // skip non-word chars
+        while !before.is_empty() && !is_word_char(before[before.len() - 1], filepath_word) {
             if let Some(ch) = before.pop() {
                 after.push(ch);
             }
         }
 
-        // backword char until whitespace
-        while !before.is_empty() && before[before.len() - 1].is_alphanumeric() {
+        // backword char until the non-word chars
+        while !before.is_empty() && is_word_char(before[before.len() - 1], filepath_word) {
             if let Some(ch) = before.pop() {
                 after.push(ch);
             }
@@ -339,22 +377,40 @@ impl Query {
     }
 
     pub fn act_forward_word(&mut self) {
+        let filepath_word = self.filepath_word;
         let (before, after) = self.get_query_ref();
-        // backword char until whitespace
-        // skip whitespace
-        while !after.is_empty() && after[after.len() - 1].is_whitespace() {
+        // skip non-word chars
+        while !after.is_empty() && !is_word_char(after[after.len() - 1], filepath_word) {
             if let Some(ch) = after.pop() {
                 before.push(ch);
             }
         }
 
-        while !after.is_empty() && !after[after.len() - 1].is_whitespace() {
+        // forward char until the non-word chars, i.e. to the end of the word
+        while !after.is_empty() && is_word_char(after[after.len() - 1], filepath_word) {
             if let Some(ch) = after.pop() {
                 before.push(ch);
             }
         }
     }
 
+    /// swap the chars around the cursor and move forward, the last two chars at the end of line
+    pub fn act_transpose_chars(&mut self) {
+        let (before, after) = self.get_query_ref();
+        if before.is_empty() {
+            return;
+        }
+
+        if let Some(ch) = after.pop() {
+            before.push(ch);
+        }
+
+        let len = before.len();
+        if len >= 2 {
+            before.swap(len - 2, len - 1);
+        }
+    }
+
     pub fn act_beginning_of_line(&mut self) {
         let (before, after) = self.get_query_ref();
         while !before.is_empty() {
@@ -386,11 +442,37 @@ impl Query {
     }
 
     pub fn act_yank(&mut self) {
-        let yank = std::mem::take(&mut self.yank);
+        let yank = match self.kill_ring.last() {
+            Some(yank) => yank.clone(),
+            None => return,
+        };
+
         for &c in &yank {
             self.act_add_char(c);
         }
-        let _ = mem::replace(&mut self.yank, yank);
+        self.yanked = Some((self.kill_ring.len() - 1, yank.len()));
+    }
+
+    /// replace the text just yanked by the one killed before it, rotating the kill ring
+    pub fn act_yank_pop(&mut self) {
+        let (index, len) = match self.yanked {
+            Some(yanked) => yanked,
+            None => return,
+        };
+
+        let index = (index + self.kill_ring.len() - 1) % self.kill_ring.len();
+        let yank = self.kill_ring[index].clone();
+        let (before, _) = self.get_query_ref();
+        before.truncate(before.len().saturating_sub(len));
+        before.extend(yank.iter());
+        self.yanked = Some((index, yank.len()));
+    }
+
+    /// replace the query of current mode, the cursor is moved to the end
+    pub fn set_query(&mut self, query: &str) {
+        let (before, after) = self.get_query_ref();
+        *before = query.chars().collect();
+        after.clear();
     }
 
     pub fn previous_history(&mut self) {
@@ -421,6 +503,15 @@ impl Query {
         }
     }
 
+    /// the modes of the prompts from top to bottom, the current one only unless split
+    fn modes_to_draw(&self) -> Vec<QueryMode> {
+        if self.split_prompt {
+            vec![QueryMode::Cmd, QueryMode::Query]
+        } else {
+            vec![self.mode]
+        }
+    }
+
     fn query_changed(
         &self,
         mode: QueryMode,
@@ -453,6 +544,11 @@ impl EventHandler for Query {
                 None => self.act_add_char(*ch),
             },
 
+            EvActAddStr(text) => match self.pasted.as_mut() {
+                Some(pasted) => pasted.push_str(text),
+                None => text.chars().for_each(|ch| self.act_add_char(ch)),
+            },
+
             EvActDeleteChar | EvActDeleteCharEOF => {
                 self.act_delete_char();
             }
@@ -515,6 +611,14 @@ impl EventHandler for Query {
                 self.act_yank();
             }
 
+            EvActYankPop => {
+                self.act_yank_pop();
+            }
+
+            EvActTransposeChars => {
+                self.act_transpose_chars();
+            }
+
             EvActToggleInteractive => {
                 self.act_query_toggle_interactive();
             }
@@ -533,7 +637,15 @@ impl EventHandler for Query {
             _ => {}
         }
 
-        if self.query_changed(mode, query_before_len, query_after_len, cmd_before_len, cmd_after_len) {
+        let changed = self.query_changed(mode, query_before_len, query_after_len, cmd_before_len, cmd_after_len);
+
+        // `yank-pop` only follows `yank` or itself, any other action in between breaks the chain
+        let acted = changed || event_action(event).is_some();
+        if acted && !matches!(event, EvActYank | EvActYankPop) {
+            self.yanked = None;
+        }
+
+        if changed {
             UpdateScreen::REDRAW
         } else {
             UpdateScreen::DONT_REDRAW
@@ -544,16 +656,17 @@ impl EventHandler for Query {
 impl Draw for Query {
     fn draw(&self, canvas: &mut dyn Canvas) -> DrawResult<()> {
         canvas.clear()?;
-        let before = self.get_before();
-        let after = self.get_after();
-        let prompt = self.get_prompt();
         clear_canvas(canvas)?;
 
-        let prompt_width = canvas.print_with_attr(0, 0, prompt, self.theme.prompt())?;
-        let before_width = canvas.print_with_attr(0, prompt_width, &before, self.theme.query())?;
-        let col = prompt_width + before_width;
-        canvas.print_with_attr(0, col, &after, self.theme.query())?;
-        canvas.set_cursor(0, col)?;
+        for (row, mode) in self.modes_to_draw().into_iter().enumerate() {
+            let prompt_width = canvas.print_with_attr(row, 0, self.get_prompt(mode), self.theme.prompt())?;
+            let before_width = canvas.print_with_attr(row, prompt_width, &self.get_before(mode), self.theme.query())?;
+            let col = prompt_width + before_width;
+            canvas.print_with_attr(row, col, &self.get_after(mode), self.theme.query())?;
+            if mode == self.mode {
+                canvas.set_cursor(row, col)?;
+            }
+        }
         canvas.show_cursor(true)?;
         Ok(())
     }
@@ -561,16 +674,32 @@ impl Draw for Query {
 
 impl Widget<Event> for Query {
     fn size_hint(&self) -> (Option<usize>, Option<usize>) {
-        let before = self.get_before();
-        let after = self.get_after();
-        let prompt = self.get_prompt();
-        (Some(prompt.width() + before.width() + after.width() + 1), None)
+        let width = self
+            .modes_to_draw()
+            .into_iter()
+            .map(|mode| self.get_prompt(mode).width() + self.get_before(mode).width() + self.get_after(mode).width())
+            .max()
+            .unwrap_or(0);
+        (Some(width + 1), None)
+    }
+}
+
+/// the chars of a word for the word motions, path components with `--filepath-word`
+fn is_word_char(ch: char, filepath_word: bool) -> bool {
+    if filepath_word {
+        ch != '/' && !ch.is_whitespace()
+    } else {
+        ch.is_alphanumeric()
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::Query;
+    use crate::event::{Event, EventHandler};
+    use crate::options::SkimOptionsBuilder;
+    use std::borrow::Cow;
+    use tuikit::widget::Widget;
 
     #[test]
     fn test_new_query() {
@@ -581,6 +710,18 @@ mod test {
         assert_eq!(query2.get_fz_query(), "abc");
     }
 
+    #[test]
+    fn test_get_cmd_with() {
+        let options = SkimOptionsBuilder::default()
+            .cmd(Some("rg {} {q} x{}"))
+            .cmd_query(Some("a b"))
+            .build()
+            .unwrap();
+        let query = Query::from_options(&options).build();
+        let cmd = query.get_cmd_with(|part| Cow::Owned(part.replace("{q}", "'Q'")));
+        assert_eq!(cmd, "rg a b 'Q' xa b");
+    }
+
     #[test]
     fn test_add_char() {
         let mut query1 = Query::builder().fz_query("").build();
@@ -612,4 +753,102 @@ mod test {
         query.act_backward_delete_char();
         assert_eq!(query.get_fz_query(), "");
     }
+
+    #[test]
+    fn test_word_motions() {
+        let mut query = Query::builder().fz_query("ab cd-ef/gh").build();
+        query.act_backward_word();
+        query.act_backward_word();
+        query.act_add_char('X');
+        assert_eq!(query.get_fz_query(), "ab cd-Xef/gh");
+        query.act_forward_word();
+        query.act_add_char('Y');
+        assert_eq!(query.get_fz_query(), "ab cd-XefY/gh");
+
+        let mut query = Query::builder().fz_query("ab cd-ef/gh").build();
+        query.filepath_word = true;
+        query.act_backward_kill_word();
+        assert_eq!(query.get_fz_query(), "ab cd-ef/");
+        query.act_backward_word();
+        query.act_add_char('X');
+        assert_eq!(query.get_fz_query(), "ab Xcd-ef/");
+        query.act_beginning_of_line();
+        query.act_kill_word();
+        assert_eq!(query.get_fz_query(), " Xcd-ef/");
+    }
+
+    #[test]
+    fn test_transpose_chars() {
+        let mut query = Query::builder().fz_query("abc").build();
+        query.act_transpose_chars();
+        assert_eq!(query.get_fz_query(), "acb");
+
+        query.act_beginning_of_line();
+        query.act_transpose_chars();
+        assert_eq!(query.get_fz_query(), "acb");
+
+        query.act_forward_char();
+        query.act_transpose_chars();
+        query.act_add_char('d');
+        assert_eq!(query.get_fz_query(), "cadb");
+    }
+
+    #[test]
+    fn test_kill_ring() {
+        let mut query = Query::builder().fz_query("one two three").build();
+        query.act_backward_kill_word();
+        query.act_backward_kill_word();
+        query.act_yank_pop();
+        assert_eq!(query.get_fz_query(), "one ");
+
+        query.act_yank();
+        assert_eq!(query.get_fz_query(), "one two ");
+        query.act_yank_pop();
+        assert_eq!(query.get_fz_query(), "one three");
+        query.act_yank_pop();
+        assert_eq!(query.get_fz_query(), "one two ");
+
+        // yank-pop only follows yank
+        query.handle(&Event::EvActBackwardChar);
+        query.handle(&Event::EvActYankPop);
+        assert_eq!(query.get_fz_query(), "one two ");
+
+        // so do the actions that leave the query alone, but not the heartbeats
+        query.handle(&Event::EvActEndOfLine);
+        query.handle(&Event::EvActYank);
+        query.handle(&Event::EvHeartBeat);
+        query.handle(&Event::EvActYankPop);
+        assert_eq!(query.get_fz_query(), "one two three");
+        query.handle(&Event::EvActDown(1));
+        query.handle(&Event::EvActYankPop);
+        assert_eq!(query.get_fz_query(), "one two three");
+    }
+
+    #[test]
+    fn test_set_query() {
+        let mut query = Query::builder().fz_query("abc").build();
+        query.act_backward_char();
+        query.set_query("中d");
+        assert_eq!(query.get_fz_query(), "中d");
+        query.act_add_char('e');
+        assert_eq!(query.get_fz_query(), "中de");
+    }
+
+    #[test]
+    fn test_split_prompt() {
+        let options = SkimOptionsBuilder::default()
+            .interactive(true)
+            .split_prompt(true)
+            .build()
+            .unwrap();
+        let mut query = Query::from_options(&options);
+        assert_eq!(query.num_rows(), 2);
+
+        query.act_add_char('a');
+        query.act_query_toggle_interactive();
+        query.act_add_char('b');
+        assert_eq!(query.get_cmd_query(), "a");
+        assert_eq!(query.get_fz_query(), "b");
+        assert_eq!(query.size_hint(), (Some(5), None));
+    }
 }
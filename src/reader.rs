@@ -6,15 +6,13 @@ use crate::options::SkimOptions;
 use crate::spinlock::SpinLock;
 use crate::{SkimItem, SkimItemReceiver};
 use crossbeam::channel::{bounded, select, Sender};
-use std::cell::RefCell;
-use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 
 const CHANNEL_SIZE: usize = 1024;
 
-pub trait CommandCollector {
+pub trait CommandCollector: Send {
     /// execute the `cmd` and produce a
     /// - skim item producer
     /// - a channel sender, any message send would mean to terminate the `cmd` process (for now).
@@ -58,8 +56,9 @@ impl ReaderControl {
 }
 
 pub struct Reader {
-    cmd_collector: Rc<RefCell<dyn CommandCollector>>,
+    cmd_collector: Arc<Mutex<dyn CommandCollector>>,
     rx_item: Option<SkimItemReceiver>,
+    on_done: Option<Arc<dyn Fn() + Send + Sync>>,
 }
 
 impl Reader {
@@ -67,6 +66,7 @@ impl Reader {
         Self {
             cmd_collector: options.cmd_collector.clone(),
             rx_item: None,
+            on_done: None,
         }
     }
 
@@ -75,6 +75,16 @@ impl Reader {
         self
     }
 
+    /// called once all the items of a run are collected, so that they are matched without waiting
+    /// for the next heart beat
+    pub fn on_done<C>(mut self, callback: C) -> Self
+    where
+        C: Fn() + Send + Sync + 'static,
+    {
+        self.on_done = Some(Arc::new(callback));
+        self
+    }
+
     pub fn run(&mut self, cmd: &str) -> ReaderControl {
         mark_new_run(cmd);
 
@@ -84,12 +94,12 @@ impl Reader {
 
         let (rx_item, tx_interrupt_cmd) = self.rx_item.take().map(|rx| (rx, None)).unwrap_or_else(|| {
             let components_to_stop_clone = components_to_stop.clone();
-            let (rx_item, tx_interrupt_cmd) = self.cmd_collector.borrow_mut().invoke(cmd, components_to_stop_clone);
+            let (rx_item, tx_interrupt_cmd) = self.cmd_collector.lock().unwrap().invoke(cmd, components_to_stop_clone);
             (rx_item, Some(tx_interrupt_cmd))
         });
 
         let components_to_stop_clone = components_to_stop.clone();
-        let tx_interrupt = collect_item(components_to_stop_clone, rx_item, items_clone);
+        let tx_interrupt = collect_item(components_to_stop_clone, rx_item, items_clone, self.on_done.clone());
 
         ReaderControl {
             tx_interrupt,
@@ -104,6 +114,7 @@ fn collect_item(
     components_to_stop: Arc<AtomicUsize>,
     rx_item: SkimItemReceiver,
     items: Arc<SpinLock<Vec<Arc<dyn SkimItem>>>>,
+    on_done: Option<Arc<dyn Fn() + Send + Sync>>,
 ) -> Sender<i32> {
     let (tx_interrupt, rx_interrupt) = bounded(CHANNEL_SIZE);
 
@@ -114,6 +125,7 @@ fn collect_item(
         components_to_stop.fetch_add(1, Ordering::SeqCst);
         started_clone.store(true, Ordering::SeqCst); // notify parent that it is started
 
+        let mut interrupted = false;
         loop {
             select! {
                 recv(rx_item) -> new_item => match new_item {
@@ -123,11 +135,17 @@ fn collect_item(
                     }
                     Err(_) => break,
                 },
-                recv(rx_interrupt) -> _msg => break,
+                recv(rx_interrupt) -> _msg => {
+                    interrupted = true;
+                    break;
+                }
             }
         }
 
         components_to_stop.fetch_sub(1, Ordering::SeqCst);
+        if let Some(on_done) = on_done.filter(|_| !interrupted) {
+            on_done();
+        }
         debug!("reader: collect_item stop");
     });
 
@@ -1,30 +1,338 @@
+use std::cell::RefCell;
 use std::cmp::max;
 use std::cmp::min;
-use std::collections::BTreeMap;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use tuikit::prelude::{Event as TermEvent, *};
 
 ///! Handle the selections of items
+use crate::engine::util::{byte_to_char_index, char_boundary_range};
 use crate::event::{Event, EventHandler, UpdateScreen};
 use crate::global::current_run_num;
-use crate::item::MatchedItem;
+use crate::helper::selector::DefaultSkimSelector;
+use crate::icon::IconMap;
+use crate::item::{MatchedItem, RankBuilder, RankValues};
 use crate::orderedvec::OrderedVec;
 use crate::theme::{ColorTheme, DEFAULT_THEME};
 use crate::util::clear_canvas;
 use crate::util::{print_item, reshape_string, LinePrinter};
-use crate::{DisplayContext, MatchRange, Matches, Selector, SkimItem, SkimOptions};
+use crate::{
+    DisplayContext, ItemIcon, ItemIdentity, MatchEngine, MatchRange, Matches, Selector, SkimItem, SkimOptions, Viewport,
+};
 use regex::Regex;
 use std::rc::Rc;
 use unicode_width::UnicodeWidthStr;
 
 type ItemIndex = (u32, u32);
 
+/// The characters of the thumb and the track of the scrollbar(`--scrollbar`)
+#[derive(Clone, Copy)]
+struct Scrollbar {
+    thumb: char,
+    track: char,
+}
+
+impl Scrollbar {
+    fn from_str(chars: &str, no_unicode: bool) -> Self {
+        let mut chars = chars.chars();
+        let default_thumb = if no_unicode { '|' } else { '│' };
+        Self {
+            thumb: chars.next().unwrap_or(default_thumb),
+            track: chars.next().unwrap_or(' '),
+        }
+    }
+}
+
+/// the first line and the number of lines of the scrollbar thumb, counted from the start of the
+/// list, `None` if all the items fit in the screen
+fn scrollbar_thumb(num_items: usize, height: usize, item_cursor: usize) -> Option<(usize, usize)> {
+    if height == 0 || num_items <= height {
+        return None;
+    }
+    let len = max(1, height * height / num_items);
+    let max_cursor = num_items - height;
+    let start = (height - len) * min(item_cursor, max_cursor) / max_cursor;
+    Some((start, len))
+}
+
+/// the item cursor that puts the middle of the scrollbar thumb at `line` of the list
+fn scrollbar_cursor(num_items: usize, height: usize, line: usize) -> usize {
+    match scrollbar_thumb(num_items, height, 0) {
+        Some((_, len)) if len < height => {
+            let track = height - len;
+            let start = min(line.saturating_sub(len / 2), track);
+            ((num_items - height) * start + track / 2) / track
+        }
+        _ => 0,
+    }
+}
+
+/// A selected item and where it was seen, so that the selections are output in input order
+struct SelectedItem {
+    index: ItemIndex,
+    item: Arc<dyn SkimItem>,
+}
+
+impl SelectedItem {
+    fn new(run_num: u32, item_idx: u32, item: Arc<dyn SkimItem>) -> Self {
+        Self {
+            index: (run_num, item_idx),
+            item,
+        }
+    }
+}
+
+/// The identities of the items of a run, a bit for each index and the keys of `SkimItem::identity`
+#[derive(Default)]
+struct ItemSet {
+    run_num: u32,
+    indices: Vec<u64>,
+    keys: HashSet<String>,
+    len: usize,
+}
+
+impl ItemSet {
+    fn new(run_num: u32) -> Self {
+        Self {
+            run_num,
+            ..Self::default()
+        }
+    }
+
+    fn insert(&mut self, identity: ItemIdentity) {
+        let inserted = match identity {
+            ItemIdentity::Key(key) => self.keys.insert(key),
+            ItemIdentity::Index(_, idx) => {
+                let (word, bit) = (idx as usize / 64, idx % 64);
+                if self.indices.len() <= word {
+                    self.indices.resize(word + 1, 0);
+                }
+                let inserted = self.indices[word] & (1 << bit) == 0;
+                self.indices[word] |= 1 << bit;
+                inserted
+            }
+        };
+        if inserted {
+            self.len += 1;
+        }
+    }
+
+    fn contains(&self, identity: &ItemIdentity) -> bool {
+        match identity {
+            ItemIdentity::Key(key) => self.keys.contains(key),
+            ItemIdentity::Index(run_num, idx) => {
+                *run_num == self.run_num
+                    && self
+                        .indices
+                        .get(*idx as usize / 64)
+                        .is_some_and(|word| word & (1 << (idx % 64)) != 0)
+            }
+        }
+    }
+}
+
+/// The matched items selected at once by `select-all` or `toggle-all`. The item list is shared
+/// instead of copying each item, and the members toggled later are kept as the exceptions.
+struct BulkSelection {
+    items: Rc<OrderedVec<MatchedItem>>,
+    members: ItemSet,
+    // the members are selected unless they are exceptions, or only the exceptions are
+    select: bool,
+    exceptions: HashSet<ItemIdentity>,
+}
+
+impl BulkSelection {
+    fn new(items: Rc<OrderedVec<MatchedItem>>, run_num: u32) -> Self {
+        let mut members = ItemSet::new(run_num);
+        items.for_each_unordered(|matched| {
+            members.insert(ItemIdentity::of(matched.item.as_ref(), run_num, matched.item_idx));
+        });
+        Self {
+            items,
+            members,
+            select: true,
+            exceptions: HashSet::new(),
+        }
+    }
+
+    /// whether the member is selected
+    fn is_selected(&self, identity: &ItemIdentity) -> bool {
+        self.select != self.exceptions.contains(identity)
+    }
+
+    fn set_selected(&mut self, identity: ItemIdentity, selected: bool) {
+        if selected == self.select {
+            self.exceptions.remove(&identity);
+        } else {
+            self.exceptions.insert(identity);
+        }
+    }
+
+    fn len(&self) -> usize {
+        if self.select {
+            self.members.len - self.exceptions.len()
+        } else {
+            self.exceptions.len()
+        }
+    }
+
+    /// visit the selected items
+    fn for_each_selected(&self, mut f: impl FnMut(&MatchedItem)) {
+        let run_num = self.members.run_num;
+        self.items.for_each_unordered(|matched| {
+            let selected = if self.exceptions.is_empty() {
+                self.select
+            } else {
+                self.is_selected(&ItemIdentity::of(matched.item.as_ref(), run_num, matched.item_idx))
+            };
+            if selected {
+                f(matched);
+            }
+        });
+    }
+}
+
+/// The selected items, the ones selected one by one and the last bulk selection
+#[derive(Default)]
+struct SelectedSet {
+    // the items that are not members of `bulk`
+    items: BTreeMap<ItemIdentity, SelectedItem>,
+    bulk: Option<BulkSelection>,
+    // bumped whenever the selected items change
+    version: u64,
+}
+
+impl SelectedSet {
+    fn contains(&self, identity: &ItemIdentity) -> bool {
+        match self.bulk.as_ref() {
+            Some(bulk) if bulk.members.contains(identity) => bulk.is_selected(identity),
+            _ => self.items.contains_key(identity),
+        }
+    }
+
+    fn set(&mut self, identity: ItemIdentity, index: ItemIndex, item: &Arc<dyn SkimItem>, selected: bool) {
+        if self.contains(&identity) == selected {
+            return;
+        }
+        self.version += 1;
+        match self.bulk.as_mut() {
+            Some(bulk) if bulk.members.contains(&identity) => bulk.set_selected(identity, selected),
+            _ if selected => {
+                self.items
+                    .insert(identity, SelectedItem::new(index.0, index.1, item.clone()));
+            }
+            _ => {
+                self.items.remove(&identity);
+            }
+        }
+    }
+
+    /// select all the `items`, or toggle them
+    fn select_all(&mut self, items: &Rc<OrderedVec<MatchedItem>>, run_num: u32, toggle: bool) {
+        self.version += 1;
+        if let Some(bulk) = self.bulk.as_mut().filter(|bulk| Rc::ptr_eq(&bulk.items, items)) {
+            if toggle {
+                bulk.select = !bulk.select;
+            } else {
+                bulk.select = true;
+                bulk.exceptions.clear();
+            }
+            return;
+        }
+
+        // the previous bulk selection is of other items, keep its items one by one
+        if let Some(bulk) = self.bulk.take() {
+            let run_num = bulk.members.run_num;
+            bulk.for_each_selected(|matched| {
+                let identity = ItemIdentity::of(matched.item.as_ref(), run_num, matched.item_idx);
+                self.items.insert(
+                    identity,
+                    SelectedItem::new(run_num, matched.item_idx, matched.item.clone()),
+                );
+            });
+        }
+
+        let mut bulk = BulkSelection::new(items.clone(), run_num);
+        for (identity, selected) in std::mem::take(&mut self.items) {
+            if !bulk.members.contains(&identity) {
+                self.items.insert(identity, selected);
+            } else if toggle {
+                bulk.exceptions.insert(identity);
+            }
+        }
+        self.bulk = Some(bulk);
+    }
+
+    fn clear(&mut self) {
+        if !self.is_empty() {
+            self.version += 1;
+        }
+        self.items.clear();
+        self.bulk = None;
+    }
+
+    fn len(&self) -> usize {
+        self.items.len() + self.bulk.as_ref().map_or(0, BulkSelection::len)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// the selected items in the order they were seen
+    fn sorted_items(&self) -> Vec<(ItemIndex, Arc<dyn SkimItem>)> {
+        let mut selected: Vec<(ItemIndex, Arc<dyn SkimItem>)> = self
+            .items
+            .values()
+            .map(|selected| (selected.index, selected.item.clone()))
+            .collect();
+        if let Some(bulk) = self.bulk.as_ref() {
+            let run_num = bulk.members.run_num;
+            bulk.for_each_selected(|matched| selected.push(((run_num, matched.item_idx), matched.item.clone())));
+        }
+        selected.sort_by_key(|(index, _)| *index);
+        selected
+    }
+}
+
+/// How the line numbers are shown in the gutter of the item list
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LineNumbers {
+    Hidden,
+    /// line number of the item in the input
+    Absolute,
+    /// distance to the current line
+    Relative,
+    /// absolute for the current line, relative for the others
+    Both,
+}
+
+impl LineNumbers {
+    fn from_str(line_numbers: &str) -> std::result::Result<Self, String> {
+        match line_numbers.to_lowercase().as_str() {
+            "absolute" => Ok(LineNumbers::Absolute),
+            "relative" => Ok(LineNumbers::Relative),
+            "both" => Ok(LineNumbers::Both),
+            _ => Err(format!(
+                "unknown type `{}`, expects absolute, relative or both",
+                line_numbers
+            )),
+        }
+    }
+}
+
+/// Check the value of `line_numbers`, refused by `SkimOptionsBuilder::build` if it is invalid
+pub(crate) fn check_line_numbers(line_numbers: &str) -> std::result::Result<(), String> {
+    LineNumbers::from_str(line_numbers).map(|_| ())
+}
+
 pub struct Selection {
     // all items
-    items: OrderedVec<MatchedItem>,
-    selected: BTreeMap<ItemIndex, Arc<dyn SkimItem>>,
+    // shared with the bulk selection, if any
+    items: Rc<OrderedVec<MatchedItem>>,
+    selected: SelectedSet,
 
     //
     // |>------ items[items.len()-1]
@@ -46,10 +354,29 @@ pub struct Selection {
     skip_to_pattern: Option<Regex>,
     height: AtomicUsize,
     tabstop: usize,
+    line_numbers: LineNumbers,
+    // the first N lines of the input are not items(--header-lines)
+    line_number_offset: usize,
+    // fallback icons of the items by extension(--icons)
+    icons: IconMap,
+    // to find the matched positions that compact matches dropped
+    match_engine: Option<Arc<dyn MatchEngine>>,
+    // the lowercase query terms to highlight in full(--highlight-all)
+    highlight_all: bool,
+    highlight_terms: Vec<Vec<char>>,
 
     // Options
     multi_selection: bool,
+    // the most items that could be selected(--multi-max), and whether a selection was refused
+    // for it since the last `take_limit_hit`
+    multi_max: Option<usize>,
+    limit_hit: bool,
     reverse: bool,
+    // the items are in the input order(`--no-sort`), the newest one goes first with `--tac`
+    nosort: bool,
+    tac: bool,
+    // keep the cursor on the newest item while it is there(`--follow`)
+    follow: bool,
     no_hscroll: bool,
     theme: Arc<ColorTheme>,
 
@@ -57,14 +384,34 @@ pub struct Selection {
     // To avoid remember all items, we'll track the latest run_num and index.
     latest_select_run_num: u32,
     pre_selected_watermark: usize,
-    selector: Option<Rc<dyn Selector>>,
+    selector: Option<Arc<dyn Selector>>,
+
+    // the scrollbar on the right edge(--scrollbar), and whether it is being dragged
+    scrollbar: Option<Scrollbar>,
+    dragging_scrollbar: AtomicBool,
+
+    // the shapes of the items drawn lately, so that redraws needn't reshape the same lines
+    shapes: RefCell<ShapeCache>,
+}
+
+/// the maximum number of shapes cached, the cache restarts once it is full
+const MAX_CACHED_SHAPES: usize = 4096;
+
+/// The `(shift, full_width)` of the items drawn, keyed by the item and its highlighted range.
+/// The shapes only hold for the container width they were computed with.
+#[derive(Default)]
+struct ShapeCache {
+    container_width: usize,
+    // the generation of the item pool, the item indices are reused by a new generation
+    generation: usize,
+    shapes: HashMap<(u32, usize, usize), (usize, usize)>,
 }
 
 impl Selection {
     pub fn new() -> Self {
         Selection {
-            items: OrderedVec::new(),
-            selected: BTreeMap::new(),
+            items: Rc::new(OrderedVec::new()),
+            selected: SelectedSet::default(),
             item_cursor: 0,
             line_cursor: 0,
             hscroll_offset: 0,
@@ -72,13 +419,27 @@ impl Selection {
             skip_to_pattern: None,
             height: AtomicUsize::new(0),
             tabstop: 8,
+            line_numbers: LineNumbers::Hidden,
+            line_number_offset: 0,
+            icons: IconMap::default(),
+            match_engine: None,
+            highlight_all: false,
+            highlight_terms: Vec::new(),
             multi_selection: false,
+            multi_max: None,
+            limit_hit: false,
             reverse: false,
+            nosort: false,
+            tac: false,
+            follow: false,
             no_hscroll: false,
             theme: Arc::new(*DEFAULT_THEME),
             latest_select_run_num: 0,
             pre_selected_watermark: 0,
             selector: None,
+            scrollbar: None,
+            dragging_scrollbar: AtomicBool::new(false),
+            shapes: RefCell::new(ShapeCache::default()),
         }
     }
 
@@ -92,6 +453,7 @@ impl Selection {
         if options.multi {
             self.multi_selection = true;
         }
+        self.multi_max = options.multi_max;
 
         if options.layout.starts_with("reverse") {
             self.reverse = true;
@@ -107,18 +469,36 @@ impl Selection {
         }
 
         if options.tac {
-            self.items.tac(true);
+            self.tac = true;
+            Rc::make_mut(&mut self.items).tac(true);
         }
 
         if options.nosort {
-            self.items.nosort(true);
+            self.nosort = true;
+            Rc::make_mut(&mut self.items).nosort(true);
         }
 
+        self.follow = options.follow;
+
         if !options.skip_to_pattern.is_empty() {
             self.skip_to_pattern = Regex::new(options.skip_to_pattern).ok();
         }
 
+        if let Some(line_numbers) = options.line_numbers {
+            self.line_numbers = LineNumbers::from_str(line_numbers).unwrap_or(LineNumbers::Hidden);
+            self.line_number_offset = options.header_lines;
+        }
+
+        if let Some(icons) = options.icons {
+            self.icons = IconMap::parse(icons);
+        }
+
+        if let Some(scrollbar) = options.scrollbar {
+            self.scrollbar = Some(Scrollbar::from_str(scrollbar, options.no_unicode));
+        }
+
         self.keep_right = options.keep_right;
+        self.highlight_all = options.highlight_all;
         self.selector = options.selector.clone();
     }
 
@@ -127,19 +507,42 @@ impl Selection {
         self
     }
 
-    pub fn append_sorted_items(&mut self, items: Vec<MatchedItem>) {
-        debug!("append_sorted_items: num: {}", items.len());
+    pub fn set_theme(&mut self, theme: Arc<ColorTheme>) {
+        self.theme = theme;
+    }
+
+    /// prefer the items that appear later in the input when the ranks tie(`--tiebreak=-index`)
+    pub fn reverse_index(mut self, reverse_index: bool) -> Self {
+        Rc::make_mut(&mut self.items).reverse_index(reverse_index);
+        self
+    }
+
+    /// append the runs of matched items, each run should be sorted
+    pub fn append_sorted_items(&mut self, runs: Vec<Vec<MatchedItem>>) {
+        let num_items: usize = runs.iter().map(Vec::len).sum();
+        debug!("append_sorted_items: num: {}", num_items);
         let current_run_num = current_run_num();
-        if !items.is_empty() && current_run_num > self.latest_select_run_num {
+        if num_items > 0 && current_run_num > self.latest_select_run_num {
             self.latest_select_run_num = current_run_num;
             self.pre_selected_watermark = 0;
         }
 
         if self.items.len() >= self.pre_selected_watermark {
-            self.pre_select(&items);
+            for run in runs.iter() {
+                self.pre_select(run);
+            }
         }
 
-        self.items.append(items);
+        let follow_newest = self.follow && (self.items.is_empty() || self.newest_index() == Some(self.cursor()));
+        Rc::make_mut(&mut self.items).append_sorted_runs(runs);
+        if self.follow && self.nosort && num_items > 0 {
+            if follow_newest {
+                self.act_move_to_newest();
+            } else if self.tac {
+                // the new items go before the cursor, keep it on the same item
+                self.item_cursor += num_items;
+            }
+        }
         self.pre_selected_watermark = max(self.pre_selected_watermark, self.items.len());
 
         let height = self.height.load(Ordering::Relaxed);
@@ -154,8 +557,33 @@ impl Selection {
         }
     }
 
+    /// the generation of the item pool that the items to append come from
+    pub fn set_item_generation(&mut self, generation: usize) {
+        let cache = self.shapes.get_mut();
+        if cache.generation != generation {
+            cache.generation = generation;
+            cache.shapes.clear();
+        }
+    }
+
+    pub fn set_match_engine(&mut self, engine: Arc<dyn MatchEngine>) {
+        self.match_engine.replace(engine);
+    }
+
+    /// the query whose terms are highlighted with `--highlight-all`
+    pub fn set_highlight_query(&mut self, query: &str) {
+        if self.highlight_all {
+            self.highlight_terms = highlight_terms(query);
+        }
+    }
+
     pub fn clear(&mut self) {
-        self.items.clear();
+        match Rc::get_mut(&mut self.items) {
+            Some(items) => items.clear(),
+            // leave the items to the bulk selection
+            None => self.items = Rc::new(self.items.empty_like()),
+        }
+        self.shapes.get_mut().shapes.clear();
     }
 
     fn pre_select(&mut self, items: &[MatchedItem]) {
@@ -207,6 +635,56 @@ impl Selection {
         self.line_cursor = line_cursor as usize;
     }
 
+    fn cursor(&self) -> usize {
+        self.item_cursor + self.line_cursor
+    }
+
+    /// the index of the newest item when the items are in the input order(`--no-sort`)
+    fn newest_index(&self) -> Option<usize> {
+        if !self.nosort || self.items.is_empty() {
+            None
+        } else if self.tac {
+            Some(0)
+        } else {
+            Some(self.items.len() - 1)
+        }
+    }
+
+    pub fn act_move_to_newest(&mut self) {
+        if let Some(newest) = self.newest_index() {
+            let height = max(self.height.load(Ordering::Relaxed), 1);
+            self.item_cursor = max(newest + 1, height) - height;
+            self.line_cursor = newest - self.item_cursor;
+        }
+    }
+
+    pub fn act_toggle_follow(&mut self) {
+        self.follow = !self.follow;
+        if self.follow {
+            self.act_move_to_newest();
+        }
+    }
+
+    /// move the cursor to the `index`th item, which is placed at the top of the screen
+    pub fn act_set_cursor(&mut self, index: usize) {
+        self.item_cursor = min(index, max(self.items.len(), 1) - 1);
+        self.line_cursor = 0;
+    }
+
+    /// move the cursor to the `index`th item, the list is scrolled only if it is off the screen
+    pub fn act_move_cursor_to(&mut self, index: usize) {
+        let diff = index as i32 - self.cursor() as i32;
+        self.act_move_line_cursor(if self.reverse { -diff } else { diff });
+    }
+
+    /// scroll the list so that the `item_cursor`th item is the first one on the screen
+    pub fn act_scroll_to(&mut self, item_cursor: usize) {
+        let height = self.height.load(Ordering::Relaxed);
+        let item_len = self.items.len();
+        self.item_cursor = min(item_cursor, max(item_len, height) - height);
+        self.line_cursor = min(self.line_cursor, max(item_len - self.item_cursor, 1) - 1);
+    }
+
     pub fn act_select_screen_row(&mut self, rows_to_top: usize) {
         let height = self.height.load(Ordering::Relaxed);
         let diff = if self.reverse {
@@ -217,7 +695,6 @@ impl Selection {
         self.act_move_line_cursor(diff);
     }
 
-    #[allow(clippy::map_entry)]
     pub fn act_toggle(&mut self) {
         if !self.multi_selection || self.items.is_empty() {
             return;
@@ -228,31 +705,70 @@ impl Selection {
             .items
             .get(cursor)
             .unwrap_or_else(|| panic!("model:act_toggle: failed to get item {}", cursor));
-        let index = (current_run_num(), current_item.item_idx);
-        if !self.selected.contains_key(&index) {
-            self.selected.insert(index, current_item.item.clone());
+        let run_num = current_run_num();
+        let identity = ItemIdentity::of(current_item.item.as_ref(), run_num, current_item.item_idx);
+        let index = (run_num, current_item.item_idx);
+        let item = current_item.item.clone();
+        drop(current_item);
+        if self.selected.contains(&identity) {
+            self.selected.set(identity, index, &item, false);
         } else {
-            self.selected.remove(&index);
+            self.try_select(identity, index, &item);
         }
     }
 
-    #[allow(clippy::map_entry)]
     pub fn act_toggle_all(&mut self) {
         if !self.multi_selection || self.items.is_empty() {
             return;
         }
 
+        if self.multi_max.is_some() {
+            self.select_all_within_limit(true);
+        } else {
+            self.selected.select_all(&self.items, current_run_num(), true);
+        }
+    }
+
+    /// whether `--multi-max` items are selected
+    fn is_full(&self) -> bool {
+        self.multi_max.is_some_and(|max| self.selected.len() >= max)
+    }
+
+    /// select the item unless the selection is full, returns whether it is selected
+    fn try_select(&mut self, identity: ItemIdentity, index: ItemIndex, item: &Arc<dyn SkimItem>) -> bool {
+        if !self.selected.contains(&identity) && self.is_full() {
+            self.limit_hit = true;
+            return false;
+        }
+        self.selected.set(identity, index, item, true);
+        true
+    }
+
+    /// select(or toggle) the items one by one until the selection is full, instead of the bulk
+    /// selection that can't be cut at the limit
+    fn select_all_within_limit(&mut self, toggle: bool) {
         let run_num = current_run_num();
-        for current_item in self.items.iter() {
-            let index = (run_num, current_item.item_idx);
-            if !self.selected.contains_key(&index) {
-                self.selected.insert(index, current_item.item.clone());
-            } else {
-                self.selected.remove(&index);
+        let items = self.items.clone();
+        for matched in items.iter() {
+            let identity = ItemIdentity::of(matched.item.as_ref(), run_num, matched.item_idx);
+            let index = (run_num, matched.item_idx);
+            if toggle && self.selected.contains(&identity) {
+                self.selected.set(identity, index, &matched.item, false);
+            } else if !self.try_select(identity, index, &matched.item) && !toggle {
+                break;
             }
         }
     }
 
+    /// whether a selection was refused by `--multi-max` since the last call
+    pub fn take_limit_hit(&mut self) -> bool {
+        std::mem::take(&mut self.limit_hit)
+    }
+
+    pub fn get_multi_max(&self) -> Option<usize> {
+        self.multi_max
+    }
+
     pub fn act_select_matched(&mut self, run_num: u32, matched: MatchedItem) {
         self.act_select_raw_item(run_num, matched.item_idx, matched.item.clone());
     }
@@ -261,7 +777,8 @@ impl Selection {
         if !self.multi_selection {
             return;
         }
-        self.selected.insert((run_num, item_index), item);
+        let identity = ItemIdentity::of(item.as_ref(), run_num, item_index);
+        self.try_select(identity, (run_num, item_index), &item);
     }
 
     pub fn act_select_all(&mut self) {
@@ -269,10 +786,33 @@ impl Selection {
             return;
         }
 
+        if self.multi_max.is_some() {
+            self.select_all_within_limit(false);
+        } else {
+            self.selected.select_all(&self.items, current_run_num(), false);
+        }
+    }
+
+    /// select the items matching the new pattern, the items read later are pre-selected by it too
+    pub fn act_pre_select_pat(&mut self, pattern: &str) {
+        match self.selector.as_ref() {
+            Some(selector) => selector.set_pattern(pattern),
+            None => self.selector = Some(Arc::new(DefaultSkimSelector::default().regex(pattern))),
+        }
+
+        if !self.multi_selection {
+            return;
+        }
+
         let run_num = current_run_num();
-        for current_item in self.items.iter() {
-            let item = current_item.item.clone();
-            self.selected.insert((run_num, current_item.item_idx), item);
+        let selector = self.selector.clone().unwrap();
+        let items = self.items.clone();
+        for current_item in items.iter() {
+            let item = &current_item.item;
+            if selector.should_select(current_item.item_idx as usize, item.as_ref()) {
+                let identity = ItemIdentity::of(item.as_ref(), run_num, current_item.item_idx);
+                self.try_select(identity, (run_num, current_item.item_idx), item);
+            }
         }
     }
 
@@ -280,6 +820,38 @@ impl Selection {
         self.selected.clear();
     }
 
+    /// (de)select the matched items whose text matches the regex, an invalid regex does nothing
+    pub fn act_select_regex(&mut self, pattern: &str, select: bool) {
+        if !self.multi_selection {
+            return;
+        }
+
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(err) => {
+                debug!("invalid regex to select: {}", err);
+                return;
+            }
+        };
+
+        let run_num = current_run_num();
+        let items = self.items.clone();
+        for current_item in items.iter() {
+            let item = &current_item.item;
+            if !regex.is_match(&item.text()) {
+                continue;
+            }
+
+            let identity = ItemIdentity::of(item.as_ref(), run_num, current_item.item_idx);
+            let index = (run_num, current_item.item_idx);
+            if select {
+                self.try_select(identity, index, item);
+            } else {
+                self.selected.set(identity, index, item, false);
+            }
+        }
+    }
+
     pub fn act_scroll(&mut self, offset: i32) {
         self.hscroll_offset += offset as i64;
     }
@@ -287,8 +859,9 @@ impl Selection {
     pub fn get_selected_indices_and_items(&self) -> (Vec<usize>, Vec<Arc<dyn SkimItem>>) {
         // select the current one
         let select_cursor = !self.multi_selection || self.selected.is_empty();
-        let mut selected: Vec<Arc<dyn SkimItem>> = self.selected.values().cloned().collect();
-        let mut item_indices: Vec<usize> = self.selected.keys().map(|(_run, idx)| *idx as usize).collect();
+        let sorted = self.selected.sorted_items();
+        let mut item_indices: Vec<usize> = sorted.iter().map(|(index, _)| index.1 as usize).collect();
+        let mut selected: Vec<Arc<dyn SkimItem>> = sorted.into_iter().map(|(_, item)| item).collect();
 
         if select_cursor && !self.items.is_empty() {
             let cursor = self.item_cursor + self.line_cursor;
@@ -304,6 +877,20 @@ impl Selection {
         (item_indices, selected)
     }
 
+    /// The items marked as selected, excluding the current item.
+    pub fn get_selected_items(&self) -> Vec<Arc<dyn SkimItem>> {
+        self.selected.sorted_items().into_iter().map(|(_, item)| item).collect()
+    }
+
+    pub fn is_selected(&self, identity: &ItemIdentity) -> bool {
+        self.selected.contains(identity)
+    }
+
+    /// changes whenever the selected items change
+    pub fn selection_version(&self) -> u64 {
+        self.selected.version
+    }
+
     pub fn get_num_of_selected_exclude_current(&self) -> usize {
         self.selected.len()
     }
@@ -325,6 +912,36 @@ impl Selection {
         self.items.get(item_idx).map(|item| item.item.clone())
     }
 
+    /// identifies the current item across the queries
+    pub fn get_current_item_identity(&self) -> Option<ItemIdentity> {
+        let item_idx = self.get_current_item_idx();
+        self.items
+            .get(item_idx)
+            .map(|matched| ItemIdentity::of(matched.item.as_ref(), current_run_num(), matched.item_idx))
+    }
+
+    /// the rank values of the current item, for the `{score}` placeholders
+    pub fn get_current_rank_values(&self, rank_builder: &RankBuilder) -> Option<RankValues> {
+        let item_idx = self.get_current_item_idx();
+        self.items.get(item_idx).map(|item| item.rank_values(rank_builder))
+    }
+
+    /// the items visible in the last drawn screen
+    pub fn viewport(&self) -> Viewport {
+        let height = self.height.load(Ordering::Relaxed);
+        let start = min(self.item_cursor, self.items.len());
+        let end = min(self.item_cursor + height, self.items.len());
+        Viewport {
+            start,
+            end,
+            cursor: self.get_current_item_idx(),
+            items: (start..end)
+                .filter_map(|idx| self.items.get(idx))
+                .map(|item| item.item.clone())
+                .collect(),
+        }
+    }
+
     pub fn get_hscroll_offset(&self) -> i64 {
         self.hscroll_offset
     }
@@ -333,6 +950,87 @@ impl Selection {
         self.items.len()
     }
 
+    /// the indices of the matched characters together with the occurrences of the query terms
+    /// inside the matching ranges of the item(`SkimItem::get_matching_ranges`, e.g. `--nth`)
+    fn highlight_indices(
+        &self,
+        text: &str,
+        matching_ranges: Option<&[(usize, usize)]>,
+        matched_range: Option<&MatchRange>,
+    ) -> Vec<usize> {
+        let mut indices = match matched_range {
+            Some(MatchRange::Chars(matched_indices)) => matched_indices.clone(),
+            Some(&MatchRange::ByteRange(start, end)) => {
+                let (start, end) = char_boundary_range(text, start, end);
+                let start_char = text[..start].chars().count();
+                (start_char..start_char + text[start..end].chars().count()).collect()
+            }
+            None => Vec::new(),
+        };
+
+        // lowercase char by char to keep the indices of the characters
+        let chars: Vec<char> = text.chars().map(|ch| ch.to_lowercase().next().unwrap_or(ch)).collect();
+        let char_ranges: Vec<(usize, usize)> = match matching_ranges {
+            Some(ranges) => ranges
+                .iter()
+                .map(|&(start, end)| (byte_to_char_index(text, start), byte_to_char_index(text, end)))
+                .collect(),
+            None => vec![(0, chars.len())],
+        };
+        for term in self.highlight_terms.iter() {
+            for &(range_start, range_end) in char_ranges.iter() {
+                for start in range_start..(range_end + 1).saturating_sub(term.len()) {
+                    if chars[start..start + term.len()] == term[..] {
+                        indices.extend(start..start + term.len());
+                    }
+                }
+            }
+        }
+
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    /// the `(shift, full_width)` to print the item text, cached until the items or the width change
+    fn shape_item(
+        &self,
+        item_idx: u32,
+        text: &str,
+        container_width: usize,
+        match_start_char: usize,
+        match_end_char: usize,
+    ) -> (usize, usize) {
+        let mut cache = self.shapes.borrow_mut();
+        if cache.container_width != container_width || cache.shapes.len() >= MAX_CACHED_SHAPES {
+            cache.container_width = container_width;
+            cache.shapes.clear();
+        }
+
+        let key = (item_idx, match_start_char, match_end_char);
+        if let Some(&shape) = cache.shapes.get(&key) {
+            return shape;
+        }
+
+        let (shift, full_width) = reshape_string(text, container_width, match_start_char, match_end_char, self.tabstop);
+
+        let shift = if self.no_hscroll {
+            0
+        } else if match_start_char == 0 && match_end_char == 0 {
+            // no match
+            if self.keep_right {
+                max(full_width, container_width) - container_width
+            } else {
+                self.calc_skip_width(text)
+            }
+        } else {
+            shift
+        };
+
+        cache.shapes.insert(key, (shift, full_width));
+        (shift, full_width)
+    }
+
     fn calc_skip_width(&self, text: &str) -> usize {
         let skip = if self.skip_to_pattern.is_none() {
             0
@@ -346,6 +1044,28 @@ impl Selection {
         };
         max(2, skip) - 2
     }
+
+    /// the width left for the items, the last column is taken by the scrollbar
+    fn list_width(&self, screen_width: usize) -> usize {
+        if self.scrollbar.is_some() {
+            screen_width.saturating_sub(1)
+        } else {
+            screen_width
+        }
+    }
+
+    /// the number to show in the gutter, `line` is the line No. from screen start
+    fn line_number(&self, matched_item: &MatchedItem, line: usize) -> Option<usize> {
+        let absolute = matched_item.item_idx as usize + self.line_number_offset + 1;
+        let relative = max(line, self.line_cursor) - min(line, self.line_cursor);
+        match self.line_numbers {
+            LineNumbers::Hidden => None,
+            LineNumbers::Absolute => Some(absolute),
+            LineNumbers::Relative => Some(relative),
+            LineNumbers::Both if line == self.line_cursor => Some(absolute),
+            LineNumbers::Both => Some(relative),
+        }
+    }
 }
 
 impl EventHandler for Selection {
@@ -364,12 +1084,24 @@ impl EventHandler for Selection {
             EvActToggleAll => {
                 self.act_toggle_all();
             }
+            EvActToggleFollow => {
+                self.act_toggle_follow();
+            }
             EvActSelectAll => {
                 self.act_select_all();
             }
             EvActDeselectAll => {
                 self.act_deselect_all();
             }
+            EvActSelect(pattern) => {
+                self.act_select_regex(pattern, true);
+            }
+            EvActDeselect(pattern) => {
+                self.act_select_regex(pattern, false);
+            }
+            EvActPreSelectPat(pattern) => {
+                self.act_pre_select_pat(pattern);
+            }
             EvActHalfPageDown(diff) => {
                 let height = 1 - (self.height.load(Ordering::Relaxed) as i32);
                 self.act_move_line_cursor(height * *diff / 2);
@@ -389,6 +1121,9 @@ impl EventHandler for Selection {
             EvActSelectRow(row) => {
                 self.act_select_screen_row(*row);
             }
+            EvActScrollTo(item_cursor) => {
+                self.act_scroll_to(*item_cursor);
+            }
             EvActScrollLeft(diff) => {
                 self.act_scroll(-*diff);
             }
@@ -402,14 +1137,31 @@ impl EventHandler for Selection {
 }
 
 impl Selection {
+    /// the icon provided by the item itself, or the one configured for its extension
+    fn item_icon(&self, item: &dyn SkimItem) -> Option<ItemIcon> {
+        item.icon().or_else(|| {
+            if self.icons.is_empty() {
+                None
+            } else {
+                self.icons.lookup(&item.text())
+            }
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn draw_item(
         &self,
         canvas: &mut dyn Canvas,
         row: usize,
         matched_item: &MatchedItem,
         is_current: bool,
+        line_number: Option<usize>,
+        gutter_width: usize,
+        icon: Option<&ItemIcon>,
+        icon_width: usize,
     ) -> DrawResult<()> {
         let (screen_width, screen_height) = canvas.size()?;
+        let screen_width = self.list_width(screen_width);
 
         // update item heights
         self.height.store(screen_height, Ordering::Relaxed);
@@ -431,20 +1183,50 @@ impl Selection {
         };
 
         // print selection cursor
-        let index = (current_run_num(), matched_item.item_idx);
-        if self.selected.contains_key(&index) {
+        let identity = ItemIdentity::of(matched_item.item.as_ref(), current_run_num(), matched_item.item_idx);
+        if self.is_selected(&identity) {
             let _ = canvas.print_with_attr(row, 1, ">", default_attr.extend(self.theme.selected()));
         } else {
             let _ = canvas.print_with_attr(row, 1, " ", default_attr);
         }
 
+        // print line number, right aligned with a trailing space
+        if let Some(line_number) = line_number {
+            let label = format!("{:>width$} ", line_number, width = gutter_width - 1);
+            let _ = canvas.print_with_attr(row, 2, &label, default_attr.extend(self.theme.info()));
+        }
+
+        // print the icon, the column is padded with a trailing space
+        if let Some(icon) = icon.filter(|_| icon_width > 0) {
+            let _ = canvas.print_with_attr(row, 2 + gutter_width, &icon.glyph, default_attr.extend(icon.attr));
+        }
+
         let item = &matched_item.item;
         let item_text = item.text();
-        let container_width = screen_width - 2;
+        let text_col = 2 + gutter_width + icon_width;
+        let container_width = screen_width - text_col;
+
+        let rematched_range;
+        let matched_range = match matched_item.matched_range.as_deref() {
+            Some(matched_range) => Some(matched_range),
+            None => {
+                rematched_range = self
+                    .match_engine
+                    .as_ref()
+                    .and_then(|engine| engine.match_item(item.clone()))
+                    .map(|result| result.matched_range);
+                rematched_range.as_ref()
+            }
+        };
 
-        let matches = match matched_item.matched_range {
-            Some(MatchRange::Chars(ref matched_indices)) => Matches::CharIndices(matched_indices),
-            Some(MatchRange::ByteRange(start, end)) => Matches::ByteRange(start, end),
+        let all_indices;
+        let matches = match matched_range {
+            _ if !self.highlight_terms.is_empty() => {
+                all_indices = self.highlight_indices(&item_text, item.get_matching_ranges(), matched_range);
+                Matches::CharIndices(&all_indices)
+            }
+            Some(MatchRange::Chars(matched_indices)) => Matches::CharIndices(matched_indices),
+            Some(&MatchRange::ByteRange(start, end)) => Matches::ByteRange(start, end),
             _ => Matches::None,
         };
 
@@ -456,19 +1238,30 @@ impl Selection {
             highlight_attr: matched_attr,
         };
 
-        let display_content = item.display(context);
+        let mut display_content = item.display(context);
+        if self.theme.keep_ansi_bg() {
+            // the item displayed without the highlight has the backgrounds of the ANSI text
+            let original = item.display(DisplayContext {
+                text: &item_text,
+                score: 0,
+                matches: Matches::None,
+                container_width,
+                highlight_attr: matched_attr,
+            });
+            display_content.keep_backgrounds_of(&original);
+        }
 
         let mut printer = if display_content.stripped() == item_text {
             // need to display the match content
-            let (match_start_char, match_end_char) = match matched_item.matched_range {
-                Some(MatchRange::Chars(ref matched_indices)) => {
+            let (match_start_char, match_end_char) = match matched_range {
+                Some(MatchRange::Chars(matched_indices)) => {
                     if !matched_indices.is_empty() {
                         (matched_indices[0], matched_indices[matched_indices.len() - 1] + 1)
                     } else {
                         (0, 0)
                     }
                 }
-                Some(MatchRange::ByteRange(match_start, match_end)) => {
+                Some(&MatchRange::ByteRange(match_start, match_end)) => {
                     let match_start_char = item_text[..match_start].chars().count();
                     let diff = item_text[match_start..match_end].chars().count();
                     (match_start_char, match_start_char + diff)
@@ -476,30 +1269,17 @@ impl Selection {
                 None => (0, 0),
             };
 
-            let (shift, full_width) = reshape_string(
+            let (shift, full_width) = self.shape_item(
+                matched_item.item_idx,
                 &item_text,
                 container_width,
                 match_start_char,
                 match_end_char,
-                self.tabstop,
             );
 
-            let shift = if self.no_hscroll {
-                0
-            } else if match_start_char == 0 && match_end_char == 0 {
-                // no match
-                if self.keep_right {
-                    max(full_width, container_width) - container_width
-                } else {
-                    self.calc_skip_width(&item_text)
-                }
-            } else {
-                shift
-            };
-
             LinePrinter::builder()
                 .row(row)
-                .col(2)
+                .col(text_col)
                 .tabstop(self.tabstop)
                 .container_width(container_width)
                 .shift(shift)
@@ -509,7 +1289,7 @@ impl Selection {
         } else {
             LinePrinter::builder()
                 .row(row)
-                .col(2)
+                .col(text_col)
                 .tabstop(self.tabstop)
                 .container_width(container_width)
                 .text_width(display_content.stripped().width_cjk())
@@ -524,10 +1304,30 @@ impl Selection {
     }
 }
 
+/// the terms of the query to highlight, without the modifiers of exact matching, the inverse
+/// terms are skipped
+fn highlight_terms(query: &str) -> Vec<Vec<char>> {
+    query
+        .split_whitespace()
+        .filter(|&term| term != "|")
+        .filter_map(|term| {
+            let term = term.trim_start_matches('\'');
+            if term.starts_with('!') {
+                return None;
+            }
+            let term = term.trim_start_matches('^');
+            let term = term.strip_suffix('$').unwrap_or(term);
+            let term: Vec<char> = term.chars().map(|ch| ch.to_lowercase().next().unwrap_or(ch)).collect();
+            Some(term).filter(|term| !term.is_empty())
+        })
+        .collect()
+}
+
 impl Draw for Selection {
     fn draw(&self, canvas: &mut dyn Canvas) -> DrawResult<()> {
-        let (_screen_width, screen_height) = canvas.size()?;
+        let (screen_width, screen_height) = canvas.size()?;
         canvas.clear()?;
+        let list_width = self.list_width(screen_width);
 
         let item_idx_lower = self.item_cursor;
         let max_upper = self.item_cursor + screen_height;
@@ -535,6 +1335,31 @@ impl Draw for Selection {
 
         clear_canvas(canvas)?;
 
+        // the gutter is as wide as the largest visible line number, hidden if there is no room
+        let gutter_width = (item_idx_lower..item_idx_upper)
+            .filter_map(|item_idx| {
+                let item = self.items.get(item_idx)?;
+                self.line_number(&item, item_idx - item_idx_lower)
+            })
+            .max()
+            .map(|num| num.to_string().len() + 1)
+            .filter(|&width| list_width >= width + 3)
+            .unwrap_or(0);
+
+        let icons: Vec<Option<ItemIcon>> = (item_idx_lower..item_idx_upper)
+            .map(|item_idx| self.item_icon(self.items.get(item_idx)?.item.as_ref()))
+            .collect();
+        // the icon column is as wide as the widest visible icon, hidden if there is no room
+        let icon_width = icons
+            .iter()
+            .flatten()
+            .map(|icon| icon.glyph.width_cjk())
+            .max()
+            .filter(|&width| width > 0)
+            .map(|width| width + 1)
+            .filter(|&width| list_width >= gutter_width + width + 3)
+            .unwrap_or(0);
+
         for item_idx in item_idx_lower..item_idx_upper {
             let line_cursor = item_idx - item_idx_lower;
             let line_no = if self.reverse {
@@ -554,7 +1379,35 @@ impl Draw for Selection {
                 .get(item_idx)
                 .unwrap_or_else(|| panic!("model:draw_items: failed to get item at {}", item_idx));
 
-            let _ = self.draw_item(canvas, line_no, &item, line_cursor == self.line_cursor);
+            let line_number = if gutter_width > 0 {
+                self.line_number(&item, line_cursor)
+            } else {
+                None
+            };
+            let _ = self.draw_item(
+                canvas,
+                line_no,
+                &item,
+                line_cursor == self.line_cursor,
+                line_number,
+                gutter_width,
+                icons[line_cursor].as_ref(),
+                icon_width,
+            );
+        }
+
+        if let Some(scrollbar) = self.scrollbar.filter(|_| screen_width > list_width) {
+            if let Some((thumb_start, thumb_len)) = scrollbar_thumb(self.items.len(), screen_height, self.item_cursor) {
+                for line in 0..screen_height {
+                    let row = if self.reverse { line } else { screen_height - 1 - line };
+                    let ch = if line >= thumb_start && line < thumb_start + thumb_len {
+                        scrollbar.thumb
+                    } else {
+                        scrollbar.track
+                    };
+                    let _ = canvas.put_char_with_attr(row, list_width, ch, self.theme.scrollbar());
+                }
+            }
         }
 
         Ok(())
@@ -562,9 +1415,27 @@ impl Draw for Selection {
 }
 
 impl Widget<Event> for Selection {
-    fn on_event(&self, event: TermEvent, _rect: Rectangle) -> Vec<Event> {
+    fn on_event(&self, event: TermEvent, rect: Rectangle) -> Vec<Event> {
         let mut ret = vec![];
+        let on_scrollbar = |col: u16| self.scrollbar.is_some() && col as usize + 1 == rect.width;
+        let scroll_to = |row: u16| {
+            let line = if self.reverse {
+                row as usize
+            } else {
+                rect.height.saturating_sub(row as usize + 1)
+            };
+            Event::EvActScrollTo(scrollbar_cursor(self.items.len(), rect.height, line))
+        };
         match event {
+            TermEvent::Key(Key::SingleClick(MouseButton::Left, row, col)) if on_scrollbar(col) => {
+                self.dragging_scrollbar.store(true, Ordering::Relaxed);
+                ret.push(scroll_to(row));
+            }
+            TermEvent::Key(Key::MouseHold(row, _)) if self.dragging_scrollbar.load(Ordering::Relaxed) => {
+                ret.push(scroll_to(row));
+            }
+            TermEvent::Key(Key::MouseRelease(..)) => self.dragging_scrollbar.store(false, Ordering::Relaxed),
+            TermEvent::Key(Key::DoubleClick(MouseButton::Left, _, col)) if on_scrollbar(col) => {}
             TermEvent::Key(Key::WheelUp(.., count)) => ret.push(Event::EvActUp(count as i32)),
             TermEvent::Key(Key::WheelDown(.., count)) => ret.push(Event::EvActDown(count as i32)),
             TermEvent::Key(Key::SingleClick(MouseButton::Left, row, _)) => {
@@ -580,3 +1451,343 @@ impl Widget<Event> for Selection {
         ret
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::SkimOptionsBuilder;
+
+    #[test]
+    fn test_scrollbar_thumb() {
+        assert_eq!(None, scrollbar_thumb(10, 10, 0));
+        assert_eq!(Some((0, 5)), scrollbar_thumb(20, 10, 0));
+        assert_eq!(Some((5, 5)), scrollbar_thumb(20, 10, 10));
+        assert_eq!(Some((9, 1)), scrollbar_thumb(1000, 10, 990));
+        assert_eq!(Some((9, 1)), scrollbar_thumb(1000, 10, 2000));
+
+        assert_eq!(0, scrollbar_cursor(20, 10, 0));
+        assert_eq!(10, scrollbar_cursor(20, 10, 9));
+        assert_eq!(0, scrollbar_cursor(10, 10, 9));
+        assert_eq!(990, scrollbar_cursor(1000, 10, 9));
+        assert_eq!(scrollbar_thumb(1000, 10, scrollbar_cursor(1000, 10, 4)), Some((4, 1)));
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        assert_eq!(LineNumbers::from_str("Both"), Ok(LineNumbers::Both));
+        assert!(LineNumbers::from_str("absolut").is_err());
+        assert!(check_line_numbers("").is_err());
+
+        // refused by the builder instead of hiding the line numbers
+        let options = SkimOptionsBuilder::default().line_numbers(Some("absolut")).build();
+        assert!(options.is_err());
+        let options = SkimOptionsBuilder::default().line_numbers(Some("relative")).build();
+        assert!(options.is_ok());
+    }
+
+    #[test]
+    fn test_highlight_indices() {
+        let terms: Vec<String> = highlight_terms("'Ab | c$ !d ^e")
+            .iter()
+            .map(|t| t.iter().collect())
+            .collect();
+        assert_eq!(terms, vec!["ab", "c", "e"]);
+
+        let mut selection = Selection::new();
+        selection.highlight_all = true;
+        selection.set_highlight_query("ab c");
+        assert_eq!(selection.highlight_indices("xAbyab", None, None), vec![1, 2, 4, 5]);
+        let matched = MatchRange::Chars(vec![0, 1]);
+        assert_eq!(
+            selection.highlight_indices("xabc", None, Some(&matched)),
+            vec![0, 1, 2, 3]
+        );
+        let matched = MatchRange::ByteRange(0, 2);
+        assert_eq!(selection.highlight_indices("éxc", None, Some(&matched)), vec![0, 2]);
+        // off the char boundaries
+        let matched = MatchRange::ByteRange(1, 5);
+        assert_eq!(selection.highlight_indices("éxé", None, Some(&matched)), vec![0, 1, 2]);
+
+        // only the occurrences inside the matching ranges(e.g. `--nth=2,4`)
+        let ranges = [(3, 6), (10, 12)];
+        assert_eq!(
+            selection.highlight_indices("ab,xab,ab,c", Some(&ranges), None),
+            vec![4, 5, 10]
+        );
+        let ranges = [(3, 7)];
+        assert_eq!(selection.highlight_indices("中,ab", Some(&ranges), None), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_move_cursor_to() {
+        let mut selection = Selection::new();
+        selection.height.store(3, Ordering::Relaxed);
+        let items = (0..5)
+            .map(|idx| MatchedItem {
+                item: Arc::new(idx.to_string()),
+                rank: [0, 0, 0, idx],
+                matched_range: None,
+                item_idx: idx as u32,
+            })
+            .collect();
+        selection.append_sorted_items(vec![items]);
+
+        selection.act_move_cursor_to(1);
+        assert_eq!((selection.item_cursor, selection.line_cursor), (0, 1));
+        selection.act_move_cursor_to(4);
+        assert_eq!((selection.item_cursor, selection.line_cursor), (2, 2));
+        selection.act_move_cursor_to(0);
+        assert_eq!((selection.item_cursor, selection.line_cursor), (0, 0));
+
+        selection.reverse = true;
+        selection.act_move_cursor_to(3);
+        assert_eq!(selection.get_current_item_idx(), 3);
+    }
+
+    #[test]
+    fn test_select_regex() {
+        let mut selection = Selection::new();
+        selection.multi_selection = true;
+        let items = ["a.rs", "b.rs", "c.md", "d.rs"]
+            .iter()
+            .enumerate()
+            .map(|(idx, text)| MatchedItem {
+                item: Arc::new(text.to_string()),
+                rank: [0, 0, 0, idx as i32],
+                matched_range: None,
+                item_idx: idx as u32,
+            })
+            .collect();
+        selection.append_sorted_items(vec![items]);
+
+        let selected = |selection: &Selection| -> Vec<String> {
+            let mut texts: Vec<String> = selection
+                .get_selected_items()
+                .iter()
+                .map(|item| item.text().into_owned())
+                .collect();
+            texts.sort();
+            texts
+        };
+
+        selection.act_select_regex(r"\.rs$", true);
+        assert_eq!(selected(&selection), vec!["a.rs", "b.rs", "d.rs"]);
+        selection.act_select_regex("^[ab]", false);
+        assert_eq!(selected(&selection), vec!["d.rs"]);
+        selection.act_select_regex("(", true);
+        assert_eq!(selected(&selection), vec!["d.rs"]);
+    }
+
+    #[test]
+    fn test_multi_max() {
+        let items = (0..4)
+            .map(|idx| MatchedItem {
+                item: Arc::new(format!("item{}", idx)),
+                rank: [0, 0, 0, idx as i32],
+                matched_range: None,
+                item_idx: idx,
+            })
+            .collect();
+        let selected = |selection: &Selection| -> Vec<String> {
+            let items = selection.get_selected_items();
+            items.iter().map(|item| item.text().into_owned()).collect()
+        };
+
+        let mut selection = Selection::new();
+        selection.multi_selection = true;
+        selection.multi_max = Some(2);
+        selection.append_sorted_items(vec![items]);
+        selection.act_toggle();
+        assert!(!selection.take_limit_hit());
+        selection.act_select_all();
+        assert_eq!(selected(&selection), vec!["item0", "item1"]);
+        assert!(selection.take_limit_hit());
+        assert!(!selection.take_limit_hit());
+
+        // deselecting is never refused
+        selection.act_toggle();
+        assert!(!selection.take_limit_hit());
+        selection.act_toggle_all();
+        assert_eq!(selected(&selection), vec!["item0", "item2"]);
+        assert!(selection.take_limit_hit());
+
+        selection.act_select_regex("item3", true);
+        assert!(selection.take_limit_hit());
+        selection.act_select_regex("item", false);
+        assert!(selected(&selection).is_empty());
+    }
+
+    #[test]
+    fn test_bulk_selection() {
+        let matched = |indices: &[u32]| -> Vec<Vec<MatchedItem>> {
+            let items = indices
+                .iter()
+                .map(|&idx| MatchedItem {
+                    item: Arc::new(format!("item{}", idx)),
+                    rank: [0, 0, 0, idx as i32],
+                    matched_range: None,
+                    item_idx: idx,
+                })
+                .collect();
+            vec![items]
+        };
+        let selected = |selection: &Selection| -> Vec<String> {
+            let items = selection.get_selected_items();
+            assert_eq!(items.len(), selection.get_num_selected());
+            items.iter().map(|item| item.text().into_owned()).collect()
+        };
+
+        let mut selection = Selection::new();
+        selection.multi_selection = true;
+        selection.append_sorted_items(matched(&[0, 1, 2, 3]));
+        selection.act_toggle();
+        selection.act_toggle_all();
+        assert_eq!(selected(&selection), vec!["item1", "item2", "item3"]);
+        selection.act_toggle_all();
+        assert_eq!(selected(&selection), vec!["item0"]);
+        selection.act_toggle();
+        selection.act_toggle_all();
+        assert_eq!(selected(&selection), vec!["item0", "item1", "item2", "item3"]);
+        selection.act_toggle();
+        selection.act_select_all();
+        assert_eq!(selected(&selection).len(), 4);
+
+        // the items of the previous bulk selection stay selected after the query changes
+        selection.act_toggle();
+        selection.clear();
+        selection.append_sorted_items(matched(&[0, 4, 5]));
+        assert!(selection.is_selected(&ItemIdentity::Index(current_run_num(), 1)));
+        assert!(!selection.is_selected(&ItemIdentity::Index(current_run_num(), 0)));
+        selection.act_toggle_all();
+        assert_eq!(
+            selected(&selection),
+            vec!["item0", "item1", "item2", "item3", "item4", "item5"]
+        );
+        selection.act_toggle();
+        assert_eq!(selected(&selection).len(), 5);
+
+        selection.act_deselect_all();
+        assert!(selected(&selection).is_empty());
+    }
+
+    #[test]
+    fn test_selection_version() {
+        let items = (0..3)
+            .map(|idx| MatchedItem {
+                item: Arc::new(format!("item{}", idx)),
+                rank: [0, 0, 0, idx],
+                matched_range: None,
+                item_idx: idx as u32,
+            })
+            .collect();
+        let mut selection = Selection::new();
+        selection.multi_selection = true;
+        selection.multi_max = Some(1);
+        selection.append_sorted_items(vec![items]);
+
+        let version = selection.selection_version();
+        selection.act_toggle();
+        assert_ne!(selection.selection_version(), version);
+
+        // refused by `--multi-max`
+        let version = selection.selection_version();
+        selection.act_move_line_cursor(1);
+        selection.act_toggle();
+        assert!(selection.take_limit_hit());
+        assert_eq!(selection.selection_version(), version);
+
+        selection.act_deselect_all();
+        assert_ne!(selection.selection_version(), version);
+        let version = selection.selection_version();
+        selection.act_deselect_all();
+        assert_eq!(selection.selection_version(), version);
+    }
+
+    #[test]
+    fn test_follow() {
+        let matched = |texts: &[&str], start: usize| -> Vec<Vec<MatchedItem>> {
+            let items = texts
+                .iter()
+                .enumerate()
+                .map(|(idx, text)| MatchedItem {
+                    item: Arc::new(text.to_string()),
+                    rank: [0; 4],
+                    matched_range: None,
+                    item_idx: (start + idx) as u32,
+                })
+                .collect();
+            vec![items]
+        };
+        let current = |selection: &Selection| selection.get_current_item().unwrap().text().into_owned();
+
+        let mut selection = Selection::new();
+        selection.nosort = true;
+        selection.tac = true;
+        Rc::make_mut(&mut selection.items).nosort(true).tac(true);
+        selection.follow = true;
+        selection.height.store(3, Ordering::Relaxed);
+
+        selection.append_sorted_items(matched(&["1", "2", "3"], 0));
+        assert_eq!(current(&selection), "3");
+        selection.append_sorted_items(matched(&["4", "5"], 3));
+        assert_eq!(current(&selection), "5");
+
+        // scrolled away, the cursor stays on the item
+        selection.act_move_line_cursor(2);
+        assert_eq!(current(&selection), "3");
+        selection.append_sorted_items(matched(&["6", "7", "8"], 5));
+        assert_eq!(current(&selection), "3");
+
+        selection.act_toggle_follow();
+        selection.act_toggle_follow();
+        assert_eq!(current(&selection), "8");
+
+        // the newest item is the last one without `--tac`
+        let mut selection = Selection::new();
+        selection.nosort = true;
+        Rc::make_mut(&mut selection.items).nosort(true);
+        selection.follow = true;
+        selection.height.store(3, Ordering::Relaxed);
+
+        selection.append_sorted_items(matched(&["1", "2", "3", "4"], 0));
+        assert_eq!(current(&selection), "4");
+        selection.append_sorted_items(matched(&["5"], 4));
+        assert_eq!(current(&selection), "5");
+        selection.act_move_line_cursor(-1);
+        selection.append_sorted_items(matched(&["6"], 5));
+        assert_eq!(current(&selection), "4");
+    }
+
+    #[test]
+    fn test_shape_cache() {
+        let text = "0123456789abcdef";
+        let mut selection = Selection::new();
+        assert_eq!(
+            selection.shape_item(0, text, 8, 12, 14),
+            reshape_string(text, 8, 12, 14, 8)
+        );
+        assert_eq!(selection.shapes.borrow().shapes.len(), 1);
+
+        // cached per item and highlighted range
+        selection.shape_item(0, text, 8, 12, 14);
+        selection.shape_item(1, text, 8, 12, 14);
+        selection.shape_item(1, text, 8, 0, 0);
+        assert_eq!(selection.shapes.borrow().shapes.len(), 3);
+
+        // resized
+        assert_eq!(
+            selection.shape_item(0, text, 6, 12, 14),
+            reshape_string(text, 6, 12, 14, 8)
+        );
+        assert_eq!(selection.shapes.borrow().shapes.len(), 1);
+
+        selection.set_item_generation(0);
+        assert_eq!(selection.shapes.borrow().shapes.len(), 1);
+        selection.set_item_generation(1);
+        assert!(selection.shapes.borrow().shapes.is_empty());
+
+        selection.shape_item(0, text, 6, 12, 14);
+        selection.clear();
+        assert!(selection.shapes.borrow().shapes.is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 ///! Handle the color theme
 use crate::options::SkimOptions;
+use std::fs;
 use tuikit::prelude::*;
 
 #[rustfmt::skip]
@@ -43,14 +44,21 @@ pub struct ColorTheme {
     selected:             Color,
     header:               Color,
     border:               Color,
+    scrollbar:            Color,
+    // the highlight of matches keeps the background colors of the ANSI input(`ansi_bg:keep`)
+    keep_ansi_bg:         bool,
 }
 
 #[rustfmt::skip]
 #[allow(dead_code)]
 impl ColorTheme {
     pub fn init_from_options(options: &SkimOptions) -> ColorTheme {
-        // register
-        if let Some(color) = options.color {
+        ColorTheme::from_color(options.color)
+    }
+
+    /// build the theme from the `--color` spec, the files(`@FILE`) in it are (re-)read
+    pub fn from_color(color: Option<&str>) -> ColorTheme {
+        if let Some(color) = color {
             ColorTheme::from_options(color)
         } else {
             ColorTheme::dark256()
@@ -81,6 +89,8 @@ impl ColorTheme {
             selected:             Color::Default,
             header:               Color::Default,
             border:               Color::Default,
+            scrollbar:            Color::Default,
+            keep_ansi_bg:         false,
         }
     }
 
@@ -108,6 +118,7 @@ impl ColorTheme {
             selected:         Color::MAGENTA,
             header:           Color::CYAN,
             border:           Color::LIGHT_BLACK,
+            scrollbar:        Color::LIGHT_BLACK,
             ..ColorTheme::empty()
         }
     }
@@ -127,6 +138,7 @@ impl ColorTheme {
             selected:         Color::AnsiValue(168),
             header:           Color::AnsiValue(109),
             border:           Color::AnsiValue(59),
+            scrollbar:        Color::AnsiValue(59),
             ..ColorTheme::empty()
         }
     }
@@ -146,6 +158,7 @@ impl ColorTheme {
             selected:         Color::AnsiValue(168),
             header:           Color::AnsiValue(109),
             border:           Color::AnsiValue(59),
+            scrollbar:        Color::AnsiValue(59),
             ..ColorTheme::empty()
         }
     }
@@ -165,6 +178,7 @@ impl ColorTheme {
             selected:         Color::AnsiValue(168),
             header:           Color::AnsiValue(31),
             border:           Color::AnsiValue(145),
+            scrollbar:        Color::AnsiValue(145),
             ..ColorTheme::empty()
         }
     }
@@ -172,7 +186,7 @@ impl ColorTheme {
     #[allow(clippy::wildcard_in_or_patterns)]
     fn from_options(color: &str) -> Self {
         let mut theme = ColorTheme::dark256();
-        for pair in color.split(',') {
+        for pair in expand_color_files(color) {
             let color: Vec<&str> = pair.split(':').collect();
             if color.len() < 2 {
                 theme = match color[0] {
@@ -186,17 +200,12 @@ impl ColorTheme {
                 continue;
             }
 
-            let new_color = if color[1].len() == 7 {
-                // 256 color
-                let r = u8::from_str_radix(&color[1][1..3], 16).unwrap_or(255);
-                let g = u8::from_str_radix(&color[1][3..5], 16).unwrap_or(255);
-                let b = u8::from_str_radix(&color[1][5..7], 16).unwrap_or(255);
-                Color::Rgb(r, g, b)
-            } else {
-                color[1].parse::<u8>()
-                    .map(Color::AnsiValue)
-                    .unwrap_or(Color::Default)
-            };
+            if color[0] == "ansi_bg" {
+                theme.keep_ansi_bg = color[1] == "keep";
+                continue;
+            }
+
+            let new_color = parse_color(color[1]);
 
             match color[0] {
                 "fg"                    => theme.fg               = new_color,
@@ -216,6 +225,7 @@ impl ColorTheme {
                 "selected" | "marker"   => theme.selected         = new_color,
                 "header"                => theme.header           = new_color,
                 "border"                => theme.border           = new_color,
+                "scrollbar"             => theme.scrollbar        = new_color,
                 _ => {}
             }
         }
@@ -317,4 +327,79 @@ impl ColorTheme {
             effect: Effect::empty(),
         }
     }
+
+    pub fn scrollbar(&self) -> Attr {
+        Attr {
+            fg: self.scrollbar,
+            bg: self.bg,
+            effect: Effect::empty(),
+        }
+    }
+
+    pub fn keep_ansi_bg(&self) -> bool {
+        self.keep_ansi_bg
+    }
+}
+
+/// replace the `@FILE` in the comma-separated color spec with the colors in the file, which are
+/// separated by commas or whitespaces, an unreadable file is ignored
+fn expand_color_files(color: &str) -> Vec<String> {
+    let mut pairs = vec![];
+    for pair in color.split(',') {
+        match pair.strip_prefix('@') {
+            Some(path) => {
+                let content = fs::read_to_string(path).unwrap_or_default();
+                pairs.extend(
+                    content
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|s| !s.is_empty())
+                        .map(String::from),
+                );
+            }
+            None => pairs.push(pair.to_string()),
+        }
+    }
+    pairs
+}
+
+/// parse the color in the form of `#rrggbb` or a 256 color number, `Color::Default` if invalid
+pub fn parse_color(color: &str) -> Color {
+    if color.len() == 7 {
+        // 256 color
+        let r = u8::from_str_radix(&color[1..3], 16).unwrap_or(255);
+        let g = u8::from_str_radix(&color[3..5], 16).unwrap_or(255);
+        let b = u8::from_str_radix(&color[5..7], 16).unwrap_or(255);
+        Color::Rgb(r, g, b)
+    } else {
+        color.parse::<u8>().map(Color::AnsiValue).unwrap_or(Color::Default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_color_files() {
+        let path = std::env::temp_dir().join(format!("sk-colors-{}", std::process::id()));
+        fs::write(&path, "light\nfg:232, bg:255\n").unwrap();
+
+        let color = format!("bg+:24,@{},info:27", path.display());
+        assert_eq!(
+            vec!["bg+:24", "light", "fg:232", "bg:255", "info:27"],
+            expand_color_files(&color)
+        );
+        let _ = fs::remove_file(&path);
+
+        // the unreadable file is ignored
+        assert_eq!(vec!["bg+:24", "info:27"], expand_color_files(&color));
+    }
+
+    #[test]
+    fn test_ansi_bg() {
+        assert!(!ColorTheme::from_color(None).keep_ansi_bg());
+        assert!(!ColorTheme::from_color(Some("fg:1")).keep_ansi_bg());
+        assert!(ColorTheme::from_color(Some("ansi_bg:keep")).keep_ansi_bg());
+        assert!(!ColorTheme::from_color(Some("ansi_bg:keep,ansi_bg:override")).keep_ansi_bg());
+    }
 }
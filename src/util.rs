@@ -1,16 +1,28 @@
 use std::borrow::Cow;
 use std::cmp::{max, min};
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
 use std::prelude::v1::*;
+use std::process::Command;
 
 use regex::{Captures, Regex};
+use tuikit::output::Output;
 use tuikit::prelude::*;
+use tuikit::raw::get_tty;
 use unicode_width::UnicodeWidthChar;
 
 use crate::field::get_string_by_range;
+use crate::item::RankValues;
 use crate::AnsiString;
 use bitflags::_core::str::FromStr;
 
+/// shown for the newlines inside the items, e.g. of `--read0`
+pub const NEWLINE_MARKER: char = '\u{2424}';
+
 lazy_static! {
+    /// the default `--delimiter`, compiled once and shared by its users
+    pub static ref DEFAULT_DELIMITER: Regex = Regex::new(r"[\t\n ]+").unwrap();
     static ref RE_ESCAPE: Regex = Regex::new(r"['\U{00}]").unwrap();
     static ref RE_NUMBER: Regex = Regex::new(r"[+|-]?\d+").unwrap();
 }
@@ -35,6 +47,71 @@ pub fn escape_single_quote(text: &str) -> String {
         .to_string()
 }
 
+/// The quote(`'`, `"` or `` ` ``) or `(` that is left open at the end of the shell command, e.g.
+/// when a `{}` with quotes is put inside the double quotes. Comments, backslashes and the nesting
+/// of `$(...)` are respected, other shell syntax is not.
+pub fn unbalanced_quote(cmd: &str) -> Option<char> {
+    let mut open: Vec<char> = Vec::new();
+    let mut chars = cmd.chars().peekable();
+    let mut word_start = true;
+    while let Some(ch) = chars.next() {
+        match (open.last(), ch) {
+            (Some('\''), '\'') => {
+                open.pop();
+            }
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                chars.next();
+            }
+            (Some('"'), '"') | (Some('`'), '`') | (Some('('), ')') => {
+                open.pop();
+            }
+            (None, '#') | (Some('('), '#') if word_start => {
+                // skip the comment till the end of line
+                chars.by_ref().find(|&ch| ch == '\n');
+            }
+            // a command substitution, whose quotes are independent of the enclosing ones
+            (_, '$') if chars.peek() == Some(&'(') => {
+                chars.next();
+                open.push('(');
+            }
+            (None, '(') | (Some('('), '(') => open.push(ch),
+            (Some('"'), '`') | (Some('`'), '\'') | (Some('`'), '"') => open.push(ch),
+            (None, '\'') | (None, '"') | (None, '`') | (Some('('), '\'') | (Some('('), '"') | (Some('('), '`') => {
+                open.push(ch)
+            }
+            _ => {}
+        }
+        word_start = ch.is_whitespace() || ch == ';' || ch == '|' || ch == '&' || ch == '(';
+    }
+    open.last().copied()
+}
+
+/// The command to fetch the items when there is no input: `$SKIM_DEFAULT_COMMAND` if set,
+/// otherwise `find .` that skips the files and directories whose names match the comma separated
+/// patterns of `--exclude`, e.g. `node_modules,*.o`.
+pub fn default_command(exclude: Option<&str>) -> String {
+    match env::var("SKIM_DEFAULT_COMMAND").as_ref().map(String::as_ref) {
+        Ok("") | Err(_) => find_command(exclude.unwrap_or("")),
+        Ok(val) => val.to_owned(),
+    }
+}
+
+fn find_command(exclude: &str) -> String {
+    let names: Vec<String> = exclude
+        .split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(|pattern| format!("-name '{}'", escape_single_quote(pattern)))
+        .collect();
+    if names.is_empty() {
+        "find .".to_owned()
+    } else {
+        // `-mindepth 1` keeps `.` itself from being pruned, e.g. by `.*`
+        format!("find . -mindepth 1 \\( {} \\) -prune -o -print", names.join(" -o "))
+    }
+}
+
 /// use to print a single line, properly handle the tabstop and shift of a string
 /// e.g. a long line will be printed as `..some content` or `some content..` or `..some content..`
 /// depends on the container's width and the size of the content.
@@ -150,16 +227,19 @@ impl LinePrinter {
         assert!(self.current_pos >= 0);
         let current = self.current_pos as usize;
 
-        if current < self.start || current >= self.end {
+        // a wide character might be partly visible, only the visible columns are counted
+        let visible_width = min(current + w, self.end).saturating_sub(max(current, self.start));
+
+        if visible_width == 0 {
             // pass if it is hidden
         } else if current < self.start + 2 && self.start > 0 {
-            // print left ".."
-            for _ in 0..min(w, current - self.start + 1) {
+            // print left "..", a wide character is never cut in half but covered by dots
+            for _ in 0..visible_width {
                 self.print_ch_to_canvas(canvas, '.', attr, skip);
             }
-        } else if self.end - current <= 2 && (self.text_width > self.end) {
+        } else if current + w > self.end - min(2, self.end) && (self.text_width > self.end) {
             // print right ".."
-            for _ in 0..min(w, self.end - current) {
+            for _ in 0..visible_width {
                 self.print_ch_to_canvas(canvas, '.', attr, skip);
             }
         } else {
@@ -185,11 +265,29 @@ impl LinePrinter {
                     self.print_char_raw(canvas, ' ', attr, skip);
                 }
             }
+            '\n' => self.print_char_raw(canvas, NEWLINE_MARKER, attr, skip),
+            ch if ch.is_control() => {
+                // the control characters would corrupt the screen, they take two columns as
+                // counted by `accumulate_text_width`
+                let (first, second) = control_char_notation(ch);
+                self.print_char_raw(canvas, first, attr, skip);
+                self.print_char_raw(canvas, second, attr, skip);
+            }
             ch => self.print_char_raw(canvas, ch, attr, skip),
         }
     }
 }
 
+/// the caret notation of the C0 control characters and DEL, e.g. `^G` for `\x07`, or the
+/// replacement character for the others
+fn control_char_notation(ch: char) -> (char, char) {
+    if (ch as u32) < 0x20 || ch == '\x7f' {
+        ('^', ((ch as u8) ^ 0x40) as char)
+    } else {
+        ('\u{FFFD}', ' ')
+    }
+}
+
 pub fn print_item(canvas: &mut dyn Canvas, printer: &mut LinePrinter, content: AnsiString, default_attr: Attr) {
     for (ch, attr) in content.iter() {
         printer.print_char(canvas, ch, default_attr.extend(attr), false);
@@ -201,10 +299,10 @@ pub fn accumulate_text_width(text: &str, tabstop: usize) -> Vec<usize> {
     let mut ret = Vec::new();
     let mut w = 0;
     for ch in text.chars() {
-        w += if ch == '\t' {
-            tabstop - (w % tabstop)
-        } else {
-            ch.width().unwrap_or(2)
+        w += match ch {
+            '\t' => tabstop - (w % tabstop),
+            '\n' => 1,
+            ch => ch.width().unwrap_or(2),
         };
         ret.push(w);
     }
@@ -308,6 +406,41 @@ pub fn parse_margin(margin_option: &str) -> (Size, Size, Size, Size) {
     }
 }
 
+/// Set for the commands whose output is captured while skim keeps the terminal, so that skim run
+/// by them could refuse to fight for the terminal.
+pub const TERMINAL_IN_USE_ENV: &str = "SKIM_TERMINAL_IN_USE";
+
+/// The working directory and extra environment variables of the spawned commands, e.g. the
+/// interactive command and the preview command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandEnv {
+    pub cwd: Option<String>,
+    pub vars: Vec<(String, String)>,
+}
+
+impl CommandEnv {
+    /// `vars` are in the form of `KEY=VALUE`, invalid ones are ignored
+    pub fn new(cwd: Option<&str>, vars: &[&str]) -> Self {
+        Self {
+            cwd: cwd.filter(|cwd| !cwd.is_empty()).map(|cwd| cwd.to_string()),
+            vars: vars
+                .iter()
+                .filter_map(|var| var.split_once('='))
+                .filter(|(key, _)| !key.is_empty())
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    pub fn apply(&self, command: &mut Command) {
+        if let Some(cwd) = self.cwd.as_ref() {
+            command.current_dir(cwd);
+        }
+        command.envs(self.vars.iter().map(|(key, value)| (key, value)));
+        command.env(TERMINAL_IN_USE_ENV, "1");
+    }
+}
+
 /// The context for injecting command.
 #[derive(Copy, Clone)]
 pub struct InjectContext<'a> {
@@ -318,17 +451,33 @@ pub struct InjectContext<'a> {
     pub selections: &'a [&'a str],
     pub query: &'a str,
     pub cmd_query: &'a str,
+    /// the output fields of the current item, see `SkimItem::output_fields`
+    pub output_fields: &'a [&'a str],
+    /// the rank values of the current item, if it is matched
+    pub rank: Option<RankValues>,
 }
 
 lazy_static! {
-    static ref RE_ITEMS: Regex = Regex::new(r"\\?(\{ *-?[0-9.+]*? *})").unwrap();
-    static ref RE_FIELDS: Regex = Regex::new(r"\\?(\{ *-?[0-9.,cq+n]*? *})").unwrap();
+    static ref RE_ITEMS: Regex =
+        Regex::new(r"\\?(\{ *(-?[0-9.+]*?|o-?[0-9]*|score|rank\.(?:begin|end|length))(:(raw|shell|url))? *})").unwrap();
+    static ref RE_FIELDS: Regex = Regex::new(
+        r"\\?(\{ *(-?[0-9.,cq+n]*?|o(?:-?[0-9]+)?|score|rank\.(?:begin|end|length)) *(:(raw|shell|url))? *})"
+    )
+    .unwrap();
 }
 
 /// Check if a command depends on item
 /// e.g. contains `{}`, `{1..}`, `{+}`
 pub fn depends_on_items(cmd: &str) -> bool {
-    RE_ITEMS.is_match(cmd)
+    // the escaped `\{}` is not injected by `inject_command`
+    RE_ITEMS.find_iter(cmd).any(|mat| !mat.as_str().starts_with('\\'))
+}
+
+/// Check if a command depends on the rank of the current item, e.g. contains `{score}`
+pub fn depends_on_rank(cmd: &str) -> bool {
+    RE_FIELDS
+        .captures_iter(cmd)
+        .any(|caps| !caps[0].starts_with('\\') && (&caps[2] == "score" || caps[2].starts_with("rank.")))
 }
 
 /// inject the fields into commands
@@ -340,18 +489,44 @@ pub fn depends_on_items(cmd: &str) -> bool {
 /// * `{+}` for all selections
 /// * `{q}` for query
 /// * `{cq}` for command query
+/// * `{o1}`, `{o-1}`, etc. for the output fields, `{o}` for all of them separated by tabs
+/// * `{score}`, `{rank.begin}`, `{rank.end}` and `{rank.length}` for the rank values of the
+///   current item, empty if it is not matched
+///
+/// The replacements are single-quoted for the shell, unless the placeholder ends with a modifier:
+/// `:raw` (e.g. `{q:raw}`) for the value as is, `:url` for the URL-encoded value, or `:shell`.
 pub fn inject_command<'a>(cmd: &'a str, context: InjectContext<'a>) -> Cow<'a, str> {
+    inject_placeholders(cmd, context, true)
+}
+
+/// Replace the placeholders as `inject_command` does, but without quoting the replacements(unless
+/// `:shell` is given) and with the escaping backslashes removed, for the texts shown on the screen
+pub fn inject_text<'a>(text: &'a str, context: InjectContext<'a>) -> Cow<'a, str> {
+    inject_placeholders(text, context, false)
+}
+
+fn inject_placeholders<'a>(cmd: &'a str, context: InjectContext<'a>, quote: bool) -> Cow<'a, str> {
     RE_FIELDS.replace_all(cmd, |caps: &Captures| {
         // \{...
         if &caps[0][0..1] == "\\" {
-            return caps[0].to_string();
+            return if quote {
+                caps[0].to_string()
+            } else {
+                caps[0][1..].to_string()
+            };
         }
 
+        let modifier = caps.get(4).map(|m| m.as_str());
+        let wrap = |replacement: &str| match modifier {
+            Some("raw") => replacement.to_string(),
+            Some("url") => url_encode(replacement),
+            Some(_) => format!("'{}'", escape_single_quote(replacement)),
+            None if quote => format!("'{}'", escape_single_quote(replacement)),
+            None => replacement.to_string(),
+        };
+
         // {1..} and other variant
-        let range = &caps[1];
-        assert!(range.len() >= 2);
-        let range = &range[1..range.len() - 1];
-        let range = range.trim();
+        let range = &caps[2];
 
         if range.starts_with('+') {
             let current_selection = vec![context.current_selection];
@@ -378,26 +553,111 @@ pub fn inject_command<'a>(cmd: &'a str, context: InjectContext<'a>) -> Cow<'a, s
                         "n" => &index_str,
                         _ => get_string_by_range(context.delimiter, s, rest).unwrap_or(""),
                     };
-                    format!("'{}'", escape_single_quote(replacement))
+                    wrap(replacement)
                 })
                 .collect::<Vec<_>>()
                 .join(" ");
         }
 
         let index_str = format!("{}", context.current_index);
+        let all_output_fields;
+        let rank_value;
         let replacement = match range {
             "" => context.current_selection,
             x if x.starts_with('+') => unreachable!(),
             "n" => &index_str,
+            "o" => {
+                all_output_fields = context.output_fields.join("\t");
+                &all_output_fields
+            }
+            x if x.starts_with('o') => x[1..]
+                .parse::<isize>()
+                .ok()
+                .and_then(|index| match index {
+                    0 => None,
+                    index if index > 0 => context.output_fields.get(index as usize - 1),
+                    index => context
+                        .output_fields
+                        .len()
+                        .checked_sub(index.unsigned_abs())
+                        .map(|i| &context.output_fields[i]),
+                })
+                .copied()
+                .unwrap_or(""),
             "q" => context.query,
             "cq" => context.cmd_query,
+            x if x == "score" || x.starts_with("rank.") => {
+                rank_value = context
+                    .rank
+                    .map(|rank| match x {
+                        "score" => rank.score.to_string(),
+                        "rank.begin" => rank.begin.to_string(),
+                        "rank.end" => rank.end.to_string(),
+                        _ => rank.length.to_string(),
+                    })
+                    .unwrap_or_default();
+                &rank_value
+            }
             _ => get_string_by_range(context.delimiter, context.current_selection, range).unwrap_or(""),
         };
 
-        format!("'{}'", escape_single_quote(replacement))
+        wrap(replacement)
     })
 }
 
+/// percent-encode all but the unreserved characters of URL(RFC 3986)
+pub fn url_encode(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => ret.push(byte as char),
+            _ => ret.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    ret
+}
+
+/// Copy `text` to the system clipboard by the OSC 52 escape sequence, which is written to the tty
+/// so that it works over ssh and needs no external tools, if the terminal supports it
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut tty = OpenOptions::new().write(true).open("/dev/tty")?;
+    write!(tty, "\x1b]52;c;{}\x07", base64_encode(text.as_bytes()))?;
+    tty.flush()
+}
+
+/// Turn the bracketed paste mode of the terminal on or off. In the mode a paste comes between
+/// `BracketedPasteStart` and `BracketedPasteEnd`, so that it is not taken as the keys typed.
+///
+/// The `Term` of tuikit keeps its output to itself, so the sequence is sent by an `Output` of
+/// tuikit to the same terminal.
+pub fn set_bracketed_paste(enable: bool) -> io::Result<()> {
+    let mut output = Output::new(Box::new(get_tty()?))?;
+    if enable {
+        output.enable_bracketed_paste();
+    } else {
+        output.disable_bracketed_paste();
+    }
+    output.flush();
+    Ok(())
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut ret = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                ret.push(TABLE[(n >> (18 - 6 * i)) & 0x3f] as char);
+            } else {
+                ret.push('=');
+            }
+        }
+    }
+    ret
+}
+
 pub fn str_lines(string: &str) -> Vec<&str> {
     string.trim_end().split('\n').collect()
 }
@@ -406,9 +666,140 @@ pub fn atoi<T: FromStr>(string: &str) -> Option<T> {
     RE_NUMBER.find(string).and_then(|mat| mat.as_str().parse::<T>().ok())
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArithToken {
+    Num(i64),
+    Op(char),
+}
+
+/// Evaluate simple integer arithmetic with `+`, `-`, `*` and `/`, e.g. `+'42'-5` or `'42'/2`.
+/// Quotes and other characters(e.g. trailing delimiters of the injected fields) are ignored.
+/// Returns `None` if the expression is malformed or divides by zero.
+pub fn eval_arithmetic(expr: &str) -> Option<i64> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '0'..='9' => {
+                let mut num = ch.to_digit(10).unwrap() as i64;
+                while let Some(digit) = chars.peek().and_then(|ch| ch.to_digit(10)) {
+                    num = num.checked_mul(10)?.checked_add(digit as i64)?;
+                    chars.next();
+                }
+                tokens.push(ArithToken::Num(num));
+            }
+            '+' | '-' | '*' | '/' => tokens.push(ArithToken::Op(ch)),
+            _ => {}
+        }
+    }
+
+    let mut tokens = tokens.into_iter().peekable();
+    let ret = eval_arith_expr(&mut tokens)?;
+    if tokens.next().is_some() {
+        None
+    } else {
+        Some(ret)
+    }
+}
+
+type ArithTokens = std::iter::Peekable<std::vec::IntoIter<ArithToken>>;
+
+// expr := term (('+'|'-') term)*
+fn eval_arith_expr(tokens: &mut ArithTokens) -> Option<i64> {
+    let mut ret = eval_arith_term(tokens)?;
+    while let Some(&ArithToken::Op(op @ ('+' | '-'))) = tokens.peek() {
+        tokens.next();
+        let rhs = eval_arith_term(tokens)?;
+        ret = if op == '+' {
+            ret.checked_add(rhs)?
+        } else {
+            ret.checked_sub(rhs)?
+        };
+    }
+    Some(ret)
+}
+
+// term := factor (('*'|'/') factor)*
+fn eval_arith_term(tokens: &mut ArithTokens) -> Option<i64> {
+    let mut ret = eval_arith_factor(tokens)?;
+    while let Some(&ArithToken::Op(op @ ('*' | '/'))) = tokens.peek() {
+        tokens.next();
+        let rhs = eval_arith_factor(tokens)?;
+        ret = if op == '*' {
+            ret.checked_mul(rhs)?
+        } else {
+            ret.checked_div(rhs)?
+        };
+    }
+    Some(ret)
+}
+
+// factor := ('+'|'-') factor | number
+fn eval_arith_factor(tokens: &mut ArithTokens) -> Option<i64> {
+    match tokens.next()? {
+        ArithToken::Num(num) => Some(num),
+        ArithToken::Op('+') => eval_arith_factor(tokens),
+        ArithToken::Op('-') => eval_arith_factor(tokens)?.checked_neg(),
+        ArithToken::Op(_) => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use unicode_width::UnicodeWidthStr;
+
+    #[test]
+    fn test_command_env() {
+        let env = CommandEnv::new(Some("/tmp"), &["A=1", "B=x=y", "=z", "C"]);
+        assert_eq!(env.cwd.as_deref(), Some("/tmp"));
+        assert_eq!(
+            env.vars,
+            vec![("A".to_string(), "1".to_string()), ("B".to_string(), "x=y".to_string())]
+        );
+        assert_eq!(CommandEnv::new(Some(""), &[]), CommandEnv::default());
+    }
+
+    #[test]
+    fn test_eval_arithmetic() {
+        assert_eq!(eval_arithmetic("+42"), Some(42));
+        assert_eq!(eval_arithmetic("+'42'-5"), Some(37));
+        assert_eq!(eval_arithmetic("+'42:'/2"), Some(21));
+        assert_eq!(eval_arithmetic("+'10'+2*3-'4'/2"), Some(14));
+        assert_eq!(eval_arithmetic("+-3"), Some(-3));
+        assert_eq!(eval_arithmetic("+'10'/0"), None);
+        assert_eq!(eval_arithmetic("+'42'*"), None);
+        assert_eq!(eval_arithmetic(""), None);
+    }
+
+    #[test]
+    fn test_unbalanced_quote() {
+        assert_eq!(unbalanced_quote("echo 'it'\\''s' \"a $(b) `c \\\"d`\""), None);
+        assert_eq!(unbalanced_quote("echo it\\'s # it's a comment\necho"), None);
+        assert_eq!(unbalanced_quote("echo a#'"), Some('\''));
+        assert_eq!(unbalanced_quote("echo \"'a\"b'\""), Some('\''));
+        assert_eq!(unbalanced_quote("echo `a"), Some('`'));
+        assert_eq!(unbalanced_quote("echo \"`a`"), Some('"'));
+        assert_eq!(unbalanced_quote("echo \"$(echo \"it's\")\" $((1 + 2))"), None);
+        assert_eq!(unbalanced_quote("echo $(echo a # it's\n)"), None);
+        assert_eq!(unbalanced_quote("echo $(echo 'a)'"), Some('('));
+
+        // a placeholder in the double quotes
+        let delimiter = Regex::new(",").unwrap();
+        let context = InjectContext {
+            current_index: 0,
+            delimiter: &delimiter,
+            current_selection: "say \"hi",
+            selections: &[],
+            indices: &[],
+            query: "",
+            cmd_query: "",
+            output_fields: &[],
+            rank: None,
+        };
+        assert_eq!(unbalanced_quote(&inject_command("cat {}", context)), None);
+        assert_eq!(unbalanced_quote(&inject_command("echo \"{}\"", context)), Some('\''));
+    }
 
     #[test]
     fn test_accumulate_text_width() {
@@ -416,6 +807,8 @@ mod tests {
         assert_eq!(accumulate_text_width("ab中de国g", 8), vec![1, 2, 4, 5, 6, 8, 9]);
         assert_eq!(accumulate_text_width("ab\tdefg", 8), vec![1, 2, 8, 9, 10, 11, 12]);
         assert_eq!(accumulate_text_width("ab中\te国g", 8), vec![1, 2, 4, 8, 9, 11, 12]);
+        // the newline marker and the caret notation
+        assert_eq!(accumulate_text_width("a\nb\x07", 8), vec![1, 2, 3, 5]);
     }
 
     #[test]
@@ -428,6 +821,70 @@ mod tests {
         assert_eq!(reshape_string("a\t中b\tc012345", 8, 0, 0, 8), (0, 23));
     }
 
+    /// print `text` shifted by `shift` columns into a container of `width` columns
+    fn print_line(text: &str, width: usize, shift: usize) -> String {
+        let mut screen = tuikit::screen::Screen::new(width, 1);
+        let mut printer = LinePrinter::builder()
+            .container_width(width)
+            .shift(shift)
+            .text_width(text.width_cjk())
+            .build();
+        for ch in text.chars() {
+            printer.print_char(&mut screen, ch, Attr::default(), false);
+        }
+
+        let mut line = String::new();
+        let mut cells = screen.iter_cell();
+        while let Some((_, _, cell)) = cells.next() {
+            line.push(cell.ch);
+            if cell.ch.width().unwrap_or(2) > 1 {
+                cells.next();
+            }
+        }
+        line
+    }
+
+    #[test]
+    fn test_line_printer_ellipsis() {
+        assert_eq!(print_line("abcdefgh", 6, 0), "abcd..");
+        assert_eq!(print_line("abcdefgh", 6, 2), "..efgh");
+        assert_eq!(print_line("abcdefgh", 6, 1), "..de..");
+
+        // wide characters are covered by dots instead of being cut in half
+        assert_eq!(print_line("中文字符", 5, 0), "中...");
+        assert_eq!(print_line("中文字符", 5, 3), "...符");
+        assert_eq!(print_line("中文字符", 4, 4), "..符");
+        assert_eq!(print_line("a中文字符", 5, 4), "...符");
+        assert_eq!(print_line("ab中文", 4, 2), "..文");
+        assert_eq!(print_line("a中文b", 4, 2), "...b");
+    }
+
+    #[test]
+    fn test_control_char_notation() {
+        assert_eq!(('^', 'G'), control_char_notation('\x07'));
+        assert_eq!(('^', '['), control_char_notation('\x1b'));
+        assert_eq!(('^', '@'), control_char_notation('\0'));
+        assert_eq!(('^', '?'), control_char_notation('\x7f'));
+        assert_eq!(('\u{FFFD}', ' '), control_char_notation('\u{9b}'));
+    }
+
+    #[test]
+    fn test_depends_on_items() {
+        assert!(depends_on_items("cat {}"));
+        assert!(depends_on_items("+{2}-/2"));
+        assert!(depends_on_items("echo {+} {1..3}"));
+        assert!(!depends_on_items("echo {q} {cq}"));
+        assert!(!depends_on_items("+10"));
+        assert!(!depends_on_items("awk '\\{}'"));
+        assert!(depends_on_items("echo \\{} {}"));
+        assert!(depends_on_items("open {o2}"));
+        assert!(depends_on_items("echo {score}"));
+        assert!(depends_on_rank("echo {rank.begin:raw}"));
+        assert!(!depends_on_rank("echo \\{score} {}"));
+        assert!(depends_on_items("open {2:url}"));
+        assert!(!depends_on_items("open {q:url}"));
+    }
+
     #[test]
     fn test_inject_command() {
         let delimiter = Regex::new(r",").unwrap();
@@ -444,6 +901,13 @@ mod tests {
             indices: &[0, 1],
             query,
             cmd_query,
+            output_fields: &["42", "a,b,c"],
+            rank: Some(RankValues {
+                score: 42,
+                begin: 1,
+                end: 3,
+                length: 5,
+            }),
         };
 
         assert_eq!("'a,b,c'", inject_command("{}", default_context));
@@ -464,9 +928,56 @@ mod tests {
         assert_eq!("'cmd_query'", inject_command("{cq}", default_context));
         assert_eq!("'a,b,c' 'x,y,z'", inject_command("{+}", default_context));
         assert_eq!("'0'", inject_command("{n}", default_context));
+        assert_eq!("'42'", inject_command("{o1}", default_context));
+        assert_eq!("'a,b,c'", inject_command("{o-1}", default_context));
+        assert_eq!("''", inject_command("{o3}", default_context));
+        assert_eq!("'42\ta,b,c'", inject_command("{o}", default_context));
+        assert_eq!("{1o2}", inject_command("{1o2}", default_context));
+        assert_eq!("{o-}", inject_command("{o-}", default_context));
         assert_eq!("'a' 'x'", inject_command("{+1}", default_context));
         assert_eq!("'b' 'y'", inject_command("{+2}", default_context));
         assert_eq!("'0' '1'", inject_command("{+n}", default_context));
+        assert_eq!("'42'", inject_command("{score}", default_context));
+        assert_eq!(
+            "1-3 of 5",
+            inject_text("{rank.begin}-{rank.end} of {rank.length}", default_context)
+        );
+        let unmatched = InjectContext {
+            rank: None,
+            ..default_context
+        };
+        assert_eq!("''", inject_command("{score}", unmatched));
+
+        assert_eq!("dir: b/query", inject_text("dir: {2}/{q}", default_context));
+        assert_eq!("a,b,c x,y,z", inject_text("{+}", default_context));
+        assert_eq!("{q}", inject_text("\\{q}", default_context));
+
+        // modifiers
+        assert_eq!("a,b,c", inject_command("{:raw}", default_context));
+        assert_eq!("echo query b", inject_command("echo {q:raw} {2:raw}", default_context));
+        assert_eq!("a%2Cb%2Cc x%2Cy%2Cz", inject_command("{+:url}", default_context));
+        assert_eq!("'b'", inject_command("{ 2 :shell }", default_context));
+        assert_eq!("'query'", inject_text("{q:shell}", default_context));
+        assert_eq!("{q:bad}", inject_command("{q:bad}", default_context));
+        assert_eq!("\\{q:raw}", inject_command("\\{q:raw}", default_context));
+    }
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!("", base64_encode(b""));
+        assert_eq!("Zg==", base64_encode(b"f"));
+        assert_eq!("Zm8=", base64_encode(b"fo"));
+        assert_eq!("Zm9v", base64_encode(b"foo"));
+        assert_eq!("Zm9vYmFy", base64_encode(b"foobar"));
+        assert_eq!("5Lit", base64_encode("中".as_bytes()));
+        assert_eq!("YQpi", base64_encode(b"a\nb"));
+    }
+
+    #[test]
+    fn test_url_encode() {
+        assert_eq!("a-b_c.d~e", url_encode("a-b_c.d~e"));
+        assert_eq!("a%20b%2F%3F%26%27", url_encode("a b/?&'"));
+        assert_eq!("%E4%B8%AD", url_encode("中"));
     }
 
     #[test]
@@ -487,4 +998,18 @@ mod tests {
         assert_eq!(None, atoi::<i32>("8589934592"));
         assert_eq!(Some(123), atoi::<i32>("+'123'"));
     }
+
+    #[test]
+    fn test_find_command() {
+        assert_eq!(find_command(""), "find .");
+        assert_eq!(find_command(" , "), "find .");
+        assert_eq!(
+            find_command("node_modules, *.o"),
+            r"find . -mindepth 1 \( -name 'node_modules' -o -name '*.o' \) -prune -o -print"
+        );
+        assert_eq!(
+            find_command("it's"),
+            r"find . -mindepth 1 \( -name 'it'\''s' \) -prune -o -print"
+        );
+    }
 }
@@ -393,6 +393,28 @@ class TestSkim(TestBase):
         self.tmux.send_keys(Ctrl('m'))
         self.tmux.until(lambda lines: not lines[-1].startswith('>'))
 
+    def test_reuse_items_on_superset(self):
+        """the items are filtered instead of running the command again when the cmd query is extended"""
+        with open('/tmp/sk-test-words', 'w') as fp:
+            fp.write('abc\nabd\nxab\nbbb\naxb\n')
+        if os.path.exists('/tmp/sk-test-runs'):
+            os.remove('/tmp/sk-test-runs')
+        command = """echo run >> /tmp/sk-test-runs; grep -F "{}" /tmp/sk-test-words"""
+        self.tmux.send_keys(f"{SK} -i --reuse-items-on-superset -c '{command}'", Key('Enter'))
+        self.tmux.until(lambda lines: lines.ready_with_lines(5))
+
+        self.tmux.send_keys('a')
+        self.tmux.until(lambda lines: lines.ready_with_lines(4))
+        self.tmux.send_keys('b')
+        self.tmux.until(lambda lines: lines.ready_with_lines(3))
+        with open('/tmp/sk-test-runs') as fp:
+            self.assertEqual(len(fp.readlines()), 2)
+
+        self.tmux.send_keys(Key('BSpace'))
+        self.tmux.until(lambda lines: lines.ready_with_lines(4))
+        with open('/tmp/sk-test-runs') as fp:
+            self.assertEqual(len(fp.readlines()), 3)
+
     def test_key_bindings_interactive(self):
         self.tmux.send_keys(f"{SK} -i --cmd-query 'foo bar foo-bar'", Key('Enter'))
         self.tmux.until(lambda lines: lines[-1].startswith('c>'))
@@ -486,6 +508,39 @@ class TestSkim(TestBase):
         lines = self.readonce().strip()
         self.assertEqual(lines, 'a\0aa\0')
 
+    def test_output_delimiter(self):
+        sk_command = self.sk('-m', "--output-delimiter ' '")
+        self.tmux.send_keys(f"echo -e 'a\\nb\\nc' | {sk_command}", Key('Enter'))
+        self.tmux.until(lambda lines: lines.ready_with_lines(3))
+        self.tmux.send_keys(Key('BTab'), Key('BTab'), Key('Enter'))
+
+        lines = self.readonce().strip()
+        self.assertEqual(lines, 'a b')
+
+        self.tmux.send_keys(f"echo -e 'a\\naa\\nb' | {self.sk('-f a', '--output-delimiter ,')}", Key('Enter'))
+
+        lines = self.readonce().strip()
+        self.assertEqual(lines, 'a,aa')
+
+    def test_print_positions(self):
+        self.tmux.send_keys(f"echo -e 'src/main.rs\\nfoo' | {self.sk('-f smr', '--print-positions')}", Key('Enter'))
+        lines = self.readonce().strip()
+        self.assertEqual(lines, 'src/main.rs\t0:1,4:5,9:10')
+
+        # the positions are of the printed text
+        self.tmux.send_keys(f"echo -e 'foo bar' | {self.sk('-f bar', '--with-nth 2', '--print-positions')}", Key('Enter'))
+        lines = self.readonce().strip()
+        self.assertEqual(lines, 'bar\t0:3')
+
+    def test_read0_print0_round_trip(self):
+        """the newlines inside the items are shown as markers and output as they are read"""
+        self.tmux.send_keys(f"printf 'a\\nb\\0c\\n\\0' | {self.sk('-m', '--read0', '--print0')}", Key('Enter'))
+        self.tmux.until(lambda lines: lines.ready_with_lines(2))
+        self.tmux.until(lambda lines: lines.any_include('a\u2424b') and lines.any_include('c\u2424'))
+        self.tmux.send_keys(Key('BTab'), Key('BTab'), Key('Enter'))
+
+        self.assertEqual(self.readonce(), 'a\nb\0c\n\0')
+
     def test_with_nth_preview(self):
         sk_command = self.sk("--delimiter ','", '--with-nth 2..', '--preview', "'echo X{1}Y'")
         self.tmux.send_keys("echo -e 'field1,field2,field3,field4' |" + sk_command, Key('Enter'))
@@ -664,6 +719,13 @@ class TestSkim(TestBase):
         self.command_until(sk_options=['--reverse', '--header', 'hello'],
                            until_predicate=lambda lines: lines[2].find("hello") != -1)
 
+    def test_expect_hint(self):
+        self.command_until(sk_options=['--expect-hint', '--expect', 'ctrl-e:edit,alt-s'],
+                           until_predicate=lambda lines: lines[-3].startswith("  ctrl-e: edit  alt-s"))
+
+        self.command_until(sk_options=['--expect-hint', '--expect', 'ctrl-e:edit', '--header', 'hello'],
+                           until_predicate=lambda lines: lines[-3].find("hello") != -1 and lines[-4].find("ctrl-e: edit") != -1)
+
     def test_header_lines(self):
         self.command_until(sk_options=['--header-lines', '1'],
                            until_predicate=lambda lines: lines[-3].find("  a1") != -1)
@@ -765,6 +827,19 @@ class TestSkim(TestBase):
 
         self.command_until(stdin="echo -e a\\0b", sk_options=['--read0 --read0'], until_predicate=find_prompt)
 
+    def test_no_height_overrides_height(self):
+        """the later one of --height and --no-height takes effect"""
+        self.tmux.send_keys(f"seq 100 | {self.sk('--min-height 2', '--no-height', '--height 5')}", Key('Enter'))
+        self.tmux.until(lambda lines: lines.ready_with_lines(100))
+        self.tmux.until(lambda lines: lines.any_include('3') and not lines.any_include('20'))
+        self.tmux.send_keys(Key('Enter'))
+        self.readonce()
+
+        sk_command = f"SKIM_DEFAULT_OPTIONS='--min-height 2 --height 5' {BASE}/target/release/sk --no-height"
+        self.tmux.send_keys(f"seq 100 | {sk_command}", Key('Enter'))
+        self.tmux.until(lambda lines: lines.ready_with_lines(100))
+        self.tmux.until(lambda lines: lines.any_include('20'))
+
     def test_single_quote_of_preview_command(self):
         # echo "'\"ABC\"'" | sk --preview="echo X{}X" => X'"ABC"'X
         echo_command = '''echo "'\\"ABC\\"'" | '''
@@ -1067,6 +1142,81 @@ class TestSkim(TestBase):
         self.tmux.until(lambda lines: re.match(r'121.*121/1000', lines[0]))
         self.tmux.send_keys(Key('Enter'))
 
+    def test_preview_auto_hide(self):
+        args = "--preview 'seq 1000' --preview-window 'left:auto-hide<10000'"
+        self.tmux.send_keys(f"""echo foo | {self.sk(args)}""", Key('Enter'))
+        self.tmux.until(lambda lines: lines.ready_with_matches(1))
+        self.tmux.until(lambda lines: lines[-3].startswith('> foo'))
+        self.tmux.send_keys(Key('Enter'))
+
+        args = "--preview 'seq 1000' --preview-window 'left:auto-hide<10'"
+        self.tmux.send_keys(f"""echo foo | {self.sk(args)}""", Key('Enter'))
+        self.tmux.until(lambda lines: re.match(r'1 .*1/1000', lines[0]))
+        self.tmux.send_keys(Key('Enter'))
+
+    def test_preview_shell_init(self):
+        init_file = self.tempname() + '.sh'
+        with open(init_file, 'w') as f:
+            f.write('greet() { echo "hello $1"; }\n')
+        args = f"--preview 'greet {{}}' --preview-shell-init {init_file}"
+        self.tmux.send_keys(f"""echo foo | {self.sk(args)}""", Key('Enter'))
+        self.tmux.until(lambda lines: any('hello foo' in line for line in lines))
+        self.tmux.send_keys(Key('Enter'))
+        os.remove(init_file)
+
+    def test_preview_debug(self):
+        args = "--preview 'echo hi {}' --preview-debug"
+        self.tmux.send_keys(f"""echo foo | {self.sk(args)}""", Key('Enter'))
+        self.tmux.until(lambda lines: any("$ echo hi 'foo'" in line for line in lines))
+        self.tmux.until(lambda lines: any('hi foo' in line for line in lines))
+        self.tmux.send_keys(Key('Enter'))
+
+    def test_preview_rules(self):
+        args = "--preview-rules 'dir:echo dir {},file:echo file {}' --preview 'echo other {}'"
+        self.tmux.send_keys(f"""printf '/tmp\\nnope' | {self.sk(args)}""", Key('Enter'))
+        self.tmux.until(lambda lines: any('dir /tmp' in line for line in lines))
+        self.tmux.send_keys(Key('Up'))
+        self.tmux.until(lambda lines: any('other nope' in line for line in lines))
+        self.tmux.send_keys(Key('Enter'))
+
+    def test_preview_builtin_file(self):
+        preview_file = self.tempname() + '.txt'
+        with open(preview_file, 'w') as f:
+            f.write(''.join(f'line {i}\n' for i in range(1, 101)))
+        args = f"--delimiter : --preview-builtin 'file:{{1}}' --preview-window '+{{2}}' --preview 'echo other {{}}'"
+        self.tmux.send_keys(f"""printf '{preview_file}:50\\nnope:1' | {self.sk(args)}""", Key('Enter'))
+        self.tmux.until(lambda lines: 'line 50' in lines[0])
+        self.tmux.send_keys(Key('Up'))
+        self.tmux.until(lambda lines: any('other nope:1' in line for line in lines))
+        self.tmux.send_keys(Key('Enter'))
+        os.remove(preview_file)
+
+    def test_drill_cmd(self):
+        args = """--ansi --drill-cmd 'test {} = b && printf "\\033[31mb1\\033[0m\\nb2"' --bind ctrl-h:parent"""
+        self.tmux.send_keys(f"""printf 'a\\nb' | {self.sk(args)}""", Key('Enter'))
+        self.tmux.until(lambda lines: lines.ready_with_lines(2))
+        self.tmux.until(lambda lines: lines[-4].startswith('  b'))
+        self.tmux.send_keys(Key('Up'))
+        self.tmux.until(lambda lines: lines[-4].startswith('> b'))
+
+        # the children are read by the reader, with --ansi
+        self.tmux.send_keys(Key('Enter'))
+        self.tmux.until(lambda lines: lines[-3].startswith('> b1'))
+        self.tmux.until(lambda lines: lines[-4].startswith('  b2'))
+
+        self.tmux.send_keys(Ctrl('h'))
+        self.tmux.until(lambda lines: lines[-4].startswith('> b'))
+
+        # no children, accepted
+        self.tmux.send_keys(Key('Down'))
+        self.tmux.send_keys(Key('Enter'))
+        self.assertEqual('a', self.readonce().strip())
+
+    def test_drill_cmd_select_1(self):
+        args = "--drill-cmd 'echo child' --select-1"
+        self.tmux.send_keys(f"""echo a | {self.sk(args)}""", Key('Enter'))
+        self.assertEqual('a', self.readonce().strip())
+
     def test_issue_359_multi_byte_and_regex(self):
         self.tmux.send_keys(f"""echo 'ああa' | {self.sk("--regex -q 'a'")}""", Key('Enter'))
         self.tmux.until(lambda lines: lines.ready_with_matches(1))